@@ -2,10 +2,10 @@ use std::{process::ExitCode, sync::Arc};
 
 use clap::Parser;
 use flymodel_cli::{
-    cmds::{Cli, Commands},
+    cmds::{Cli, Commands, Gc, Retention},
     config::ServeConfig,
 };
-use flymodel_service::app::start_server;
+use flymodel_service::app::{start_server, ServerConfiguration};
 use futures_util::FutureExt;
 
 use tracing::{level_filters::LevelFilter, Level};
@@ -42,12 +42,40 @@ async fn serve_server<S>(
         conf.server.temp_dir,
         tracer,
         Arc::new(storage),
-        conf.server.tls,
+        ServerConfiguration {
+            auth: conf.auth,
+            uploads: conf.server.uploads,
+            retention: conf.retention,
+            gc: conf.gc,
+            relocation: conf.relocation,
+            webhooks: conf.webhooks,
+            tls: conf.server.tls,
+        },
         cli.dry,
     )
     .await
 }
 
+async fn run_retention<S>(
+    cli: Cli,
+    retention: Retention,
+    reload_handle: reload::Handle<filter::LevelFilter, S>,
+) -> anyhow::Result<()> {
+    let conf = cli.load_config()?;
+    conf.log.reload(reload_handle)?;
+    retention.run(conf, cli.dry).await
+}
+
+async fn run_gc<S>(
+    cli: Cli,
+    gc: Gc,
+    reload_handle: reload::Handle<filter::LevelFilter, S>,
+) -> anyhow::Result<()> {
+    let conf = cli.load_config()?;
+    conf.log.reload(reload_handle)?;
+    gc.run(conf, cli.dry).await
+}
+
 async fn run<S>(
     cmd: Cli,
     reload_handle: reload::Handle<filter::LevelFilter, S>,
@@ -56,6 +84,11 @@ async fn run<S>(
         Commands::Serve(ref server) => serve_server(cmd.clone(), server, reload_handle).await,
         Commands::Migrate(migrate) => migrate.run().await,
         Commands::SetupStorage => setup_storage(cmd, reload_handle).await,
+        Commands::ApiKey(api_key) => api_key.run().await,
+        Commands::Retention(ref retention) => {
+            run_retention(cmd.clone(), retention.clone(), reload_handle).await
+        }
+        Commands::Gc(ref gc) => run_gc(cmd.clone(), gc.clone(), reload_handle).await,
         Commands::Upsert => unimplemented!(),
     }
 }
@@ -1,19 +1,38 @@
 use crate::{
-    config::{MigrationConfig, ServeConfig},
+    config::{
+        CreateApiKeyConfig, GcRunConfig, MigrationConfig, RetentionRunConfig, RevokeApiKeyConfig,
+        ServeConfig,
+    },
     log::LoggingConfig,
 };
 use clap::{Parser, Subcommand};
 use config::Config;
 use flymodel::{
-    config::auth::{AuthConfiguration, AuthHandlers},
+    config::{
+        auth::{hash_api_key, AuthConfiguration, AuthHandlers},
+        gc::GcConfiguration,
+        relocation::RelocationConfiguration,
+        retention::RetentionConfiguration,
+        uploads::UploadConfiguration,
+        webhooks::WebhookConfiguration,
+    },
+    perms::Permission,
     tls::TlsConf,
 };
+use flymodel_entities::{db::DbLoader, entities};
 use flymodel_members::server::MembershipConfig;
 use flymodel_migration::Migrator;
 use flymodel_registry::storage::StorageConfig;
+use flymodel_service::{
+    auth::api_key::generate_api_key, gc::collect_garbage, retention::run_retention,
+};
 use flymodel_tracing::{tracer::OtlpTracerConfig, TracingConfiguration};
 use sea_orm_migration::MigratorTrait;
-use std::path::PathBuf;
+use std::{
+    io::{BufRead, IsTerminal, Write},
+    path::PathBuf,
+    time::Duration,
+};
 use tracing::warn;
 
 #[derive(Debug, Clone, Parser)]
@@ -35,6 +54,12 @@ pub enum Commands {
     #[command(subcommand)]
     Migrate(Migration),
     SetupStorage,
+    #[command(subcommand)]
+    ApiKey(ApiKey),
+    #[command(subcommand)]
+    Retention(Retention),
+    #[command(subcommand)]
+    Gc(Gc),
 
     Upsert,
 }
@@ -65,6 +90,111 @@ impl Migration {
     }
 }
 
+#[derive(Debug, Clone, Subcommand)]
+pub enum ApiKey {
+    /// create a key in the `api_key` table, printing it once
+    Create(CreateApiKeyConfig),
+    Revoke(RevokeApiKeyConfig),
+    /// hash a key read from stdin for use in the `api_key` auth configuration,
+    /// so it stays out of the shell history
+    Hash,
+}
+
+fn read_secret(prompt: &str) -> anyhow::Result<String> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        eprint!("{prompt}: ");
+        std::io::stderr().flush()?;
+    }
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line)?;
+    let secret = line.trim_end_matches(['\r', '\n']);
+    if secret.is_empty() {
+        anyhow::bail!("no {prompt} given on stdin");
+    }
+    Ok(secret.to_string())
+}
+
+impl ApiKey {
+    pub async fn run(self) -> anyhow::Result<()> {
+        match self {
+            Self::Create(conf) => {
+                for perm in &conf.permissions {
+                    Permission::try_from(perm.as_str())?;
+                }
+                let key = generate_api_key()?;
+                let db =
+                    DbLoader::<entities::api_key::Model>::new(conf.db.to_connection().await?, None);
+                db.loader()
+                    .create_key(conf.name, hash_api_key(&key), conf.permissions)
+                    .await?;
+                println!("{key}");
+            }
+            Self::Revoke(conf) => {
+                let db =
+                    DbLoader::<entities::api_key::Model>::new(conf.db.to_connection().await?, None);
+                if !db.loader().revoke_key(conf.name.clone()).await? {
+                    warn!("no active api key named {}", conf.name);
+                }
+            }
+            Self::Hash => println!("{}", hash_api_key(&read_secret("api key")?)),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Retention {
+    /// remove what the `retention` configuration expired, printing a report.
+    /// nothing is removed with `--dry`
+    Run(RetentionRunConfig),
+}
+
+impl Retention {
+    pub async fn run(self, conf: Conf, dry: bool) -> anyhow::Result<()> {
+        match self {
+            Self::Run(run) => {
+                let storage = conf.storage.build().await?;
+                let report = run_retention(
+                    &run.db.to_connection().await?,
+                    &storage,
+                    &conf.retention,
+                    dry,
+                )
+                .await?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Gc {
+    /// remove the blobs & stored objects nothing references, printing a report.
+    /// nothing is removed with `--dry`
+    Run(GcRunConfig),
+}
+
+impl Gc {
+    pub async fn run(self, conf: Conf, dry: bool) -> anyhow::Result<()> {
+        match self {
+            Self::Run(run) => {
+                let storage = conf.storage.build().await?;
+                let report = collect_garbage(
+                    &run.db.to_connection().await?,
+                    &storage,
+                    Duration::from_secs(run.grace_period.unwrap_or(conf.gc.grace_period)),
+                    dry,
+                )
+                .await?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(serde::Deserialize, Debug)]
 pub struct Conf {
     pub storage: StorageConfig,
@@ -77,6 +207,14 @@ pub struct Conf {
     pub membership: MembershipConfig,
     #[serde(default)]
     pub server: ServerConfig,
+    #[serde(default)]
+    pub retention: RetentionConfiguration,
+    #[serde(default)]
+    pub gc: GcConfiguration,
+    #[serde(default)]
+    pub relocation: RelocationConfiguration,
+    #[serde(default)]
+    pub webhooks: WebhookConfiguration,
 }
 
 #[derive(serde::Deserialize, Debug, Default)]
@@ -85,6 +223,9 @@ pub struct ServerConfig {
     pub temp_dir: PathBuf,
 
     pub tls: Option<TlsConf>,
+
+    #[serde(default)]
+    pub uploads: UploadConfiguration,
 }
 
 fn default_temp_dir() -> PathBuf {
@@ -131,6 +272,17 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_server_load_filesystem_toml() -> anyhow::Result<()> {
+        let cli = super::Cli {
+            command: super::Commands::SetupStorage,
+            config: "../../conf/flymodel-fs.toml".into(),
+            dry: false,
+        };
+        let _conf = cli.load_config()?;
+        Ok(())
+    }
+
     #[test]
     fn test_server_load_yaml() -> anyhow::Result<()> {
         let cli = super::Cli {
@@ -29,6 +29,40 @@ pub struct MigrationConfig {
     pub steps: Option<u32>,
 }
 
+#[derive(Debug, Clone, Args)]
+pub struct CreateApiKeyConfig {
+    #[clap(flatten)]
+    pub db: DatabaseConfig,
+    #[arg(short, long)]
+    pub name: String,
+    /// permissions granted to the key, e.g. `namespace:1:write`
+    #[arg(long = "permission")]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RevokeApiKeyConfig {
+    #[clap(flatten)]
+    pub db: DatabaseConfig,
+    #[arg(short, long)]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RetentionRunConfig {
+    #[clap(flatten)]
+    pub db: DatabaseConfig,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct GcRunConfig {
+    #[clap(flatten)]
+    pub db: DatabaseConfig,
+    /// seconds unreferenced blobs & objects are kept, overriding `gc.grace_period`
+    #[arg(long)]
+    pub grace_period: Option<u64>,
+}
+
 impl DatabaseConfig {
     pub async fn to_connection(&self) -> FlymodelResult<DatabaseConnection> {
         sea_orm::Database::connect(self.database_url.clone())
@@ -2,7 +2,10 @@
 use flymodel_graphql::enums::*;
 use flymodel_macros::hybrid_feature_class;
 
-use reqwest::multipart::{Form, Part};
+use reqwest::{
+    header::{HeaderMap, CONTENT_DISPOSITION},
+    multipart::{Form, Part},
+};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::marker::PhantomData;
 
@@ -134,6 +137,150 @@ pub struct ModelVersionResponse {
     pub version: String,
 }
 
+#[hybrid_feature_class(python = true, into_ts = true)]
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ModelArtifactResponse {
+    pub id: i64,
+    pub version_id: i64,
+    pub blob: i64,
+    pub name: String,
+}
+
+/// an artifact downloaded into memory, after its digest was checked
+#[hybrid_feature_class(python = true, into_ts = true, py_getters = false)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DownloadedArtifact {
+    pub name: Option<String>,
+    pub sha256: String,
+    pub size: u64,
+    pub data: Vec<u8>,
+}
+
+#[cfg(feature = "python")]
+#[pyo3::prelude::pymethods]
+impl DownloadedArtifact {
+    #[getter]
+    fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    #[getter]
+    fn sha256(&self) -> String {
+        self.sha256.clone()
+    }
+
+    #[getter]
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    #[getter]
+    fn data<'py>(&self, py: pyo3::Python<'py>) -> &'py pyo3::types::PyBytes {
+        pyo3::types::PyBytes::new(py, &self.data)
+    }
+}
+
+/// an artifact downloaded to `path`, after its digest was checked
+#[hybrid_feature_class(python = true, into_ts = true)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DownloadedFile {
+    pub name: Option<String>,
+    pub sha256: String,
+    pub size: u64,
+    pub path: String,
+}
+
+pub(crate) fn sha256_digest(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("Digest")?
+        .to_str()
+        .ok()?
+        .strip_prefix("sha256=")
+        .map(str::to_ascii_lowercase)
+}
+
+pub(crate) fn attachment_name(headers: &HeaderMap) -> Option<String> {
+    let (_, name) = headers
+        .get(CONTENT_DISPOSITION)?
+        .to_str()
+        .ok()?
+        .split_once("filename=")?;
+    Some(name.trim_matches('"').to_string())
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct CreateResumableUpload<D: Serialize> {
+    #[serde(flatten)]
+    pub(crate) artifact: D,
+    pub(crate) size: i64,
+    pub(crate) sha256: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) chunk_size: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ResumableTicket {
+    pub(crate) id: i64,
+    pub(crate) size: i64,
+    pub(crate) chunk_size: Option<i64>,
+}
+
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn digest_reader<R: std::io::Read>(data: &mut R) -> std::io::Result<(u64, String)> {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    let mut size = 0;
+    loop {
+        match data.read(&mut buf)? {
+            0 => return Ok((size, hex::encode(hasher.finalize()))),
+            read => {
+                hasher.update(&buf[..read]);
+                size += read as u64;
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn partial_path(path: &std::path::Path) -> std::path::PathBuf {
+    let nonce = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|since| since.subsec_nanos())
+        .unwrap_or_default();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.{}-{nonce}.part", std::process::id()))
+}
+
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn read_chunk<R: std::io::Read + std::io::Seek>(
+    data: &mut R,
+    part: i32,
+    chunk_size: u64,
+) -> std::io::Result<Vec<u8>> {
+    use std::io::{Read, SeekFrom};
+
+    data.seek(SeekFrom::Start((part as u64 - 1) * chunk_size))?;
+    let mut chunk = Vec::with_capacity(chunk_size as usize);
+    data.take(chunk_size).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn retry_delay(attempt: usize) -> std::time::Duration {
+    std::time::Duration::from_millis(250 << attempt.saturating_sub(2).min(5))
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ResumableUpload {
+    pub(crate) ticket: ResumableTicket,
+    pub(crate) received: Vec<i32>,
+}
+
 upload_impl!(Experiment, [
     (#[context] experiment: i64),
 ]);
@@ -147,6 +294,9 @@ upload_impl!(ModelVersion, [
 flymodel_graphql::jsvalue! {
     ExperimentResponse,
     ModelVersionResponse,
+    ModelArtifactResponse,
+    DownloadedArtifact,
+    DownloadedFile,
 }
 
 #[cfg(test)]
@@ -186,6 +336,50 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn download_headers() {
+        use reqwest::header::{HeaderMap, HeaderValue, CONTENT_DISPOSITION};
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(super::sha256_digest(&headers), None);
+        assert_eq!(super::attachment_name(&headers), None);
+
+        headers.insert("Digest", HeaderValue::from_static("sha256=ABC123"));
+        headers.insert(
+            CONTENT_DISPOSITION,
+            HeaderValue::from_static(r#"attachment; filename="weights.bin""#),
+        );
+        assert_eq!(super::sha256_digest(&headers), Some("abc123".into()));
+        assert_eq!(super::attachment_name(&headers), Some("weights.bin".into()));
+
+        headers.insert("Digest", HeaderValue::from_static("md5=abc123"));
+        assert_eq!(super::sha256_digest(&headers), None);
+    }
+
+    #[test]
+    fn resumable_chunks() -> anyhow::Result<()> {
+        use sha2::{Digest, Sha256};
+
+        let mut data = std::io::Cursor::new(b"hello world".to_vec());
+        assert_eq!(
+            super::digest_reader(&mut data)?,
+            (11, hex::encode(Sha256::digest(b"hello world")))
+        );
+        assert_eq!(super::read_chunk(&mut data, 1, 4)?, b"hell");
+        assert_eq!(super::read_chunk(&mut data, 3, 4)?, b"rld");
+        assert_eq!(super::read_chunk(&mut data, 2, 4)?, b"o wo");
+        assert!(super::read_chunk(&mut data, 4, 4)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn retry_delays() {
+        let delays: Vec<_> = (2..10)
+            .map(|attempt| super::retry_delay(attempt).as_millis())
+            .collect();
+        assert_eq!(delays, vec![250, 500, 1000, 2000, 4000, 8000, 8000, 8000]);
+    }
+
     #[test]
     fn upload_experiment_ser() -> anyhow::Result<()> {
         let up = super::UploadExperiment::new(
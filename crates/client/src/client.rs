@@ -48,9 +48,25 @@ pub enum Error {
     #[error("Base url error: {0}")]
     BaseUrlError(#[from] url::ParseError),
 
+    #[error("Invalid api key: {0}")]
+    ApiKeyError(#[from] reqwest::header::InvalidHeaderValue),
+
     #[error("Upload error: {0}")]
     UploadError(reqwest::Error),
 
+    #[error("Integrity error: expected sha256 {expect}, received {receive}")]
+    IntegrityError { expect: String, receive: String },
+
+    #[error("Cannot resume upload {ticket}: it expects {expect} bytes, the local artifact has {receive}")]
+    ResumeError {
+        ticket: i64,
+        expect: i64,
+        receive: u64,
+    },
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
@@ -78,7 +94,10 @@ impl Into<wasm_bindgen::JsValue> for Error {
 #[cfg(feature = "python")]
 impl From<Error> for pyo3::PyErr {
     fn from(value: Error) -> PyErr {
-        pyo3::PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(value.to_string())
+        match value {
+            Error::IntegrityError { .. } => crate::py::IntegrityError::new_err(value.to_string()),
+            _ => pyo3::PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(value.to_string()),
+        }
     }
 }
 
@@ -95,6 +114,14 @@ fn raise_for<T: Debug>(result: GraphQlResponse<T, ErrorExt>) -> Result<T> {
     }
 }
 
+const CHUNK_ATTEMPTS: usize = 3;
+
+struct Downloaded {
+    name: Option<String>,
+    sha256: String,
+    size: u64,
+}
+
 impl Client {
     pub async fn upload<'a, D: Serialize, R: DeserializeOwned>(
         &self,
@@ -142,6 +169,224 @@ impl Client {
             .await?)
     }
 
+    /// checks what was received against the `Digest` the server sends, failing without one
+    async fn download_into<W: tokio::io::AsyncWrite + Unpin>(
+        &self,
+        url: &str,
+        artifact_id: i64,
+        out: &mut W,
+    ) -> Result<Downloaded> {
+        use sha2::{Digest, Sha256};
+        use tokio::io::AsyncWriteExt;
+
+        let url = self.base_url.join(url)?;
+        let mut response = self
+            .client
+            .get(url)
+            .query(&[("artifact_id", artifact_id)])
+            .send()
+            .await?;
+        if let Err(err) = response.error_for_status_ref().map(|_| ()) {
+            return Err(match response.json::<crate::maybe::ServerError>().await {
+                Ok(server) => server.into(),
+                Err(_) => err.into(),
+            });
+        }
+
+        let expect = artifacts::sha256_digest(response.headers());
+        let name = artifacts::attachment_name(response.headers());
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+            out.write_all(&chunk).await?;
+        }
+        out.flush().await?;
+
+        let sha256 = hex::encode(hasher.finalize());
+        match expect {
+            Some(expect) if expect == sha256 => Ok(Downloaded { name, sha256, size }),
+            expect => Err(Error::IntegrityError {
+                expect: expect.unwrap_or_else(|| "(no Digest header)".into()),
+                receive: sha256,
+            }),
+        }
+    }
+
+    async fn download(&self, url: &str, artifact_id: i64) -> Result<artifacts::DownloadedArtifact> {
+        let mut data = vec![];
+        let downloaded = self.download_into(url, artifact_id, &mut data).await?;
+        Ok(artifacts::DownloadedArtifact {
+            name: downloaded.name,
+            sha256: downloaded.sha256,
+            size: downloaded.size,
+            data,
+        })
+    }
+
+    /// downloads next to `path` first, only replacing it once the download was verified
+    #[cfg(not(feature = "wasm"))]
+    async fn download_file(
+        &self,
+        url: &str,
+        artifact_id: i64,
+        path: String,
+    ) -> Result<artifacts::DownloadedFile> {
+        let target = std::path::Path::new(&path);
+        let partial = artifacts::partial_path(target);
+        let mut file = tokio::io::BufWriter::new(tokio::fs::File::create(&partial).await?);
+        let downloaded = self.download_into(url, artifact_id, &mut file).await;
+        drop(file);
+        let downloaded = match downloaded {
+            Ok(downloaded) => tokio::fs::rename(&partial, target)
+                .await
+                .map(|_| downloaded)
+                .map_err(Error::from),
+            Err(err) => Err(err),
+        };
+        match downloaded {
+            Ok(downloaded) => Ok(artifacts::DownloadedFile {
+                name: downloaded.name,
+                sha256: downloaded.sha256,
+                size: downloaded.size,
+                path,
+            }),
+            Err(err) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                Err(err)
+            }
+        }
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    pub async fn download_model_version_artifact_to_file(
+        &self,
+        artifact_id: i64,
+        path: String,
+    ) -> Result<artifacts::DownloadedFile> {
+        self.download_file("/download/model-version-artifact", artifact_id, path)
+            .await
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    pub async fn download_experiment_artifact_to_file(
+        &self,
+        artifact_id: i64,
+        path: String,
+    ) -> Result<artifacts::DownloadedFile> {
+        self.download_file("/download/experiment-artifact", artifact_id, path)
+            .await
+    }
+
+    async fn send<R: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<ServerResult<R>> {
+        Ok(request.send().await?.json().await?)
+    }
+
+    /// uploads in chunks retried on their own, passing the `ticket` of an interrupted upload resumes it
+    #[cfg(not(feature = "wasm"))]
+    pub async fn upload_model_version_artifact_resumable(
+        &self,
+        artifact: artifacts::UploadModelVersionArgs,
+        path: String,
+        ticket: Option<i64>,
+    ) -> Result<artifacts::ModelArtifactResponse> {
+        let mut file = std::fs::File::open(path)?;
+        self.upload_resumable(artifact, &mut file, ticket).await
+    }
+
+    /// uploads what `data` reads through a resumable upload, reading one chunk at a time
+    #[cfg(not(feature = "wasm"))]
+    pub async fn upload_resumable<R: std::io::Read + std::io::Seek + Send>(
+        &self,
+        artifact: artifacts::UploadModelVersionArgs,
+        data: &mut R,
+        ticket: Option<i64>,
+    ) -> Result<artifacts::ModelArtifactResponse> {
+        use sha2::{Digest, Sha256};
+        use std::io::SeekFrom;
+
+        let upload: artifacts::ResumableUpload = match ticket {
+            Some(ticket) => {
+                let url = self.base_url.join(&format!("/upload/resumable/{ticket}"))?;
+                let upload: artifacts::ResumableUpload = self
+                    .send(self.client.get(url))
+                    .await?
+                    .map_err(Error::from)?;
+                let size = data.seek(SeekFrom::End(0))?;
+                if size != upload.ticket.size as u64 {
+                    return Err(Error::ResumeError {
+                        ticket,
+                        expect: upload.ticket.size,
+                        receive: size,
+                    });
+                }
+                upload
+            }
+            None => {
+                let (size, sha256) = artifacts::digest_reader(data)?;
+                let created: ServerResult<artifacts::ResumableUpload> = self
+                    .post(
+                        "/upload/resumable",
+                        artifacts::CreateResumableUpload {
+                            artifact,
+                            size: size as i64,
+                            sha256,
+                            chunk_size: None,
+                        },
+                    )
+                    .await?;
+                created.map_err(Error::from)?
+            }
+        };
+
+        let id = upload.ticket.id;
+        let chunk_size = upload.ticket.chunk_size.ok_or(Error::EmptyResult)? as u64;
+        let chunks = (upload.ticket.size as u64).div_ceil(chunk_size) as i32;
+        for part in 1..=chunks {
+            if upload.received.contains(&part) {
+                continue;
+            }
+            let chunk = artifacts::read_chunk(data, part, chunk_size)?;
+            let url = self
+                .base_url
+                .join(&format!("/upload/resumable/{id}/{part}"))?;
+            let digest = format!("sha256={}", hex::encode(Sha256::digest(&chunk)));
+            let mut attempt = 1;
+            loop {
+                let request = self
+                    .client
+                    .put(url.clone())
+                    .header("Digest", &digest)
+                    .body(chunk.clone());
+                match self.send::<serde_json::Value>(request).await {
+                    Ok(sent) => {
+                        sent.map_err(Error::from)?;
+                        break;
+                    }
+                    Err(err) if attempt < CHUNK_ATTEMPTS => {
+                        attempt += 1;
+                        let delay = artifacts::retry_delay(attempt);
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("retrying chunk {part} of upload {id} in {delay:?}: {err}");
+                        #[cfg(not(feature = "tracing"))]
+                        let _ = err;
+                        tokio::time::sleep(delay).await;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        let url = self
+            .base_url
+            .join(&format!("/upload/resumable/{id}/complete"))?;
+        self.send(self.client.post(url)).await?.map_err(Error::from)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip(self)))]
     #[inline]
     pub async fn perform_mutation<Vars, M: MutationBuilder<Vars> + DeserializeOwned>(
@@ -172,14 +417,29 @@ impl Client {
 
     #[cfg(not(feature = "wasm"))]
     pub fn new(base_url: &str) -> Result<Client> {
-        Client::new_common(base_url)
+        Client::new_common(base_url, None)
+    }
+
+    /// a client which sends `api_key` as a bearer token on every request
+    #[cfg(not(feature = "wasm"))]
+    pub fn with_api_key(base_url: &str, api_key: &str) -> Result<Client> {
+        Client::new_common(base_url, Some(api_key))
     }
 
     #[inline]
-    pub(crate) fn new_common(base_url: &str) -> Result<Self> {
+    pub(crate) fn new_common(base_url: &str, api_key: Option<&str>) -> Result<Self> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(api_key) = api_key {
+            let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {api_key}"))?;
+            value.set_sensitive(true);
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
         Ok(Self {
             base_url: base_url.parse()?,
-            client: reqwest::ClientBuilder::new().build().expect("ok"),
+            client: reqwest::ClientBuilder::new()
+                .default_headers(headers)
+                .build()
+                .expect("ok"),
         })
     }
 }
@@ -188,8 +448,8 @@ impl Client {
 impl Client {
     #[cfg(feature = "wasm")]
     #[wasm_bindgen(constructor)]
-    pub fn new(base_url: &str) -> Result<Client> {
-        Client::new_common(base_url)
+    pub fn new(base_url: &str, api_key: Option<String>) -> Result<Client> {
+        Client::new_common(base_url, api_key.as_deref())
     }
 
     #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "uploadExperimentArtifact"))]
@@ -205,6 +465,39 @@ impl Client {
             .map_err(Error::from)?)
     }
 
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "uploadModelVersionArtifact"))]
+    pub async fn upload_model_version_artifact(
+        &self,
+        artifact: crate::artifacts::UploadModelVersionArgs,
+        data: Vec<u8>,
+    ) -> Result<artifacts::ModelArtifactResponse> {
+        let command = CommandDescriptor::new(artifact, data);
+        self.upload("/upload/model-version-artifact", command)
+            .await?
+            .map_err(Error::from)
+    }
+
+    #[cfg_attr(
+        feature = "wasm",
+        wasm_bindgen(js_name = "downloadModelVersionArtifact")
+    )]
+    pub async fn download_model_version_artifact(
+        &self,
+        artifact_id: i64,
+    ) -> Result<artifacts::DownloadedArtifact> {
+        self.download("/download/model-version-artifact", artifact_id)
+            .await
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "downloadExperimentArtifact"))]
+    pub async fn download_experiment_artifact(
+        &self,
+        artifact_id: i64,
+    ) -> Result<artifacts::DownloadedArtifact> {
+        self.download("/download/experiment-artifact", artifact_id)
+            .await
+    }
+
     #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "createBucket"))]
     pub async fn create_bucket(
         &self,
@@ -309,6 +602,65 @@ impl Client {
         self.perform_mutation(experiment).await
     }
 
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "startExperiment"))]
+    pub async fn start_experiment(
+        &self,
+        run: update_experiment_state::ExperimentRunVariables,
+    ) -> Result<update_experiment_state::StartExperiment> {
+        self.perform_mutation(run).await
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "passExperiment"))]
+    pub async fn pass_experiment(
+        &self,
+        run: update_experiment_state::ExperimentFinishVariables,
+    ) -> Result<update_experiment_state::PassExperiment> {
+        self.perform_mutation(run).await
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "failExperiment"))]
+    pub async fn fail_experiment(
+        &self,
+        run: update_experiment_state::ExperimentFinishVariables,
+    ) -> Result<update_experiment_state::FailExperiment> {
+        self.perform_mutation(run).await
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "retryExperiment"))]
+    pub async fn retry_experiment(
+        &self,
+        run: update_experiment_state::ExperimentRunVariables,
+    ) -> Result<update_experiment_state::RetryExperiment> {
+        self.perform_mutation(run).await
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "logMetrics"))]
+    pub async fn log_metrics(
+        &self,
+        metrics: log_metrics::LogMetricsVariables,
+    ) -> Result<log_metrics::LogMetrics> {
+        self.perform_mutation(metrics).await
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "setExperimentParams"))]
+    pub async fn set_experiment_params(
+        &self,
+        params: set_experiment_params::SetExperimentParamsVariables,
+    ) -> Result<set_experiment_params::SetExperimentParams> {
+        self.perform_mutation(params).await
+    }
+
+    #[cfg_attr(
+        feature = "wasm",
+        wasm_bindgen(js_name = "recordExperimentEnvironment")
+    )]
+    pub async fn record_experiment_environment(
+        &self,
+        environment: record_experiment_environment::RecordExperimentEnvironmentVariables,
+    ) -> Result<record_experiment_environment::RecordExperimentEnvironment> {
+        self.perform_mutation(environment).await
+    }
+
     #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "queryNamespaces"))]
     pub async fn query_namespaces(
         &self,
@@ -341,3 +693,59 @@ impl Client {
         self.perform_query(vars).await
     }
 }
+
+#[cfg(all(test, not(feature = "wasm")))]
+mod test {
+    use sha2::{Digest, Sha256};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{Client, Error};
+
+    // answers every request with `body`, declaring the sha256 `digest`
+    async fn serve(body: &'static [u8], digest: String) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 4096];
+                let _ = socket.read(&mut request).await;
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\ndigest: sha256={digest}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(body).await;
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_download_file_replaces_once_verified() {
+        let dir = std::env::temp_dir().join(format!("flymodel-download-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("weights.bin");
+        std::fs::write(&path, b"previous").unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let corrupt = Client::new(&serve(b"received", "00".repeat(32)).await).unwrap();
+        let err = corrupt
+            .download_model_version_artifact_to_file(1, path.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::IntegrityError { .. }), "{err}");
+        assert_eq!(std::fs::read(&path).unwrap(), b"previous");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let digest = hex::encode(Sha256::digest(b"received"));
+        let client = Client::new(&serve(b"received", digest.clone()).await).unwrap();
+        let downloaded = client
+            .download_model_version_artifact_to_file(1, path.clone())
+            .await
+            .unwrap();
+        assert_eq!(downloaded.sha256, digest);
+        assert_eq!(std::fs::read(&path).unwrap(), b"received");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+use std::{path::Path, process::Command};
+
+use flymodel_graphql::{
+    enums::PackageEcosystem,
+    gql::record_experiment_environment::{EnvVarInput, EnvironmentInput, PackageInput},
+};
+
+/// shared unless an experiment allows others, a trailing `*` matches any suffix
+pub const DEFAULT_ENV_ALLOWLIST: &[&str] = &[
+    "CUDA_*",
+    "NVIDIA_VISIBLE_DEVICES",
+    "OMP_NUM_THREADS",
+    "MKL_NUM_THREADS",
+    "PYTHONHASHSEED",
+    "LANG",
+    "TZ",
+];
+
+pub(crate) fn default_allowlist() -> Vec<String> {
+    DEFAULT_ENV_ALLOWLIST
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .collect()
+}
+
+fn allowed(name: &str, allowlist: &[String]) -> bool {
+    allowlist
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        })
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn hostname() -> Option<String> {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+fn locked_packages(lock: &str) -> Vec<PackageInput> {
+    fn quoted<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+        line.strip_prefix(key)?
+            .trim_start()
+            .strip_prefix('=')?
+            .trim()
+            .strip_prefix('"')?
+            .strip_suffix('"')
+    }
+    let mut packages = Vec::new();
+    let mut name = None;
+    for line in lock.lines().map(str::trim) {
+        if line == "[[package]]" {
+            name = None;
+        } else if let Some(value) = quoted(line, "name") {
+            name = Some(value.to_string());
+        } else if let (Some(version), Some(name)) = (quoted(line, "version"), name.take()) {
+            packages.push(PackageInput {
+                ecosystem: PackageEcosystem::Rust,
+                name,
+                version: version.to_string(),
+            });
+        }
+    }
+    packages
+}
+
+fn rust_packages() -> Vec<PackageInput> {
+    let Ok(cwd) = std::env::current_dir() else {
+        return Vec::new();
+    };
+    cwd.ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lock| Path::is_file(lock))
+        .and_then(|lock| std::fs::read_to_string(lock).ok())
+        .map(|lock| locked_packages(&lock))
+        .unwrap_or_default()
+}
+
+pub(crate) fn capture(allowlist: &[String], packages: Vec<PackageInput>) -> EnvironmentInput {
+    let git_commit = git(&["rev-parse", "HEAD"]);
+    let git_dirty = git_commit
+        .as_ref()
+        .and_then(|_| git(&["status", "--porcelain"]))
+        .map(|status| !status.is_empty());
+    let mut variables: Vec<EnvVarInput> = std::env::vars()
+        .filter(|(name, _)| allowed(name, allowlist))
+        .map(|(name, value)| EnvVarInput { name, value })
+        .collect();
+    variables.sort_by(|a, b| a.name.cmp(&b.name));
+    EnvironmentInput {
+        git_commit,
+        git_dirty,
+        hostname: hostname(),
+        packages: rust_packages().into_iter().chain(packages).collect(),
+        variables,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{allowed, default_allowlist, locked_packages};
+
+    #[test]
+    fn test_allowed() {
+        let allowlist = default_allowlist();
+        assert!(allowed("CUDA_VISIBLE_DEVICES", &allowlist));
+        assert!(allowed("TZ", &allowlist));
+        assert!(!allowed("TZDIR", &allowlist));
+        assert!(!allowed("AWS_SECRET_ACCESS_KEY", &allowlist));
+    }
+
+    #[test]
+    fn test_locked_packages() {
+        let packages = locked_packages(
+            r#"
+version = 3
+
+[[package]]
+name = "serde"
+version = "1.0.193"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "flymodel"
+version = "0.1.0-beta1"
+dependencies = [
+ "serde",
+]
+"#,
+        );
+        let packages: Vec<_> = packages
+            .iter()
+            .map(|package| (package.name.as_str(), package.version.as_str()))
+            .collect();
+        assert_eq!(
+            packages,
+            vec![("serde", "1.0.193"), ("flymodel", "0.1.0-beta1")]
+        );
+    }
+}
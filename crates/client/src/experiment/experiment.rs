@@ -1,7 +1,14 @@
-use super::state::*;
+use super::{
+    metrics::{metric, MetricBuffer},
+    state::*,
+};
 use crate::artifacts::{self, PartialUploadExperimentArgs};
 use flymodel_graphql::gql::create_experiment;
+#[cfg(not(feature = "wasm"))]
+use flymodel_graphql::gql::record_experiment_environment::RecordExperimentEnvironmentVariables;
 use rust_fsm::*;
+#[cfg(not(feature = "wasm"))]
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -22,6 +29,10 @@ pub struct Experiment {
     experiment: Arc<flymodel_graphql::gql::create_experiment::Experiment>,
     client: Arc<crate::client::Client>,
     state: Arc<Mutex<StateMachine<ExperimentState>>>,
+    clock: RunClock,
+    metrics: MetricBuffer,
+    #[cfg(not(feature = "wasm"))]
+    env_allowlist: Arc<Vec<String>>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -38,6 +49,10 @@ pub enum ExperimentError {
     #[error("Read state error: {0}")]
     StateReadError(#[from] tokio::sync::TryLockError),
 
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("Environment capture error: {0}")]
+    CaptureError(#[from] tokio::task::JoinError),
+
     #[cfg(feature = "wasm")]
     #[error("Js runtime error: {0}")]
     JsRuntimeError(String),
@@ -66,6 +81,9 @@ impl Experiment {
         Ok(Self {
             client,
             state,
+            clock: RunClock::default(),
+            metrics: MetricBuffer::default(),
+            env_allowlist: Arc::new(super::environment::default_allowlist()),
             experiment: Arc::new(experiment.create_experiment),
         })
     }
@@ -82,19 +100,53 @@ impl Experiment {
         Ok(Self {
             client,
             state,
+            clock: RunClock::default(),
+            metrics: MetricBuffer::default(),
             experiment: Arc::new(experiment.create_experiment),
         })
     }
 
-    #[allow(dead_code)]
     async fn consume(&self, state: ExperimentStateInput) -> Result<(), ExperimentError> {
-        consume_mu(self.state.clone(), state).await
+        let entered = consume_mu(self.state.clone(), state).await?;
+        report(&self.client, self.experiment.id, entered, &self.clock).await
+    }
+
+    fn ensure_tests(&self, action: &str) -> Result<(), ExperimentError> {
+        if !matches!(
+            self.state.clone().try_lock()?.state(),
+            ExperimentStateState::Tests
+        ) {
+            return Err(ExperimentError::InvalidStateError(format!(
+                "Cannot {action} in non-started state",
+            )));
+        }
+        Ok(())
+    }
+
+    async fn close<T>(&self, res: Result<T, ExperimentError>) -> Result<T, ExperimentError> {
+        // the run passed even when its metrics could not be sent, which is returned after
+        let flushed = self.flush().await;
+        match res {
+            Ok(res) => {
+                self.consume(ExperimentStateInput::WaitClose).await?;
+                flushed?;
+                Ok(res)
+            }
+            Err(err) => {
+                if let Err(flush) = flushed {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("failed to send the metrics of the experiment: {flush}");
+                    #[cfg(not(feature = "tracing"))]
+                    let _ = flush;
+                }
+                self.consume(ExperimentStateInput::Failed).await?;
+                Err(err)
+            }
+        }
     }
 
     #[cfg(feature = "wasm")]
-    async fn run_unguarded(self, experiment_fn: &js_sys::Function) -> Result<(), ExperimentError> {
-        self.consume(ExperimentStateInput::Started).await?;
-        self.consume(ExperimentStateInput::Entered).await?;
+    async fn call(&self, experiment_fn: &js_sys::Function) -> Result<(), ExperimentError> {
         let value = wasm_bindgen::JsValue::null();
         tracing::debug!("function: {:#?}", experiment_fn);
         let fut = experiment_fn
@@ -108,13 +160,65 @@ impl Experiment {
         } else {
             tracing::debug!("complete");
         }
-        self.consume(ExperimentStateInput::WaitClose).await?;
         Ok(())
     }
 
     #[cfg(feature = "wasm")]
     pub async fn run(self, experiment_fn: &js_sys::Function) -> Result<(), ExperimentError> {
-        Ok(self.run_unguarded(experiment_fn).await?)
+        self.consume(ExperimentStateInput::Started).await?;
+        self.consume(ExperimentStateInput::Entered).await?;
+        let res = self.call(experiment_fn).await;
+        self.close(res).await
+    }
+
+    /// also captures the environment variables matching `patterns`, such as `WANDB_*`
+    #[cfg(not(feature = "wasm"))]
+    pub fn allow_env<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Arc::make_mut(&mut self.env_allowlist).extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    async fn record_environment(&self) -> Result<(), ExperimentError> {
+        let allowlist = self.env_allowlist.clone();
+        // capturing shells out to git, which would stall the runtime
+        let environment = tokio::task::spawn_blocking(move || {
+            super::environment::capture(&allowlist, Vec::new())
+        })
+        .await?;
+        self.client
+            .record_experiment_environment(RecordExperimentEnvironmentVariables {
+                experiment: self.experiment.id,
+                environment,
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// runs `experiment_fn`, reporting whether it passed. failing to record the environment only warns
+    #[cfg(not(feature = "wasm"))]
+    pub async fn run<F, Fut, T>(self, experiment_fn: F) -> Result<T, ExperimentError>
+    where
+        F: FnOnce(Experiment) -> Fut,
+        Fut: std::future::Future<Output = Result<T, ExperimentError>>,
+    {
+        self.consume(ExperimentStateInput::Started).await?;
+        self.consume(ExperimentStateInput::Entered).await?;
+        if let Err(err) = self.record_environment().await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "failed to record the environment of experiment {}: {err}",
+                self.experiment.id
+            );
+            #[cfg(not(feature = "tracing"))]
+            let _ = err;
+        }
+        let res = experiment_fn(self.clone()).await;
+        self.close(res).await
     }
 
     #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "saveArtifact"))]
@@ -123,18 +227,75 @@ impl Experiment {
         artifact: PartialUploadExperimentArgs,
         data: Vec<u8>,
     ) -> Result<artifacts::ExperimentResponse, ExperimentError> {
-        if !matches!(
-            self.state.clone().try_lock()?.state(),
-            ExperimentStateState::Tests
-        ) {
-            return Err(ExperimentError::InvalidStateError(format!(
-                "Cannot save an artifact in non-started state",
-            )));
-        }
+        self.ensure_tests("save an artifact")?;
         Ok(self
             .client
             .upload_experiment_artifact(artifact.with_context(self.experiment.id.into()), data)
             .await
             .map_err(ExperimentError::from)?)
     }
+
+    /// buffers a metric measured now, sent with the next flush
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "logMetric"))]
+    pub async fn log_metric(
+        &self,
+        key: String,
+        value: f64,
+        step: Option<i32>,
+    ) -> Result<(), ExperimentError> {
+        self.ensure_tests("log a metric")?;
+        Ok(self
+            .metrics
+            .push(&self.client, self.experiment.id, [metric(key, value, step)])
+            .await?)
+    }
+
+    /// buffers a value for each key, all measured now at the same `step`
+    #[cfg(not(feature = "wasm"))]
+    pub async fn log_metrics(
+        &self,
+        metrics: HashMap<String, f64>,
+        step: Option<i32>,
+    ) -> Result<(), ExperimentError> {
+        self.ensure_tests("log metrics")?;
+        Ok(self
+            .metrics
+            .push(
+                &self.client,
+                self.experiment.id,
+                metrics
+                    .into_iter()
+                    .map(|(key, value)| metric(key, value, step)),
+            )
+            .await?)
+    }
+
+    /// buffers a value for each key of `metrics`, an object of numbers, all measured now
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = "logMetrics")]
+    pub async fn log_metrics(
+        &self,
+        metrics: JsValue,
+        step: Option<i32>,
+    ) -> Result<(), ExperimentError> {
+        self.ensure_tests("log metrics")?;
+        let metrics: std::collections::HashMap<String, f64> =
+            serde_wasm_bindgen::from_value(metrics)
+                .map_err(|err| ExperimentError::JsRuntimeError(err.to_string()))?;
+        Ok(self
+            .metrics
+            .push(
+                &self.client,
+                self.experiment.id,
+                metrics
+                    .into_iter()
+                    .map(|(key, value)| metric(key, value, step)),
+            )
+            .await?)
+    }
+
+    /// sends the buffered metrics, returning how many the server stored
+    pub async fn flush(&self) -> Result<i32, ExperimentError> {
+        Ok(self.metrics.flush(&self.client, self.experiment.id).await?)
+    }
 }
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use flymodel_graphql::gql::log_metrics::{LogMetricsVariables, MetricInput};
+use tokio::sync::Mutex;
+
+use crate::client::{Client, Error};
+
+pub(crate) const FLUSH_AT: usize = 100;
+
+const MAX_BATCH: usize = 1000;
+
+pub(crate) fn metric(key: String, value: f64, step: Option<i32>) -> MetricInput {
+    MetricInput {
+        key,
+        value,
+        step,
+        timestamp: Some(chrono::Utc::now()),
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct MetricBuffer(Arc<Mutex<Vec<MetricInput>>>);
+
+impl MetricBuffer {
+    pub(crate) async fn push(
+        &self,
+        client: &Client,
+        experiment: i32,
+        metrics: impl IntoIterator<Item = MetricInput>,
+    ) -> Result<(), Error> {
+        let pending = {
+            let mut buffered = self.0.lock().await;
+            buffered.extend(metrics);
+            buffered.len()
+        };
+        if pending >= FLUSH_AT {
+            self.flush(client, experiment).await?;
+        }
+        Ok(())
+    }
+
+    /// the metrics of a batch the server did not accept are kept for the next flush
+    pub(crate) async fn flush(&self, client: &Client, experiment: i32) -> Result<i32, Error> {
+        let mut buffered = self.0.lock().await;
+        let mut stored = 0;
+        while !buffered.is_empty() {
+            let size = buffered.len().min(MAX_BATCH);
+            let rest = buffered.split_off(size);
+            let batch = std::mem::replace(&mut *buffered, rest);
+            match client
+                .log_metrics(LogMetricsVariables {
+                    experiment,
+                    metrics: batch.clone(),
+                })
+                .await
+            {
+                Ok(logged) => stored += logged.log_metrics,
+                Err(err) => {
+                    let rest = std::mem::replace(&mut *buffered, batch);
+                    buffered.extend(rest);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(stored)
+    }
+
+    #[cfg(test)]
+    async fn pending(&self) -> usize {
+        self.0.lock().await.len()
+    }
+}
+
+#[cfg(all(test, not(feature = "wasm")))]
+mod test {
+    use super::{metric, MetricBuffer, FLUSH_AT};
+    use crate::client::Client;
+
+    #[tokio::test]
+    async fn test_buffer_kept_on_failure() {
+        // nothing listens on the port, so every flush fails
+        let client = Client::new("http://127.0.0.1:9").unwrap();
+        let buffer = MetricBuffer::default();
+        buffer
+            .push(
+                &client,
+                1,
+                (0..3).map(|step| metric("loss".into(), 0.1, Some(step))),
+            )
+            .await
+            .unwrap();
+        assert_eq!(buffer.pending().await, 3);
+
+        assert!(buffer
+            .push(
+                &client,
+                1,
+                (0..FLUSH_AT).map(|_| metric("acc".into(), 0.9, None))
+            )
+            .await
+            .is_err());
+        assert_eq!(buffer.pending().await, FLUSH_AT + 3);
+        assert!(buffer.flush(&client, 1).await.is_err());
+        assert_eq!(buffer.pending().await, FLUSH_AT + 3);
+    }
+}
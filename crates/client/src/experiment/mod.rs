@@ -1,3 +1,6 @@
+#[cfg(not(feature = "wasm"))]
+pub mod environment;
 pub(crate) mod experiment;
+pub(crate) mod metrics;
 pub mod state;
 pub use experiment::Experiment;
@@ -1,15 +1,19 @@
 #![allow(dead_code)]
 use std::sync::Arc;
 
+use flymodel_graphql::gql::update_experiment_state::{
+    ExperimentFinishVariables, ExperimentRunVariables,
+};
 use rust_fsm::*;
 use tokio::sync::Mutex;
 #[cfg(feature = "tracing")]
 use tracing::trace;
 
 use super::experiment::ExperimentError;
+use crate::client::Client;
 
 state_machine! {
-    derive(Debug)
+    derive(Debug, Clone, Copy, PartialEq)
 
     pub(crate) ExperimentState(Init)
 
@@ -25,14 +29,81 @@ state_machine! {
 pub(crate) async fn consume_mu(
     this: Arc<Mutex<StateMachine<ExperimentState>>>,
     state: ExperimentStateInput,
-) -> Result<(), ExperimentError> {
+) -> Result<ExperimentStateState, ExperimentError> {
     let mut this = this.lock_owned().await;
     #[cfg(feature = "tracing")]
     trace!(name: "experiment-state", "before: {:#?} -> maybe: {:#?}", this.state(), state);
     this.consume(&state)?;
     #[cfg(feature = "tracing")]
     trace!(name: "experiment-state", "after: {:#?}", this.state());
+    let entered = *this.state();
     drop(this);
+    Ok(entered)
+}
+
+fn now_ms() -> f64 {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "wasm")] {
+            js_sys::Date::now()
+        } else {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|since| since.as_secs_f64() * 1000.0)
+                .unwrap_or_default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RunClock(Arc<std::sync::Mutex<Option<f64>>>);
+
+impl RunClock {
+    fn start(&self) {
+        if let Ok(mut started) = self.0.lock() {
+            *started = Some(now_ms());
+        }
+    }
+
+    fn elapsed_ms(&self) -> Option<i32> {
+        let started = (*self.0.lock().ok()?)?;
+        Some((now_ms() - started).clamp(0.0, i32::MAX as f64) as i32)
+    }
+}
+
+/// only entering the tests, passing & closing are reported, the server records the rest
+pub(crate) async fn report(
+    client: &Client,
+    experiment: i32,
+    entered: ExperimentStateState,
+    clock: &RunClock,
+) -> Result<(), ExperimentError> {
+    match entered {
+        ExperimentStateState::Tests => {
+            clock.start();
+            client
+                .start_experiment(ExperimentRunVariables { id: experiment })
+                .await?;
+        }
+        ExperimentStateState::Pass => {
+            client
+                .pass_experiment(ExperimentFinishVariables {
+                    id: experiment,
+                    duration_ms: clock.elapsed_ms(),
+                })
+                .await?;
+        }
+        ExperimentStateState::Closed => {
+            client
+                .fail_experiment(ExperimentFinishVariables {
+                    id: experiment,
+                    duration_ms: clock.elapsed_ms(),
+                })
+                .await?;
+        }
+        ExperimentStateState::Init
+        | ExperimentStateState::Running
+        | ExperimentStateState::Termination => {}
+    }
     Ok(())
 }
 
@@ -65,6 +136,14 @@ mod test {
             .expect_err("must fail");
     }
 
+    #[test]
+    fn test_run_clock() {
+        let clock = RunClock::default();
+        assert_eq!(clock.elapsed_ms(), None);
+        clock.start();
+        assert!(clock.elapsed_ms().is_some_and(|elapsed| elapsed >= 0));
+    }
+
     #[test]
     fn test_invalid_path_no_experiment_reuse() {
         let mut state: StateMachine<ExperimentState> = StateMachine::new();
@@ -10,6 +10,13 @@ pub mod experiment;
 
 static INIT: Once = Once::new();
 
+pyo3::create_exception!(
+    client,
+    IntegrityError,
+    pyo3::exceptions::PyValueError,
+    "a downloaded artifact did not match the digest sent by the server"
+);
+
 tokio::task_local! {
     static TASKS: once_cell::unsync::OnceCell<pyo3_asyncio::TaskLocals>;
 }
@@ -23,6 +30,10 @@ fn client(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<artifacts::UploadModelVersionArgs>()?;
     m.add_class::<artifacts::PartialUploadModelVersionArgs>()?;
     m.add_class::<artifacts::UploadRequestParams>()?;
+    m.add_class::<artifacts::ModelArtifactResponse>()?;
+    m.add_class::<artifacts::DownloadedArtifact>()?;
+    m.add_class::<artifacts::DownloadedFile>()?;
+    m.add("IntegrityError", py.get_type::<IntegrityError>())?;
 
     m.add_submodule(flymodel_graphql::py::submodule(py)?)?;
     Ok(())
@@ -149,21 +160,23 @@ macro_rules! impl_associated_futures {
             #[pymethods]
             impl PythonClient {
                 #[new]
+                #[pyo3(signature = (base_url, api_key = None))]
                 pub fn new(
                         base_url: String,
+                        api_key: Option<String>,
                     ) -> Result<Self> {
                     Ok(PythonClient{
-                        shared: Arc::new(Client::new_common(&base_url)?),
+                        shared: Arc::new(Client::new_common(&base_url, api_key.as_deref())?),
                         rt: Runtime::new(),
                     })
                 }
 
                 $(
-                    pub fn $name<'py>(&self, py: Python<'py>, $($arg: $typ)*) -> PyResult<&'py PyAny> {
+                    pub fn $name<'py>(&self, py: Python<'py>, $($arg: $typ),*) -> PyResult<&'py PyAny> {
                         let client = self.shared.clone();
                         let handle = self.rt.handle.clone();
                         self.rt.pyfut(py, async move {
-                            let res = client.$name($($arg)*).await?;
+                            let res = client.$name($($arg),*).await?;
                             Ok(res)
                         }).map(|re| {
                             drop(handle);
@@ -223,6 +236,20 @@ impl_associated_futures! {
         experiment: delete_experiment::DeleteExperimentVariables,
     ) -> Result<delete_experiment::DeleteExperiment>,
 
+    pub async fn start_experiment(&self, run: update_experiment_state::ExperimentRunVariables) -> Result<update_experiment_state::StartExperiment>,
+
+    pub async fn pass_experiment(&self, run: update_experiment_state::ExperimentFinishVariables) -> Result<update_experiment_state::PassExperiment>,
+
+    pub async fn fail_experiment(&self, run: update_experiment_state::ExperimentFinishVariables) -> Result<update_experiment_state::FailExperiment>,
+
+    pub async fn retry_experiment(&self, run: update_experiment_state::ExperimentRunVariables) -> Result<update_experiment_state::RetryExperiment>,
+
+    pub async fn log_metrics(&self, metrics: log_metrics::LogMetricsVariables) -> Result<log_metrics::LogMetrics>,
+
+    pub async fn set_experiment_params(&self, params: set_experiment_params::SetExperimentParamsVariables) -> Result<set_experiment_params::SetExperimentParams>,
+
+    pub async fn record_experiment_environment(&self, environment: record_experiment_environment::RecordExperimentEnvironmentVariables) -> Result<record_experiment_environment::RecordExperimentEnvironment>,
+
     pub async fn query_namespaces(&self, vars: query_namespaces::QueryNamespacesVariables) -> Result<query_namespaces::QueryNamespaces> ,
 
     pub async fn query_buckets(&self, vars: query_buckets::QueryBucketsVariables) -> Result<query_buckets::QueryBuckets>,
@@ -233,4 +260,33 @@ impl_associated_futures! {
     ) -> Result<query_models::NamespaceModels>,
 
     pub async fn query_experiment(&self, vars: query_experiment::QueryExperimentVariables) -> Result<query_experiment::QueryExperiment>,
+
+    pub async fn upload_model_version_artifact(
+        &self,
+        artifact: artifacts::UploadModelVersionArgs,
+        data: Vec<u8>,
+    ) -> Result<artifacts::ModelArtifactResponse>,
+
+    pub async fn download_model_version_artifact(&self, artifact_id: i64) -> Result<artifacts::DownloadedArtifact>,
+
+    pub async fn download_experiment_artifact(&self, artifact_id: i64) -> Result<artifacts::DownloadedArtifact>,
+
+    pub async fn download_model_version_artifact_to_file(
+        &self,
+        artifact_id: i64,
+        path: String,
+    ) -> Result<artifacts::DownloadedFile>,
+
+    pub async fn download_experiment_artifact_to_file(
+        &self,
+        artifact_id: i64,
+        path: String,
+    ) -> Result<artifacts::DownloadedFile>,
+
+    pub async fn upload_model_version_artifact_resumable(
+        &self,
+        artifact: artifacts::UploadModelVersionArgs,
+        path: String,
+        ticket: Option<i64>,
+    ) -> Result<artifacts::ModelArtifactResponse>,
 }
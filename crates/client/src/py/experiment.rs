@@ -1,11 +1,26 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{artifacts::PartialUploadExperimentArgs, experiment::experiment::ExperimentError};
 
-use crate::experiment::state::*;
+use crate::experiment::{
+    environment::{capture, default_allowlist},
+    metrics::{metric, MetricBuffer},
+    state::*,
+};
 
-use flymodel_graphql::gql::{self, create_experiment};
-use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use flymodel_graphql::{
+    enums::PackageEcosystem,
+    gql::{
+        self, create_experiment,
+        record_experiment_environment::{PackageInput, RecordExperimentEnvironmentVariables},
+        set_experiment_params::ParamInput,
+    },
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyTypeError},
+    prelude::*,
+    types::{PyBool, PyFloat, PyLong, PyString},
+};
 use rust_fsm::*;
 use tokio::sync::Mutex;
 use tracing::debug;
@@ -16,6 +31,9 @@ pub struct Experiment {
     experiment: Arc<Mutex<Option<flymodel_graphql::gql::create_experiment::Experiment>>>,
     client: Arc<crate::py::PythonClient>,
     state: Arc<Mutex<StateMachine<ExperimentState>>>,
+    clock: RunClock,
+    metrics: MetricBuffer,
+    env_allowlist: Arc<Vec<String>>,
     args: Arc<create_experiment::CreateExperimentVariables>,
 }
 
@@ -31,9 +49,78 @@ impl From<ExperimentError> for PyErr {
     }
 }
 
+fn param_of(key: String, value: &PyAny) -> PyResult<ParamInput> {
+    // bool is a subclass of int, so it is matched first
+    if value.is_instance_of::<PyBool>() {
+        Ok(ParamInput::bool(key, value.extract()?))
+    } else if value.is_instance_of::<PyLong>() {
+        Ok(ParamInput::int(key, value.extract()?))
+    } else if value.is_instance_of::<PyFloat>() {
+        Ok(ParamInput::float(key, value.extract()?))
+    } else if value.is_instance_of::<PyString>() {
+        Ok(ParamInput::text(key, value.extract::<String>()?))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "param {key} must be a bool, int, float or str, got {}",
+            value.get_type().name()?
+        )))
+    }
+}
+
+fn python_packages(py: Python) -> PyResult<Vec<PackageInput>> {
+    let mut packages = Vec::new();
+    for dist in py
+        .import("importlib.metadata")?
+        .call_method0("distributions")?
+        .iter()?
+    {
+        let dist = dist?;
+        let name: Option<String> = dist.getattr("metadata")?.get_item("Name")?.extract()?;
+        let version: Option<String> = dist.getattr("version")?.extract()?;
+        if let (Some(name), Some(version)) = (name, version) {
+            packages.push(PackageInput {
+                ecosystem: PackageEcosystem::Python,
+                name,
+                version,
+            });
+        }
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages.dedup_by(|a, b| a.name == b.name);
+    Ok(packages)
+}
+
 impl Experiment {
+    async fn record_environment(&self, experiment: i32) -> Result<(), ExperimentError> {
+        let packages = Python::with_gil(python_packages).unwrap_or_else(|err| {
+            tracing::warn!("failed to list the python packages: {err}");
+            Vec::new()
+        });
+        let allowlist = self.env_allowlist.clone();
+        let environment =
+            tokio::task::spawn_blocking(move || capture(&allowlist, packages)).await?;
+        self.client
+            .shared
+            .record_experiment_environment(RecordExperimentEnvironmentVariables {
+                experiment,
+                environment,
+            })
+            .await?;
+        Ok(())
+    }
+
     async fn consume(&self, state: ExperimentStateInput) -> Result<(), ExperimentError> {
-        consume_mu(self.state.clone(), state).await
+        let entered = consume_mu(self.state.clone(), state).await?;
+        let created = self
+            .experiment
+            .lock()
+            .await
+            .as_ref()
+            .map(|remote| remote.id);
+        if let Some(id) = created {
+            report(&self.client.shared, id, entered, &self.clock).await?;
+        }
+        Ok(())
     }
 
     fn ensure_aenter() -> PyErr {
@@ -60,6 +147,35 @@ impl Experiment {
             .ok_or_else(Self::ensure_aenter)
     }
 
+    async fn push_metrics(
+        &self,
+        metrics: impl IntoIterator<Item = gql::log_metrics::MetricInput>,
+    ) -> PyResult<()> {
+        if !matches!(self.state.lock().await.state(), ExperimentStateState::Tests) {
+            return Err(Self::ensure_aenter());
+        }
+        let remote = self.py_experiment().await?;
+        let res = self
+            .metrics
+            .push(&self.client.shared, remote.id, metrics)
+            .await
+            .map_err(ExperimentError::from);
+        self.fail_on(res).await
+    }
+
+    async fn flush_metrics(&self) -> Result<i32, ExperimentError> {
+        let created = self
+            .experiment
+            .lock()
+            .await
+            .as_ref()
+            .map(|remote| remote.id);
+        match created {
+            Some(id) => Ok(self.metrics.flush(&self.client.shared, id).await?),
+            None => Ok(0),
+        }
+    }
+
     async fn fail_on<T, E: Into<PyErr>>(&self, res: Result<T, E>) -> PyResult<T> {
         match res {
             Ok(res) => Ok(res),
@@ -75,17 +191,36 @@ impl Experiment {
 
 #[pymethods]
 impl Experiment {
+    /**
+        `params` maps hyperparameters to bools, ints, floats or strs, while
+        `env_allowlist` adds to the patterns of the environment variables captured
+    */
     #[new]
+    #[pyo3(signature = (client, args, params = None, env_allowlist = None))]
     pub fn new(
         client: crate::py::PythonClient,
-        args: create_experiment::CreateExperimentVariables,
-    ) -> Self {
-        Self {
+        mut args: create_experiment::CreateExperimentVariables,
+        params: Option<HashMap<String, &PyAny>>,
+        env_allowlist: Option<Vec<String>>,
+    ) -> PyResult<Self> {
+        if let Some(params) = params {
+            let params = params
+                .into_iter()
+                .map(|(key, value)| param_of(key, value))
+                .collect::<PyResult<Vec<_>>>()?;
+            args.params.get_or_insert_with(Vec::new).extend(params);
+        }
+        let mut allowlist = default_allowlist();
+        allowlist.extend(env_allowlist.unwrap_or_default());
+        Ok(Self {
             state: Arc::new(Mutex::new(StateMachine::new())),
+            clock: RunClock::default(),
+            metrics: MetricBuffer::default(),
+            env_allowlist: Arc::new(allowlist),
             client: Arc::new(client),
             args: Arc::new(args),
             experiment: Arc::new(Mutex::new(None)),
-        }
+        })
     }
 
     fn __aenter__<'py>(slf: PyRef<'py, Self>, py: Python<'py>) -> PyResult<&'py PyAny> {
@@ -104,6 +239,7 @@ impl Experiment {
                 )
                 .await?;
 
+            let id = exp.create_experiment.id;
             let mut take = this.experiment.lock().await;
             *take = Some(exp.create_experiment);
             drop(take);
@@ -111,6 +247,9 @@ impl Experiment {
             this.consume(ExperimentStateInput::Entered)
                 .await
                 .map_err(PyErr::from)?;
+            if let Err(err) = this.record_environment(id).await {
+                tracing::warn!("failed to record the environment of experiment {id}: {err}");
+            }
 
             Ok(this.clone())
         })
@@ -150,6 +289,48 @@ impl Experiment {
         })
     }
 
+    /// buffers a metric measured now, sent once enough are pending or the experiment exits
+    #[pyo3(signature = (key, value, step = None))]
+    fn log_metric<'py>(
+        slf: PyRef<'py, Self>,
+        py: Python<'py>,
+        key: String,
+        value: f64,
+        step: Option<i32>,
+    ) -> PyResult<&'py PyAny> {
+        let this = slf.clone();
+        slf.client.runtime().pyfut(py, async move {
+            this.push_metrics([metric(key, value, step)]).await
+        })
+    }
+
+    /// buffers a value for each key of `metrics`, all measured now at the same `step`
+    #[pyo3(signature = (metrics, step = None))]
+    fn log_metrics<'py>(
+        slf: PyRef<'py, Self>,
+        py: Python<'py>,
+        metrics: HashMap<String, f64>,
+        step: Option<i32>,
+    ) -> PyResult<&'py PyAny> {
+        let this = slf.clone();
+        slf.client.runtime().pyfut(py, async move {
+            this.push_metrics(
+                metrics
+                    .into_iter()
+                    .map(|(key, value)| metric(key, value, step)),
+            )
+            .await
+        })
+    }
+
+    /// sends the buffered metrics, returning how many the server stored
+    fn flush<'py>(slf: PyRef<'py, Self>, py: Python<'py>) -> PyResult<&'py PyAny> {
+        let this = slf.clone();
+        slf.client.runtime().pyfut(py, async move {
+            this.flush_metrics().await.map_err(PyErr::from)
+        })
+    }
+
     #[getter]
     fn id<'py>(this: PyRef<'py, Self>) -> PyResult<i32> {
         Ok(this.blocking_experiment()?.id)
@@ -170,15 +351,22 @@ impl Experiment {
         let this = self.clone();
         match (exc_type, exc_value, traceback) {
             (None, None, None) => self.client.runtime().pyfut(py, async move {
+                // the run passed even when its metrics could not be sent, which is raised after
+                let flushed = this.flush_metrics().await;
                 this.consume(ExperimentStateInput::WaitClose)
                     .await
                     .map_err(PyErr::from)?;
+                flushed.map_err(PyErr::from)?;
                 Ok(())
             }),
             (ty, value, trace) => {
                 // how do we want to handle this
                 tracing::error!("{:#?}\n{:#?}\n{:#?}", ty, value, trace);
                 self.client.runtime().pyfut(py, async move {
+                    // the metrics of a failed run are still worth keeping
+                    if let Err(err) = this.flush_metrics().await {
+                        tracing::warn!("failed to send the metrics of the experiment: {err}");
+                    }
                     this.consume(ExperimentStateInput::Failed)
                         .await
                         .map_err(PyErr::from)?;
@@ -199,12 +387,17 @@ mod test {
 
     fn base_exp() -> Experiment {
         Experiment::new(
-            PythonClient::new("http://localhost:9009".into()).unwrap(),
+            PythonClient::new("http://localhost:9009".into(), None).unwrap(),
             CreateExperimentVariables {
                 experiment_name: "abc".into(),
                 model_version_id: 1,
+                params: None,
+                environment: None,
             },
+            None,
+            None,
         )
+        .unwrap()
     }
 
     #[tokio::test]
@@ -0,0 +1,102 @@
+use crate::db::DbLoader;
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use sea_orm::{entity::prelude::*, ActiveValue};
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(table_name = "api_key")]
+#[graphql(name = "ApiKey")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    #[sea_orm(column_type = "Text")]
+    pub name: String,
+    #[graphql(skip)]
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub permissions: Json,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn permission_strings(&self) -> Vec<String> {
+        self.permissions
+            .as_array()
+            .map(|perms| {
+                perms
+                    .iter()
+                    .filter_map(|perm| perm.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl DbLoader<Model> {
+    pub async fn find_active_key(&self, key_hash: &str) -> Result<Option<Model>, FlymodelError> {
+        let found = Entity::find()
+            .filter(Column::KeyHash.eq(key_hash))
+            .filter(Column::RevokedAt.is_null())
+            .one(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        if let Some(found) = &found {
+            let used = ActiveModel {
+                id: ActiveValue::Set(found.id),
+                last_used_at: ActiveValue::Set(Some(Utc::now())),
+                ..Default::default()
+            };
+            if let Err(err) = used.update(&self.db).await {
+                tracing::warn!("failed to record api key usage: {err}");
+            }
+        }
+        Ok(found)
+    }
+
+    pub async fn create_key(
+        &self,
+        name: String,
+        key_hash: String,
+        permissions: Vec<String>,
+    ) -> Result<Model, FlymodelError> {
+        ActiveModel {
+            name: ActiveValue::Set(name),
+            key_hash: ActiveValue::Set(key_hash),
+            permissions: ActiveValue::Set(Json::from(permissions)),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await
+        .map_err(FlymodelError::DbOperationError)
+    }
+
+    pub async fn revoke_key(&self, name: String) -> Result<bool, FlymodelError> {
+        let res = Entity::update_many()
+            .col_expr(Column::RevokedAt, Expr::value(Utc::now()))
+            .filter(Column::Name.eq(name))
+            .filter(Column::RevokedAt.is_null())
+            .exec(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        Ok(res.rows_affected == 1)
+    }
+}
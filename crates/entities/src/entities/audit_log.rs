@@ -0,0 +1,91 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use sea_orm::{entity::prelude::*, QueryOrder};
+
+use crate::{bulk_loader, db::DbLoader, paginated};
+
+use super::page::{PageInput, PaginatedResult};
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(table_name = "audit_log")]
+#[graphql(name = "AuditLogEntry")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    /// the subject of the principal which made the change
+    #[sea_orm(column_type = "Text")]
+    pub actor: String,
+    #[sea_orm(column_type = "Text")]
+    pub action: String,
+    #[sea_orm(column_type = "Text")]
+    pub resource: String,
+    pub resource_id: i64,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub before: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub after: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+paginated! {
+    Model,
+    Entity
+}
+
+impl DbLoader<Model> {
+    pub async fn record<C: ConnectionTrait>(
+        db: &C,
+        entries: Vec<ActiveModel>,
+    ) -> Result<(), FlymodelError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        Entity::insert_many(entries)
+            .exec_without_returning(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn entries(
+        &self,
+        resource: Option<String>,
+        resource_id: Option<i64>,
+        actor: Option<String>,
+        page: PageInput,
+    ) -> PaginatedResult<Model> {
+        let mut query = Entity::find();
+        if let Some(resource) = resource {
+            query = query.filter(Column::Resource.eq(resource));
+        }
+        if let Some(resource_id) = resource_id {
+            query = query.filter(Column::ResourceId.eq(resource_id));
+        }
+        if let Some(actor) = actor {
+            query = query.filter(Column::Actor.eq(actor));
+        }
+        self.load_paginated(query.order_by_desc(Column::Id), page)
+            .await
+    }
+}
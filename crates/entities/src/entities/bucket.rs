@@ -34,7 +34,6 @@ pub struct Model {
     pub name: String,
     #[sea_orm(column_type = "Text")]
     pub region: String,
-    #[sea_orm(select_as = "text")]
     pub role: Lifecycle,
     #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
     pub created_at: chrono::DateTime<Utc>,
@@ -104,6 +103,32 @@ impl DbLoader<Model> {
             .ok_or_else(on_missing)?)
     }
 
+    /// the bucket matching the current lifecycle of a version, where its artifacts belong
+    pub async fn of_version<C: ConnectionTrait>(
+        db: &C,
+        version_id: i64,
+    ) -> Result<Option<Model>, FlymodelError> {
+        let state = super::model_state::Entity::find()
+            .filter(super::model_state::Column::VersionId.eq(version_id))
+            .one(db)
+            .await?;
+        let owner = super::model_version::owner_of_version(db, version_id).await?;
+        let (Some(state), Some((namespace, _))) = (state, owner) else {
+            return Ok(None);
+        };
+        Ok(Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .filter(
+                Expr::expr(Expr::col(Column::Role).cast_as(Alias::new("varchar"))).eq(state
+                    .state
+                    .into_value()
+                    .as_str()
+                    .to_string()),
+            )
+            .one(db)
+            .await?)
+    }
+
     pub async fn find_by_namespace(
         &self,
         namespaces: Option<Vec<i64>>,
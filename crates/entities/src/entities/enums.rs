@@ -117,3 +117,146 @@ pub enum RunState {
     #[sea_orm(string_value = "failed")]
     Failed,
 }
+
+#[derive(
+    Copy,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Enum,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[graphql(name = "UploadTicketStatus")]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "upload_ticket_status"
+)]
+pub enum UploadTicketStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "uploading")]
+    Uploading,
+    #[sea_orm(string_value = "erred")]
+    Erred,
+    #[sea_orm(string_value = "expired")]
+    Expired,
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+    #[sea_orm(string_value = "completed")]
+    Completed,
+}
+
+#[derive(
+    Copy,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Enum,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[graphql(name = "PromotionRequestStatus")]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "promotion_request_status"
+)]
+pub enum PromotionRequestStatus {
+    #[sea_orm(string_value = "open")]
+    Open,
+    #[sea_orm(string_value = "approved")]
+    Approved,
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+}
+
+#[derive(
+    Copy,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Enum,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[graphql(name = "ParamKind")]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "param_kind")]
+pub enum ParamKind {
+    #[sea_orm(string_value = "int")]
+    Int,
+    #[sea_orm(string_value = "float")]
+    Float,
+    #[sea_orm(string_value = "bool")]
+    Bool,
+    #[sea_orm(string_value = "text")]
+    Text,
+}
+
+#[derive(
+    Copy,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Enum,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[graphql(name = "WebhookEvent")]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "webhook_event")]
+pub enum WebhookEvent {
+    #[sea_orm(string_value = "model_version_state_changed")]
+    ModelVersionStateChanged,
+    #[sea_orm(string_value = "artifact_uploaded")]
+    ArtifactUploaded,
+    #[sea_orm(string_value = "experiment_finished")]
+    ExperimentFinished,
+}
+
+#[derive(
+    Copy,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Enum,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[graphql(name = "WebhookDeliveryStatus")]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "webhook_delivery_status"
+)]
+pub enum WebhookDeliveryStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "delivered")]
+    Delivered,
+    /// given up on after its last attempt failed
+    #[sea_orm(string_value = "dead")]
+    Dead,
+}
@@ -6,8 +6,11 @@ use crate::{
 };
 use async_graphql::{ComplexObject, SimpleObject};
 use chrono::{DateTime, Utc};
-use flymodel::errs::FlymodelError;
-use sea_orm::{entity::prelude::*, ActiveValue};
+use flymodel::{errs::FlymodelError, lifecycle::Lifecycle, perms::ReadScope};
+use sea_orm::{
+    entity::prelude::*, sea_query::Query, ActiveValue, Condition, DatabaseTransaction,
+    IntoActiveModel, JoinType, QueryOrder, QuerySelect, TransactionTrait,
+};
 use std::sync::Arc;
 
 use super::page::{PageInput, PaginatedResult};
@@ -34,6 +37,9 @@ pub struct Model {
     pub name: String,
     #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
     pub created_at: DateTime<Utc>,
+    /// when the experiment was moved to the trash, either on its own or with its version
+    #[serde(skip_deserializing)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -62,21 +68,46 @@ impl Related<super::experiment_artifact::Entity> for Entity {
     }
 }
 
+impl Related<super::experiment_state::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ExperimentState.def()
+    }
+}
+
 impl Related<super::model_version::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ModelVersion.def()
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn after_save<'a, C>(model: Model, db: &'a C, insert: bool) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait + 'a,
+    {
+        if insert {
+            super::experiment_state::ActiveModel {
+                experiment_id: ActiveValue::Set(model.id),
+                state: ActiveValue::Set(super::enums::RunState::Created),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+        }
+        Ok(model)
+    }
+}
 
 bulk_loader! {
-    Model
+    Model,
+    soft_delete
 }
 
 paginated! {
     Model,
-    Entity
+    Entity,
+    soft_delete
 }
 
 #[ComplexObject]
@@ -133,24 +164,88 @@ impl Model {
             )
             .await
     }
+
+    /// the hyperparameters of the experiment, ordered by key
+    pub async fn params(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> QueryResult<Vec<super::experiment_param::Model>> {
+        let loader: &DbLoader<super::experiment_param::Model> =
+            DbLoader::with_context(ctx)?.loader();
+        DbLoader::<super::experiment_param::Model>::of_experiment(&loader.db, self.id)
+            .await
+            .map_err(|err| err.into_graphql_error())
+    }
+
+    /// the environment the experiment last ran in, once a client captured it
+    pub async fn environment(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> QueryResult<Option<super::experiment_environment::Model>> {
+        let loader: &DbLoader<super::experiment_environment::Model> =
+            DbLoader::with_context(ctx)?.loader();
+
+        super::experiment_environment::Entity::find()
+            .filter(super::experiment_environment::Column::ExperimentId.eq(self.id))
+            .one(&loader.db)
+            .await
+            .map_err(|err| FlymodelError::DbLoaderError(Arc::new(err)).into_graphql_error())
+    }
+
+    /// the metric series logged for the experiment, averaged down to `max_points` each (0 keeps every point)
+    pub async fn metrics(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        keys: Option<Vec<String>>,
+        #[graphql(default = 1000)] max_points: usize,
+    ) -> QueryResult<Vec<super::experiment_metric::MetricSeries>> {
+        DbLoader::<super::experiment_metric::Model>::with_context(ctx)?
+            .loader()
+            .series(self.id, keys, max_points)
+            .await
+            .map_err(|err| err.into_graphql_error())
+    }
 }
 
 impl DbLoader<Model> {
     pub async fn bulk_paginated_experiments(
         &self,
         name: Option<String>,
-        version_id: Option<i64>,
+        model_id: Option<i64>,
+        scope: Option<ReadScope>,
+        tags: Option<Vec<String>>,
+        deleted: bool,
         page: PageInput,
     ) -> PaginatedResult<Model> {
+        let query = Self::filtered(name, model_id, scope, tags);
+        if deleted {
+            self.load_paginated_with_deleted(query.filter(Column::DeletedAt.is_not_null()), page)
+                .await
+        } else {
+            self.load_paginated(query, page).await
+        }
+    }
+
+    pub fn filtered(
+        name: Option<String>,
+        model_id: Option<i64>,
+        scope: Option<ReadScope>,
+        tags: Option<Vec<String>>,
+    ) -> sea_orm::Select<Entity> {
         let mut query = Entity::find();
         if let Some(name) = name {
             query = Self::find_by_name(query, name);
         }
-        if let Some(version_id) = version_id {
-            query = Self::model_version(query, version_id);
+        if let Some(model_id) = model_id {
+            query = Self::of_model(query, model_id);
         }
-
-        self.load_paginated(query, page).await
+        if let Some(scope) = scope {
+            query = Self::within_scope(query, scope);
+        }
+        if let Some(tags) = tags {
+            query = Self::with_tags(query, tags);
+        }
+        query
     }
 
     pub fn find_by_name(sel: sea_orm::Select<Entity>, name: String) -> sea_orm::Select<Entity> {
@@ -161,28 +256,184 @@ impl DbLoader<Model> {
         sel.filter(Column::VersionId.eq(version_id))
     }
 
-    pub async fn delete_experiment(&self, id: i64) -> Result<bool, async_graphql::Error> {
-        let res = Entity::delete_by_id(id)
-            .exec(&self.db)
+    /// the experiments of every version of a model
+    pub fn of_model(sel: sea_orm::Select<Entity>, model_id: i64) -> sea_orm::Select<Entity> {
+        sel.filter(
+            Column::VersionId.in_subquery(
+                Query::select()
+                    .column(super::model_version::Column::Id)
+                    .from(super::model_version::Entity)
+                    .and_where(super::model_version::Column::ModelId.eq(model_id))
+                    .to_owned(),
+            ),
+        )
+    }
+
+    pub fn with_tags(sel: sea_orm::Select<Entity>, tags: Vec<String>) -> sea_orm::Select<Entity> {
+        sel.filter(super::namespace_tag::tagged(
+            Column::Id,
+            super::experiment_tag::Column::ExperimentId,
+            super::experiment_tag::Column::Tag,
+            tags,
+        ))
+    }
+
+    pub fn within_scope(sel: sea_orm::Select<Entity>, scope: ReadScope) -> sea_orm::Select<Entity> {
+        sel.join(JoinType::InnerJoin, Relation::ModelVersion.def())
+            .join(
+                JoinType::InnerJoin,
+                super::model_version::Relation::Model.def(),
+            )
+            .filter(
+                Condition::any()
+                    .add(super::model::Column::NamespaceId.is_in(scope.namespaces))
+                    .add(super::model::Column::Id.is_in(scope.models)),
+            )
+    }
+
+    pub async fn owner(&self, id: i64) -> Result<Option<(i64, i64)>, FlymodelError> {
+        let experiment = Entity::find_by_id(id)
+            .one(&self.db)
             .await
-            .map_err(|err| FlymodelError::DbOperationError(err).into_graphql_error())?;
+            .map_err(FlymodelError::DbOperationError)?;
+        match experiment {
+            Some(experiment) => {
+                super::model_version::owner_of_version(&self.db, experiment.version_id).await
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn expired<C: ConnectionTrait>(
+        db: &C,
+        state: Lifecycle,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        Ok(Entity::find()
+            .join(JoinType::InnerJoin, Relation::ModelVersion.def())
+            .join(
+                JoinType::InnerJoin,
+                super::model_version::Relation::ModelState.def(),
+            )
+            .filter(super::model_state::Column::State.eq(state))
+            .filter(Column::CreatedAt.lt(before))
+            .order_by_asc(Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// deletes an expired experiment, `None` when its version changed lifecycle meanwhile
+    pub async fn expire(
+        tx: &DatabaseTransaction,
+        experiment: &Model,
+        state: Lifecycle,
+    ) -> Result<Option<Vec<super::object_blob::Model>>, FlymodelError> {
+        let current = super::model_state::Entity::find()
+            .filter(super::model_state::Column::VersionId.eq(experiment.version_id))
+            .lock_exclusive()
+            .one(tx)
+            .await?;
+        if current.map(|current| current.state) != Some(state) {
+            return Ok(None);
+        }
+        Self::purge(tx, experiment.id).await
+    }
+
+    pub async fn soft_delete(&self, id: i64) -> Result<bool, FlymodelError> {
+        let res = Entity::update_many()
+            .col_expr(Column::DeletedAt, Expr::value(Utc::now()))
+            .filter(Column::Id.eq(id))
+            .filter(Column::DeletedAt.is_null())
+            .exec(&self.db)
+            .await?;
         Ok(res.rows_affected == 1)
     }
 
+    pub async fn restore(&self, id: i64) -> Result<Model, FlymodelError> {
+        let tx = self.db.begin().await?;
+        let experiment = Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&tx)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(id))?;
+        if experiment.deleted_at.is_none() {
+            return Err(FlymodelError::ContraintError(format!(
+                "experiment {id} is not deleted"
+            )));
+        }
+        let version_id = experiment.version_id;
+        super::model_version::live_version(&tx, version_id)
+            .await
+            .map_err(|err| match err {
+                FlymodelError::InvalidResourceId(_) => FlymodelError::ContraintError(format!(
+                    "version {version_id} of experiment {id} is deleted, restore it first"
+                )),
+                err => err,
+            })?;
+        let mut active = experiment.into_active_model();
+        active.deleted_at = ActiveValue::Set(None);
+        let restored = active
+            .update(&tx)
+            .await
+            .map_err(|err| match err.sql_err() {
+                Some(SqlErr::UniqueConstraintViolation(..)) => FlymodelError::ContraintError(
+                    format!("experiment {id} was recreated since it was deleted"),
+                ),
+                _ => FlymodelError::DbOperationError(err),
+            })?;
+        tx.commit().await?;
+        Ok(restored)
+    }
+
+    pub async fn purge<C: ConnectionTrait>(
+        db: &C,
+        id: i64,
+    ) -> Result<Option<Vec<super::object_blob::Model>>, FlymodelError> {
+        let blobs = DbLoader::<super::object_blob::Model>::of_experiment(db, id).await?;
+        if Entity::delete_by_id(id).exec(db).await?.rows_affected == 0 {
+            return Ok(None);
+        }
+        Ok(Some(
+            DbLoader::<super::object_blob::Model>::release(db, blobs).await?,
+        ))
+    }
+
     pub async fn create_experiment(
         &self,
         version_id: i64,
         name: String,
+        params: Vec<super::experiment_param::ParamInput>,
+        environment: Option<super::experiment_environment::EnvironmentInput>,
     ) -> Result<Model, async_graphql::Error> {
+        let tx = self
+            .db
+            .begin()
+            .await
+            .map_err(|err| FlymodelError::DbOperationError(err).into_graphql_error())?;
+        super::model_version::live_version(&tx, version_id)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
         let active_model = ActiveModel {
             version_id: ActiveValue::Set(version_id),
             name: ActiveValue::Set(name),
             ..Default::default()
         };
-        active_model
-            .insert(&self.db)
+        let created = active_model
+            .insert(&tx)
             .await
-            .map_err(|err| FlymodelError::DbOperationError(err).into_graphql_error())
+            .map_err(|err| FlymodelError::DbOperationError(err).into_graphql_error())?;
+        DbLoader::<super::experiment_param::Model>::assign(&tx, created.id, params)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        if let Some(environment) = environment {
+            DbLoader::<super::experiment_environment::Model>::capture(&tx, created.id, environment)
+                .await
+                .map_err(|err| err.into_graphql_error())?;
+        }
+        tx.commit()
+            .await
+            .map_err(|err| FlymodelError::DbOperationError(err).into_graphql_error())?;
+        Ok(created)
     }
 
     pub async fn single_model_version(
@@ -207,3 +458,115 @@ impl DbLoader<Model> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use flymodel::{errs::FlymodelError, lifecycle::Lifecycle};
+
+    use super::Model;
+    use crate::{
+        db::DbLoader,
+        entities::{model_version, page::PageInput},
+        testing,
+    };
+
+    #[tokio::test]
+    async fn test_soft_delete_hides_experiment() {
+        let db = testing::database().await;
+        let ns = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let version = testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let kept = testing::experiment(&db, version.id, "kept").await;
+        let trashed = testing::experiment(&db, version.id, "trashed").await;
+        let loader = DbLoader::<Model>::new(db.clone(), None);
+
+        assert!(loader.loader().soft_delete(trashed.id).await.unwrap());
+        let listed = |deleted| {
+            let loader = loader.loader();
+            async move {
+                loader
+                    .bulk_paginated_experiments(
+                        None,
+                        Some(model.id),
+                        None,
+                        None,
+                        deleted,
+                        PageInput::default(),
+                    )
+                    .await
+                    .unwrap()
+                    .data
+                    .into_iter()
+                    .map(|experiment| experiment.id)
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(listed(false).await, vec![kept.id]);
+        assert_eq!(listed(true).await, vec![trashed.id]);
+        assert!(loader.load_one(trashed.id).await.unwrap().is_none());
+        assert!(loader.load_one(kept.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_filter_by_model() {
+        let db = testing::database().await;
+        let ns = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let other = testing::model(&db, ns.id, "other").await;
+        let v1 = testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let v2 = testing::version(&db, model.id, "v2", Lifecycle::Test, Utc::now()).await;
+        let elsewhere = testing::version(&db, other.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let first = testing::experiment(&db, v1.id, "first").await;
+        let second = testing::experiment(&db, v2.id, "second").await;
+        testing::experiment(&db, elsewhere.id, "unrelated").await;
+
+        let mut listed: Vec<_> = DbLoader::<Model>::new(db.clone(), None)
+            .loader()
+            .bulk_paginated_experiments(
+                None,
+                Some(model.id),
+                None,
+                None,
+                false,
+                PageInput::default(),
+            )
+            .await
+            .unwrap()
+            .data
+            .into_iter()
+            .map(|experiment| experiment.id)
+            .collect();
+        listed.sort();
+        assert_eq!(listed, vec![first.id, second.id]);
+    }
+
+    #[tokio::test]
+    async fn test_restore_of_trashed_version() {
+        let db = testing::database().await;
+        let ns = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let version = testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let experiment = testing::experiment(&db, version.id, "run").await;
+        let experiments = DbLoader::<Model>::new(db.clone(), None);
+        let versions = DbLoader::<model_version::Model>::new(db.clone(), None);
+
+        assert!(versions.loader().soft_delete(version.id).await.unwrap());
+        assert!(matches!(
+            experiments.loader().restore(experiment.id).await,
+            Err(FlymodelError::ContraintError(_))
+        ));
+        versions.loader().restore(version.id).await.unwrap();
+        assert!(matches!(
+            experiments.loader().restore(experiment.id).await,
+            Err(FlymodelError::ContraintError(_))
+        ));
+        assert!(experiments
+            .loader()
+            .soft_delete(experiment.id)
+            .await
+            .unwrap());
+        let restored = experiments.loader().restore(experiment.id).await.unwrap();
+        assert!(restored.deleted_at.is_none());
+    }
+}
@@ -3,7 +3,7 @@ use async_graphql::{ComplexObject, SimpleObject};
 use flymodel::errs::FlymodelError;
 use sea_orm::{entity::prelude::*, ActiveValue, DatabaseTransaction};
 
-use super::upload::UploadBlobRequestParams;
+use super::{enums::WebhookEvent, upload::UploadBlobRequestParams};
 
 #[derive(
     Clone,
@@ -115,7 +115,13 @@ impl DbLoader<Model> {
                     ),
                 )
             })?;
-
+        DbLoader::<super::webhook_delivery::Model>::enqueue_of_version(
+            conn,
+            version.id,
+            WebhookEvent::ArtifactUploaded,
+            &ret,
+        )
+        .await?;
         Ok(ret)
     }
 
@@ -0,0 +1,269 @@
+use crate::{
+    bulk_loader,
+    db::{DbLoader, QueryResult},
+};
+
+use async_graphql::{ComplexObject, Enum, InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use sea_orm::{
+    entity::prelude::*, sea_query::OnConflict, ActiveValue, QuerySelect, TransactionTrait,
+};
+use std::collections::BTreeMap;
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[graphql(name = "ExperimentEnvironment")]
+#[graphql(complex)]
+#[sea_orm(table_name = "experiment_environment")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    pub experiment_id: i64,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub git_commit: Option<String>,
+    /// whether the working tree had uncommitted changes
+    pub git_dirty: Option<bool>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub hostname: Option<String>,
+    #[graphql(skip)]
+    #[sea_orm(column_type = "JsonBinary")]
+    pub packages: Json,
+    #[graphql(skip)]
+    #[sea_orm(column_type = "JsonBinary")]
+    pub variables: Json,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub captured_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::experiment::Entity",
+        from = "Column::ExperimentId",
+        to = "super::experiment::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Experiment,
+}
+
+impl Related<super::experiment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Experiment.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PackageEcosystem {
+    Python,
+    Rust,
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Eq, SimpleObject, InputObject, serde::Serialize, serde::Deserialize,
+)]
+#[graphql(input_name = "PackageInput")]
+pub struct Package {
+    pub ecosystem: PackageEcosystem,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Eq, SimpleObject, InputObject, serde::Serialize, serde::Deserialize,
+)]
+#[graphql(input_name = "EnvVarInput")]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+}
+
+/// what a client captured of the environment an experiment runs in
+#[derive(Clone, Debug, Default, PartialEq, Eq, InputObject)]
+pub struct EnvironmentInput {
+    pub git_commit: Option<String>,
+    pub git_dirty: Option<bool>,
+    pub hostname: Option<String>,
+    #[graphql(default)]
+    pub packages: Vec<Package>,
+    /// the environment variables the client allowed to be shared
+    #[graphql(default)]
+    pub variables: Vec<EnvVar>,
+}
+
+#[ComplexObject]
+impl Model {
+    /// the packages installed in the environment, optionally of a single ecosystem
+    pub async fn packages(&self, ecosystem: Option<PackageEcosystem>) -> QueryResult<Vec<Package>> {
+        let packages: Vec<Package> =
+            serde_json::from_value(self.packages.clone()).map_err(|err| {
+                FlymodelError::NonDeterministicError(format!(
+                    "invalid packages of environment {}: {err}",
+                    self.id
+                ))
+                .into_graphql_error()
+            })?;
+        Ok(packages
+            .into_iter()
+            .filter(|package| ecosystem.map_or(true, |ecosystem| package.ecosystem == ecosystem))
+            .collect())
+    }
+
+    /// the environment variables, ordered by name
+    pub async fn variables(&self) -> QueryResult<Vec<EnvVar>> {
+        let variables: BTreeMap<String, String> = serde_json::from_value(self.variables.clone())
+            .map_err(|err| {
+                FlymodelError::NonDeterministicError(format!(
+                    "invalid variables of environment {}: {err}",
+                    self.id
+                ))
+                .into_graphql_error()
+            })?;
+        Ok(variables
+            .into_iter()
+            .map(|(name, value)| EnvVar { name, value })
+            .collect())
+    }
+}
+
+fn checked(env: &EnvironmentInput) -> Result<(Json, Json), FlymodelError> {
+    if let Some(package) = env
+        .packages
+        .iter()
+        .find(|package| package.name.trim().is_empty() || package.version.trim().is_empty())
+    {
+        return Err(FlymodelError::ContraintError(format!(
+            "packages need a name & version: {package:?}"
+        )));
+    }
+    if env.variables.iter().any(|var| var.name.is_empty()) {
+        return Err(FlymodelError::ContraintError(
+            "environment variables need a name".into(),
+        ));
+    }
+    let variables: BTreeMap<&str, &str> = env
+        .variables
+        .iter()
+        .map(|var| (var.name.as_str(), var.value.as_str()))
+        .collect();
+    Ok((
+        serde_json::to_value(&env.packages)
+            .map_err(|err| FlymodelError::NonDeterministicError(err.to_string()))?,
+        serde_json::to_value(variables)
+            .map_err(|err| FlymodelError::NonDeterministicError(err.to_string()))?,
+    ))
+}
+
+impl DbLoader<Model> {
+    pub async fn capture<C: ConnectionTrait>(
+        db: &C,
+        experiment_id: i64,
+        env: EnvironmentInput,
+    ) -> Result<Model, FlymodelError> {
+        let (packages, variables) = checked(&env)?;
+        Ok(Entity::insert(ActiveModel {
+            experiment_id: ActiveValue::Set(experiment_id),
+            git_commit: ActiveValue::Set(env.git_commit),
+            git_dirty: ActiveValue::Set(env.git_dirty),
+            hostname: ActiveValue::Set(env.hostname),
+            packages: ActiveValue::Set(packages),
+            variables: ActiveValue::Set(variables),
+            captured_at: ActiveValue::Set(Utc::now()),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(Column::ExperimentId)
+                .update_columns([
+                    Column::GitCommit,
+                    Column::GitDirty,
+                    Column::Hostname,
+                    Column::Packages,
+                    Column::Variables,
+                    Column::CapturedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await?)
+    }
+
+    pub async fn record(
+        &self,
+        experiment_id: i64,
+        env: EnvironmentInput,
+    ) -> Result<Model, FlymodelError> {
+        let tx = self.db.begin().await?;
+        super::experiment::Entity::find_by_id(experiment_id)
+            .filter(super::experiment::Column::DeletedAt.is_null())
+            .lock_shared()
+            .one(&tx)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(experiment_id))?;
+        let recorded = Self::capture(&tx, experiment_id, env).await?;
+        tx.commit().await?;
+        Ok(recorded)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{checked, EnvVar, EnvironmentInput, Package, PackageEcosystem};
+
+    #[test]
+    fn test_checked() {
+        let var = |name: &str, value: &str| EnvVar {
+            name: name.into(),
+            value: value.into(),
+        };
+        let (packages, variables) = checked(&EnvironmentInput {
+            packages: vec![Package {
+                ecosystem: PackageEcosystem::Python,
+                name: "torch".into(),
+                version: "2.1.0".into(),
+            }],
+            variables: vec![
+                var("CUDA_VISIBLE_DEVICES", "0"),
+                var("CUDA_VISIBLE_DEVICES", "1"),
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            packages,
+            serde_json::json!([{"ecosystem": "PYTHON", "name": "torch", "version": "2.1.0"}])
+        );
+        assert_eq!(variables, serde_json::json!({"CUDA_VISIBLE_DEVICES": "1"}));
+
+        assert!(checked(&EnvironmentInput {
+            variables: vec![var("", "x")],
+            ..Default::default()
+        })
+        .is_err());
+        assert!(checked(&EnvironmentInput {
+            packages: vec![Package {
+                ecosystem: PackageEcosystem::Rust,
+                name: "serde".into(),
+                version: " ".into(),
+            }],
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
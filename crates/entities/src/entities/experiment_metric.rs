@@ -0,0 +1,391 @@
+use crate::{bulk_loader, db::DbLoader};
+
+use async_graphql::{InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Expr, Func, OnConflict, SimpleExpr},
+    ActiveValue, QueryOrder, QuerySelect, TransactionTrait,
+};
+use std::collections::HashMap;
+
+pub const MAX_BATCH: usize = 1000;
+
+#[derive(
+    Clone, Debug, PartialEq, DeriveEntityModel, SimpleObject, serde::Serialize, serde::Deserialize,
+)]
+#[graphql(name = "ExperimentMetric")]
+#[sea_orm(table_name = "experiment_metric")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    pub experiment_id: i64,
+    #[sea_orm(column_type = "Text")]
+    pub key: String,
+    pub step: i64,
+    pub value: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::experiment::Entity",
+        from = "Column::ExperimentId",
+        to = "super::experiment::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Experiment,
+}
+
+impl Related<super::experiment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Experiment.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+/// a value to log against a key of an experiment
+#[derive(Clone, Debug, PartialEq, InputObject)]
+pub struct MetricInput {
+    pub key: String,
+    pub value: f64,
+    /// defaults to the step after the last one logged for the key
+    pub step: Option<i64>,
+    /// when the value was measured, defaulting to when it is logged
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, SimpleObject)]
+pub struct MetricPoint {
+    pub step: i64,
+    pub value: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// the values logged for a key, ordered by step
+#[derive(Clone, Debug, PartialEq, SimpleObject)]
+pub struct MetricSeries {
+    pub key: String,
+    pub points: Vec<MetricPoint>,
+}
+
+/// numbers metrics missing a step after the last one of their key, the last value of a repeated step winning
+fn assign_steps(
+    metrics: Vec<MetricInput>,
+    mut last: HashMap<String, i64>,
+    now: DateTime<Utc>,
+) -> Vec<(String, i64, f64, DateTime<Utc>)> {
+    let mut assigned: Vec<(String, i64, f64, DateTime<Utc>)> = Vec::with_capacity(metrics.len());
+    let mut seen: HashMap<(String, i64), usize> = HashMap::new();
+    for metric in metrics {
+        let step = metric
+            .step
+            .unwrap_or_else(|| last.get(&metric.key).map_or(0, |step| step + 1));
+        let highest = last.entry(metric.key.clone()).or_insert(step);
+        *highest = (*highest).max(step);
+        let entry = (
+            metric.key,
+            step,
+            metric.value,
+            metric.timestamp.unwrap_or(now),
+        );
+        match seen.get(&(entry.0.clone(), step)) {
+            Some(&at) => assigned[at] = entry,
+            None => {
+                seen.insert((entry.0.clone(), step), assigned.len());
+                assigned.push(entry);
+            }
+        }
+    }
+    assigned
+}
+
+fn bucket_width(points: i64, first: i64, last: i64, max_points: usize) -> Option<i64> {
+    let max_points = i64::try_from(max_points).unwrap_or(i64::MAX);
+    if max_points == 0 || points <= max_points {
+        return None;
+    }
+    Some((last - first) / max_points + 1)
+}
+
+impl DbLoader<Model> {
+    pub async fn log(
+        &self,
+        experiment_id: i64,
+        metrics: Vec<MetricInput>,
+    ) -> Result<u64, FlymodelError> {
+        if metrics.is_empty() {
+            return Ok(0);
+        }
+        if metrics.len() > MAX_BATCH {
+            return Err(FlymodelError::ContraintError(format!(
+                "at most {MAX_BATCH} metrics may be logged at once, got {}",
+                metrics.len()
+            )));
+        }
+        for metric in &metrics {
+            if metric.key.trim().is_empty() {
+                return Err(FlymodelError::ContraintError(
+                    "metric keys may not be empty".into(),
+                ));
+            }
+            if !metric.value.is_finite() {
+                return Err(FlymodelError::ContraintError(format!(
+                    "the value of {} is not finite: {}",
+                    metric.key, metric.value
+                )));
+            }
+            if metric.step.is_some_and(|step| step < 0) {
+                return Err(FlymodelError::ContraintError(format!(
+                    "the step of {} may not be negative",
+                    metric.key
+                )));
+            }
+        }
+
+        let tx = self.db.begin().await?;
+        super::experiment::Entity::find_by_id(experiment_id)
+            .filter(super::experiment::Column::DeletedAt.is_null())
+            .lock_shared()
+            .one(&tx)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(experiment_id))?;
+        let unnumbered: Vec<String> = metrics
+            .iter()
+            .filter(|metric| metric.step.is_none())
+            .map(|metric| metric.key.clone())
+            .collect();
+        let last: HashMap<String, i64> = if unnumbered.is_empty() {
+            HashMap::new()
+        } else {
+            Entity::find()
+                .select_only()
+                .column(Column::Key)
+                .column_as(Column::Step.max(), "step")
+                .filter(Column::ExperimentId.eq(experiment_id))
+                .filter(Column::Key.is_in(unnumbered))
+                .group_by(Column::Key)
+                .into_tuple::<(String, i64)>()
+                .all(&tx)
+                .await?
+                .into_iter()
+                .collect()
+        };
+
+        let rows = assign_steps(metrics, last, Utc::now());
+        let written = rows.len() as u64;
+        Entity::insert_many(
+            rows.into_iter()
+                .map(|(key, step, value, timestamp)| ActiveModel {
+                    experiment_id: ActiveValue::Set(experiment_id),
+                    key: ActiveValue::Set(key),
+                    step: ActiveValue::Set(step),
+                    value: ActiveValue::Set(value),
+                    timestamp: ActiveValue::Set(timestamp),
+                    ..Default::default()
+                }),
+        )
+        .on_conflict(
+            OnConflict::columns([Column::ExperimentId, Column::Key, Column::Step])
+                .update_columns([Column::Value, Column::Timestamp])
+                .to_owned(),
+        )
+        .exec_without_returning(&tx)
+        .await?;
+        tx.commit().await?;
+        Ok(written)
+    }
+
+    /// series of more than `max_points` are averaged over runs of steps, a `max_points` of 0 keeps every point
+    pub async fn series(
+        &self,
+        experiment_id: i64,
+        keys: Option<Vec<String>>,
+        max_points: usize,
+    ) -> Result<Vec<MetricSeries>, FlymodelError> {
+        let mut sel = Entity::find().filter(Column::ExperimentId.eq(experiment_id));
+        if let Some(keys) = keys {
+            sel = sel.filter(Column::Key.is_in(keys));
+        }
+        let extents = sel
+            .clone()
+            .select_only()
+            .column(Column::Key)
+            .column_as(Column::Id.count(), "points")
+            .column_as(Column::Step.min(), "first")
+            .column_as(Column::Step.max(), "last")
+            .group_by(Column::Key)
+            .order_by_asc(Column::Key)
+            .into_tuple::<(String, i64, i64, i64)>()
+            .all(&self.db)
+            .await?;
+
+        let mut series = Vec::with_capacity(extents.len());
+        for (key, points, first, last) in extents {
+            let sel = sel.clone().filter(Column::Key.eq(key.clone()));
+            let points = match bucket_width(points, first, last, max_points) {
+                None => sel
+                    .order_by_asc(Column::Step)
+                    .all(&self.db)
+                    .await?
+                    .into_iter()
+                    .map(|metric| MetricPoint {
+                        step: metric.step,
+                        value: metric.value,
+                        timestamp: metric.timestamp,
+                    })
+                    .collect(),
+                Some(width) => sel
+                    .select_only()
+                    .column_as(Column::Step.max(), "step")
+                    .column_as(
+                        SimpleExpr::from(Func::avg(Expr::col(Column::Value))),
+                        "value",
+                    )
+                    .column_as(Column::Timestamp.max(), "timestamp")
+                    .group_by(Expr::col(Column::Step).sub(first).div(width))
+                    .order_by_asc(Column::Step.max())
+                    .into_tuple::<(i64, f64, DateTime<Utc>)>()
+                    .all(&self.db)
+                    .await?
+                    .into_iter()
+                    .map(|(step, value, timestamp)| MetricPoint {
+                        step,
+                        value,
+                        timestamp,
+                    })
+                    .collect(),
+            };
+            series.push(MetricSeries { key, points });
+        }
+        Ok(series)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use chrono::{TimeZone, Utc};
+
+    use flymodel::lifecycle::Lifecycle;
+
+    use super::{assign_steps, bucket_width, MetricInput, MetricPoint, Model};
+    use crate::{db::DbLoader, testing};
+
+    fn metric(key: &str, value: f64, step: Option<i64>) -> MetricInput {
+        MetricInput {
+            key: key.into(),
+            value,
+            step,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn test_assign_steps() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let assigned = assign_steps(
+            vec![
+                metric("loss", 0.5, None),
+                metric("acc", 0.9, None),
+                metric("loss", 0.4, None),
+                metric("acc", 0.95, Some(10)),
+                metric("acc", 0.96, None),
+                metric("loss", 0.3, Some(5)),
+            ],
+            HashMap::from([("loss".to_string(), 4)]),
+            now,
+        );
+        let steps: Vec<_> = assigned
+            .iter()
+            .map(|(key, step, value, _)| (key.as_str(), *step, *value))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("loss", 5, 0.3),
+                ("acc", 0, 0.9),
+                ("loss", 6, 0.4),
+                ("acc", 10, 0.95),
+                ("acc", 11, 0.96),
+            ]
+        );
+        assert!(assigned.iter().all(|(_, _, _, at)| *at == now));
+    }
+
+    #[test]
+    fn test_bucket_width() {
+        assert_eq!(bucket_width(5, 0, 4, 0), None);
+        assert_eq!(bucket_width(5, 0, 4, 5), None);
+        assert_eq!(bucket_width(5, 0, 4, 2), Some(3));
+        assert_eq!(bucket_width(3, 10, 1000, 2), Some(496));
+    }
+
+    #[tokio::test]
+    async fn test_series() {
+        let db = testing::database().await;
+        let ns = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let version = testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let experiment = testing::experiment(&db, version.id, "run").await;
+        let metrics = DbLoader::<Model>::new(db.clone(), None);
+        let at = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let logged: Vec<_> = (0..5)
+            .map(|step| MetricInput {
+                key: "loss".into(),
+                value: step as f64,
+                step: Some(step),
+                timestamp: Some(at + chrono::Duration::seconds(step)),
+            })
+            .chain([metric("acc", 0.9, None)])
+            .collect();
+        metrics.loader().log(experiment.id, logged).await.unwrap();
+
+        let every = metrics
+            .loader()
+            .series(experiment.id, Some(vec!["loss".into()]), 0)
+            .await
+            .unwrap();
+        assert_eq!(every.len(), 1);
+        assert_eq!(every[0].points.len(), 5);
+
+        let reduced = metrics
+            .loader()
+            .series(experiment.id, None, 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            reduced
+                .iter()
+                .map(|series| series.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["acc", "loss"]
+        );
+        assert_eq!(reduced[0].points.len(), 1);
+        assert_eq!(
+            reduced[1].points,
+            vec![
+                MetricPoint {
+                    step: 2,
+                    value: 1.0,
+                    timestamp: at + chrono::Duration::seconds(2),
+                },
+                MetricPoint {
+                    step: 4,
+                    value: 3.5,
+                    timestamp: at + chrono::Duration::seconds(4),
+                },
+            ]
+        );
+    }
+}
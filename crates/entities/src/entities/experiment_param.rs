@@ -0,0 +1,220 @@
+use crate::{bulk_loader, db::DbLoader};
+
+use super::enums::ParamKind;
+use async_graphql::{InputObject, SimpleObject};
+use flymodel::errs::FlymodelError;
+use sea_orm::{
+    entity::prelude::*, sea_query::OnConflict, ActiveValue, QueryOrder, QuerySelect,
+    TransactionTrait,
+};
+use std::collections::HashMap;
+
+pub const MAX_PARAMS: usize = 1000;
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[graphql(name = "ExperimentParam")]
+#[sea_orm(table_name = "experiment_param")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    pub experiment_id: i64,
+    #[sea_orm(column_type = "Text")]
+    pub key: String,
+    pub kind: ParamKind,
+    /// the value in its canonical form, such as `0.01` or `true`
+    #[sea_orm(column_type = "Text")]
+    pub value: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::experiment::Entity",
+        from = "Column::ExperimentId",
+        to = "super::experiment::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Experiment,
+}
+
+impl Related<super::experiment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Experiment.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+/// a hyperparameter, its value given as text & parsed according to its kind
+#[derive(Clone, Debug, PartialEq, Eq, InputObject)]
+pub struct ParamInput {
+    pub key: String,
+    pub kind: ParamKind,
+    pub value: String,
+}
+
+fn canonical(kind: ParamKind, value: &str) -> Option<String> {
+    match kind {
+        ParamKind::Int => value.trim().parse::<i64>().ok().map(|int| int.to_string()),
+        ParamKind::Float => value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|float| float.is_finite())
+            .map(|float| float.to_string()),
+        ParamKind::Bool => match value.trim().to_ascii_lowercase().as_str() {
+            "true" => Some("true".into()),
+            "false" => Some("false".into()),
+            _ => None,
+        },
+        ParamKind::Text => Some(value.into()),
+    }
+}
+
+fn checked(params: Vec<ParamInput>) -> Result<Vec<(String, ParamKind, String)>, FlymodelError> {
+    if params.len() > MAX_PARAMS {
+        return Err(FlymodelError::ContraintError(format!(
+            "an experiment may be given at most {MAX_PARAMS} params at once, got {}",
+            params.len()
+        )));
+    }
+    let mut checked: Vec<(String, ParamKind, String)> = Vec::with_capacity(params.len());
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for param in params {
+        if param.key.trim().is_empty() {
+            return Err(FlymodelError::ContraintError(
+                "param keys may not be empty".into(),
+            ));
+        }
+        let value = canonical(param.kind, &param.value).ok_or_else(|| {
+            FlymodelError::ContraintError(format!(
+                "the value of {} is not a valid {}: {}",
+                param.key,
+                param.kind.to_value(),
+                param.value
+            ))
+        })?;
+        let entry = (param.key, param.kind, value);
+        match seen.get(&entry.0) {
+            Some(&at) => checked[at] = entry,
+            None => {
+                seen.insert(entry.0.clone(), checked.len());
+                checked.push(entry);
+            }
+        }
+    }
+    Ok(checked)
+}
+
+impl DbLoader<Model> {
+    pub async fn of_experiment<C: ConnectionTrait>(
+        db: &C,
+        experiment_id: i64,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        Ok(Entity::find()
+            .filter(Column::ExperimentId.eq(experiment_id))
+            .order_by_asc(Column::Key)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn assign<C: ConnectionTrait>(
+        db: &C,
+        experiment_id: i64,
+        params: Vec<ParamInput>,
+    ) -> Result<(), FlymodelError> {
+        let params = checked(params)?;
+        if params.is_empty() {
+            return Ok(());
+        }
+        Entity::insert_many(params.into_iter().map(|(key, kind, value)| ActiveModel {
+            experiment_id: ActiveValue::Set(experiment_id),
+            key: ActiveValue::Set(key),
+            kind: ActiveValue::Set(kind),
+            value: ActiveValue::Set(value),
+            ..Default::default()
+        }))
+        .on_conflict(
+            OnConflict::columns([Column::ExperimentId, Column::Key])
+                .update_columns([Column::Kind, Column::Value])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set_params(
+        &self,
+        experiment_id: i64,
+        params: Vec<ParamInput>,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        let tx = self.db.begin().await?;
+        super::experiment::Entity::find_by_id(experiment_id)
+            .filter(super::experiment::Column::DeletedAt.is_null())
+            .lock_shared()
+            .one(&tx)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(experiment_id))?;
+        Self::assign(&tx, experiment_id, params).await?;
+        let params = Self::of_experiment(&tx, experiment_id).await?;
+        tx.commit().await?;
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{canonical, checked, ParamInput};
+    use crate::entities::enums::ParamKind;
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(canonical(ParamKind::Int, " 42 "), Some("42".into()));
+        assert_eq!(canonical(ParamKind::Int, "4.2"), None);
+        assert_eq!(canonical(ParamKind::Float, "1e-2"), Some("0.01".into()));
+        assert_eq!(canonical(ParamKind::Float, "3"), Some("3".into()));
+        assert_eq!(canonical(ParamKind::Float, "NaN"), None);
+        assert_eq!(canonical(ParamKind::Bool, "True"), Some("true".into()));
+        assert_eq!(canonical(ParamKind::Bool, "1"), None);
+        assert_eq!(canonical(ParamKind::Text, " adam "), Some(" adam ".into()));
+    }
+
+    #[test]
+    fn test_checked() {
+        let param = |key: &str, kind, value: &str| ParamInput {
+            key: key.into(),
+            kind,
+            value: value.into(),
+        };
+        assert_eq!(
+            checked(vec![
+                param("lr", ParamKind::Float, "0.1"),
+                param("epochs", ParamKind::Int, "10"),
+                param("lr", ParamKind::Float, "0.010"),
+            ])
+            .unwrap(),
+            vec![
+                ("lr".into(), ParamKind::Float, "0.01".into()),
+                ("epochs".into(), ParamKind::Int, "10".into()),
+            ]
+        );
+        assert!(checked(vec![param("", ParamKind::Text, "x")]).is_err());
+        assert!(checked(vec![param("epochs", ParamKind::Int, "ten")]).is_err());
+    }
+}
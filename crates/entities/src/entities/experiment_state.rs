@@ -1,9 +1,13 @@
-use crate::bulk_loader;
+use crate::{bulk_loader, db::DbLoader};
 
-use super::enums::RunState;
+use super::enums::{RunState, WebhookEvent};
 use async_graphql::SimpleObject;
 use chrono::{DateTime, Utc};
-use sea_orm::entity::prelude::*;
+use flymodel::errs::FlymodelError;
+use sea_orm::{
+    entity::prelude::*, sea_query::OnConflict, ActiveValue, IntoActiveModel, QuerySelect,
+    TransactionTrait,
+};
 
 #[derive(
     Clone,
@@ -22,6 +26,7 @@ pub struct Model {
     pub id: i64,
     pub experiment_id: i64,
     pub state: RunState,
+    /// the number of times the run was retried after failing
     pub retry: Option<i32>,
     #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
     pub last_modified: DateTime<Utc>,
@@ -50,3 +55,155 @@ bulk_loader! {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+/// a step of the run of an experiment reported by a client
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunTransition {
+    Start,
+    Pass,
+    Fail,
+    Retry,
+}
+
+impl RunTransition {
+    pub fn apply(self, from: RunState) -> Option<RunState> {
+        match (self, from) {
+            (Self::Start, RunState::Created) => Some(RunState::Running),
+            (Self::Pass, RunState::Running) => Some(RunState::Passed),
+            (Self::Fail, RunState::Created | RunState::Running) => Some(RunState::Failed),
+            (Self::Retry, RunState::Failed) => Some(RunState::Running),
+            _ => None,
+        }
+    }
+
+    fn verb(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Pass => "pass",
+            Self::Fail => "fail",
+            Self::Retry => "retry",
+        }
+    }
+}
+
+impl DbLoader<Model> {
+    /// passing or failing records the result of the run, retrying discards it. returns the state left along with the new one
+    pub async fn advance(
+        &self,
+        experiment_id: i64,
+        transition: RunTransition,
+        duration_ms: Option<i64>,
+    ) -> Result<(Model, RunState), FlymodelError> {
+        if duration_ms.is_some_and(|duration| duration < 0) {
+            return Err(FlymodelError::ContraintError(
+                "run durations may not be negative".into(),
+            ));
+        }
+        let tx = self.db.begin().await?;
+        let experiment = super::experiment::Entity::find_by_id(experiment_id)
+            .filter(super::experiment::Column::DeletedAt.is_null())
+            .lock_shared()
+            .one(&tx)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(experiment_id))?;
+        let current = Entity::find()
+            .filter(Column::ExperimentId.eq(experiment_id))
+            .lock_exclusive()
+            .one(&tx)
+            .await?
+            .ok_or_else(|| {
+                FlymodelError::NonDeterministicError(format!("should have state: {experiment_id}"))
+            })?;
+        let from = current.state;
+        let state = transition.apply(from).ok_or_else(|| {
+            FlymodelError::ContraintError(format!(
+                "cannot {} experiment {experiment_id} while it is {}",
+                transition.verb(),
+                from.to_value()
+            ))
+        })?;
+
+        let now = Utc::now();
+        let started = current.last_modified;
+        let mut retry = current.retry;
+        if transition == RunTransition::Retry {
+            retry = Some(retry.unwrap_or_default().saturating_add(1));
+            super::experiment_result::Entity::delete_many()
+                .filter(super::experiment_result::Column::ExperimentId.eq(experiment_id))
+                .exec(&tx)
+                .await?;
+        }
+        let mut active = current.into_active_model();
+        active.state = ActiveValue::Set(state);
+        active.retry = ActiveValue::Set(retry);
+        active.last_modified = ActiveValue::Set(now);
+        let updated = active.update(&tx).await?;
+
+        if matches!(state, RunState::Passed | RunState::Failed) {
+            let duration_ms = duration_ms.unwrap_or_else(|| match from {
+                RunState::Running => (now - started).num_milliseconds().max(0),
+                _ => 0,
+            });
+            let result =
+                super::experiment_result::Entity::insert(super::experiment_result::ActiveModel {
+                    experiment_id: ActiveValue::Set(experiment_id),
+                    state: ActiveValue::Set(state),
+                    retries: ActiveValue::Set(retry.unwrap_or_default()),
+                    duration_ms: ActiveValue::Set(duration_ms),
+                    finished_at: ActiveValue::Set(now),
+                    ..Default::default()
+                })
+                .on_conflict(
+                    OnConflict::column(super::experiment_result::Column::ExperimentId)
+                        .update_columns([
+                            super::experiment_result::Column::State,
+                            super::experiment_result::Column::Retries,
+                            super::experiment_result::Column::DurationMs,
+                            super::experiment_result::Column::FinishedAt,
+                        ])
+                        .to_owned(),
+                )
+                .exec_with_returning(&tx)
+                .await?;
+            DbLoader::<super::webhook_delivery::Model>::enqueue_of_version(
+                &tx,
+                experiment.version_id,
+                WebhookEvent::ExperimentFinished,
+                &result,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok((updated, from))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RunTransition;
+    use crate::entities::enums::RunState;
+
+    #[test]
+    fn test_run_transitions() {
+        assert_eq!(
+            RunTransition::Start.apply(RunState::Created),
+            Some(RunState::Running)
+        );
+        assert_eq!(
+            RunTransition::Pass.apply(RunState::Running),
+            Some(RunState::Passed)
+        );
+        assert_eq!(
+            RunTransition::Fail.apply(RunState::Created),
+            Some(RunState::Failed)
+        );
+        assert_eq!(
+            RunTransition::Retry.apply(RunState::Failed),
+            Some(RunState::Running)
+        );
+        assert_eq!(RunTransition::Pass.apply(RunState::Created), None);
+        assert_eq!(RunTransition::Start.apply(RunState::Running), None);
+        assert_eq!(RunTransition::Retry.apply(RunState::Passed), None);
+        assert_eq!(RunTransition::Fail.apply(RunState::Passed), None);
+    }
+}
@@ -3,7 +3,7 @@ use chrono::{DateTime, Utc};
 
 use sea_orm::entity::prelude::*;
 
-use crate::{bulk_loader, paginated, tags_meta};
+use crate::{bulk_loader, paginated, tag_link, tags_meta};
 
 #[derive(
     Clone,
@@ -18,7 +18,8 @@ use crate::{bulk_loader, paginated, tags_meta};
 #[sea_orm(table_name = "experiment_tag")]
 #[graphql(name = "ExperimentTag", complex)]
 pub struct Model {
-    #[sea_orm(primary_key, auto_increment = false)]
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
     pub id: i64,
     pub experiment_id: i64,
     pub tag: i64,
@@ -69,6 +70,11 @@ paginated! {
     Entity
 }
 
+tag_link! {
+    experiment_id,
+    ExperimentId
+}
+
 #[ComplexObject]
 impl Model {
     tags_meta! {}
@@ -0,0 +1,391 @@
+use crate::db::DbLoader;
+
+use super::{
+    enums::RunState,
+    experiment::{Column, Entity, Model},
+    experiment_metric, experiment_param, experiment_result, experiment_state,
+    page::{PageInput, Paginated, PaginatedResult},
+};
+use async_graphql::{Enum, InputObject, SimpleObject};
+use flymodel::{errs::FlymodelError, perms::ReadScope};
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Alias, Expr, Order, Query, SelectStatement, SimpleExpr},
+    QueryOrder, QuerySelect,
+};
+use std::collections::{BTreeMap, HashMap};
+
+pub const MAX_COMPARED: usize = 10;
+
+/// how the values a metric was logged with are reduced to a score
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Enum)]
+pub enum MetricAggregate {
+    /// the value of the highest step
+    #[default]
+    Last,
+    Min,
+    Max,
+}
+
+/// a field of the result of a finished run
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum ResultField {
+    DurationMs,
+    Retries,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Enum)]
+pub enum RankOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// what a leaderboard ranks by, either a metric or a field of the result
+#[derive(Clone, Debug, Default, PartialEq, Eq, InputObject)]
+pub struct RankBy {
+    pub metric: Option<String>,
+    #[graphql(default)]
+    pub aggregate: MetricAggregate,
+    pub field: Option<ResultField>,
+}
+
+/// the experiments a leaderboard ranks
+#[derive(Clone, Debug, Default, PartialEq, Eq, InputObject)]
+pub struct LeaderboardFilter {
+    pub state: Option<RunState>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rank {
+    Metric {
+        key: String,
+        aggregate: MetricAggregate,
+    },
+    Result(ResultField),
+}
+
+impl Rank {
+    pub fn of(
+        metric: Option<String>,
+        aggregate: MetricAggregate,
+        field: Option<ResultField>,
+    ) -> Result<Self, FlymodelError> {
+        match (metric, field) {
+            (Some(key), None) => Ok(Self::Metric { key, aggregate }),
+            (None, Some(field)) => Ok(Self::Result(field)),
+            _ => Err(FlymodelError::ContraintError(
+                "experiments are ranked by either a metric or a result field".into(),
+            )),
+        }
+    }
+
+    fn scored(&self) -> SelectStatement {
+        match self {
+            Self::Metric { key, .. } => Query::select()
+                .column(experiment_metric::Column::ExperimentId)
+                .from(experiment_metric::Entity)
+                .and_where(experiment_metric::Column::Key.eq(key.clone()))
+                .to_owned(),
+            Self::Result(_) => Query::select()
+                .column(experiment_result::Column::ExperimentId)
+                .from(experiment_result::Entity)
+                .to_owned(),
+        }
+    }
+
+    fn score(&self) -> SimpleExpr {
+        let mut sub = Query::select();
+        match self {
+            Self::Metric { key, aggregate } => {
+                let value =
+                    Expr::col((experiment_metric::Entity, experiment_metric::Column::Value));
+                sub.from(experiment_metric::Entity)
+                    .and_where(
+                        Expr::col((
+                            experiment_metric::Entity,
+                            experiment_metric::Column::ExperimentId,
+                        ))
+                        .equals((Entity, Column::Id)),
+                    )
+                    .and_where(experiment_metric::Column::Key.eq(key.clone()));
+                match aggregate {
+                    MetricAggregate::Last => sub
+                        .expr(value)
+                        .order_by(
+                            (experiment_metric::Entity, experiment_metric::Column::Step),
+                            Order::Desc,
+                        )
+                        .limit(1),
+                    MetricAggregate::Min => sub.expr(value.min()),
+                    MetricAggregate::Max => sub.expr(value.max()),
+                };
+            }
+            Self::Result(field) => {
+                let column = match field {
+                    ResultField::DurationMs => experiment_result::Column::DurationMs,
+                    ResultField::Retries => experiment_result::Column::Retries,
+                };
+                sub.column((experiment_result::Entity, column))
+                    .from(experiment_result::Entity)
+                    .and_where(
+                        Expr::col((
+                            experiment_result::Entity,
+                            experiment_result::Column::ExperimentId,
+                        ))
+                        .equals((Entity, Column::Id)),
+                    );
+            }
+        }
+        Expr::expr(SimpleExpr::SubQuery(
+            None,
+            Box::new(sub.into_sub_query_statement()),
+        ))
+        .cast_as(Alias::new("double precision"))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, SimpleObject)]
+pub struct LeaderboardEntry {
+    /// the position of the experiment across every page, starting at 1
+    pub rank: u64,
+    pub score: Option<f64>,
+    pub experiment: Model,
+}
+
+#[derive(Clone, Debug, PartialEq, SimpleObject)]
+pub struct MetricSummary {
+    /// the value of the highest step
+    pub last: f64,
+    pub step: i64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// the values of a param, one per compared experiment
+#[derive(Clone, Debug, PartialEq, SimpleObject)]
+pub struct ParamComparison {
+    pub key: String,
+    pub values: Vec<Option<String>>,
+}
+
+/// the summaries of a metric, one per compared experiment
+#[derive(Clone, Debug, PartialEq, SimpleObject)]
+pub struct MetricComparison {
+    pub key: String,
+    pub values: Vec<Option<MetricSummary>>,
+}
+
+#[derive(Clone, Debug, PartialEq, SimpleObject)]
+pub struct ExperimentComparison {
+    pub experiments: Vec<Model>,
+    pub params: Vec<ParamComparison>,
+    pub metrics: Vec<MetricComparison>,
+}
+
+fn side_by_side<T>(ids: &[i64], rows: Vec<(i64, String, T)>) -> Vec<(String, Vec<Option<T>>)> {
+    let position: HashMap<i64, usize> = ids.iter().enumerate().map(|(at, id)| (*id, at)).collect();
+    let mut keys: BTreeMap<String, Vec<Option<T>>> = BTreeMap::new();
+    for (id, key, value) in rows {
+        let Some(&at) = position.get(&id) else {
+            continue;
+        };
+        keys.entry(key)
+            .or_insert_with(|| ids.iter().map(|_| None).collect())[at] = Some(value);
+    }
+    keys.into_iter().collect()
+}
+
+impl DbLoader<Model> {
+    /// ranks the experiments of a model's versions, leaving out those without a score
+    pub async fn leaderboard(
+        &self,
+        model_id: i64,
+        rank: Rank,
+        order: RankOrder,
+        filter: LeaderboardFilter,
+        scope: Option<ReadScope>,
+        page: PageInput,
+    ) -> PaginatedResult<LeaderboardEntry> {
+        let LeaderboardFilter { state, tags } = filter;
+        let mut query = Self::filtered(None, Some(model_id), scope, tags)
+            .filter(Column::Id.in_subquery(rank.scored()));
+        if let Some(state) = state {
+            query = query.filter(
+                Column::Id.in_subquery(
+                    Query::select()
+                        .column(experiment_state::Column::ExperimentId)
+                        .from(experiment_state::Entity)
+                        .and_where(experiment_state::Column::State.eq(state))
+                        .to_owned(),
+                ),
+            );
+        }
+        let order = match order {
+            RankOrder::Asc => Order::Asc,
+            RankOrder::Desc => Order::Desc,
+        };
+        let first = page.page * page.size as u64;
+        let ranked = self
+            .load_paginated(
+                query.order_by(rank.score(), order).order_by_asc(Column::Id),
+                page,
+            )
+            .await?;
+
+        let scores: HashMap<i64, Option<f64>> = Entity::find()
+            .select_only()
+            .column(Column::Id)
+            .column_as(rank.score(), "score")
+            .filter(Column::Id.is_in(ranked.data.iter().map(|experiment| experiment.id)))
+            .into_tuple::<(i64, Option<f64>)>()
+            .all(&self.db)
+            .await
+            .map_err(|err| FlymodelError::DbOperationError(err).into_graphql_error())?
+            .into_iter()
+            .collect();
+        Ok(Paginated {
+            page: ranked.page,
+            total_pages: ranked.total_pages,
+            total_items: ranked.total_items,
+            data: ranked
+                .data
+                .into_iter()
+                .zip(first + 1..)
+                .map(|(experiment, rank)| LeaderboardEntry {
+                    rank,
+                    score: scores.get(&experiment.id).copied().flatten(),
+                    experiment,
+                })
+                .collect(),
+        })
+    }
+
+    pub async fn compare(
+        &self,
+        experiments: Vec<Model>,
+        keys: Option<Vec<String>>,
+    ) -> Result<ExperimentComparison, FlymodelError> {
+        if experiments.len() > MAX_COMPARED {
+            return Err(FlymodelError::ContraintError(format!(
+                "at most {MAX_COMPARED} experiments may be compared, got {}",
+                experiments.len()
+            )));
+        }
+        let ids: Vec<i64> = experiments.iter().map(|experiment| experiment.id).collect();
+
+        let params = experiment_param::Entity::find()
+            .filter(experiment_param::Column::ExperimentId.is_in(ids.clone()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|param| (param.experiment_id, param.key, param.value))
+            .collect();
+
+        let mut metrics = experiment_metric::Entity::find()
+            .filter(experiment_metric::Column::ExperimentId.is_in(ids.clone()));
+        if let Some(keys) = keys {
+            metrics = metrics.filter(experiment_metric::Column::Key.is_in(keys));
+        }
+        let bounds: HashMap<(i64, String), (f64, f64)> = metrics
+            .clone()
+            .select_only()
+            .column(experiment_metric::Column::ExperimentId)
+            .column(experiment_metric::Column::Key)
+            .column_as(experiment_metric::Column::Value.min(), "min")
+            .column_as(experiment_metric::Column::Value.max(), "max")
+            .group_by(experiment_metric::Column::ExperimentId)
+            .group_by(experiment_metric::Column::Key)
+            .into_tuple::<(i64, String, f64, f64)>()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|(id, key, min, max)| ((id, key), (min, max)))
+            .collect();
+        let summaries = metrics
+            .distinct_on([
+                experiment_metric::Column::ExperimentId,
+                experiment_metric::Column::Key,
+            ])
+            .order_by_asc(experiment_metric::Column::ExperimentId)
+            .order_by_asc(experiment_metric::Column::Key)
+            .order_by_desc(experiment_metric::Column::Step)
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .filter_map(|last| {
+                let (min, max) = bounds.get(&(last.experiment_id, last.key.clone()))?;
+                Some((
+                    last.experiment_id,
+                    last.key,
+                    MetricSummary {
+                        last: last.value,
+                        step: last.step,
+                        min: *min,
+                        max: *max,
+                    },
+                ))
+            })
+            .collect();
+
+        Ok(ExperimentComparison {
+            params: side_by_side(&ids, params)
+                .into_iter()
+                .map(|(key, values)| ParamComparison { key, values })
+                .collect(),
+            metrics: side_by_side(&ids, summaries)
+                .into_iter()
+                .map(|(key, values)| MetricComparison { key, values })
+                .collect(),
+            experiments,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{side_by_side, MetricAggregate, Rank, ResultField};
+
+    #[test]
+    fn test_side_by_side() {
+        let compared = side_by_side(
+            &[3, 1],
+            vec![
+                (1, "lr".to_string(), "0.01"),
+                (3, "epochs".to_string(), "10"),
+                (3, "lr".to_string(), "0.1"),
+                (2, "lr".to_string(), "1"),
+            ],
+        );
+        assert_eq!(
+            compared,
+            vec![
+                ("epochs".to_string(), vec![Some("10"), None]),
+                ("lr".to_string(), vec![Some("0.1"), Some("0.01")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rank_of() {
+        assert_eq!(
+            Rank::of(Some("acc".into()), MetricAggregate::Max, None).unwrap(),
+            Rank::Metric {
+                key: "acc".into(),
+                aggregate: MetricAggregate::Max,
+            }
+        );
+        assert_eq!(
+            Rank::of(None, MetricAggregate::Last, Some(ResultField::DurationMs)).unwrap(),
+            Rank::Result(ResultField::DurationMs)
+        );
+        assert!(Rank::of(None, MetricAggregate::Last, None).is_err());
+        assert!(Rank::of(
+            Some("acc".into()),
+            MetricAggregate::Last,
+            Some(ResultField::Retries)
+        )
+        .is_err());
+    }
+}
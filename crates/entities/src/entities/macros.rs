@@ -1,6 +1,6 @@
 #[macro_export]
 macro_rules! bulk_loader {
-    ($model: ty) => {
+    (@find $model: ty, $find: expr) => {
         impl async_graphql::dataloader::Loader<i64> for crate::db::DbLoader<$model> {
             type Value = $model;
             type Error = std::sync::Arc<DbErr>;
@@ -12,7 +12,7 @@ macro_rules! bulk_loader {
                 Output = Result<std::collections::HashMap<i64, Self::Value>, Self::Error>,
             > + Send {
                 async move {
-                    Entity::find()
+                    $find
                         .filter(
                             Column::Id.is_in(keys.iter().map(|it| *it as i64).collect::<Vec<_>>()),
                         )
@@ -28,13 +28,20 @@ macro_rules! bulk_loader {
             }
         }
     };
+    // rows with a `deleted_at` are not loaded
+    ($model: ty, soft_delete) => {
+        $crate::bulk_loader!(@find $model, Entity::find().filter(Column::DeletedAt.is_null()));
+    };
+    ($model: ty) => {
+        $crate::bulk_loader!(@find $model, Entity::find());
+    };
 }
 
 #[macro_export]
 macro_rules! paginated {
-    ($model: ty, $entity: ty) => {
+    (@paginate $model: ty, $entity: ty, $name: ident) => {
         impl $crate::db::DbLoader<$model> {
-            pub async fn load_paginated(
+            pub async fn $name(
                 &self,
                 sel: Select<$entity>,
                 page: $crate::entities::page::PageInput,
@@ -60,6 +67,24 @@ macro_rules! paginated {
             }
         }
     };
+    // rows with a `deleted_at` are only paginated through `load_paginated_with_deleted`
+    ($model: ty, $entity: ty, soft_delete) => {
+        $crate::paginated!(@paginate $model, $entity, load_paginated_with_deleted);
+
+        impl $crate::db::DbLoader<$model> {
+            pub async fn load_paginated(
+                &self,
+                sel: Select<$entity>,
+                page: $crate::entities::page::PageInput,
+            ) -> $crate::entities::page::PaginatedResult<$model> {
+                self.load_paginated_with_deleted(sel.filter(Column::DeletedAt.is_null()), page)
+                    .await
+            }
+        }
+    };
+    ($model: ty, $entity: ty) => {
+        $crate::paginated!(@paginate $model, $entity, load_paginated);
+    };
 }
 
 #[macro_export]
@@ -107,3 +132,53 @@ macro_rules! tags_of {
         }
     };
 }
+
+/// attaching & detaching a namespace tag to the resource `$field` of a tag table refers to
+#[macro_export]
+macro_rules! tag_link {
+    ($field: ident, $col: ident) => {
+        impl $crate::db::DbLoader<Model> {
+            pub async fn attach(
+                &self,
+                owner: i64,
+                tag: i64,
+            ) -> Result<Model, flymodel::errs::FlymodelError> {
+                Entity::insert(ActiveModel {
+                    $field: sea_orm::ActiveValue::Set(owner),
+                    tag: sea_orm::ActiveValue::Set(tag),
+                    ..Default::default()
+                })
+                .on_conflict(
+                    sea_orm::sea_query::OnConflict::columns([Column::$col, Column::Tag])
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(&self.db)
+                .await?;
+                Entity::find()
+                    .filter(Column::$col.eq(owner))
+                    .filter(Column::Tag.eq(tag))
+                    .one(&self.db)
+                    .await?
+                    .ok_or_else(|| {
+                        flymodel::errs::FlymodelError::NonDeterministicError(format!(
+                            "tag {tag} should be attached to {owner}"
+                        ))
+                    })
+            }
+
+            pub async fn detach(
+                &self,
+                owner: i64,
+                tag: i64,
+            ) -> Result<bool, flymodel::errs::FlymodelError> {
+                let res = Entity::delete_many()
+                    .filter(Column::$col.eq(owner))
+                    .filter(Column::Tag.eq(tag))
+                    .exec(&self.db)
+                    .await?;
+                Ok(res.rows_affected == 1)
+            }
+        }
+    };
+}
@@ -1,16 +1,23 @@
 pub mod prelude;
 
+pub mod api_key;
+pub mod audit_log;
 pub mod bucket;
 pub mod enums;
 pub mod experiment;
 pub mod experiment_artifact;
+pub mod experiment_environment;
+pub mod experiment_metric;
+pub mod experiment_param;
 pub mod experiment_result;
 pub mod experiment_state;
 pub mod experiment_tag;
+pub mod leaderboard;
 pub mod macros;
 pub mod model;
 pub mod model_artifact;
 pub mod model_state;
+pub mod model_state_history;
 pub mod model_tag;
 pub mod model_version;
 pub mod model_version_tag;
@@ -18,4 +25,11 @@ pub mod namespace;
 pub mod namespace_tag;
 pub mod object_blob;
 pub mod page;
+pub mod promotion_approval;
+pub mod promotion_policy;
+pub mod promotion_request;
 pub mod upload;
+pub mod upload_ticket;
+pub mod upload_ticket_part;
+pub mod webhook;
+pub mod webhook_delivery;
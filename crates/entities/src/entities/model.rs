@@ -5,8 +5,8 @@ use crate::{
 };
 use async_graphql::SimpleObject;
 use chrono::{DateTime, Utc};
-use flymodel::{errs::FlymodelError, lifecycle::Lifecycle};
-use sea_orm::{entity::prelude::*, ActiveValue};
+use flymodel::{errs::FlymodelError, lifecycle::Lifecycle, perms::ReadScope};
+use sea_orm::{entity::prelude::*, ActiveValue, Condition};
 
 #[derive(
     Clone,
@@ -98,10 +98,12 @@ impl Model {
             .map_err(|it| FlymodelError::DbLoaderError(it).into_graphql_error())
     }
 
+    /// the versions of the model, or those in the trash when `deleted` is set
     async fn versions(
         &self,
         ctx: &async_graphql::Context<'_>,
         version: Option<String>,
+        deleted: Option<bool>,
         page: Option<PageInput>,
     ) -> PaginatedResult<super::model_version::Model> {
         let db = DbLoader::<super::model_version::Model>::with_context(ctx)?.loader();
@@ -110,7 +112,15 @@ impl Model {
         if let Some(version) = version {
             query = db.find_by_version(query, version);
         }
-        db.load_paginated(query, page.unwrap_or_default()).await
+        if deleted.unwrap_or_default() {
+            db.load_paginated_with_deleted(
+                query.filter(super::model_version::Column::DeletedAt.is_not_null()),
+                page.unwrap_or_default(),
+            )
+            .await
+        } else {
+            db.load_paginated(query, page.unwrap_or_default()).await
+        }
     }
 }
 
@@ -123,6 +133,23 @@ impl DbLoader<Model> {
         sel.filter(Column::NamespaceId.is_in(ns))
     }
 
+    pub fn select_mlmodel_scope(&self, sel: Select<Entity>, scope: ReadScope) -> Select<Entity> {
+        sel.filter(
+            Condition::any()
+                .add(Column::NamespaceId.is_in(scope.namespaces))
+                .add(Column::Id.is_in(scope.models)),
+        )
+    }
+
+    pub fn select_mlmodel_tags(&self, sel: Select<Entity>, tags: Vec<String>) -> Select<Entity> {
+        sel.filter(super::namespace_tag::tagged(
+            Column::Id,
+            super::model_tag::Column::ModelId,
+            super::model_tag::Column::Tag,
+            tags,
+        ))
+    }
+
     pub async fn bulk_paginated_models(&self, page: PageInput) -> PaginatedResult<Model> {
         self.load_paginated(Entity::find(), page).await
     }
@@ -142,6 +169,8 @@ impl DbLoader<Model> {
         name: Option<String>,
         ns: Option<Vec<i64>>,
         _roles: Option<Vec<Lifecycle>>,
+        scope: Option<ReadScope>,
+        tags: Option<Vec<String>>,
         page: PageInput,
     ) -> PaginatedResult<Model> {
         let mut sel = Entity::find();
@@ -151,6 +180,12 @@ impl DbLoader<Model> {
         if let Some(ns) = ns {
             sel = self.select_mlmodel_namespace(sel, ns);
         }
+        if let Some(scope) = scope {
+            sel = self.select_mlmodel_scope(sel, scope);
+        }
+        if let Some(tags) = tags {
+            sel = self.select_mlmodel_tags(sel, tags);
+        }
 
         self.load_paginated(sel, page).await
     }
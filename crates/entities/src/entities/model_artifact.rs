@@ -5,7 +5,7 @@ use tracing::warn;
 
 use crate::{bulk_loader, db::DbLoader, paginated, utils::handle::constraint_or_db_operational};
 
-use super::upload::UploadBlobRequestParams;
+use super::{enums::WebhookEvent, upload::UploadBlobRequestParams};
 
 #[derive(
     Clone,
@@ -91,7 +91,7 @@ impl DbLoader<Model> {
             id: ActiveValue::NotSet,
         };
 
-        Ok(Entity::insert(this)
+        let created = Entity::insert(this)
             .exec_with_returning(conn)
             .await
             .map_err(|err| {
@@ -104,7 +104,15 @@ impl DbLoader<Model> {
                         id = version.id
                     ),
                 )
-            })?)
+            })?;
+        DbLoader::<super::webhook_delivery::Model>::enqueue_of_version(
+            conn,
+            version.id,
+            WebhookEvent::ArtifactUploaded,
+            &created,
+        )
+        .await?;
+        Ok(created)
     }
 }
 
@@ -2,8 +2,20 @@ use crate::db::DbLoader;
 
 use async_graphql::{ComplexObject, SimpleObject};
 use chrono::{DateTime, Utc};
-use flymodel::{errs::FlymodelError, lifecycle::Lifecycle};
-use sea_orm::{entity::prelude::*, ActiveValue, IntoActiveModel};
+use flymodel::{
+    errs::FlymodelError,
+    lifecycle::Lifecycle,
+    promotion::{check_transition, VersionFacts},
+};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, DatabaseTransaction, IntoActiveModel, PaginatorTrait,
+    QuerySelect, TransactionTrait,
+};
+
+use super::{
+    enums::{RunState, WebhookEvent},
+    page::{PageInput, PaginatedResult},
+};
 use tracing::warn;
 
 #[derive(
@@ -70,55 +82,124 @@ impl Model {
                 async_graphql::Error::new("Model not found")
             })
     }
+
+    /// the lifecycle transitions of this version, most recent first
+    async fn history(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        page: Option<PageInput>,
+    ) -> PaginatedResult<super::model_state_history::Model> {
+        DbLoader::<super::model_state_history::Model>::with_context(ctx)?
+            .loader()
+            .history(self.version_id, page.unwrap_or_default())
+            .await
+    }
 }
 
 impl DbLoader<Model> {
+    async fn facts<C: ConnectionTrait>(
+        db: &C,
+        version_id: i64,
+    ) -> Result<VersionFacts, FlymodelError> {
+        let passed_experiments = super::experiment::Entity::find()
+            .filter(super::experiment::Column::VersionId.eq(version_id))
+            .inner_join(super::experiment_state::Entity)
+            .filter(super::experiment_state::Column::State.eq(RunState::Passed))
+            .count(db)
+            .await?;
+        let artifacts = super::model_artifact::Entity::find()
+            .filter(super::model_artifact::Column::VersionId.eq(version_id))
+            .count(db)
+            .await?;
+        let tags = super::namespace_tag::Entity::find()
+            .inner_join(super::model_version_tag::Entity)
+            .filter(super::model_version_tag::Column::VersionId.eq(version_id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|tag| tag.tag)
+            .collect();
+        Ok(VersionFacts {
+            passed_experiments,
+            artifacts,
+            tags,
+            ..Default::default()
+        })
+    }
+
+    /// checks the promotion rules & records the transition in one transaction, unless the version already is in `state`
     pub async fn update_state(
         &self,
         version_id: i64,
         state: Lifecycle,
-    ) -> Result<Model, async_graphql::Error> {
-        let current = Entity::find()
-            .filter(Column::VersionId.eq(version_id))
-            .one(&self.db)
+        actor: String,
+        reason: Option<String>,
+    ) -> Result<(Model, Option<super::model_state_history::Model>), async_graphql::Error> {
+        let tx = self
+            .db
+            .begin()
+            .await
+            .map_err(|err| FlymodelError::DbOperationError(err).into_graphql_error())?;
+        let updated = Self::transition(&tx, version_id, state, actor, reason, 0)
             .await
-            .map_err(|err| {
-                FlymodelError::DbLoaderError(std::sync::Arc::new(err)).into_graphql_error()
-            })?;
-        let model = current
-            .ok_or_else(|| FlymodelError::InvalidResourceId(version_id).into_graphql_error())?;
-        let current_state = model.state;
-        if current_state == state {
-            return Ok(model);
+            .map_err(|err| err.into_graphql_error())?;
+        tx.commit()
+            .await
+            .map_err(|err| FlymodelError::DbOperationError(err).into_graphql_error())?;
+        Ok(updated)
+    }
+
+    pub(crate) async fn transition(
+        tx: &DatabaseTransaction,
+        version_id: i64,
+        state: Lifecycle,
+        actor: String,
+        reason: Option<String>,
+        approvals: u64,
+    ) -> Result<(Model, Option<super::model_state_history::Model>), FlymodelError> {
+        super::model_version::live_version(tx, version_id).await?;
+        let model = Entity::find()
+            .filter(Column::VersionId.eq(version_id))
+            .lock_exclusive()
+            .one(tx)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(version_id))?;
+        let current = model.state;
+        if current == state {
+            return Ok((model, None));
         }
 
-        let mut active = model.into_active_model();
-        if current_state < state {
-            match (current_state, state) {
-                (Lifecycle::Test, Lifecycle::Qa)
-                | (Lifecycle::Qa, Lifecycle::Stage)
-                | (Lifecycle::Stage, _) => {
-                    active.state = ActiveValue::Set(state);
-                }
-                _ => {
-                    return Err(FlymodelError::InvalidTransition {
-                        current: current_state,
-                        requested: state,
-                    }
-                    .into_graphql_error())
-                }
-            }
-        } else if current_state == Lifecycle::Prod {
-            return Err(FlymodelError::InvalidTransition {
-                current: current_state,
-                requested: state,
-            }
-            .into_graphql_error());
+        let (namespace, model_id) = super::model_version::owner_of_version(tx, version_id)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(version_id))?;
+        let leaving =
+            DbLoader::<super::promotion_policy::Model>::rules(tx, namespace, current).await?;
+        let entering =
+            DbLoader::<super::promotion_policy::Model>::rules(tx, namespace, state).await?;
+        let mut facts = if state > current && !entering.is_unconstrained() {
+            Self::facts(tx, version_id).await?
         } else {
-            active.state = ActiveValue::Set(state)
-        }
+            VersionFacts::default()
+        };
+        facts.approvals = approvals;
+        check_transition(current, state, &leaving, &entering, &facts)?;
 
-        let model = active.update(&self.db).await?;
-        Ok(model)
+        let mut active = model.into_active_model();
+        active.state = ActiveValue::Set(state);
+        active.last_modified = ActiveValue::Set(Utc::now());
+        let updated = active.update(tx).await?;
+        let recorded = DbLoader::<super::model_state_history::Model>::record(
+            tx, version_id, current, state, actor, reason,
+        )
+        .await?;
+        DbLoader::<super::webhook_delivery::Model>::enqueue(
+            tx,
+            namespace,
+            model_id,
+            WebhookEvent::ModelVersionStateChanged,
+            &recorded,
+        )
+        .await?;
+        Ok((updated, Some(recorded)))
     }
 }
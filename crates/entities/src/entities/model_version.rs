@@ -3,8 +3,12 @@ use crate::{
     utils::sql_errs::parse_column_contraint_violation,
 };
 use async_graphql::{ComplexObject, SimpleObject};
+use chrono::{DateTime, Utc};
 use flymodel::{errs::FlymodelError, lifecycle::Lifecycle};
-use sea_orm::{entity::prelude::*, ActiveValue};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, DatabaseTransaction, IntoActiveModel, JoinType, QueryOrder,
+    QuerySelect, TransactionTrait,
+};
 
 use tracing::warn;
 
@@ -30,6 +34,9 @@ pub struct Model {
     pub model_id: i64,
     #[sea_orm(column_type = "Text")]
     pub version: String,
+    /// when the version was moved to the trash, soft deleted versions can be restored
+    #[serde(skip_deserializing)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -109,12 +116,41 @@ impl ActiveModelBehavior for ActiveModel {
 }
 
 bulk_loader! {
-    Model
+    Model,
+    soft_delete
+}
+
+pub(crate) async fn owner_of_version<C: ConnectionTrait>(
+    db: &C,
+    version_id: i64,
+) -> Result<Option<(i64, i64)>, FlymodelError> {
+    let found = Entity::find_by_id(version_id)
+        .find_also_related(super::model::Entity)
+        .one(db)
+        .await
+        .map_err(FlymodelError::DbOperationError)?;
+    Ok(found
+        .and_then(|(_, model)| model)
+        .map(|model| (model.namespace_id, model.id)))
+}
+
+/// a version which exists & is not in the trash, locked until the transaction ends
+pub(crate) async fn live_version<C: ConnectionTrait>(
+    db: &C,
+    version_id: i64,
+) -> Result<Model, FlymodelError> {
+    Entity::find_by_id(version_id)
+        .filter(Column::DeletedAt.is_null())
+        .lock_shared()
+        .one(db)
+        .await?
+        .ok_or(FlymodelError::InvalidResourceId(version_id))
 }
 
 paginated! {
     Model,
-    Entity
+    Entity,
+    soft_delete
 }
 
 impl DbLoader<Model> {
@@ -126,12 +162,76 @@ impl DbLoader<Model> {
         sel.filter(Column::Version.like(version))
     }
 
-    pub async fn delete_version(&self, id: i64) -> Result<bool, async_graphql::Error> {
-        let res = Entity::delete_by_id(id)
-            .exec(&self.db)
+    /// experiments already in the trash keep their timestamp, so restoring the version only brings back those trashed with it
+    pub async fn soft_delete(&self, id: i64) -> Result<bool, FlymodelError> {
+        let tx = self.db.begin().await?;
+        let now = Utc::now();
+        let res = Entity::update_many()
+            .col_expr(Column::DeletedAt, Expr::value(now))
+            .filter(Column::Id.eq(id))
+            .filter(Column::DeletedAt.is_null())
+            .exec(&tx)
+            .await?;
+        if res.rows_affected != 1 {
+            return Ok(false);
+        }
+        super::experiment::Entity::update_many()
+            .col_expr(super::experiment::Column::DeletedAt, Expr::value(now))
+            .filter(super::experiment::Column::VersionId.eq(id))
+            .filter(super::experiment::Column::DeletedAt.is_null())
+            .exec(&tx)
+            .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn restore(&self, id: i64) -> Result<Model, FlymodelError> {
+        let tx = self.db.begin().await?;
+        let version = Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&tx)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(id))?;
+        let Some(deleted_at) = version.deleted_at else {
+            return Err(FlymodelError::ContraintError(format!(
+                "version {id} is not deleted"
+            )));
+        };
+        let mut active = version.into_active_model();
+        active.deleted_at = ActiveValue::Set(None);
+        let restored = active
+            .update(&tx)
             .await
-            .map_err(|err| FlymodelError::DbOperationError(err).into_graphql_error())?;
-        Ok(res.rows_affected == 1)
+            .map_err(|err| match err.sql_err() {
+                Some(SqlErr::UniqueConstraintViolation(..)) => FlymodelError::ContraintError(
+                    format!("version {id} was recreated since it was deleted"),
+                ),
+                _ => FlymodelError::DbOperationError(err),
+            })?;
+        super::experiment::Entity::update_many()
+            .col_expr(
+                super::experiment::Column::DeletedAt,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            )
+            .filter(super::experiment::Column::VersionId.eq(id))
+            .filter(super::experiment::Column::DeletedAt.eq(deleted_at))
+            .exec(&tx)
+            .await?;
+        tx.commit().await?;
+        Ok(restored)
+    }
+
+    pub async fn purge<C: ConnectionTrait>(
+        db: &C,
+        id: i64,
+    ) -> Result<Option<Vec<super::object_blob::Model>>, FlymodelError> {
+        let blobs = DbLoader::<super::object_blob::Model>::of_version(db, id).await?;
+        if Entity::delete_by_id(id).exec(db).await?.rows_affected == 0 {
+            return Ok(None);
+        }
+        Ok(Some(
+            DbLoader::<super::object_blob::Model>::release(db, blobs).await?,
+        ))
     }
 
     pub async fn create_version(
@@ -170,9 +270,42 @@ impl DbLoader<Model> {
             .map_err(|err| FlymodelError::DbOperationError(err))
     }
 
-    // pub async fn namespace(&self, ent: &Model,) {
-    //     ent.;
-    // }
+    pub async fn owner(&self, id: i64) -> Result<Option<(i64, i64)>, FlymodelError> {
+        owner_of_version(&self.db, id).await
+    }
+
+    pub async fn expired<C: ConnectionTrait>(
+        db: &C,
+        state: Lifecycle,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        Ok(Entity::find()
+            .join(JoinType::InnerJoin, Relation::ModelState.def())
+            .filter(super::model_state::Column::State.eq(state))
+            .filter(super::model_state::Column::LastModified.lt(before))
+            .order_by_asc(super::model_state::Column::LastModified)
+            .all(db)
+            .await?)
+    }
+
+    /// its state is locked, so a concurrent transition waits on the deletion. `None` when it changed lifecycle meanwhile
+    pub async fn expire(
+        tx: &DatabaseTransaction,
+        id: i64,
+        state: Lifecycle,
+        before: DateTime<Utc>,
+    ) -> Result<Option<Vec<super::object_blob::Model>>, FlymodelError> {
+        let current = super::model_state::Entity::find()
+            .filter(super::model_state::Column::VersionId.eq(id))
+            .lock_exclusive()
+            .one(tx)
+            .await?;
+        match current {
+            Some(current) if current.state == state && current.last_modified < before => {}
+            _ => return Ok(None),
+        }
+        Self::purge(tx, id).await
+    }
 }
 
 #[ComplexObject]
@@ -223,6 +356,31 @@ impl Model {
             .await
     }
 
+    /// the lifecycle transitions of this version, most recent first
+    pub async fn history(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        page: Option<PageInput>,
+    ) -> PaginatedResult<super::model_state_history::Model> {
+        DbLoader::<super::model_state_history::Model>::with_context(ctx)?
+            .loader()
+            .history(self.id, page.unwrap_or_default())
+            .await
+    }
+
+    /// requests to promote this version, most recent first
+    pub async fn promotion_requests(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        status: Option<Vec<super::enums::PromotionRequestStatus>>,
+        page: Option<PageInput>,
+    ) -> PaginatedResult<super::promotion_request::Model> {
+        DbLoader::<super::promotion_request::Model>::with_context(ctx)?
+            .loader()
+            .requests(Some(self.id), None, status, None, page.unwrap_or_default())
+            .await
+    }
+
     pub async fn state(
         &self,
         ctx: &async_graphql::Context<'_>,
@@ -234,3 +392,100 @@ impl Model {
             .map_err(|err| err.into_graphql_error())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use flymodel::{errs::FlymodelError, lifecycle::Lifecycle};
+    use sea_orm::{DbConn, EntityTrait};
+
+    use super::{Entity, Model};
+    use crate::{
+        db::DbLoader,
+        entities::{experiment, page::PageInput},
+        testing,
+    };
+
+    async fn listed(db: &DbConn) -> Vec<i64> {
+        DbLoader::<Model>::new(db.clone(), None)
+            .loader()
+            .load_paginated(Entity::find(), PageInput::default())
+            .await
+            .unwrap()
+            .data
+            .into_iter()
+            .map(|version| version.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete_hides_version() {
+        let db = testing::database().await;
+        let ns = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let kept = testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let trashed = testing::version(&db, model.id, "v2", Lifecycle::Test, Utc::now()).await;
+        let loader = DbLoader::<Model>::new(db.clone(), None);
+
+        assert!(loader.loader().soft_delete(trashed.id).await.unwrap());
+        assert!(!loader.loader().soft_delete(trashed.id).await.unwrap());
+        assert_eq!(listed(&db).await, vec![kept.id]);
+        assert!(loader.load_one(trashed.id).await.unwrap().is_none());
+        assert!(loader.load_one(kept.id).await.unwrap().is_some());
+        let found = Entity::find_by_id(trashed.id).one(&db).await.unwrap();
+        assert!(found.unwrap().deleted_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_restore_experiments_trashed_with_version() {
+        let db = testing::database().await;
+        let ns = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let version = testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let before = testing::experiment(&db, version.id, "before").await;
+        let along = testing::experiment(&db, version.id, "along").await;
+        let experiments = DbLoader::<experiment::Model>::new(db.clone(), None);
+        let versions = DbLoader::<Model>::new(db.clone(), None);
+
+        assert!(experiments.loader().soft_delete(before.id).await.unwrap());
+        assert!(versions.loader().soft_delete(version.id).await.unwrap());
+        let restored = versions.loader().restore(version.id).await.unwrap();
+        assert!(restored.deleted_at.is_none());
+        let deleted = |id| {
+            let db = db.clone();
+            async move {
+                experiment::Entity::find_by_id(id)
+                    .one(&db)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .deleted_at
+                    .is_some()
+            }
+        };
+        assert!(deleted(before.id).await);
+        assert!(!deleted(along.id).await);
+        assert!(matches!(
+            versions.loader().restore(version.id).await,
+            Err(FlymodelError::ContraintError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_restore_reused_name() {
+        let db = testing::database().await;
+        let ns = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let version = testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let versions = DbLoader::<Model>::new(db.clone(), None);
+
+        assert!(versions.loader().soft_delete(version.id).await.unwrap());
+        testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        assert!(matches!(
+            versions.loader().restore(version.id).await,
+            Err(FlymodelError::ContraintError(_))
+        ));
+        let found = Entity::find_by_id(version.id).one(&db).await.unwrap();
+        assert!(found.unwrap().deleted_at.is_some());
+    }
+}
@@ -1,7 +1,7 @@
 use async_graphql::{ComplexObject, SimpleObject};
 use sea_orm::entity::prelude::*;
 
-use crate::{bulk_loader, paginated, tags_meta};
+use crate::{bulk_loader, paginated, tag_link, tags_meta};
 
 #[derive(
     Clone,
@@ -16,7 +16,8 @@ use crate::{bulk_loader, paginated, tags_meta};
 #[sea_orm(table_name = "model_version_tag")]
 #[graphql(name = "ModelVersionTag", complex)]
 pub struct Model {
-    #[sea_orm(primary_key, auto_increment = false)]
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
     pub id: i64,
     pub version_id: i64,
     pub tag: i64,
@@ -66,6 +67,11 @@ paginated! {
     Entity
 }
 
+tag_link! {
+    version_id,
+    VersionId
+}
+
 #[ComplexObject]
 impl Model {
     tags_meta! {}
@@ -2,7 +2,7 @@ use crate::{bulk_loader, db::DbLoader, filters::filter_like, paginated};
 use async_graphql::{dataloader::DataLoader, Context, SimpleObject};
 use chrono::Utc;
 
-use flymodel::errs::FlymodelError;
+use flymodel::{errs::FlymodelError, perms::ReadScope};
 use sea_orm::{entity::prelude::*, ActiveValue};
 use tracing::debug;
 
@@ -70,12 +70,16 @@ impl DbLoader<Model> {
     pub async fn bulk_paginated_namespaces(
         &self,
         name: Option<String>,
+        scope: Option<ReadScope>,
         page: PageInput,
     ) -> PaginatedResult<Model> {
         let mut query = Entity::find();
         if let Some(name) = name {
             query = Self::find_by_name(query, name);
         }
+        if let Some(scope) = scope {
+            query = query.filter(Column::Id.is_in(scope.namespaces));
+        }
         self.load_paginated(query, page).await
     }
 
@@ -175,4 +179,32 @@ impl Model {
             .find_by_namespace(vec![self.id], page.unwrap_or_default())
             .await
     }
+
+    /// the tags models, versions & experiments of the namespace may carry
+    async fn tags<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        page: Option<PageInput>,
+    ) -> PaginatedResult<super::namespace_tag::Model> {
+        DbLoader::<super::namespace_tag::Model>::with_context(ctx)?
+            .loader()
+            .load_paginated(
+                super::namespace_tag::Entity::find()
+                    .filter(super::namespace_tag::Column::NamespaceId.eq(self.id)),
+                page.unwrap_or_default(),
+            )
+            .await
+    }
+
+    /// the promotion rules configured for each lifecycle
+    async fn promotion_policies<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+    ) -> Result<Vec<super::promotion_policy::Model>, async_graphql::Error> {
+        DbLoader::<super::promotion_policy::Model>::with_context(ctx)?
+            .loader()
+            .policies(self.id)
+            .await
+            .map_err(|err| err.into_graphql_error())
+    }
 }
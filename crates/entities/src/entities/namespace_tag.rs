@@ -1,7 +1,12 @@
 use async_graphql::{ComplexObject, SimpleObject};
-use sea_orm::entity::prelude::*;
+use flymodel::errs::FlymodelError;
+use sea_orm::{entity::prelude::*, ActiveValue, Condition};
+use sea_query::Query;
 
-use crate::{bulk_loader, paginated, tags_meta};
+use crate::{
+    bulk_loader, db::DbLoader, paginated, tags_meta,
+    utils::sql_errs::parse_column_contraint_violation,
+};
 
 #[derive(
     Clone,
@@ -16,7 +21,8 @@ use crate::{bulk_loader, paginated, tags_meta};
 #[sea_orm(table_name = "namespace_tag")]
 #[graphql(name = "NamespaceTag", complex)]
 pub struct Model {
-    #[sea_orm(primary_key, auto_increment = false)]
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
     pub id: i64,
     pub namespace_id: i64,
     #[sea_orm(column_type = "Text")]
@@ -83,3 +89,127 @@ paginated! {
 impl Model {
     tags_meta! {}
 }
+
+fn valid_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => {
+            matches!(hex.len(), 3 | 6) && hex.chars().all(|digit| digit.is_ascii_hexdigit())
+        }
+        None => false,
+    }
+}
+
+fn check_color(color: &str) -> Result<(), FlymodelError> {
+    if valid_color(color) {
+        Ok(())
+    } else {
+        Err(FlymodelError::ContraintError(format!(
+            "tag colors are hex colors such as #1f77b4, received: {color}"
+        )))
+    }
+}
+
+fn tag_error(namespace: Option<i64>, err: DbErr) -> FlymodelError {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(..)) => {
+            FlymodelError::ContraintError("the namespace already has a tag of this name".into())
+        }
+        Some(SqlErr::ForeignKeyConstraintViolation(source))
+            if parse_column_contraint_violation(&source)
+                == Some("namespace_tag_namespace_id_fkey") =>
+        {
+            FlymodelError::ContraintError(format!(
+                "The given namespace does not exist: {}",
+                namespace.unwrap_or_default()
+            ))
+        }
+        _ => FlymodelError::DbOperationError(err),
+    }
+}
+
+/// rows whose `owner` carries every tag of `tags`, `link` & `link_tag` being the columns of the tag table
+pub(crate) fn tagged<L: ColumnTrait, O: ColumnTrait>(
+    owner: O,
+    link: L,
+    link_tag: L,
+    tags: Vec<String>,
+) -> Condition {
+    tags.into_iter().fold(Condition::all(), |cond, tag| {
+        cond.add(
+            owner.in_subquery(
+                Query::select()
+                    .column(link)
+                    .from(link.entity_name())
+                    .inner_join(
+                        Entity,
+                        Expr::col((Entity, Column::Id)).equals((link.entity_name(), link_tag)),
+                    )
+                    .and_where(Expr::col((Entity, Column::Tag)).eq(tag))
+                    .to_owned(),
+            ),
+        )
+    })
+}
+
+impl DbLoader<Model> {
+    pub async fn create_tag(
+        &self,
+        namespace: i64,
+        tag: String,
+        color: String,
+    ) -> Result<Model, FlymodelError> {
+        check_color(&color)?;
+        ActiveModel {
+            namespace_id: ActiveValue::Set(namespace),
+            tag: ActiveValue::Set(tag),
+            color: ActiveValue::Set(color),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await
+        .map_err(|err| tag_error(Some(namespace), err))
+    }
+
+    pub async fn update_tag(
+        &self,
+        id: i64,
+        tag: Option<String>,
+        color: Option<String>,
+    ) -> Result<Model, FlymodelError> {
+        let mut active = ActiveModel {
+            id: ActiveValue::Unchanged(id),
+            ..Default::default()
+        };
+        if let Some(tag) = tag {
+            active.tag = ActiveValue::Set(tag);
+        }
+        if let Some(color) = color {
+            check_color(&color)?;
+            active.color = ActiveValue::Set(color);
+        }
+        active.update(&self.db).await.map_err(|err| match err {
+            DbErr::RecordNotUpdated => FlymodelError::InvalidResourceId(id),
+            err => tag_error(None, err),
+        })
+    }
+
+    pub async fn delete_tag(&self, id: i64) -> Result<bool, FlymodelError> {
+        let res = Entity::delete_by_id(id).exec(&self.db).await?;
+        Ok(res.rows_affected == 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::valid_color;
+
+    #[test]
+    fn test_valid_color() {
+        assert!(valid_color("#1f77b4"));
+        assert!(valid_color("#FFF"));
+        assert!(!valid_color("1f77b4"));
+        assert!(!valid_color("#1f77b"));
+        assert!(!valid_color("#gggggg"));
+        assert!(!valid_color("red"));
+    }
+}
@@ -8,7 +8,11 @@ use super::{
 use async_graphql::SimpleObject;
 use chrono::{DateTime, Utc};
 use flymodel::errs::FlymodelError;
-use sea_orm::{entity::prelude::*, ActiveValue, DatabaseTransaction};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, Condition, DatabaseTransaction, JoinType, PaginatorTrait,
+    QuerySelect,
+};
+use sea_query::Query;
 use tracing::debug;
 
 #[derive(
@@ -84,6 +88,28 @@ paginated! {
     Entity
 }
 
+fn referenced_by_version(version_id: i64) -> Condition {
+    Condition::any()
+        .add(
+            Column::Id.in_subquery(
+                Query::select()
+                    .column(super::model_artifact::Column::Blob)
+                    .from(super::model_artifact::Entity)
+                    .and_where(super::model_artifact::Column::VersionId.eq(version_id))
+                    .to_owned(),
+            ),
+        )
+        .add(
+            Column::Id.in_subquery(
+                Query::select()
+                    .column(super::experiment_artifact::Column::Blob)
+                    .from(super::experiment_artifact::Entity)
+                    .and_where(super::experiment_artifact::Column::VersionId.eq(version_id))
+                    .to_owned(),
+            ),
+        )
+}
+
 impl DbLoader<Model> {
     pub async fn create_new_blob(
         conn: &DatabaseTransaction,
@@ -112,4 +138,239 @@ impl DbLoader<Model> {
             .await
             .map_err(|err| FlymodelError::DbOperationError(err))
     }
+
+    pub async fn misplaced<C: ConnectionTrait>(
+        db: &C,
+        version_id: i64,
+        bucket_id: i64,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        Ok(Entity::find()
+            .filter(Column::BucketId.ne(bucket_id))
+            .filter(referenced_by_version(version_id))
+            .all(db)
+            .await?)
+    }
+
+    pub async fn of_version<C: ConnectionTrait>(
+        db: &C,
+        version_id: i64,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        Ok(Entity::find()
+            .filter(referenced_by_version(version_id))
+            .all(db)
+            .await?)
+    }
+
+    pub async fn of_experiment<C: ConnectionTrait>(
+        db: &C,
+        experiment_id: i64,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        Ok(Entity::find()
+            .filter(
+                Column::Id.in_subquery(
+                    Query::select()
+                        .column(super::experiment_artifact::Column::Blob)
+                        .from(super::experiment_artifact::Entity)
+                        .and_where(
+                            super::experiment_artifact::Column::ExperimentId.eq(experiment_id),
+                        )
+                        .to_owned(),
+                ),
+            )
+            .all(db)
+            .await?)
+    }
+
+    /// the objects of the released blobs are left in storage for the caller to delete
+    pub async fn release<C: ConnectionTrait>(
+        db: &C,
+        mut blobs: Vec<Model>,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        blobs.sort_unstable_by_key(|blob| blob.id);
+        blobs.dedup_by_key(|blob| blob.id);
+        let ids: Vec<i64> = blobs.iter().map(|blob| blob.id).collect();
+        let mut referenced: Vec<i64> = super::model_artifact::Entity::find()
+            .select_only()
+            .column(super::model_artifact::Column::Blob)
+            .filter(super::model_artifact::Column::Blob.is_in(ids.clone()))
+            .into_tuple()
+            .all(db)
+            .await?;
+        let experiments: Vec<i64> = super::experiment_artifact::Entity::find()
+            .select_only()
+            .column(super::experiment_artifact::Column::Blob)
+            .filter(super::experiment_artifact::Column::Blob.is_in(ids))
+            .into_tuple()
+            .all(db)
+            .await?;
+        referenced.extend(experiments);
+        blobs.retain(|blob| !referenced.contains(&blob.id));
+        if !blobs.is_empty() {
+            Entity::delete_many()
+                .filter(Column::Id.is_in(blobs.iter().map(|blob| blob.id)))
+                .exec(db)
+                .await?;
+        }
+        Ok(blobs)
+    }
+
+    pub async fn orphaned<C: ConnectionTrait>(
+        db: &C,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        Ok(Entity::find()
+            .filter(Column::CreatedAt.lt(before))
+            .filter(
+                Column::Id.not_in_subquery(
+                    Query::select()
+                        .column(super::model_artifact::Column::Blob)
+                        .from(super::model_artifact::Entity)
+                        .to_owned(),
+                ),
+            )
+            .filter(
+                Column::Id.not_in_subquery(
+                    Query::select()
+                        .column(super::experiment_artifact::Column::Blob)
+                        .from(super::experiment_artifact::Entity)
+                        .to_owned(),
+                ),
+            )
+            .all(db)
+            .await?)
+    }
+
+    pub async fn stored<C: ConnectionTrait>(
+        db: &C,
+        bucket_id: i64,
+    ) -> Result<Vec<(String, String)>, FlymodelError> {
+        Ok(Entity::find()
+            .select_only()
+            .column(Column::Key)
+            .column(Column::VersionId)
+            .filter(Column::BucketId.eq(bucket_id))
+            .into_tuple()
+            .all(db)
+            .await?)
+    }
+
+    pub async fn misplaced_versions<C: ConnectionTrait>(db: &C) -> Result<Vec<i64>, FlymodelError> {
+        let outside_role = || {
+            Expr::col((super::bucket::Entity, super::bucket::Column::Role)).ne(Expr::col((
+                super::model_state::Entity,
+                super::model_state::Column::State,
+            )))
+        };
+        let mut versions: Vec<i64> = super::model_artifact::Entity::find()
+            .select_only()
+            .column(super::model_artifact::Column::VersionId)
+            .distinct()
+            .join(
+                JoinType::InnerJoin,
+                super::model_artifact::Relation::ObjectBlob.def(),
+            )
+            .join(JoinType::InnerJoin, Relation::Bucket.def())
+            .join(
+                JoinType::InnerJoin,
+                super::model_artifact::Relation::ModelVersion.def(),
+            )
+            .join(
+                JoinType::InnerJoin,
+                super::model_version::Relation::ModelState.def(),
+            )
+            .filter(outside_role())
+            .into_tuple()
+            .all(db)
+            .await?;
+        let experiments: Vec<i64> = super::experiment_artifact::Entity::find()
+            .select_only()
+            .column(super::experiment_artifact::Column::VersionId)
+            .distinct()
+            .join(
+                JoinType::InnerJoin,
+                super::experiment_artifact::Relation::ObjectBlob.def(),
+            )
+            .join(JoinType::InnerJoin, Relation::Bucket.def())
+            .join(
+                JoinType::InnerJoin,
+                super::experiment_artifact::Relation::ModelVersion.def(),
+            )
+            .join(
+                JoinType::InnerJoin,
+                super::model_version::Relation::ModelState.def(),
+            )
+            .filter(outside_role())
+            .into_tuple()
+            .all(db)
+            .await?;
+        versions.extend(experiments);
+        versions.sort_unstable();
+        versions.dedup();
+        Ok(versions)
+    }
+
+    /// a concurrent relocation of the blob waits on the lock, then finds it no longer referenced
+    pub async fn claim(
+        tx: &DatabaseTransaction,
+        id: i64,
+        version_id: i64,
+    ) -> Result<bool, FlymodelError> {
+        if Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(tx)
+            .await?
+            .is_none()
+        {
+            return Ok(false);
+        }
+        let artifacts = super::model_artifact::Entity::find()
+            .filter(super::model_artifact::Column::Blob.eq(id))
+            .filter(super::model_artifact::Column::VersionId.eq(version_id))
+            .count(tx)
+            .await?;
+        let experiment_artifacts = super::experiment_artifact::Entity::find()
+            .filter(super::experiment_artifact::Column::Blob.eq(id))
+            .filter(super::experiment_artifact::Column::VersionId.eq(version_id))
+            .count(tx)
+            .await?;
+        Ok(artifacts + experiment_artifacts > 0)
+    }
+
+    pub async fn relocate(
+        tx: &DatabaseTransaction,
+        blob: &Model,
+        bucket_id: i64,
+        version_id: String,
+        model_version_id: i64,
+    ) -> Result<Model, FlymodelError> {
+        let copy = ActiveModel {
+            bucket_id: ActiveValue::Set(bucket_id),
+            key: ActiveValue::Set(blob.key.clone()),
+            version_id: ActiveValue::Set(version_id),
+            size: ActiveValue::Set(blob.size),
+            sha256: ActiveValue::Set(blob.sha256.clone()),
+            encode: ActiveValue::Set(blob.encode),
+            format: ActiveValue::Set(blob.format),
+            created_at: ActiveValue::Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(tx)
+        .await?;
+        super::model_artifact::Entity::update_many()
+            .col_expr(super::model_artifact::Column::Blob, Expr::value(copy.id))
+            .filter(super::model_artifact::Column::Blob.eq(blob.id))
+            .filter(super::model_artifact::Column::VersionId.eq(model_version_id))
+            .exec(tx)
+            .await?;
+        super::experiment_artifact::Entity::update_many()
+            .col_expr(
+                super::experiment_artifact::Column::Blob,
+                Expr::value(copy.id),
+            )
+            .filter(super::experiment_artifact::Column::Blob.eq(blob.id))
+            .filter(super::experiment_artifact::Column::VersionId.eq(model_version_id))
+            .exec(tx)
+            .await?;
+        Ok(copy)
+    }
 }
@@ -56,6 +56,10 @@ impl PageOutput {
     name = "PaginatedModelState",
     params(crate::entities::model_state::Model)
 ))]
+#[graphql(concrete(
+    name = "PaginatedModelStateHistory",
+    params(crate::entities::model_state_history::Model)
+))]
 #[graphql(concrete(
     name = "PaginatedModelVersion",
     params(crate::entities::model_version::Model)
@@ -81,6 +85,22 @@ impl PageOutput {
     params(crate::entities::model_version_tag::Model)
 ))]
 #[graphql(concrete(name = "PaginatedModelTag", params(crate::entities::model_tag::Model)))]
+#[graphql(concrete(
+    name = "PaginatedPromotionRequest",
+    params(crate::entities::promotion_request::Model)
+))]
+#[graphql(concrete(
+    name = "PaginatedLeaderboardEntry",
+    params(crate::entities::leaderboard::LeaderboardEntry)
+))]
+#[graphql(concrete(
+    name = "PaginatedWebhookDelivery",
+    params(crate::entities::webhook_delivery::Model)
+))]
+#[graphql(concrete(
+    name = "PaginatedAuditLogEntry",
+    params(crate::entities::audit_log::Model)
+))]
 pub struct Paginated<T>
 where
     T: OutputType + Send + Clone,
@@ -1,7 +1,10 @@
 pub use super::{
-    bucket::Entity as Bucket, experiment::Entity as Experiment,
+    api_key::Entity as ApiKey, bucket::Entity as Bucket, experiment::Entity as Experiment,
     experiment_artifact::Entity as ExperimentArtifact, model::Entity as Model,
     model_artifact::Entity as ModelArtifact, model_state::Entity as ModelState,
-    model_version::Entity as ModelVersion, namespace::Entity as Namespace,
-    object_blob::Entity as ObjectBlob,
+    model_state_history::Entity as ModelStateHistory, model_version::Entity as ModelVersion,
+    namespace::Entity as Namespace, object_blob::Entity as ObjectBlob,
+    promotion_approval::Entity as PromotionApproval, promotion_policy::Entity as PromotionPolicy,
+    promotion_request::Entity as PromotionRequest, upload_ticket::Entity as UploadTicket,
+    upload_ticket_part::Entity as UploadTicketPart,
 };
@@ -0,0 +1,107 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use sea_orm::{entity::prelude::*, ActiveValue, PaginatorTrait, QueryOrder, SqlErr};
+
+use crate::{bulk_loader, db::DbLoader};
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(table_name = "promotion_approval")]
+#[graphql(name = "PromotionApproval")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    pub request_id: i64,
+    /// the subject of the principal which decided on the request
+    #[sea_orm(column_type = "Text")]
+    pub approver: String,
+    /// false when the request was rejected
+    pub approved: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub comment: Option<String>,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::promotion_request::Entity",
+        from = "Column::RequestId",
+        to = "super::promotion_request::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    PromotionRequest,
+}
+
+impl Related<super::promotion_request::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PromotionRequest.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+impl DbLoader<Model> {
+    pub async fn approvals(&self, request_id: i64) -> Result<Vec<Model>, FlymodelError> {
+        Entity::find()
+            .filter(Column::RequestId.eq(request_id))
+            .order_by_asc(Column::CreatedAt)
+            .all(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)
+    }
+
+    /// records the decision of an approver, who may only decide on a request once
+    pub async fn record<C: ConnectionTrait>(
+        db: &C,
+        request_id: i64,
+        approver: String,
+        approved: bool,
+        comment: Option<String>,
+    ) -> Result<Model, FlymodelError> {
+        ActiveModel {
+            request_id: ActiveValue::Set(request_id),
+            approver: ActiveValue::Set(approver.clone()),
+            approved: ActiveValue::Set(approved),
+            comment: ActiveValue::Set(comment),
+            created_at: ActiveValue::Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .map_err(|err| match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(..)) => FlymodelError::ContraintError(format!(
+                "{approver} has already decided on promotion request {request_id}"
+            )),
+            _ => FlymodelError::DbOperationError(err),
+        })
+    }
+
+    pub async fn count_approved<C: ConnectionTrait>(
+        db: &C,
+        request_id: i64,
+    ) -> Result<u64, FlymodelError> {
+        Entity::find()
+            .filter(Column::RequestId.eq(request_id))
+            .filter(Column::Approved.eq(true))
+            .count(db)
+            .await
+            .map_err(FlymodelError::DbOperationError)
+    }
+}
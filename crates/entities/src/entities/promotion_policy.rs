@@ -0,0 +1,176 @@
+use async_graphql::{ComplexObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use flymodel::{errs::FlymodelError, lifecycle::Lifecycle, promotion::PromotionRules};
+use sea_orm::{entity::prelude::*, sea_query::OnConflict, ActiveValue};
+
+use crate::{bulk_loader, db::DbLoader};
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(table_name = "promotion_policy")]
+#[graphql(name = "PromotionPolicy", complex)]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    pub namespace_id: i64,
+    /// the lifecycle the rules guard
+    pub state: Lifecycle,
+    pub require_passed_experiment: bool,
+    pub min_artifacts: i32,
+    #[graphql(skip)]
+    pub required_tags: Json,
+    pub block_demotion: bool,
+    /// approvals a promotion request into the lifecycle needs
+    pub required_approvals: i32,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub last_modified: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::namespace::Entity",
+        from = "Column::NamespaceId",
+        to = "super::namespace::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Namespace,
+}
+
+impl Related<super::namespace::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Namespace.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+impl Model {
+    pub fn tags(&self) -> Vec<String> {
+        self.required_tags
+            .as_array()
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(|tag| tag.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn rules(&self) -> PromotionRules {
+        PromotionRules {
+            require_passed_experiment: self.require_passed_experiment,
+            min_artifacts: self.min_artifacts.max(0) as u64,
+            required_tags: self.tags(),
+            block_demotion: self.block_demotion,
+            required_approvals: self.required_approvals.max(0) as u64,
+        }
+    }
+}
+
+#[ComplexObject]
+impl Model {
+    /// tags a version must carry to enter the lifecycle
+    async fn required_tags(&self) -> Vec<String> {
+        self.tags()
+    }
+}
+
+impl DbLoader<Model> {
+    pub async fn policies(&self, namespace_id: i64) -> Result<Vec<Model>, FlymodelError> {
+        Entity::find()
+            .filter(Column::NamespaceId.eq(namespace_id))
+            .all(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)
+    }
+
+    /// the rules of a lifecycle in a namespace, falling back to the defaults
+    pub async fn rules<C: ConnectionTrait>(
+        db: &C,
+        namespace_id: i64,
+        state: Lifecycle,
+    ) -> Result<PromotionRules, FlymodelError> {
+        Ok(Entity::find()
+            .filter(Column::NamespaceId.eq(namespace_id))
+            .filter(Column::State.eq(state))
+            .one(db)
+            .await
+            .map_err(FlymodelError::DbOperationError)?
+            .map(|policy| policy.rules())
+            .unwrap_or_else(|| PromotionRules::default_for(state)))
+    }
+
+    pub async fn set_policy(
+        &self,
+        namespace_id: i64,
+        state: Lifecycle,
+        rules: PromotionRules,
+    ) -> Result<Model, FlymodelError> {
+        let min_artifacts = i32::try_from(rules.min_artifacts).map_err(|_| {
+            FlymodelError::ContraintError(format!("min_artifacts may be at most {}", i32::MAX))
+        })?;
+        let required_approvals = i32::try_from(rules.required_approvals).map_err(|_| {
+            FlymodelError::ContraintError(format!("required_approvals may be at most {}", i32::MAX))
+        })?;
+        let policy = ActiveModel {
+            namespace_id: ActiveValue::Set(namespace_id),
+            state: ActiveValue::Set(state),
+            require_passed_experiment: ActiveValue::Set(rules.require_passed_experiment),
+            min_artifacts: ActiveValue::Set(min_artifacts),
+            required_tags: ActiveValue::Set(Json::Array(
+                rules.required_tags.into_iter().map(Json::String).collect(),
+            )),
+            block_demotion: ActiveValue::Set(rules.block_demotion),
+            required_approvals: ActiveValue::Set(required_approvals),
+            last_modified: ActiveValue::Set(Utc::now()),
+            ..Default::default()
+        };
+        Entity::insert(policy)
+            .on_conflict(
+                OnConflict::columns([Column::NamespaceId, Column::State])
+                    .update_columns([
+                        Column::RequirePassedExperiment,
+                        Column::MinArtifacts,
+                        Column::RequiredTags,
+                        Column::BlockDemotion,
+                        Column::RequiredApprovals,
+                        Column::LastModified,
+                    ])
+                    .to_owned(),
+            )
+            .exec_with_returning(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)
+    }
+
+    pub async fn delete_policy(
+        &self,
+        namespace_id: i64,
+        state: Lifecycle,
+    ) -> Result<bool, FlymodelError> {
+        let res = Entity::delete_many()
+            .filter(Column::NamespaceId.eq(namespace_id))
+            .filter(Column::State.eq(state))
+            .exec(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        Ok(res.rows_affected == 1)
+    }
+}
@@ -0,0 +1,410 @@
+use async_graphql::{ComplexObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use flymodel::{
+    errs::FlymodelError,
+    lifecycle::Lifecycle,
+    perms::{Principal, ReadScope},
+    promotion::skipped_lifecycle,
+};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, Condition, IntoActiveModel, JoinType, QueryOrder, QuerySelect,
+    SqlErr, TransactionTrait,
+};
+
+use crate::{bulk_loader, db::DbLoader, paginated};
+
+use super::{
+    enums::PromotionRequestStatus,
+    page::{PageInput, PaginatedResult},
+};
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(table_name = "promotion_request")]
+#[graphql(name = "PromotionRequest", complex)]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    pub version_id: i64,
+    /// the lifecycle the version moves to once the request is approved
+    pub state: Lifecycle,
+    /// the subject of the principal which opened the request
+    #[sea_orm(column_type = "Text")]
+    pub requested_by: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub reason: Option<String>,
+    pub status: PromotionRequestStatus,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub last_modified: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::model_version::Entity",
+        from = "Column::VersionId",
+        to = "super::model_version::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    ModelVersion,
+    #[sea_orm(has_many = "super::promotion_approval::Entity")]
+    PromotionApproval,
+}
+
+impl Related<super::model_version::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ModelVersion.def()
+    }
+}
+
+impl Related<super::promotion_approval::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PromotionApproval.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+paginated! {
+    Model,
+    Entity
+}
+
+#[ComplexObject]
+impl Model {
+    /// the decisions made on the request so far, oldest first
+    async fn approvals(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> Result<Vec<super::promotion_approval::Model>, async_graphql::Error> {
+        DbLoader::<super::promotion_approval::Model>::with_context(ctx)?
+            .loader()
+            .approvals(self.id)
+            .await
+            .map_err(|err| err.into_graphql_error())
+    }
+
+    async fn version(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+    ) -> Result<Option<super::model_version::Model>, async_graphql::Error> {
+        DbLoader::<super::model_version::Model>::with_context(ctx)?
+            .load_one(self.version_id)
+            .await
+            .map_err(|err| FlymodelError::DbLoaderError(err).into_graphql_error())
+    }
+}
+
+impl DbLoader<Model> {
+    pub async fn requests(
+        &self,
+        version_id: Option<i64>,
+        namespace: Option<i64>,
+        status: Option<Vec<PromotionRequestStatus>>,
+        scope: Option<ReadScope>,
+        page: PageInput,
+    ) -> PaginatedResult<Model> {
+        let mut sel = Entity::find();
+        if let Some(version_id) = version_id {
+            sel = sel.filter(Column::VersionId.eq(version_id));
+        }
+        if let Some(status) = status {
+            sel = sel.filter(Column::Status.is_in(status));
+        }
+        if namespace.is_some() || scope.is_some() {
+            sel = sel
+                .join(JoinType::InnerJoin, Relation::ModelVersion.def())
+                .join(
+                    JoinType::InnerJoin,
+                    super::model_version::Relation::Model.def(),
+                );
+        }
+        if let Some(namespace) = namespace {
+            sel = sel.filter(super::model::Column::NamespaceId.eq(namespace));
+        }
+        if let Some(scope) = scope {
+            sel = sel.filter(
+                Condition::any()
+                    .add(super::model::Column::NamespaceId.is_in(scope.namespaces))
+                    .add(super::model::Column::Id.is_in(scope.models)),
+            );
+        }
+        self.load_paginated(sel.order_by_desc(Column::Id), page)
+            .await
+    }
+
+    pub async fn owner(&self, id: i64) -> Result<Option<(i64, i64)>, FlymodelError> {
+        let request = Entity::find_by_id(id)
+            .one(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        match request {
+            Some(request) => {
+                super::model_version::owner_of_version(&self.db, request.version_id).await
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// a version has one open request at a time
+    pub async fn open_request(
+        &self,
+        version_id: i64,
+        state: Lifecycle,
+        requested_by: String,
+        reason: Option<String>,
+    ) -> Result<Model, FlymodelError> {
+        super::model_version::live_version(&self.db, version_id).await?;
+        let current = super::model_state::Entity::find()
+            .filter(super::model_state::Column::VersionId.eq(version_id))
+            .one(&self.db)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(version_id))?;
+        if state <= current.state {
+            return Err(FlymodelError::ContraintError(format!(
+                "version {version_id} is already {}, promotion requests move to a later lifecycle",
+                current.state
+            )));
+        }
+        // a request which skips a lifecycle could never be applied once approved
+        if let Some(skipped) = skipped_lifecycle(current.state, state) {
+            return Err(FlymodelError::InvalidTransition {
+                current: current.state,
+                requested: state,
+                failed: vec![skipped],
+            });
+        }
+        let (namespace, _) = super::model_version::owner_of_version(&self.db, version_id)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(version_id))?;
+        let rules =
+            DbLoader::<super::promotion_policy::Model>::rules(&self.db, namespace, state).await?;
+        if rules.required_approvals == 0 {
+            return Err(FlymodelError::ContraintError(format!(
+                "{state} does not require approval, update the version state instead"
+            )));
+        }
+
+        ActiveModel {
+            version_id: ActiveValue::Set(version_id),
+            state: ActiveValue::Set(state),
+            requested_by: ActiveValue::Set(requested_by),
+            reason: ActiveValue::Set(reason),
+            status: ActiveValue::Set(PromotionRequestStatus::Open),
+            created_at: ActiveValue::Set(Utc::now()),
+            last_modified: ActiveValue::Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await
+        .map_err(|err| match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(..)) => FlymodelError::ContraintError(format!(
+                "version {version_id} already has an open promotion request"
+            )),
+            _ => FlymodelError::DbOperationError(err),
+        })
+    }
+
+    /// the approval meeting the quorum applies the promotion in the same transaction.
+    /// requesters may not decide on their own requests, unless no authorizer tells callers apart
+    pub async fn decide(
+        &self,
+        id: i64,
+        approver: &Principal,
+        approved: bool,
+        comment: Option<String>,
+    ) -> Result<(Model, Option<super::model_state_history::Model>), FlymodelError> {
+        let tx = self.db.begin().await?;
+        let request = Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&tx)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(id))?;
+        if request.status != PromotionRequestStatus::Open {
+            return Err(FlymodelError::ContraintError(format!(
+                "promotion request {id} is no longer open"
+            )));
+        }
+        if request.requested_by == approver.subject && !approver.is_anonymous() {
+            return Err(FlymodelError::ContraintError(
+                "requesters may not decide on their own promotion request".into(),
+            ));
+        }
+        DbLoader::<super::promotion_approval::Model>::record(
+            &tx,
+            id,
+            approver.subject.clone(),
+            approved,
+            comment,
+        )
+        .await?;
+
+        let mut transitioned = None;
+        let status = if approved {
+            let approvals =
+                DbLoader::<super::promotion_approval::Model>::count_approved(&tx, id).await?;
+            let (namespace, _) = super::model_version::owner_of_version(&tx, request.version_id)
+                .await?
+                .ok_or(FlymodelError::InvalidResourceId(request.version_id))?;
+            let rules =
+                DbLoader::<super::promotion_policy::Model>::rules(&tx, namespace, request.state)
+                    .await?;
+            if approvals >= rules.required_approvals {
+                let reason = match &request.reason {
+                    Some(reason) => format!("promotion request {id}: {reason}"),
+                    None => format!("promotion request {id}"),
+                };
+                (_, transitioned) = DbLoader::<super::model_state::Model>::transition(
+                    &tx,
+                    request.version_id,
+                    request.state,
+                    approver.subject.clone(),
+                    Some(reason),
+                    approvals,
+                )
+                .await?;
+                PromotionRequestStatus::Approved
+            } else {
+                PromotionRequestStatus::Open
+            }
+        } else {
+            PromotionRequestStatus::Rejected
+        };
+
+        let mut active = request.into_active_model();
+        active.status = ActiveValue::Set(status);
+        active.last_modified = ActiveValue::Set(Utc::now());
+        let updated = active.update(&tx).await?;
+        tx.commit().await?;
+        Ok((updated, transitioned))
+    }
+
+    pub async fn cancel(&self, id: i64) -> Result<Model, FlymodelError> {
+        let res = Entity::update_many()
+            .col_expr(
+                Column::Status,
+                Expr::value(PromotionRequestStatus::Cancelled),
+            )
+            .col_expr(Column::LastModified, Expr::value(Utc::now()))
+            .filter(Column::Id.eq(id))
+            .filter(Column::Status.eq(PromotionRequestStatus::Open))
+            .exec(&self.db)
+            .await?;
+        if res.rows_affected != 1 {
+            return Err(FlymodelError::ContraintError(format!(
+                "promotion request {id} is no longer open"
+            )));
+        }
+        Entity::find_by_id(id)
+            .one(&self.db)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use flymodel::{
+        errs::FlymodelError,
+        lifecycle::Lifecycle,
+        perms::{Perm, Permission, Permissions, Principal},
+    };
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    use super::Model;
+    use crate::{
+        db::DbLoader,
+        entities::{enums::PromotionRequestStatus, model_state},
+        testing,
+    };
+
+    #[tokio::test]
+    async fn test_decide_own_request() {
+        let db = testing::database().await;
+        let ns = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let version = testing::version(&db, model.id, "v1", Lifecycle::Stage, Utc::now()).await;
+        let requests = DbLoader::<Model>::new(db.clone(), None);
+
+        let alice = Principal::new(
+            "alice",
+            Permissions::new(vec![Permission::Global { perm: Perm::W }]),
+        );
+        let opened = requests
+            .loader()
+            .open_request(version.id, Lifecycle::Prod, alice.subject.clone(), None)
+            .await
+            .unwrap();
+        let err = requests
+            .loader()
+            .decide(opened.id, &alice, true, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FlymodelError::ContraintError(..)), "{err}");
+        requests.loader().cancel(opened.id).await.unwrap();
+
+        // without an authorizer every caller is the same, so they approve their own requests
+        let anonymous = Principal::anonymous();
+        let opened = requests
+            .loader()
+            .open_request(version.id, Lifecycle::Prod, anonymous.subject.clone(), None)
+            .await
+            .unwrap();
+        let (decided, transitioned) = requests
+            .loader()
+            .decide(opened.id, &anonymous, true, None)
+            .await
+            .unwrap();
+        assert_eq!(decided.status, PromotionRequestStatus::Approved);
+        assert_eq!(transitioned.unwrap().state, Lifecycle::Prod);
+        let state = model_state::Entity::find()
+            .filter(model_state::Column::VersionId.eq(version.id))
+            .one(&db)
+            .await
+            .unwrap();
+        assert_eq!(state.map(|state| state.state), Some(Lifecycle::Prod));
+    }
+
+    #[tokio::test]
+    async fn test_open_request_skipping_lifecycle() {
+        let db = testing::database().await;
+        let ns = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let version = testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let requests = DbLoader::<Model>::new(db.clone(), None);
+
+        let err = requests
+            .loader()
+            .open_request(version.id, Lifecycle::Prod, "alice".into(), None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                FlymodelError::InvalidTransition {
+                    current: Lifecycle::Test,
+                    requested: Lifecycle::Prod,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,503 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseTransaction, QuerySelect, SqlErr};
+
+use crate::{bulk_loader, db::DbLoader};
+
+use super::{
+    enums::{ArchiveCompression, ArchiveFormat, UploadTicketStatus},
+    upload::UploadBlobRequestParams,
+};
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(table_name = "upload_tickets")]
+#[graphql(name = "UploadTicket")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    pub status: UploadTicketStatus,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub last_modified: DateTime<Utc>,
+    pub bucket_id: i64,
+    #[sea_orm(column_type = "Text")]
+    pub key: String,
+    pub model_version_id: Option<i64>,
+    pub experiment_id: Option<i64>,
+    #[sea_orm(column_type = "Text")]
+    pub artifact_name: String,
+    pub encode: Option<ArchiveCompression>,
+    pub format: Option<ArchiveFormat>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub extra: Option<Json>,
+    pub size: i64,
+    pub sha256: String,
+    #[sea_orm(column_type = "Text")]
+    pub created_by: String,
+    pub expires_at: DateTime<Utc>,
+    pub blob: Option<i64>,
+    pub chunk_size: Option<i64>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::bucket::Entity",
+        from = "Column::BucketId",
+        to = "super::bucket::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Bucket,
+    #[sea_orm(
+        belongs_to = "super::model_version::Entity",
+        from = "Column::ModelVersionId",
+        to = "super::model_version::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    ModelVersion,
+    #[sea_orm(
+        belongs_to = "super::experiment::Entity",
+        from = "Column::ExperimentId",
+        to = "super::experiment::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Experiment,
+    #[sea_orm(
+        belongs_to = "super::object_blob::Entity",
+        from = "Column::Blob",
+        to = "super::object_blob::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    ObjectBlob,
+    #[sea_orm(has_many = "super::upload_ticket_part::Entity")]
+    UploadTicketPart,
+}
+
+impl Related<super::bucket::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bucket.def()
+    }
+}
+
+impl Related<super::object_blob::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ObjectBlob.def()
+    }
+}
+
+impl Related<super::upload_ticket_part::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UploadTicketPart.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum UploadTarget {
+    ModelVersion(i64),
+    Experiment(i64),
+}
+
+pub struct NewUploadTicket {
+    pub bucket_id: i64,
+    pub key: String,
+    pub target: UploadTarget,
+    pub blob: UploadBlobRequestParams,
+    pub extra: Option<Json>,
+    pub size: i64,
+    pub sha256: String,
+    pub created_by: String,
+    pub expires_at: DateTime<Utc>,
+    /// set for resumable uploads, which receive the artifact in chunks
+    pub chunk_size: Option<i64>,
+}
+
+impl Model {
+    pub fn target(&self) -> Result<UploadTarget, FlymodelError> {
+        match (self.model_version_id, self.experiment_id) {
+            (Some(id), None) => Ok(UploadTarget::ModelVersion(id)),
+            (None, Some(id)) => Ok(UploadTarget::Experiment(id)),
+            _ => Err(FlymodelError::NonDeterministicError(format!(
+                "upload ticket {} must reference exactly one artifact owner",
+                self.id
+            ))),
+        }
+    }
+
+    pub fn chunks(&self) -> Option<i64> {
+        self.chunk_size
+            .map(|chunk_size| (self.size + chunk_size - 1) / chunk_size)
+    }
+
+    pub fn chunk_len(&self, part_number: i32) -> Option<i64> {
+        let chunk_size = self.chunk_size?;
+        let part_number = part_number as i64;
+        if part_number < 1 || part_number > self.chunks()? {
+            return None;
+        }
+        Some(chunk_size.min(self.size - (part_number - 1) * chunk_size))
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self.status,
+            UploadTicketStatus::Pending | UploadTicketStatus::Uploading
+        )
+    }
+
+    pub fn blob_params(&self) -> UploadBlobRequestParams {
+        UploadBlobRequestParams {
+            artifact_name: self.artifact_name.clone(),
+            encode: self.encode,
+            format: self.format,
+        }
+    }
+}
+
+impl DbLoader<Model> {
+    pub async fn create_ticket(&self, ticket: NewUploadTicket) -> Result<Model, FlymodelError> {
+        let (model_version_id, experiment_id) = match ticket.target {
+            UploadTarget::ModelVersion(id) => (Some(id), None),
+            UploadTarget::Experiment(id) => (None, Some(id)),
+        };
+        ActiveModel {
+            status: ActiveValue::Set(UploadTicketStatus::Pending),
+            bucket_id: ActiveValue::Set(ticket.bucket_id),
+            key: ActiveValue::Set(ticket.key.clone()),
+            model_version_id: ActiveValue::Set(model_version_id),
+            experiment_id: ActiveValue::Set(experiment_id),
+            artifact_name: ActiveValue::Set(ticket.blob.artifact_name),
+            encode: ActiveValue::Set(ticket.blob.encode),
+            format: ActiveValue::Set(ticket.blob.format),
+            extra: ActiveValue::Set(ticket.extra),
+            size: ActiveValue::Set(ticket.size),
+            sha256: ActiveValue::Set(ticket.sha256),
+            created_by: ActiveValue::Set(ticket.created_by),
+            expires_at: ActiveValue::Set(ticket.expires_at),
+            chunk_size: ActiveValue::Set(ticket.chunk_size),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await
+        .map_err(|err| match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(..)) => FlymodelError::ContraintError(format!(
+                "an upload to {} is already in progress",
+                ticket.key
+            )),
+            _ => FlymodelError::DbOperationError(err),
+        })
+    }
+
+    pub async fn transition_ticket(
+        &self,
+        id: i64,
+        from: &[UploadTicketStatus],
+        to: UploadTicketStatus,
+    ) -> Result<bool, FlymodelError> {
+        let res = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(to))
+            .col_expr(Column::LastModified, Expr::value(Utc::now()))
+            .filter(Column::Id.eq(id))
+            .filter(Column::Status.is_in(from.iter().copied()))
+            .exec(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        Ok(res.rows_affected == 1)
+    }
+
+    /// moves the ticket to uploading & pushes its expiry back, failing once it is no longer active
+    pub async fn receive_chunk(
+        conn: &DatabaseTransaction,
+        id: i64,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), FlymodelError> {
+        let res = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(UploadTicketStatus::Uploading))
+            .col_expr(Column::ExpiresAt, Expr::value(expires_at))
+            .col_expr(Column::LastModified, Expr::value(Utc::now()))
+            .filter(Column::Id.eq(id))
+            .filter(
+                Column::Status.is_in([UploadTicketStatus::Pending, UploadTicketStatus::Uploading]),
+            )
+            .filter(Column::ExpiresAt.gt(Utc::now()))
+            .exec(conn)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        if res.rows_affected != 1 {
+            return Err(FlymodelError::ContraintError(format!(
+                "upload ticket {id} is no longer accepting chunks"
+            )));
+        }
+        Ok(())
+    }
+
+    pub async fn expire_stale(&self) -> Result<u64, FlymodelError> {
+        let res = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(UploadTicketStatus::Expired))
+            .col_expr(Column::LastModified, Expr::value(Utc::now()))
+            .filter(
+                Column::Status.is_in([UploadTicketStatus::Pending, UploadTicketStatus::Uploading]),
+            )
+            .filter(Column::ExpiresAt.lt(Utc::now()))
+            .exec(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        Ok(res.rows_affected)
+    }
+
+    pub async fn awaited_keys<C: ConnectionTrait>(
+        db: &C,
+        bucket_id: i64,
+    ) -> Result<Vec<String>, FlymodelError> {
+        Ok(Entity::find()
+            .select_only()
+            .column(Column::Key)
+            .filter(Column::BucketId.eq(bucket_id))
+            .filter(
+                Column::Status.is_in([UploadTicketStatus::Pending, UploadTicketStatus::Uploading]),
+            )
+            .into_tuple()
+            .all(db)
+            .await?)
+    }
+
+    pub async fn complete_ticket(
+        conn: &DatabaseTransaction,
+        id: i64,
+        blob: i64,
+    ) -> Result<(), FlymodelError> {
+        let res = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(UploadTicketStatus::Completed))
+            .col_expr(Column::Blob, Expr::value(blob))
+            .col_expr(Column::LastModified, Expr::value(Utc::now()))
+            .filter(Column::Id.eq(id))
+            .filter(Column::Status.eq(UploadTicketStatus::Uploading))
+            .exec(conn)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        if res.rows_affected != 1 {
+            return Err(FlymodelError::ContraintError(format!(
+                "upload ticket {id} is no longer uploading"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+    use flymodel::{errs::FlymodelError, lifecycle::Lifecycle};
+    use sea_orm::{ActiveModelTrait, ActiveValue, DbConn, TransactionTrait};
+
+    use super::{ActiveModel, Model};
+    use crate::{db::DbLoader, entities::enums::UploadTicketStatus, testing};
+
+    fn sized(size: i64, chunk_size: i64) -> Model {
+        Model {
+            id: 1,
+            status: UploadTicketStatus::Pending,
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+            bucket_id: 1,
+            key: "weights".into(),
+            model_version_id: Some(1),
+            experiment_id: None,
+            artifact_name: "weights".into(),
+            encode: None,
+            format: None,
+            extra: None,
+            size,
+            sha256: String::new(),
+            created_by: "test".into(),
+            expires_at: Utc::now(),
+            blob: None,
+            chunk_size: Some(chunk_size),
+        }
+    }
+
+    async fn resumable(db: &DbConn, key: &str, status: UploadTicketStatus) -> Model {
+        let ns = testing::namespace(db, key).await;
+        let bucket = testing::bucket(db, ns.id, key, Lifecycle::Test).await;
+        let model = testing::model(db, ns.id, key).await;
+        let version = testing::version(db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        let ticket = testing::upload_ticket(db, bucket.id, version.id, key, status).await;
+        ActiveModel {
+            id: ActiveValue::Unchanged(ticket.id),
+            chunk_size: ActiveValue::Set(Some(4)),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+        .unwrap()
+    }
+
+    async fn expire(db: &DbConn, ticket: &Model) {
+        ActiveModel {
+            id: ActiveValue::Unchanged(ticket.id),
+            expires_at: ActiveValue::Set(Utc::now() - Duration::minutes(1)),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+        .unwrap();
+    }
+
+    async fn receive(db: &DbConn, id: i64) -> Result<(), FlymodelError> {
+        let tx = db.begin().await.unwrap();
+        DbLoader::<Model>::receive_chunk(&tx, id, Utc::now() + Duration::hours(1)).await?;
+        tx.commit().await.unwrap();
+        Ok(())
+    }
+
+    async fn complete(db: &DbConn, ticket: &Model, version: &str) -> Result<(), FlymodelError> {
+        let blob = testing::blob(db, ticket.bucket_id, &ticket.key, version, Utc::now()).await;
+        let tx = db.begin().await.unwrap();
+        DbLoader::<Model>::complete_ticket(&tx, ticket.id, blob.id).await?;
+        tx.commit().await.unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunk_lengths() {
+        let partial = sized(11, 4);
+        assert_eq!(partial.chunks(), Some(3));
+        assert_eq!(
+            (0..=4)
+                .map(|part| partial.chunk_len(part))
+                .collect::<Vec<_>>(),
+            vec![None, Some(4), Some(4), Some(3), None]
+        );
+
+        let exact = sized(12, 4);
+        assert_eq!(exact.chunks(), Some(3));
+        assert_eq!(exact.chunk_len(3), Some(4));
+        assert_eq!(exact.chunk_len(4), None);
+
+        let empty = sized(0, 4);
+        assert_eq!(empty.chunks(), Some(0));
+        assert_eq!(empty.chunk_len(1), None);
+
+        let single = Model {
+            chunk_size: None,
+            ..sized(11, 4)
+        };
+        assert_eq!(single.chunks(), None);
+        assert_eq!(single.chunk_len(1), None);
+    }
+
+    #[tokio::test]
+    async fn test_receive_chunk() {
+        let db = testing::database().await;
+        let tickets = DbLoader::<Model>::new(db.clone(), None);
+
+        let pending = resumable(&db, "pending", UploadTicketStatus::Pending).await;
+        receive(&db, pending.id).await.unwrap();
+        let uploading = tickets.load_one(pending.id).await.unwrap().unwrap();
+        assert_eq!(uploading.status, UploadTicketStatus::Uploading);
+        assert!(uploading.expires_at > pending.expires_at);
+
+        let expired = resumable(&db, "expired", UploadTicketStatus::Uploading).await;
+        expire(&db, &expired).await;
+        assert!(matches!(
+            receive(&db, expired.id).await,
+            Err(FlymodelError::ContraintError(..))
+        ));
+
+        let completed = resumable(&db, "completed", UploadTicketStatus::Completed).await;
+        assert!(matches!(
+            receive(&db, completed.id).await,
+            Err(FlymodelError::ContraintError(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_transition_ticket() {
+        let db = testing::database().await;
+        let tickets = DbLoader::<Model>::new(db.clone(), None);
+        let ticket = resumable(&db, "weights", UploadTicketStatus::Uploading).await;
+        let active = [UploadTicketStatus::Pending, UploadTicketStatus::Uploading];
+
+        assert!(tickets
+            .loader()
+            .transition_ticket(ticket.id, &active, UploadTicketStatus::Cancelled)
+            .await
+            .unwrap());
+        assert!(!tickets
+            .loader()
+            .transition_ticket(ticket.id, &active, UploadTicketStatus::Cancelled)
+            .await
+            .unwrap());
+        // a cancelled ticket neither receives chunks nor completes
+        assert!(receive(&db, ticket.id).await.is_err());
+        assert!(matches!(
+            complete(&db, &ticket, "v1").await,
+            Err(FlymodelError::ContraintError(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_complete_ticket() {
+        let db = testing::database().await;
+        let tickets = DbLoader::<Model>::new(db.clone(), None);
+        let ticket = resumable(&db, "weights", UploadTicketStatus::Pending).await;
+
+        // nothing was received yet
+        assert!(complete(&db, &ticket, "v2").await.is_err());
+        receive(&db, ticket.id).await.unwrap();
+        complete(&db, &ticket, "v3").await.unwrap();
+        assert_eq!(
+            tickets.load_one(ticket.id).await.unwrap().unwrap().status,
+            UploadTicketStatus::Completed
+        );
+        assert!(complete(&db, &ticket, "v4").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_expire_stale() {
+        let db = testing::database().await;
+        let tickets = DbLoader::<Model>::new(db.clone(), None);
+        let live = resumable(&db, "live", UploadTicketStatus::Uploading).await;
+        let stale = resumable(&db, "stale", UploadTicketStatus::Uploading).await;
+        let done = resumable(&db, "done", UploadTicketStatus::Completed).await;
+        expire(&db, &stale).await;
+        expire(&db, &done).await;
+
+        assert_eq!(tickets.loader().expire_stale().await.unwrap(), 1);
+        assert_eq!(tickets.loader().expire_stale().await.unwrap(), 0);
+        let status = |ticket: Option<Model>| ticket.unwrap().status;
+        assert_eq!(
+            status(tickets.load_one(live.id).await.unwrap()),
+            UploadTicketStatus::Uploading
+        );
+        assert_eq!(
+            status(tickets.load_one(stale.id).await.unwrap()),
+            UploadTicketStatus::Expired
+        );
+        assert_eq!(
+            status(tickets.load_one(done.id).await.unwrap()),
+            UploadTicketStatus::Completed
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use sea_orm::{
+    entity::prelude::*, ActiveValue, DatabaseTransaction, JoinType, QueryOrder, QuerySelect,
+};
+
+use crate::db::DbLoader;
+
+use super::enums::UploadTicketStatus;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, serde::Serialize, serde::Deserialize)]
+#[sea_orm(table_name = "upload_ticket_part")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub ticket_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub part_number: i32,
+    pub version_id: String,
+    pub size: i64,
+    pub sha256: String,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::upload_ticket::Entity",
+        from = "Column::TicketId",
+        to = "super::upload_ticket::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    UploadTicket,
+}
+
+impl Related<super::upload_ticket::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UploadTicket.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl DbLoader<Model> {
+    pub async fn parts(&self, ticket_id: i64) -> Result<Vec<Model>, FlymodelError> {
+        Entity::find()
+            .filter(Column::TicketId.eq(ticket_id))
+            .order_by_asc(Column::PartNumber)
+            .all(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)
+    }
+
+    pub async fn record_part(
+        conn: &DatabaseTransaction,
+        part: Model,
+    ) -> Result<Option<Model>, FlymodelError> {
+        let previous = Entity::find_by_id((part.ticket_id, part.part_number))
+            .one(conn)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        let mut active = ActiveModel::from(part);
+        active.created_at = ActiveValue::Set(Utc::now());
+        if previous.is_some() {
+            active.reset_all().update(conn).await
+        } else {
+            active.insert(conn).await
+        }
+        .map_err(FlymodelError::DbOperationError)?;
+        Ok(previous)
+    }
+
+    pub async fn stale_parts(
+        &self,
+    ) -> Result<Vec<(Model, super::upload_ticket::Model)>, FlymodelError> {
+        Ok(Entity::find()
+            .find_also_related(super::upload_ticket::Entity)
+            .filter(
+                super::upload_ticket::Column::Status
+                    .is_not_in([UploadTicketStatus::Pending, UploadTicketStatus::Uploading]),
+            )
+            .order_by_asc(Column::TicketId)
+            .all(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)?
+            .into_iter()
+            .filter_map(|(part, ticket)| Some((part, ticket?)))
+            .collect())
+    }
+
+    pub async fn staged<C: ConnectionTrait>(
+        db: &C,
+        bucket_id: i64,
+    ) -> Result<Vec<Model>, FlymodelError> {
+        Ok(Entity::find()
+            .join(JoinType::InnerJoin, Relation::UploadTicket.def())
+            .filter(super::upload_ticket::Column::BucketId.eq(bucket_id))
+            .all(db)
+            .await?)
+    }
+
+    pub async fn delete_part(&self, part: &Model) -> Result<(), FlymodelError> {
+        Entity::delete_by_id((part.ticket_id, part.part_number))
+            .exec(&self.db)
+            .await
+            .map_err(FlymodelError::DbOperationError)?;
+        Ok(())
+    }
+}
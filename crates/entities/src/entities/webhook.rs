@@ -0,0 +1,245 @@
+use crate::{bulk_loader, db::DbLoader};
+
+use super::enums::WebhookEvent;
+use async_graphql::{ComplexObject, InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use sea_orm::{entity::prelude::*, ActiveValue, IntoActiveModel, QueryOrder};
+
+pub const MIN_SECRET_LEN: usize = 16;
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(table_name = "webhook")]
+#[graphql(name = "Webhook", complex)]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    pub namespace_id: i64,
+    #[sea_orm(column_type = "Text")]
+    pub url: String,
+    #[graphql(skip)]
+    #[sea_orm(column_type = "JsonBinary")]
+    pub events: Json,
+    #[graphql(skip)]
+    #[serde(skip_serializing)]
+    #[sea_orm(column_type = "Text")]
+    pub secret: String,
+    /// whether deliveries are sent, those queued while inactive wait for it to be active again
+    pub active: bool,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub last_modified: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::namespace::Entity",
+        from = "Column::NamespaceId",
+        to = "super::namespace::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Namespace,
+    #[sea_orm(has_many = "super::webhook_delivery::Entity")]
+    WebhookDelivery,
+}
+
+impl Related<super::namespace::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Namespace.def()
+    }
+}
+
+impl Related<super::webhook_delivery::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WebhookDelivery.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+impl Model {
+    pub fn subscribed(&self) -> Vec<WebhookEvent> {
+        serde_json::from_value(self.events.clone()).unwrap_or_default()
+    }
+}
+
+#[ComplexObject]
+impl Model {
+    /// the events delivered to the webhook
+    async fn events(&self) -> Vec<WebhookEvent> {
+        self.subscribed()
+    }
+}
+
+/// an endpoint to deliver events of a namespace to
+#[derive(Clone, Debug, PartialEq, Eq, InputObject)]
+pub struct WebhookInput {
+    /// an http or https url
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    /// the key deliveries are signed with, at least 16 characters long
+    pub secret: String,
+    #[graphql(default = true)]
+    pub active: bool,
+}
+
+/// changes to a webhook, unset fields are kept
+#[derive(Clone, Debug, Default, PartialEq, Eq, InputObject)]
+pub struct WebhookUpdate {
+    pub url: Option<String>,
+    pub events: Option<Vec<WebhookEvent>>,
+    pub secret: Option<String>,
+    pub active: Option<bool>,
+}
+
+fn checked_url(url: &str) -> Result<String, FlymodelError> {
+    let parsed = url::Url::parse(url.trim()).map_err(|err| {
+        FlymodelError::ContraintError(format!("invalid webhook url {url}: {err}"))
+    })?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host().is_none() {
+        return Err(FlymodelError::ContraintError(format!(
+            "webhook urls must be http or https urls with a host: {url}"
+        )));
+    }
+    Ok(parsed.to_string())
+}
+
+fn checked_events(events: Vec<WebhookEvent>) -> Result<Json, FlymodelError> {
+    let mut checked: Vec<WebhookEvent> = Vec::with_capacity(events.len());
+    for event in events {
+        if !checked.contains(&event) {
+            checked.push(event);
+        }
+    }
+    if checked.is_empty() {
+        return Err(FlymodelError::ContraintError(
+            "webhooks must subscribe to at least one event".into(),
+        ));
+    }
+    serde_json::to_value(checked)
+        .map_err(|err| FlymodelError::NonDeterministicError(err.to_string()))
+}
+
+fn checked_secret(secret: String) -> Result<String, FlymodelError> {
+    if secret.chars().count() < MIN_SECRET_LEN {
+        return Err(FlymodelError::ContraintError(format!(
+            "webhook secrets must be at least {MIN_SECRET_LEN} characters long"
+        )));
+    }
+    Ok(secret)
+}
+
+impl DbLoader<Model> {
+    pub async fn of_namespace(&self, namespace_id: i64) -> Result<Vec<Model>, FlymodelError> {
+        Ok(Entity::find()
+            .filter(Column::NamespaceId.eq(namespace_id))
+            .order_by_asc(Column::Id)
+            .all(&self.db)
+            .await?)
+    }
+
+    pub async fn create_webhook(
+        &self,
+        namespace_id: i64,
+        input: WebhookInput,
+    ) -> Result<Model, FlymodelError> {
+        let now = Utc::now();
+        Ok(ActiveModel {
+            namespace_id: ActiveValue::Set(namespace_id),
+            url: ActiveValue::Set(checked_url(&input.url)?),
+            events: ActiveValue::Set(checked_events(input.events)?),
+            secret: ActiveValue::Set(checked_secret(input.secret)?),
+            active: ActiveValue::Set(input.active),
+            created_at: ActiveValue::Set(now),
+            last_modified: ActiveValue::Set(now),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await?)
+    }
+
+    pub async fn update_webhook(
+        &self,
+        id: i64,
+        update: WebhookUpdate,
+    ) -> Result<Model, FlymodelError> {
+        let mut active = Entity::find_by_id(id)
+            .one(&self.db)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(id))?
+            .into_active_model();
+        if let Some(url) = update.url {
+            active.url = ActiveValue::Set(checked_url(&url)?);
+        }
+        if let Some(events) = update.events {
+            active.events = ActiveValue::Set(checked_events(events)?);
+        }
+        if let Some(secret) = update.secret {
+            active.secret = ActiveValue::Set(checked_secret(secret)?);
+        }
+        if let Some(enabled) = update.active {
+            active.active = ActiveValue::Set(enabled);
+        }
+        active.last_modified = ActiveValue::Set(Utc::now());
+        Ok(active.update(&self.db).await?)
+    }
+
+    pub async fn delete_webhook(&self, id: i64) -> Result<bool, FlymodelError> {
+        let res = Entity::delete_by_id(id).exec(&self.db).await?;
+        Ok(res.rows_affected == 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{checked_events, checked_secret, checked_url};
+    use crate::entities::enums::WebhookEvent;
+
+    #[test]
+    fn test_checked_url() {
+        assert_eq!(
+            checked_url(" https://hooks.example.com/flymodel ").unwrap(),
+            "https://hooks.example.com/flymodel"
+        );
+        assert!(checked_url("ftp://hooks.example.com").is_err());
+        assert!(checked_url("file:///etc/passwd").is_err());
+        assert!(checked_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_checked_events() {
+        assert_eq!(
+            checked_events(vec![
+                WebhookEvent::ArtifactUploaded,
+                WebhookEvent::ExperimentFinished,
+                WebhookEvent::ArtifactUploaded,
+            ])
+            .unwrap(),
+            serde_json::json!(["ARTIFACT_UPLOADED", "EXPERIMENT_FINISHED"])
+        );
+        assert!(checked_events(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_checked_secret() {
+        assert!(checked_secret("too-short".into()).is_err());
+        assert!(checked_secret("0123456789abcdef".into()).is_ok());
+    }
+}
@@ -0,0 +1,308 @@
+use crate::{bulk_loader, db::DbLoader, paginated};
+
+use super::{
+    enums::{WebhookDeliveryStatus, WebhookEvent},
+    page::{PageInput, PaginatedResult},
+};
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Expr, LockBehavior, LockType, Query},
+    ActiveValue, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::Serialize;
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    DeriveEntityModel,
+    Eq,
+    SimpleObject,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(table_name = "webhook_delivery")]
+#[graphql(name = "WebhookDelivery")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: WebhookEvent,
+    /// the body delivered, signed with the secret of the webhook
+    #[sea_orm(column_type = "JsonBinary")]
+    pub payload: Json,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    /// when a pending delivery is attempted next
+    pub next_attempt_at: DateTime<Utc>,
+    /// the status the endpoint answered the last attempt with
+    pub response_status: Option<i32>,
+    /// why the last attempt failed
+    #[sea_orm(column_type = "Text", nullable)]
+    pub error: Option<String>,
+    #[serde(skip_deserializing, default = "chrono::offset::Utc::now")]
+    pub created_at: DateTime<Utc>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::webhook::Entity",
+        from = "Column::WebhookId",
+        to = "super::webhook::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Webhook,
+}
+
+impl Related<super::webhook::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Webhook.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+bulk_loader! {
+    Model
+}
+
+paginated! {
+    Model,
+    Entity
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Attempt {
+    Delivered {
+        status: i32,
+    },
+    Failed {
+        status: Option<i32>,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    },
+}
+
+fn payload(
+    event: WebhookEvent,
+    namespace_id: i64,
+    model_id: i64,
+    occurred_at: DateTime<Utc>,
+    data: Json,
+) -> Json {
+    serde_json::json!({
+        "event": event,
+        "namespace": namespace_id,
+        "model": model_id,
+        "occurred_at": occurred_at,
+        "data": data,
+    })
+}
+
+impl DbLoader<Model> {
+    /// queued in the transaction of the change, so deliveries are sent exactly when it is committed
+    pub async fn enqueue<C: ConnectionTrait>(
+        db: &C,
+        namespace_id: i64,
+        model_id: i64,
+        event: WebhookEvent,
+        data: &impl Serialize,
+    ) -> Result<u64, FlymodelError> {
+        let webhooks: Vec<i64> = super::webhook::Entity::find()
+            .filter(super::webhook::Column::NamespaceId.eq(namespace_id))
+            .filter(super::webhook::Column::Active.eq(true))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter(|webhook| webhook.subscribed().contains(&event))
+            .map(|webhook| webhook.id)
+            .collect();
+        if webhooks.is_empty() {
+            return Ok(0);
+        }
+        let now = Utc::now();
+        let data = serde_json::to_value(data)
+            .map_err(|err| FlymodelError::NonDeterministicError(err.to_string()))?;
+        let payload = payload(event, namespace_id, model_id, now, data);
+        let queued = webhooks.len() as u64;
+        Entity::insert_many(webhooks.into_iter().map(|webhook_id| ActiveModel {
+            webhook_id: ActiveValue::Set(webhook_id),
+            event: ActiveValue::Set(event),
+            payload: ActiveValue::Set(payload.clone()),
+            status: ActiveValue::Set(WebhookDeliveryStatus::Pending),
+            attempts: ActiveValue::Set(0),
+            next_attempt_at: ActiveValue::Set(now),
+            created_at: ActiveValue::Set(now),
+            ..Default::default()
+        }))
+        .exec_without_returning(db)
+        .await?;
+        Ok(queued)
+    }
+
+    pub async fn enqueue_of_version<C: ConnectionTrait>(
+        db: &C,
+        version_id: i64,
+        event: WebhookEvent,
+        data: &impl Serialize,
+    ) -> Result<u64, FlymodelError> {
+        let (namespace_id, model_id) = super::model_version::owner_of_version(db, version_id)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(version_id))?;
+        Self::enqueue(db, namespace_id, model_id, event, data).await
+    }
+
+    /// claimed deliveries are put off by `lease`, so other servers skip them & they are retried should the attempt never be recorded
+    pub async fn claim_due(
+        db: &DbConn,
+        limit: u64,
+        lease: chrono::Duration,
+    ) -> Result<Vec<(Model, super::webhook::Model)>, FlymodelError> {
+        let tx = db.begin().await?;
+        let now = Utc::now();
+        let due: Vec<i64> = Entity::find()
+            .select_only()
+            .column(Column::Id)
+            .filter(Column::Status.eq(WebhookDeliveryStatus::Pending))
+            .filter(Column::NextAttemptAt.lte(now))
+            .filter(
+                Column::WebhookId.in_subquery(
+                    Query::select()
+                        .column(super::webhook::Column::Id)
+                        .from(super::webhook::Entity)
+                        .and_where(super::webhook::Column::Active.eq(true))
+                        .to_owned(),
+                ),
+            )
+            .order_by_asc(Column::NextAttemptAt)
+            .limit(limit)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .into_tuple()
+            .all(&tx)
+            .await?;
+        if due.is_empty() {
+            return Ok(Vec::new());
+        }
+        Entity::update_many()
+            .col_expr(Column::NextAttemptAt, Expr::value(now + lease))
+            .filter(Column::Id.is_in(due.clone()))
+            .exec(&tx)
+            .await?;
+        tx.commit().await?;
+        Ok(Entity::find()
+            .find_also_related(super::webhook::Entity)
+            .filter(Column::Id.is_in(due))
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(delivery, webhook)| Some((delivery, webhook?)))
+            .collect())
+    }
+
+    pub async fn record_attempt(&self, id: i64, attempt: Attempt) -> Result<(), FlymodelError> {
+        let now = Utc::now();
+        let update = Entity::update_many()
+            .col_expr(Column::Attempts, Expr::col(Column::Attempts).add(1))
+            .col_expr(Column::LastAttemptAt, Expr::value(now));
+        let update = match attempt {
+            Attempt::Delivered { status } => update
+                .col_expr(
+                    Column::Status,
+                    Expr::value(WebhookDeliveryStatus::Delivered),
+                )
+                .col_expr(Column::ResponseStatus, Expr::value(status))
+                .col_expr(Column::Error, Expr::value(Option::<String>::None))
+                .col_expr(Column::DeliveredAt, Expr::value(now)),
+            Attempt::Failed {
+                status,
+                error,
+                retry_at,
+            } => update
+                .col_expr(
+                    Column::Status,
+                    Expr::value(match retry_at {
+                        Some(_) => WebhookDeliveryStatus::Pending,
+                        None => WebhookDeliveryStatus::Dead,
+                    }),
+                )
+                .col_expr(Column::ResponseStatus, Expr::value(status))
+                .col_expr(Column::Error, Expr::value(error))
+                .col_expr(Column::NextAttemptAt, Expr::value(retry_at.unwrap_or(now))),
+        };
+        update
+            .filter(Column::Id.eq(id))
+            .filter(Column::Status.eq(WebhookDeliveryStatus::Pending))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn deliveries(
+        &self,
+        webhook_id: i64,
+        status: Option<Vec<WebhookDeliveryStatus>>,
+        page: PageInput,
+    ) -> PaginatedResult<Model> {
+        let mut query = Entity::find().filter(Column::WebhookId.eq(webhook_id));
+        if let Some(status) = status {
+            query = query.filter(Column::Status.is_in(status));
+        }
+        self.load_paginated(query.order_by_desc(Column::Id), page)
+            .await
+    }
+
+    /// queues a delivery to be attempted again right away, with its full share of attempts
+    pub async fn redeliver(&self, id: i64) -> Result<Model, FlymodelError> {
+        let res = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(WebhookDeliveryStatus::Pending))
+            .col_expr(Column::Attempts, Expr::value(0))
+            .col_expr(Column::NextAttemptAt, Expr::value(Utc::now()))
+            .col_expr(
+                Column::DeliveredAt,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            )
+            .filter(Column::Id.eq(id))
+            .exec_with_returning(&self.db)
+            .await?;
+        res.into_iter()
+            .next()
+            .ok_or(FlymodelError::InvalidResourceId(id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::payload;
+    use crate::entities::enums::WebhookEvent;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_payload() {
+        let at = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        assert_eq!(
+            payload(
+                WebhookEvent::ArtifactUploaded,
+                1,
+                2,
+                at,
+                serde_json::json!({"id": 3, "name": "weights"})
+            ),
+            serde_json::json!({
+                "event": "ARTIFACT_UPLOADED",
+                "namespace": 1,
+                "model": 2,
+                "occurred_at": "2024-01-02T03:04:05Z",
+                "data": {"id": 3, "name": "weights"},
+            })
+        );
+    }
+}
@@ -2,5 +2,7 @@ pub mod db;
 pub mod entities;
 pub mod filters;
 pub mod prelude;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod utils;
 pub use async_graphql;
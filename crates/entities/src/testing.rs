@@ -0,0 +1,275 @@
+/*!
+    an in-memory sqlite database with the tables of every entity, and fixtures to
+    fill it with. the indexes & triggers of the postgres migrations are not created,
+    besides the unique indexes constraints are checked against.
+*/
+use chrono::Utc;
+use flymodel::lifecycle::Lifecycle;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ConnectionTrait, Database, DbConn, EntityTrait, Schema,
+};
+
+use crate::entities::{
+    self,
+    enums::{RunState, UploadTicketStatus},
+};
+
+const INDEXES: &[&str] = &[
+    "create unique index model_version_model_version_idx on model_version (model_id, version) where deleted_at is null",
+    "create unique index experiment_name_idx on experiment (version_id, name) where deleted_at is null",
+    "create unique index model_state_version_idx on model_state (version_id)",
+    "create unique index object_blob_version_idx on object_blob (version_id, key)",
+    "create unique index model_artifact_blob_idx on model_artifact (blob)",
+    "create unique index experiment_artifact_blob_idx on experiment_artifact (blob)",
+    "create unique index experiment_metric_series_idx on experiment_metric (experiment_id, key, step)",
+];
+
+macro_rules! create_tables {
+    ($db: ident, $($entity: ident),* $(,)?) => {
+        let backend = $db.get_database_backend();
+        let schema = Schema::new(backend);
+        $(
+            $db.execute(backend.build(&schema.create_table_from_entity(entities::$entity::Entity)))
+                .await
+                .expect(concat!("create ", stringify!($entity)));
+        )*
+    };
+}
+
+pub async fn database() -> DbConn {
+    let db = Database::connect("sqlite::memory:")
+        .await
+        .expect("in-memory sqlite");
+    create_tables! {
+        db,
+        namespace,
+        bucket,
+        model,
+        model_version,
+        model_state,
+        model_state_history,
+        object_blob,
+        model_artifact,
+        experiment,
+        experiment_artifact,
+        experiment_state,
+        experiment_result,
+        experiment_metric,
+        experiment_param,
+        experiment_environment,
+        experiment_tag,
+        model_tag,
+        model_version_tag,
+        namespace_tag,
+        upload_ticket,
+        upload_ticket_part,
+        promotion_policy,
+        promotion_request,
+        promotion_approval,
+        webhook,
+        webhook_delivery,
+        audit_log,
+    }
+    for index in INDEXES {
+        db.execute_unprepared(index).await.expect(index);
+    }
+    db
+}
+
+pub async fn namespace<C: ConnectionTrait>(db: &C, name: &str) -> entities::namespace::Model {
+    entities::namespace::ActiveModel {
+        name: ActiveValue::Set(name.into()),
+        description: ActiveValue::Set(String::new()),
+        created_at: ActiveValue::Set(Utc::now()),
+        last_modified: ActiveValue::Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("namespace")
+}
+
+pub async fn bucket<C: ConnectionTrait>(
+    db: &C,
+    namespace: i64,
+    name: &str,
+    role: Lifecycle,
+) -> entities::bucket::Model {
+    entities::bucket::ActiveModel {
+        namespace: ActiveValue::Set(namespace),
+        name: ActiveValue::Set(name.into()),
+        region: ActiveValue::Set("ca-local".into()),
+        role: ActiveValue::Set(role),
+        created_at: ActiveValue::Set(Utc::now()),
+        last_modified: ActiveValue::Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("bucket")
+}
+
+pub async fn model<C: ConnectionTrait>(
+    db: &C,
+    namespace: i64,
+    name: &str,
+) -> entities::model::Model {
+    entities::model::ActiveModel {
+        namespace_id: ActiveValue::Set(namespace),
+        name: ActiveValue::Set(name.into()),
+        created_at: ActiveValue::Set(Utc::now()),
+        last_modified: ActiveValue::Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("model")
+}
+
+pub async fn version<C: ConnectionTrait>(
+    db: &C,
+    model: i64,
+    version: &str,
+    state: Lifecycle,
+    since: chrono::DateTime<Utc>,
+) -> entities::model_version::Model {
+    // inserted without the hook which creates its state, as its timestamp has no default here
+    let version = entities::model_version::Entity::insert(entities::model_version::ActiveModel {
+        model_id: ActiveValue::Set(model),
+        version: ActiveValue::Set(version.into()),
+        deleted_at: ActiveValue::Set(None),
+        ..Default::default()
+    })
+    .exec_with_returning(db)
+    .await
+    .expect("model version");
+    entities::model_state::ActiveModel {
+        version_id: ActiveValue::Set(version.id),
+        state: ActiveValue::Set(state),
+        last_modified: ActiveValue::Set(since),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("model state");
+    version
+}
+
+pub async fn experiment<C: ConnectionTrait>(
+    db: &C,
+    version: i64,
+    name: &str,
+) -> entities::experiment::Model {
+    // inserted without the hook which creates its state, as for versions
+    let experiment = entities::experiment::Entity::insert(entities::experiment::ActiveModel {
+        version_id: ActiveValue::Set(version),
+        name: ActiveValue::Set(name.into()),
+        created_at: ActiveValue::Set(Utc::now()),
+        deleted_at: ActiveValue::Set(None),
+        ..Default::default()
+    })
+    .exec_with_returning(db)
+    .await
+    .expect("experiment");
+    entities::experiment_state::ActiveModel {
+        experiment_id: ActiveValue::Set(experiment.id),
+        state: ActiveValue::Set(RunState::Created),
+        last_modified: ActiveValue::Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("experiment state");
+    experiment
+}
+
+pub async fn blob<C: ConnectionTrait>(
+    db: &C,
+    bucket: i64,
+    key: &str,
+    version_id: &str,
+    created_at: chrono::DateTime<Utc>,
+) -> entities::object_blob::Model {
+    entities::object_blob::ActiveModel {
+        bucket_id: ActiveValue::Set(bucket),
+        key: ActiveValue::Set(key.into()),
+        version_id: ActiveValue::Set(version_id.into()),
+        size: ActiveValue::Set(11),
+        sha256: ActiveValue::Set(String::new()),
+        encode: ActiveValue::Set(None),
+        format: ActiveValue::Set(None),
+        created_at: ActiveValue::Set(created_at),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("object blob")
+}
+
+pub async fn model_artifact<C: ConnectionTrait>(
+    db: &C,
+    version: i64,
+    blob: i64,
+    name: &str,
+) -> entities::model_artifact::Model {
+    entities::model_artifact::ActiveModel {
+        version_id: ActiveValue::Set(version),
+        blob: ActiveValue::Set(blob),
+        extra: ActiveValue::Set(None),
+        name: ActiveValue::Set(name.into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("model artifact")
+}
+
+pub async fn experiment_artifact<C: ConnectionTrait>(
+    db: &C,
+    experiment: &entities::experiment::Model,
+    blob: i64,
+    name: &str,
+) -> entities::experiment_artifact::Model {
+    entities::experiment_artifact::ActiveModel {
+        experiment_id: ActiveValue::Set(experiment.id),
+        version_id: ActiveValue::Set(experiment.version_id),
+        blob: ActiveValue::Set(blob),
+        name: ActiveValue::Set(name.into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("experiment artifact")
+}
+
+pub async fn upload_ticket<C: ConnectionTrait>(
+    db: &C,
+    bucket: i64,
+    version: i64,
+    key: &str,
+    status: UploadTicketStatus,
+) -> entities::upload_ticket::Model {
+    entities::upload_ticket::ActiveModel {
+        status: ActiveValue::Set(status),
+        created_at: ActiveValue::Set(Utc::now()),
+        last_modified: ActiveValue::Set(Utc::now()),
+        bucket_id: ActiveValue::Set(bucket),
+        key: ActiveValue::Set(key.into()),
+        model_version_id: ActiveValue::Set(Some(version)),
+        experiment_id: ActiveValue::Set(None),
+        artifact_name: ActiveValue::Set(key.into()),
+        encode: ActiveValue::Set(None),
+        format: ActiveValue::Set(None),
+        extra: ActiveValue::Set(None),
+        size: ActiveValue::Set(11),
+        sha256: ActiveValue::Set(String::new()),
+        created_by: ActiveValue::Set("test".into()),
+        expires_at: ActiveValue::Set(Utc::now() + chrono::Duration::hours(1)),
+        blob: ActiveValue::Set(None),
+        chunk_size: ActiveValue::Set(None),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("upload ticket")
+}
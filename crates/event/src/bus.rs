@@ -0,0 +1,98 @@
+use flymodel::errs::FlymodelError;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::event::AuditableEvent;
+
+/// emitting waits while the subscriber is `capacity` events behind rather than dropping events
+#[derive(Clone, Debug)]
+pub struct Publisher<T> {
+    sender: mpsc::Sender<T>,
+    #[cfg(feature = "ipc")]
+    ipc: mpsc::Sender<T>,
+}
+
+impl<T: AuditableEvent> Publisher<T> {
+    /// emits an event, logging rather than failing when the bus has no subscriber left
+    pub async fn emit(&self, event: T) {
+        #[cfg(feature = "ipc")]
+        if let Err(err) = self.ipc.try_send(event) {
+            // other processes only get the events they keep up with
+            tracing::debug!("not publishing {event:?} to other processes: {err}");
+        }
+        if self.sender.send(event).await.is_err() {
+            warn!(
+                "no subscriber left on the {} bus, dropping {event:?}",
+                T::SERVICE
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Subscriber<T> {
+    receiver: mpsc::Receiver<T>,
+}
+
+impl<T: AuditableEvent> Subscriber<T> {
+    pub(crate) fn new(receiver: mpsc::Receiver<T>) -> Self {
+        Self { receiver }
+    }
+
+    pub async fn recv(&mut self) -> Option<T> {
+        self.receiver.recv().await
+    }
+
+    pub fn drain(&mut self, into: &mut Vec<T>, limit: usize) {
+        while into.len() < limit {
+            match self.receiver.try_recv() {
+                Ok(event) => into.push(event),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// with the `ipc` feature, events are also published for other processes on the host to [crate::ipc::listen] to
+pub fn bus<T: AuditableEvent>(
+    capacity: usize,
+) -> Result<(Publisher<T>, Subscriber<T>), FlymodelError> {
+    let (sender, receiver) = mpsc::channel(capacity);
+    let publisher = Publisher {
+        sender,
+        #[cfg(feature = "ipc")]
+        ipc: crate::ipc::forward(capacity)?,
+    };
+    Ok((publisher, Subscriber::new(receiver)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::bus;
+    use crate::event::{AuditAction, AuditEvent, ResourceKind};
+
+    #[tokio::test]
+    async fn test_bus() {
+        let (publisher, mut subscriber) = bus::<AuditEvent>(8).unwrap();
+        for id in 1..=3 {
+            publisher
+                .emit(AuditEvent::new(AuditAction::Create, ResourceKind::Model, id).by("alice"))
+                .await;
+        }
+        let first = subscriber.recv().await.unwrap();
+        assert_eq!(first.resource_id, 1);
+        assert_eq!(first.actor.as_str(), "alice");
+        let mut rest = Vec::new();
+        subscriber.drain(&mut rest, 1);
+        assert_eq!(rest.len(), 1);
+        subscriber.drain(&mut rest, 8);
+        assert_eq!(
+            rest.iter()
+                .map(|event| event.resource_id)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        drop(publisher);
+        assert!(subscriber.recv().await.is_none());
+    }
+}
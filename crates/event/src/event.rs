@@ -0,0 +1,240 @@
+use std::fmt::Debug;
+
+use chrono::{DateTime, Utc};
+
+pub const ACTOR_LEN: usize = 128;
+
+pub const SUMMARY_LEN: usize = 512;
+
+/**
+    a payload of an audit bus, identified by the name of its bus.
+
+    # Safety
+
+    payloads are copied between processes byte for byte, so they must be
+    `#[repr(C)]`, of a fixed size, and hold no pointers or references.
+*/
+pub unsafe trait AuditableEvent: Copy + Debug + Send + 'static {
+    const SERVICE: &'static str;
+}
+
+/// a string of at most `N` bytes, stored inline. longer strings are cut at a char boundary
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct FixedStr<const N: usize> {
+    len: u16,
+    bytes: [u8; N],
+}
+
+impl<const N: usize> FixedStr<N> {
+    pub fn new(value: &str) -> Self {
+        let mut len = value.len().min(N).min(u16::MAX as usize);
+        while !value.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut bytes = [0; N];
+        bytes[..len].copy_from_slice(&value.as_bytes()[..len]);
+        Self {
+            len: len as u16,
+            bytes,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // only a corrupted sample would not be utf-8
+        std::str::from_utf8(&self.bytes[..(self.len as usize).min(N)]).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<const N: usize> Default for FixedStr<N> {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            bytes: [0; N],
+        }
+    }
+}
+
+impl<const N: usize> Debug for FixedStr<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    /// moved to another lifecycle
+    Transition,
+    Request,
+    Approve,
+    Reject,
+    Cancel,
+    Start,
+    Pass,
+    Fail,
+    Retry,
+    Attach,
+    Detach,
+    Upload,
+    Download,
+    Verify,
+    Redeliver,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Restore => "restore",
+            Self::Transition => "transition",
+            Self::Request => "request",
+            Self::Approve => "approve",
+            Self::Reject => "reject",
+            Self::Cancel => "cancel",
+            Self::Start => "start",
+            Self::Pass => "pass",
+            Self::Fail => "fail",
+            Self::Retry => "retry",
+            Self::Attach => "attach",
+            Self::Detach => "detach",
+            Self::Upload => "upload",
+            Self::Download => "download",
+            Self::Verify => "verify",
+            Self::Redeliver => "redeliver",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ResourceKind {
+    Namespace,
+    Bucket,
+    Model,
+    ModelVersion,
+    Experiment,
+    ModelArtifact,
+    ExperimentArtifact,
+    PromotionPolicy,
+    PromotionRequest,
+    NamespaceTag,
+    UploadTicket,
+    Webhook,
+    WebhookDelivery,
+}
+
+impl ResourceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Namespace => "namespace",
+            Self::Bucket => "bucket",
+            Self::Model => "model",
+            Self::ModelVersion => "model_version",
+            Self::Experiment => "experiment",
+            Self::ModelArtifact => "model_artifact",
+            Self::ExperimentArtifact => "experiment_artifact",
+            Self::PromotionPolicy => "promotion_policy",
+            Self::PromotionRequest => "promotion_request",
+            Self::NamespaceTag => "namespace_tag",
+            Self::UploadTicket => "upload_ticket",
+            Self::Webhook => "webhook",
+            Self::WebhookDelivery => "webhook_delivery",
+        }
+    }
+}
+
+/// `before` and `after` summarize the resource around a change, empty when there is nothing to summarize
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct AuditEvent {
+    /// milliseconds since the unix epoch
+    pub timestamp: i64,
+    pub resource_id: i64,
+    pub action: AuditAction,
+    pub resource: ResourceKind,
+    pub actor: FixedStr<ACTOR_LEN>,
+    pub before: FixedStr<SUMMARY_LEN>,
+    pub after: FixedStr<SUMMARY_LEN>,
+}
+
+// SAFETY: repr(C), and every field is stored inline
+unsafe impl AuditableEvent for AuditEvent {
+    const SERVICE: &'static str = "audit";
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction, resource: ResourceKind, resource_id: i64) -> Self {
+        Self {
+            timestamp: Utc::now().timestamp_millis(),
+            resource_id,
+            action,
+            resource,
+            actor: FixedStr::default(),
+            before: FixedStr::default(),
+            after: FixedStr::default(),
+        }
+    }
+
+    pub fn by(mut self, actor: &str) -> Self {
+        self.actor = FixedStr::new(actor);
+        self
+    }
+
+    pub fn before(mut self, summary: &str) -> Self {
+        self.before = FixedStr::new(summary);
+        self
+    }
+
+    pub fn after(mut self, summary: &str) -> Self {
+        self.after = FixedStr::new(summary);
+        self
+    }
+
+    pub fn occurred_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.timestamp).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AuditAction, AuditEvent, FixedStr, ResourceKind, ACTOR_LEN, SUMMARY_LEN};
+
+    #[test]
+    fn test_fixed_str() {
+        assert_eq!(FixedStr::<8>::new("weights").as_str(), "weights");
+        assert_eq!(FixedStr::<4>::new("weights").as_str(), "weig");
+        // "é" takes 2 bytes, which do not both fit
+        assert_eq!(FixedStr::<4>::new("abcé").as_str(), "abc");
+        assert!(FixedStr::<4>::default().is_empty());
+        assert_eq!(format!("{:?}", FixedStr::<4>::new("ab")), "\"ab\"");
+    }
+
+    #[test]
+    fn test_event() {
+        let event = AuditEvent::new(AuditAction::Transition, ResourceKind::ModelVersion, 3)
+            .by("alice")
+            .before("TEST")
+            .after("QA");
+        assert_eq!(event.actor.as_str(), "alice");
+        assert_eq!(event.before.as_str(), "TEST");
+        assert_eq!(event.after.as_str(), "QA");
+        assert_eq!(event.occurred_at().timestamp_millis(), event.timestamp);
+        assert_eq!(event.resource.as_str(), "model_version");
+        assert_eq!(event.action.as_str(), "transition");
+        // summaries are held inline, cut to fit
+        assert!(std::mem::size_of::<AuditEvent>() >= ACTOR_LEN + 2 * SUMMARY_LEN);
+        let long = event.after(&"x".repeat(SUMMARY_LEN * 2));
+        assert_eq!(long.after.as_str().len(), SUMMARY_LEN);
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use flymodel::errs::FlymodelError;
+use iceoryx2::prelude::*;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::{bus::Subscriber, event::AuditableEvent};
+
+const POLL: Duration = Duration::from_millis(100);
+
+pub fn service_name<T: AuditableEvent>() -> String {
+    format!(
+        "flymodel.events.{service}.{ver}",
+        service = T::SERVICE,
+        ver = env!("CARGO_PKG_VERSION")
+    )
+}
+
+fn ipc_error(err: impl std::fmt::Debug) -> FlymodelError {
+    FlymodelError::RuntimeDependencyError(format!("iceoryx2: {err:?}"))
+}
+
+/// iceoryx2 ports borrow their service & cannot be shared across threads, so each runs on a thread of its own
+fn spawn_port<F>(name: &str, port: F) -> Result<(), FlymodelError>
+where
+    F: FnOnce(&dyn Fn(Result<(), FlymodelError>)) + Send + 'static,
+{
+    let (ready, started) = std::sync::mpsc::channel();
+    std::thread::Builder::new()
+        .name(name.into())
+        .spawn(move || {
+            port(&|res| {
+                let _ = ready.send(res);
+            })
+        })
+        .map_err(ipc_error)?;
+    started
+        .recv()
+        .map_err(|_| ipc_error("the port thread exited before starting"))?
+}
+
+pub(crate) fn forward<T: AuditableEvent>(
+    capacity: usize,
+) -> Result<mpsc::Sender<T>, FlymodelError> {
+    let name = ServiceName::new(&service_name::<T>()).map_err(ipc_error)?;
+    let (sender, mut receiver) = mpsc::channel::<T>(capacity);
+    spawn_port("flymodel-events-publisher", move |ready| {
+        let service = match zero_copy::Service::new(&name)
+            .publish_subscribe()
+            .open_or_create::<T>()
+        {
+            Ok(service) => service,
+            Err(err) => return ready(Err(ipc_error(err))),
+        };
+        let publisher = match service.publisher().create() {
+            Ok(publisher) => publisher,
+            Err(err) => return ready(Err(ipc_error(err))),
+        };
+        ready(Ok(()));
+        while let Some(event) = receiver.blocking_recv() {
+            if let Err(err) = publisher.send_copy(event) {
+                warn!("failed to publish {event:?} to other processes: {err:?}");
+            }
+        }
+    })?;
+    Ok(sender)
+}
+
+/// subscribes to the events other processes on the host publish on the bus of `T`
+pub fn listen<T: AuditableEvent>(capacity: usize) -> Result<Subscriber<T>, FlymodelError> {
+    let name = ServiceName::new(&service_name::<T>()).map_err(ipc_error)?;
+    let (sender, receiver) = mpsc::channel::<T>(capacity);
+    spawn_port("flymodel-events-subscriber", move |ready| {
+        let service = match zero_copy::Service::new(&name)
+            .publish_subscribe()
+            .open_or_create::<T>()
+        {
+            Ok(service) => service,
+            Err(err) => return ready(Err(ipc_error(err))),
+        };
+        let subscriber = match service.subscriber().create() {
+            Ok(subscriber) => subscriber,
+            Err(err) => return ready(Err(ipc_error(err))),
+        };
+        ready(Ok(()));
+        while !sender.is_closed() {
+            loop {
+                match subscriber.receive() {
+                    Ok(Some(sample)) => {
+                        if sender.blocking_send(*sample).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(err) => {
+                        warn!("failed to receive events from other processes: {err:?}");
+                        break;
+                    }
+                }
+            }
+            std::thread::sleep(POLL);
+        }
+    })?;
+    Ok(Subscriber::new(receiver))
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use flymodel_entities::{db::DbLoader, entities::audit_log};
+use sea_orm::{ActiveValue, DbConn};
+use tracing::{debug, error, warn};
+
+use crate::{bus::Subscriber, event::AuditEvent};
+
+const BATCH: usize = 256;
+
+const RETRY_AFTER: Duration = Duration::from_secs(5);
+
+const ATTEMPTS: usize = 5;
+
+fn summary<const N: usize>(summary: &crate::event::FixedStr<N>) -> Option<String> {
+    (!summary.is_empty()).then(|| summary.as_str().to_string())
+}
+
+pub fn entry(event: &AuditEvent) -> audit_log::ActiveModel {
+    audit_log::ActiveModel {
+        actor: ActiveValue::Set(event.actor.as_str().to_string()),
+        action: ActiveValue::Set(event.action.as_str().to_string()),
+        resource: ActiveValue::Set(event.resource.as_str().to_string()),
+        resource_id: ActiveValue::Set(event.resource_id),
+        before: ActiveValue::Set(summary(&event.before)),
+        after: ActiveValue::Set(summary(&event.after)),
+        occurred_at: ActiveValue::Set(event.occurred_at()),
+        ..Default::default()
+    }
+}
+
+// a batch still failing after its attempts is logged & dropped, rather than holding up publishers
+async fn record(db: &DbConn, batch: &[AuditEvent], retry_after: Duration) -> bool {
+    for attempt in 1..=ATTEMPTS {
+        match DbLoader::<audit_log::Model>::record(db, batch.iter().map(entry).collect()).await {
+            Ok(()) => return true,
+            Err(err) if attempt < ATTEMPTS => {
+                warn!(
+                    "failed to record {} audit events, retrying in {retry_after:?}: {err}",
+                    batch.len()
+                );
+                tokio::time::sleep(retry_after).await;
+            }
+            Err(err) => {
+                error!(
+                    "dropping {} audit events after {ATTEMPTS} attempts: {err}",
+                    batch.len()
+                );
+                for event in batch {
+                    error!("dropped audit event {event:?}");
+                }
+            }
+        }
+    }
+    false
+}
+
+/// a batch which fails to be recorded is retried a few times, holding up publishers meanwhile
+pub fn spawn_audit_log(
+    db: DbConn,
+    mut subscriber: Subscriber<AuditEvent>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut batch = Vec::with_capacity(BATCH);
+        while let Some(event) = subscriber.recv().await {
+            batch.push(event);
+            subscriber.drain(&mut batch, BATCH);
+            record(&db, &batch, RETRY_AFTER).await;
+            batch.clear();
+        }
+        debug!("the audit log has no publisher left");
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use sea_orm::{ActiveValue, Database};
+
+    use super::{entry, record};
+    use crate::event::{AuditAction, AuditEvent, ResourceKind};
+
+    #[test]
+    fn test_entry() {
+        let event = AuditEvent::new(AuditAction::Upload, ResourceKind::ModelArtifact, 5)
+            .by("alice")
+            .after("weights.safetensors");
+        let entry = entry(&event);
+        assert_eq!(entry.actor, ActiveValue::Set("alice".to_string()));
+        assert_eq!(entry.action, ActiveValue::Set("upload".to_string()));
+        assert_eq!(
+            entry.resource,
+            ActiveValue::Set("model_artifact".to_string())
+        );
+        assert_eq!(entry.resource_id, ActiveValue::Set(5));
+        assert_eq!(entry.before, ActiveValue::Set(None));
+        assert_eq!(
+            entry.after,
+            ActiveValue::Set(Some("weights.safetensors".to_string()))
+        );
+        assert_eq!(entry.occurred_at, ActiveValue::Set(event.occurred_at()));
+        assert_eq!(entry.id, ActiveValue::NotSet);
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let batch = vec![
+            AuditEvent::new(AuditAction::Create, ResourceKind::Model, 1).by("alice"),
+            AuditEvent::new(AuditAction::Delete, ResourceKind::Model, 1).by("alice"),
+        ];
+        // without an audit_log table every attempt fails, and the batch is given up on
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        assert!(!record(&db, &batch, Duration::ZERO).await);
+    }
+}
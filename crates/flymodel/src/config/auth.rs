@@ -16,6 +16,8 @@ pub enum AuthHandlers {
     NoOp,
     #[serde(rename = "oauth2")]
     OAuth2(Oauth2Configuration),
+    #[serde(rename = "api_key")]
+    ApiKey(ApiKeyConfiguration),
 }
 
 fn default_callback() -> String {
@@ -26,6 +28,18 @@ fn default_scopes() -> Vec<String> {
     vec!["openid".to_string()]
 }
 
+fn default_permissions_claim() -> String {
+    "flymodel_permissions".to_string()
+}
+
+fn default_jwks_ttl() -> u64 {
+    300
+}
+
+fn default_jwks_refetch_interval() -> u64 {
+    60
+}
+
 #[derive(Clone, serde::Deserialize, Debug, PartialEq)]
 pub struct Oauth2Configuration {
     pub base_url: Url,
@@ -36,4 +50,48 @@ pub struct Oauth2Configuration {
     #[serde(default = "default_scopes")]
     pub scopes: Vec<String>,
     pub well_known_url: Url,
+    /// expected `aud` of bearer tokens, defaults to the client id
+    pub audience: Option<String>,
+    /// the claim holding permission strings, e.g. `namespace:3:write`
+    #[serde(default = "default_permissions_claim")]
+    pub permissions_claim: String,
+    /// seconds a fetched jwks is trusted before being refreshed
+    #[serde(default = "default_jwks_ttl")]
+    pub jwks_ttl: u64,
+    /// seconds after a fetch before a token signed by an unknown key may refetch the jwks
+    #[serde(default = "default_jwks_refetch_interval")]
+    pub jwks_refetch_interval: u64,
+}
+
+impl Oauth2Configuration {
+    pub fn redirect_url(&self) -> Result<Url, url::ParseError> {
+        self.base_url.join(&self.callback)
+    }
+
+    pub fn audience(&self) -> &str {
+        self.audience.as_deref().unwrap_or(&self.client_id)
+    }
+}
+
+/// api keys are only ever stored & compared as their sha256 digest
+pub fn hash_api_key(key: &str) -> String {
+    sha256::digest(key)
+}
+
+#[derive(Clone, serde::Deserialize, Debug, PartialEq)]
+pub struct ApiKeyEntry {
+    pub name: String,
+    /// the hex encoded sha256 of the key, see [hash_api_key]
+    pub hash: Secret<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Clone, serde::Deserialize, Debug, PartialEq, Default)]
+pub struct ApiKeyConfiguration {
+    #[serde(default)]
+    pub keys: Vec<ApiKeyEntry>,
+    /// whether keys are also looked up in the `api_key` table
+    #[serde(default)]
+    pub database: bool,
 }
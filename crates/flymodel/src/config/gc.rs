@@ -0,0 +1,27 @@
+fn default_interval() -> u64 {
+    6 * 60 * 60
+}
+
+fn default_grace_period() -> u64 {
+    24 * 60 * 60
+}
+
+/// the `[gc]` section, removing blobs & stored objects nothing references
+#[derive(Clone, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct GcConfiguration {
+    /// seconds between garbage collections of the server
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// seconds an unreferenced blob or object is kept before it is removed
+    #[serde(default = "default_grace_period")]
+    pub grace_period: u64,
+}
+
+impl Default for GcConfiguration {
+    fn default() -> Self {
+        Self {
+            interval: default_interval(),
+            grace_period: default_grace_period(),
+        }
+    }
+}
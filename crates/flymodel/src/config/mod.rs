@@ -1,2 +1,7 @@
 pub mod auth;
+pub mod gc;
+pub mod relocation;
+pub mod retention;
 pub mod secret;
+pub mod uploads;
+pub mod webhooks;
@@ -0,0 +1,19 @@
+fn default_interval() -> u64 {
+    15 * 60
+}
+
+/// the `[relocation]` section, copying artifacts into the bucket of their version's lifecycle
+#[derive(Clone, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct RelocationConfiguration {
+    /// seconds between sweeps resuming relocations left unfinished by a promotion
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+impl Default for RelocationConfiguration {
+    fn default() -> Self {
+        Self {
+            interval: default_interval(),
+        }
+    }
+}
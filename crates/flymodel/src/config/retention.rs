@@ -0,0 +1,164 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::lifecycle::Lifecycle;
+
+fn default_interval() -> u64 {
+    24 * 60 * 60
+}
+
+/// how many days the registry keeps what belongs to a lifecycle, unset ages are kept forever
+#[derive(Clone, Copy, serde::Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct RetentionRule {
+    /// days a model version is kept after it entered the lifecycle
+    pub models: Option<u64>,
+    /// days an experiment of a version in the lifecycle is kept after it was created
+    pub runs: Option<u64>,
+}
+
+impl RetentionRule {
+    fn tightest(self, other: Self) -> Self {
+        fn min(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+        Self {
+            models: min(self.models, other.models),
+            runs: min(self.runs, other.runs),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Rules {
+    Many(Vec<RetentionRule>),
+    One(RetentionRule),
+}
+
+/// accepts a table, an array of tables (keeping the shortest ages) or nothing
+fn rules<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RetentionRule, D::Error> {
+    Ok(match Option::<Rules>::deserialize(deserializer)? {
+        None => RetentionRule::default(),
+        Some(Rules::One(rule)) => rule,
+        Some(Rules::Many(rules)) => rules
+            .into_iter()
+            .fold(RetentionRule::default(), RetentionRule::tightest),
+    })
+}
+
+/// the `[retention]` section, expiring model versions & experiments per lifecycle
+#[derive(Clone, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct RetentionConfiguration {
+    /// seconds between retention runs of the server
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    #[serde(default, deserialize_with = "rules")]
+    pub test: RetentionRule,
+    #[serde(default, deserialize_with = "rules")]
+    pub qa: RetentionRule,
+    #[serde(default, deserialize_with = "rules")]
+    pub stage: RetentionRule,
+    #[serde(default, deserialize_with = "rules")]
+    pub prod: RetentionRule,
+}
+
+impl Default for RetentionConfiguration {
+    fn default() -> Self {
+        Self {
+            interval: default_interval(),
+            test: RetentionRule::default(),
+            qa: RetentionRule::default(),
+            stage: RetentionRule::default(),
+            prod: RetentionRule::default(),
+        }
+    }
+}
+
+impl RetentionConfiguration {
+    pub fn rule(&self, state: Lifecycle) -> RetentionRule {
+        match state {
+            Lifecycle::Test => self.test,
+            Lifecycle::Qa => self.qa,
+            Lifecycle::Stage => self.stage,
+            Lifecycle::Prod => self.prod,
+        }
+    }
+
+    pub fn keeps_everything(&self) -> bool {
+        [self.test, self.qa, self.stage, self.prod]
+            .iter()
+            .all(|rule| *rule == RetentionRule::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use config::{Config, File, FileFormat};
+
+    use super::{RetentionConfiguration, RetentionRule};
+    use crate::lifecycle::Lifecycle;
+
+    fn load(source: &str, format: FileFormat) -> anyhow::Result<RetentionConfiguration> {
+        Ok(Config::builder()
+            .add_source(File::from_str(source, format))
+            .build()?
+            .get::<RetentionConfiguration>("retention")?)
+    }
+
+    #[test]
+    fn test_retention_tables() -> anyhow::Result<()> {
+        let conf = load(
+            r#"
+            [retention]
+            interval = 60
+
+            [[retention.prod]]
+
+            [[retention.test]]
+            models = 365
+            runs = 28
+
+            [[retention.test]]
+            runs = 7
+            "#,
+            FileFormat::Toml,
+        )?;
+        assert_eq!(conf.interval, 60);
+        assert_eq!(conf.rule(Lifecycle::Prod), RetentionRule::default());
+        assert_eq!(conf.rule(Lifecycle::Qa), RetentionRule::default());
+        assert_eq!(
+            conf.rule(Lifecycle::Test),
+            RetentionRule {
+                models: Some(365),
+                runs: Some(7),
+            }
+        );
+        assert!(!conf.keeps_everything());
+        Ok(())
+    }
+
+    #[test]
+    fn test_retention_yaml() -> anyhow::Result<()> {
+        let conf = load(
+            "
+retention:
+    qa:
+        models: 30
+    stage:
+",
+            FileFormat::Yaml,
+        )?;
+        assert_eq!(
+            conf.rule(Lifecycle::Qa),
+            RetentionRule {
+                models: Some(30),
+                runs: None,
+            }
+        );
+        assert_eq!(conf.rule(Lifecycle::Stage), RetentionRule::default());
+        assert_eq!(conf.interval, 24 * 60 * 60);
+        Ok(())
+    }
+}
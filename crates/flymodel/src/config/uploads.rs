@@ -0,0 +1,43 @@
+fn default_chunk_size() -> u64 {
+    8 * 1024 * 1024
+}
+
+fn default_max_chunk_size() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_ticket_ttl() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_sweep_interval() -> u64 {
+    5 * 60
+}
+
+/// resumable (chunked) upload settings
+#[derive(Clone, serde::Deserialize, Debug, PartialEq)]
+pub struct UploadConfiguration {
+    /// the chunk size handed out when a client does not request one
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: u64,
+    /// the largest chunk a client may request, chunks are buffered in memory
+    #[serde(default = "default_max_chunk_size")]
+    pub max_chunk_size: u64,
+    /// seconds a ticket stays valid after it was created or last received a chunk
+    #[serde(default = "default_ticket_ttl")]
+    pub ticket_ttl: u64,
+    /// seconds between sweeps expiring stale tickets & removing their chunks
+    #[serde(default = "default_sweep_interval")]
+    pub sweep_interval: u64,
+}
+
+impl Default for UploadConfiguration {
+    fn default() -> Self {
+        Self {
+            chunk_size: default_chunk_size(),
+            max_chunk_size: default_max_chunk_size(),
+            ticket_ttl: default_ticket_ttl(),
+            sweep_interval: default_sweep_interval(),
+        }
+    }
+}
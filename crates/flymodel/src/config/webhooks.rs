@@ -0,0 +1,59 @@
+fn default_interval() -> u64 {
+    5
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+fn default_max_attempts() -> u32 {
+    8
+}
+
+fn default_backoff() -> u64 {
+    30
+}
+
+fn default_max_backoff() -> u64 {
+    60 * 60
+}
+
+fn default_batch() -> u64 {
+    50
+}
+
+/// the `[webhooks]` section, delivering registry events to the webhooks of namespaces
+#[derive(Clone, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct WebhookConfiguration {
+    /// seconds between looking for due deliveries
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// seconds an endpoint has to answer a delivery
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// attempts of a delivery before it is given up on as dead
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// seconds before the first retry, doubled for every retry after it
+    #[serde(default = "default_backoff")]
+    pub backoff: u64,
+    /// the most seconds waited between retries
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff: u64,
+    /// the most deliveries attempted at a time
+    #[serde(default = "default_batch")]
+    pub batch: u64,
+}
+
+impl Default for WebhookConfiguration {
+    fn default() -> Self {
+        Self {
+            interval: default_interval(),
+            timeout: default_timeout(),
+            max_attempts: default_max_attempts(),
+            backoff: default_backoff(),
+            max_backoff: default_max_backoff(),
+            batch: default_batch(),
+        }
+    }
+}
@@ -8,14 +8,16 @@ use actix_web::{
 };
 use async_graphql::ErrorExtensions;
 use aws_sdk_s3::operation::{
-    delete_object::DeleteObjectError, get_object::GetObjectError, put_object::PutObjectError,
+    copy_object::CopyObjectError, delete_object::DeleteObjectError, get_object::GetObjectError,
+    head_object::HeadObjectError, list_object_versions::ListObjectVersionsError,
+    put_object::PutObjectError,
 };
 use aws_smithy_runtime_api::{client::result::SdkError as AwsError, http::Response as AwsResponse};
 use sea_orm::DbErr;
 use std::{error::Error, str::FromStr, sync::Arc};
 use thiserror::Error;
 
-use crate::lifecycle::Lifecycle;
+use crate::{lifecycle::Lifecycle, promotion::Precondition};
 
 #[derive(Error, Debug)]
 pub enum FlymodelError {
@@ -48,13 +50,28 @@ pub enum FlymodelError {
     StorageSetupError(anyhow::Error),
 
     #[error("S3 operation error (put): {0}")]
-    S3PutObjectError(#[from] AwsError<PutObjectError, AwsResponse>),
+    S3PutObjectError(Box<AwsError<PutObjectError, AwsResponse>>),
 
     #[error("S3 operation error (delete): {0}")]
-    S3DelObjectError(#[from] AwsError<DeleteObjectError, AwsResponse>),
+    S3DelObjectError(Box<AwsError<DeleteObjectError, AwsResponse>>),
 
     #[error("S3 operation error (get): {0}")]
-    S3GetObjectError(#[from] AwsError<GetObjectError, AwsResponse>),
+    S3GetObjectError(Box<AwsError<GetObjectError, AwsResponse>>),
+
+    #[error("S3 operation error (head): {0}")]
+    S3HeadObjectError(Box<AwsError<HeadObjectError, AwsResponse>>),
+
+    #[error("S3 operation error (copy): {0}")]
+    S3CopyObjectError(Box<AwsError<CopyObjectError, AwsResponse>>),
+
+    #[error("S3 operation error (list): {0}")]
+    S3ListObjectsError(Box<AwsError<ListObjectVersionsError, AwsResponse>>),
+
+    #[error("S3 operation error (multipart): {0}")]
+    S3MultipartError(anyhow::Error),
+
+    #[error("Storage io error: {0}")]
+    StorageIoError(std::io::Error),
 
     #[error("S3 operation error (get::collect()): {0}")]
     S3BinaryLoadError(#[from] aws_sdk_s3::primitives::ByteStreamError),
@@ -81,7 +98,44 @@ pub enum FlymodelError {
     InvalidTransition {
         current: Lifecycle,
         requested: Lifecycle,
+        /// every precondition of the transition which was not met
+        failed: Vec<Precondition>,
+    },
+
+    #[error("Permission denied: {subject} requires {perm} on {resource}")]
+    PermissionDenied {
+        subject: String,
+        resource: String,
+        perm: String,
     },
+
+    #[error("Unauthenticated: {0}")]
+    Unauthenticated(String),
+
+    #[error("Unsupported operation: {0}")]
+    UnsupportedOperation(String),
+}
+
+// sdk errors are boxed, as they would otherwise make up most of the size of every result
+macro_rules! from_sdk_error {
+    ($($err:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<AwsError<$err, AwsResponse>> for FlymodelError {
+                fn from(err: AwsError<$err, AwsResponse>) -> Self {
+                    Self::$variant(Box::new(err))
+                }
+            }
+        )*
+    };
+}
+
+from_sdk_error! {
+    PutObjectError => S3PutObjectError,
+    DeleteObjectError => S3DelObjectError,
+    GetObjectError => S3GetObjectError,
+    HeadObjectError => S3HeadObjectError,
+    CopyObjectError => S3CopyObjectError,
+    ListObjectVersionsError => S3ListObjectsError,
 }
 
 impl FlymodelError {
@@ -106,6 +160,14 @@ impl FlymodelError {
             Self::InvalidResourceId(_) => 17,
             Self::InternalError(_) => 18,
             Self::InvalidTransition { .. } => 19,
+            Self::PermissionDenied { .. } => 20,
+            Self::Unauthenticated(_) => 21,
+            Self::S3MultipartError(_) => 22,
+            Self::StorageIoError(_) => 23,
+            Self::S3HeadObjectError(_) => 24,
+            Self::UnsupportedOperation(_) => 25,
+            Self::S3CopyObjectError(_) => 26,
+            Self::S3ListObjectsError(_) => 27,
         } + 9008)
     }
 
@@ -119,7 +181,12 @@ impl FlymodelError {
             Self::S3BinaryLoadError(..)
             | Self::S3GetObjectError(..)
             | Self::S3DelObjectError(..)
-            | Self::S3PutObjectError(..) => "StorageError",
+            | Self::S3PutObjectError(..)
+            | Self::S3MultipartError(..)
+            | Self::S3HeadObjectError(..)
+            | Self::S3CopyObjectError(..)
+            | Self::S3ListObjectsError(..)
+            | Self::StorageIoError(..) => "StorageError",
             Self::IntegrityError { .. } => "IntegrityError",
             Self::InvalidPermission(..) => "InvalidPermission",
             Self::ContraintError(..) => "ContraintError",
@@ -127,6 +194,9 @@ impl FlymodelError {
             Self::NonDeterministicError(..) => "NonDeterministicBehaviourError",
             Self::InvalidResourceId(..) => "InvalidResourceId",
             Self::InvalidTransition { .. } => "InvalidTransition",
+            Self::PermissionDenied { .. } => "PermissionDenied",
+            Self::Unauthenticated(..) => "Unauthenticated",
+            Self::UnsupportedOperation(..) => "UnsupportedOperation",
             _ => "SystemError",
         }
     }
@@ -144,7 +214,11 @@ impl FlymodelError {
                 "An error occured loading binary data from storage".to_string()
             }
             Self::S3GetObjectError(..) => "An error occured loading data from storage".to_string(),
-            Self::S3PutObjectError(..) => "An error occured uploading data to storage".to_string(),
+            Self::S3PutObjectError(..) | Self::S3MultipartError(..) => {
+                "An error occured uploading data to storage".to_string()
+            }
+            Self::S3CopyObjectError(..) => "An error occured copying data in storage".to_string(),
+            Self::S3ListObjectsError(..) => "An error occured listing data in storage".to_string(),
             Self::ContraintError(source) => {
                 format!("The following contraint failed validation: {source}")
             }
@@ -157,13 +231,23 @@ impl FlymodelError {
             Self::InvalidResourceId(id) => {
                 format!("{id} could not be found")
             }
-            Self::InvalidTransition { current, requested } => {
-                let base = format!("{current} cannot transition to {requested}.");
-                match current {
-                    Lifecycle::Prod => base + &format!(" prod is immutable."),
-                    _ => base,
-                }
+            Self::InvalidTransition {
+                current,
+                requested,
+                failed,
+            } => {
+                let failed = failed
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                format!("{current} cannot transition to {requested}: {failed}")
+            }
+            Self::PermissionDenied { resource, perm, .. } => {
+                format!("{perm} access to {resource} is not permitted")
             }
+            Self::Unauthenticated(..) => "Valid credentials are required".to_string(),
+            Self::UnsupportedOperation(reason) => reason.clone(),
             _ => "A system error occured".to_string(),
         }
     }
@@ -173,9 +257,22 @@ impl FlymodelError {
         async_graphql::Error::new(self.code_description()).extend_with(|_, ext| {
             ext.set("code", self.code());
             ext.set("kind", self.code_str());
+            if let Some(Ok(failed)) = self
+                .failed_preconditions()
+                .map(async_graphql::Value::from_json)
+            {
+                ext.set("failed", failed);
+            }
         })
     }
 
+    fn failed_preconditions(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::InvalidTransition { failed, .. } => serde_json::to_value(failed).ok(),
+            _ => None,
+        }
+    }
+
     pub fn internal_error<'a, E: Error + Sync + Send + 'static>(err: E) -> FlymodelError {
         FlymodelError::InternalError(anyhow::Error::from(err))
     }
@@ -188,7 +285,9 @@ impl actix_web::error::ResponseError for FlymodelError {
             Self::IntegrityError { .. }
             | Self::ContraintError(..)
             | Self::InvalidTransition { .. } => StatusCode::EXPECTATION_FAILED,
-            Self::InvalidPermission(..) => StatusCode::FORBIDDEN,
+            Self::InvalidPermission(..) | Self::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+            Self::Unauthenticated(..) => StatusCode::UNAUTHORIZED,
+            Self::UnsupportedOperation(..) => StatusCode::NOT_IMPLEMENTED,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -198,10 +297,17 @@ impl actix_web::error::ResponseError for FlymodelError {
 
         let mut resp = HttpResponse::new(self.status_code()).set_body(BoxBody::new(
             // this is infallible serialization
-            match serde_json::to_vec(&serde_json::json!({
-                "code": self.code(),
-                "kind": self.code_str()
-            })) {
+            match serde_json::to_vec(&match self.failed_preconditions() {
+                Some(failed) => serde_json::json!({
+                    "code": self.code(),
+                    "kind": self.code_str(),
+                    "failed": failed
+                }),
+                None => serde_json::json!({
+                    "code": self.code(),
+                    "kind": self.code_str()
+                }),
+            }) {
                 Ok(enc) => enc,
                 Err(..) => unreachable!(),
             },
@@ -2,6 +2,7 @@ pub mod config;
 pub mod errs;
 pub mod lifecycle;
 pub mod perms;
+pub mod promotion;
 pub mod services;
 pub mod storage;
 pub mod tls;
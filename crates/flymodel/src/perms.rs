@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::errs::FlymodelError;
+use crate::errs::{FlymodelError, FlymodelResult};
 
 bitflags::bitflags! {
     #[repr(transparent)]
@@ -38,6 +38,7 @@ impl ToString for Perm {
     however, we want a common representation across auth styles for
     our own internal resolutions across access layers.
 */
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Permission {
     Global { perm: Perm },
     Namespace { perm: Perm, id: i64 },
@@ -85,6 +86,7 @@ impl TryFrom<&str> for Permission {
     }
 }
 
+#[derive(Debug, Clone, Default)]
 pub struct Permissions(Vec<Permission>);
 
 impl Permissions {
@@ -129,6 +131,25 @@ impl Permissions {
         });
         perms
     }
+
+    pub fn global_permission(&self) -> Option<Perm> {
+        self.as_ref().iter().fold(None, |acc, perm| match perm {
+            Permission::Global { perm } => Some(acc.unwrap_or(Perm::R) | *perm),
+            _ => acc,
+        })
+    }
+
+    /// model grants are additive on top of the namespace the model belongs to
+    pub fn model_permission(&self, ns: i64, model: i64) -> Option<Perm> {
+        let base = self
+            .namespace_permissions(vec![ns])
+            .remove(&ns)
+            .unwrap_or_default();
+        self.as_ref().iter().fold(base, |acc, perm| match perm {
+            Permission::Model { perm, id } if *id == model => Some(acc.unwrap_or(Perm::R) | *perm),
+            _ => acc,
+        })
+    }
 }
 
 impl AsRef<[Permission]> for Permissions {
@@ -143,11 +164,127 @@ impl Into<Vec<String>> for Permissions {
     }
 }
 
+#[inline]
+fn satisfies(found: Option<Perm>, required: Perm) -> bool {
+    found.is_some_and(|found| found.contains(required))
+}
+
+/// what a principal may read without a global grant, listing queries narrow their selections to it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadScope {
+    pub namespaces: Vec<i64>,
+    pub models: Vec<i64>,
+}
+
+impl ReadScope {
+    pub fn restrict_namespaces(&self, requested: Option<Vec<i64>>) -> Vec<i64> {
+        match requested {
+            Some(requested) => requested
+                .into_iter()
+                .filter(|ns| self.namespaces.contains(ns))
+                .collect(),
+            None => self.namespaces.clone(),
+        }
+    }
+}
+
+/// the authenticated caller of a request, checked by every resolver & route
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub subject: String,
+    pub permissions: Permissions,
+    anonymous: bool,
+}
+
+impl Principal {
+    pub fn new(subject: impl Into<String>, permissions: Permissions) -> Self {
+        Self {
+            subject: subject.into(),
+            permissions,
+            anonymous: false,
+        }
+    }
+
+    /// every caller when no authorizer is configured, trusted with everything
+    pub fn anonymous() -> Self {
+        Self {
+            anonymous: true,
+            ..Self::new(
+                "anonymous",
+                Permissions::new(vec![Permission::Global { perm: Perm::W }]),
+            )
+        }
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.anonymous
+    }
+
+    fn denied(&self, resource: String, perm: Perm) -> FlymodelError {
+        FlymodelError::PermissionDenied {
+            subject: self.subject.clone(),
+            resource,
+            perm: perm.to_string(),
+        }
+    }
+
+    pub fn namespace_permission(&self, ns: i64) -> Option<Perm> {
+        self.permissions
+            .namespace_permissions(vec![ns])
+            .remove(&ns)
+            .unwrap_or_default()
+    }
+
+    pub fn authorize_global(&self, perm: Perm) -> FlymodelResult<()> {
+        if satisfies(self.permissions.global_permission(), perm) {
+            Ok(())
+        } else {
+            Err(self.denied("global".into(), perm))
+        }
+    }
+
+    pub fn authorize_namespace(&self, ns: i64, perm: Perm) -> FlymodelResult<()> {
+        if satisfies(self.namespace_permission(ns), perm) {
+            Ok(())
+        } else {
+            Err(self.denied(format!("namespace:{ns}"), perm))
+        }
+    }
+
+    pub fn authorize_model(&self, ns: i64, model: i64, perm: Perm) -> FlymodelResult<()> {
+        if satisfies(self.permissions.model_permission(ns, model), perm) {
+            Ok(())
+        } else {
+            Err(self.denied(format!("model:{model}"), perm))
+        }
+    }
+
+    /// `None` when the principal may read everything
+    pub fn read_scope(&self) -> Option<ReadScope> {
+        if self.permissions.global_permission().is_some() {
+            return None;
+        }
+        let mut scope = ReadScope::default();
+        for perm in self.permissions.as_ref() {
+            match perm {
+                Permission::Namespace { id, .. } if !scope.namespaces.contains(id) => {
+                    scope.namespaces.push(*id)
+                }
+                Permission::Model { id, .. } if !scope.models.contains(id) => {
+                    scope.models.push(*id)
+                }
+                _ => (),
+            }
+        }
+        Some(scope)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
 
-    use super::{Perm, Permission};
+    use super::{Perm, Permission, Principal, ReadScope};
 
     #[test]
     fn test_perm() {
@@ -225,4 +362,69 @@ mod test {
 
         assert_eq!(found, expect);
     }
+
+    #[test]
+    fn test_principal_namespace_authorization() {
+        let principal = Principal::new(
+            "someone",
+            super::Permissions::new(vec![
+                Permission::Namespace {
+                    perm: Perm::R,
+                    id: 1,
+                },
+                Permission::Namespace {
+                    perm: Perm::W,
+                    id: 2,
+                },
+            ]),
+        );
+
+        assert!(principal.authorize_namespace(1, Perm::R).is_ok());
+        assert!(principal.authorize_namespace(1, Perm::W).is_err());
+        assert!(principal.authorize_namespace(2, Perm::W).is_ok());
+        assert!(principal.authorize_namespace(3, Perm::R).is_err());
+        assert!(principal.authorize_global(Perm::R).is_err());
+        assert_eq!(
+            principal.read_scope(),
+            Some(ReadScope {
+                namespaces: vec![1, 2],
+                models: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_principal_model_grants() {
+        let principal = Principal::new(
+            "someone",
+            super::Permissions::new(vec![
+                Permission::Namespace {
+                    perm: Perm::R,
+                    id: 1,
+                },
+                Permission::Model {
+                    perm: Perm::W,
+                    id: 7,
+                },
+            ]),
+        );
+
+        assert!(principal.authorize_model(1, 7, Perm::W).is_ok());
+        assert!(principal.authorize_model(1, 8, Perm::W).is_err());
+        assert!(principal.authorize_model(1, 8, Perm::R).is_ok());
+        assert!(principal.authorize_model(2, 7, Perm::R).is_ok());
+        assert!(principal.authorize_model(2, 8, Perm::R).is_err());
+    }
+
+    #[test]
+    fn test_global_principal_is_unscoped() {
+        let principal = Principal::new(
+            "admin",
+            super::Permissions::new(vec![Permission::Global { perm: Perm::R }]),
+        );
+        assert_eq!(principal.read_scope(), None);
+        assert!(principal.authorize_namespace(42, Perm::R).is_ok());
+        assert!(principal.authorize_namespace(42, Perm::W).is_err());
+        assert!(principal.authorize_global(Perm::W).is_err());
+    }
 }
@@ -0,0 +1,302 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{errs::FlymodelError, lifecycle::Lifecycle};
+
+/// the rules a namespace places on model versions entering (and leaving) a lifecycle
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromotionRules {
+    /// at least one experiment of the version must have passed
+    pub require_passed_experiment: bool,
+    /// the fewest artifacts the version may have
+    pub min_artifacts: u64,
+    /// tags the version must carry
+    pub required_tags: Vec<String>,
+    /// versions in this lifecycle may not move back to an earlier one
+    pub block_demotion: bool,
+    /// approvals a promotion request into this lifecycle needs before it is applied
+    pub required_approvals: u64,
+}
+
+impl PromotionRules {
+    /// the rules of a lifecycle without a configured policy: prod may not be left,
+    /// and is only entered through an approved promotion request
+    pub fn default_for(state: Lifecycle) -> Self {
+        let prod = state == Lifecycle::Prod;
+        Self {
+            block_demotion: prod,
+            required_approvals: prod as u64,
+            ..Default::default()
+        }
+    }
+
+    /// whether the rules check nothing stored of the version itself
+    pub fn is_unconstrained(&self) -> bool {
+        !self.require_passed_experiment && self.min_artifacts == 0 && self.required_tags.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionFacts {
+    pub passed_experiments: u64,
+    pub artifacts: u64,
+    pub tags: Vec<String>,
+    /// approvals of the promotion request applying the transition, if any
+    pub approvals: u64,
+}
+
+/// a precondition of a lifecycle transition which was not met
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Precondition {
+    /// promotions below stage move a single lifecycle at a time
+    Sequence {
+        next: Lifecycle,
+    },
+    DemotionBlocked {
+        state: Lifecycle,
+    },
+    PassedExperiment,
+    MinArtifacts {
+        required: u64,
+        found: u64,
+    },
+    RequiredTag {
+        tag: String,
+    },
+    Approvals {
+        required: u64,
+        found: u64,
+    },
+}
+
+impl Display for Precondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sequence { next } => write!(f, "the next lifecycle is {next}"),
+            Self::DemotionBlocked { state } => write!(f, "{state} versions may not be demoted"),
+            Self::PassedExperiment => write!(f, "a passed experiment is required"),
+            Self::MinArtifacts { required, found } => {
+                write!(f, "{required} artifacts are required, {found} found")
+            }
+            Self::RequiredTag { tag } => write!(f, "the tag {tag} is required"),
+            Self::Approvals { required, found } => {
+                write!(f, "{required} approvals are required, {found} found")
+            }
+        }
+    }
+}
+
+/// a promotion may not skip a lifecycle on its way to prod
+pub fn skipped_lifecycle(current: Lifecycle, requested: Lifecycle) -> Option<Precondition> {
+    match (current, requested) {
+        (Lifecycle::Test, Lifecycle::Qa)
+        | (Lifecycle::Qa, Lifecycle::Stage)
+        | (Lifecycle::Stage, _) => None,
+        (Lifecycle::Test, _) => Some(Precondition::Sequence {
+            next: Lifecycle::Qa,
+        }),
+        _ => Some(Precondition::Sequence {
+            next: Lifecycle::Stage,
+        }),
+    }
+}
+
+/// collects every precondition which failed rather than the first
+pub fn check_transition(
+    current: Lifecycle,
+    requested: Lifecycle,
+    leaving: &PromotionRules,
+    entering: &PromotionRules,
+    facts: &VersionFacts,
+) -> Result<(), FlymodelError> {
+    let mut failed = vec![];
+    if requested < current {
+        if leaving.block_demotion {
+            failed.push(Precondition::DemotionBlocked { state: current });
+        }
+    } else if requested > current {
+        failed.extend(skipped_lifecycle(current, requested));
+        if entering.require_passed_experiment && facts.passed_experiments == 0 {
+            failed.push(Precondition::PassedExperiment);
+        }
+        if facts.artifacts < entering.min_artifacts {
+            failed.push(Precondition::MinArtifacts {
+                required: entering.min_artifacts,
+                found: facts.artifacts,
+            });
+        }
+        failed.extend(
+            entering
+                .required_tags
+                .iter()
+                .filter(|tag| !facts.tags.contains(tag))
+                .map(|tag| Precondition::RequiredTag { tag: tag.clone() }),
+        );
+        if facts.approvals < entering.required_approvals {
+            failed.push(Precondition::Approvals {
+                required: entering.required_approvals,
+                found: facts.approvals,
+            });
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(FlymodelError::InvalidTransition {
+            current,
+            requested,
+            failed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_transition, Precondition, PromotionRules, VersionFacts};
+    use crate::{errs::FlymodelError, lifecycle::Lifecycle};
+
+    fn failed(result: Result<(), FlymodelError>) -> Vec<Precondition> {
+        match result {
+            Ok(()) => vec![],
+            Err(FlymodelError::InvalidTransition { failed, .. }) => failed,
+            Err(err) => panic!("unexpected error {err}"),
+        }
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let check = |current, requested| {
+            failed(check_transition(
+                current,
+                requested,
+                &PromotionRules::default_for(current),
+                &PromotionRules::default_for(requested),
+                &VersionFacts::default(),
+            ))
+        };
+        assert!(check(Lifecycle::Test, Lifecycle::Qa).is_empty());
+        assert!(check(Lifecycle::Stage, Lifecycle::Test).is_empty());
+        assert_eq!(
+            check(Lifecycle::Stage, Lifecycle::Prod),
+            vec![Precondition::Approvals {
+                required: 1,
+                found: 0
+            }]
+        );
+        assert_eq!(
+            check(Lifecycle::Test, Lifecycle::Prod),
+            vec![
+                Precondition::Sequence {
+                    next: Lifecycle::Qa
+                },
+                Precondition::Approvals {
+                    required: 1,
+                    found: 0
+                }
+            ]
+        );
+        assert_eq!(
+            check(Lifecycle::Prod, Lifecycle::Stage),
+            vec![Precondition::DemotionBlocked {
+                state: Lifecycle::Prod
+            }]
+        );
+    }
+
+    #[test]
+    fn test_policy_lists_every_failure() {
+        let entering = PromotionRules {
+            require_passed_experiment: true,
+            min_artifacts: 2,
+            required_tags: vec!["reviewed".into(), "benchmarked".into()],
+            block_demotion: false,
+            required_approvals: 0,
+        };
+        let facts = VersionFacts {
+            passed_experiments: 0,
+            artifacts: 1,
+            tags: vec!["benchmarked".into()],
+            approvals: 0,
+        };
+        assert_eq!(
+            failed(check_transition(
+                Lifecycle::Test,
+                Lifecycle::Stage,
+                &PromotionRules::default(),
+                &entering,
+                &facts,
+            )),
+            vec![
+                Precondition::Sequence {
+                    next: Lifecycle::Qa
+                },
+                Precondition::PassedExperiment,
+                Precondition::MinArtifacts {
+                    required: 2,
+                    found: 1
+                },
+                Precondition::RequiredTag {
+                    tag: "reviewed".into()
+                },
+            ]
+        );
+
+        let facts = VersionFacts {
+            passed_experiments: 1,
+            artifacts: 2,
+            tags: vec!["benchmarked".into(), "reviewed".into()],
+            approvals: 0,
+        };
+        assert!(failed(check_transition(
+            Lifecycle::Qa,
+            Lifecycle::Stage,
+            &PromotionRules::default(),
+            &entering,
+            &facts,
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn test_prod_demotion_may_be_allowed() {
+        assert!(failed(check_transition(
+            Lifecycle::Prod,
+            Lifecycle::Stage,
+            &PromotionRules::default(),
+            &PromotionRules::default(),
+            &VersionFacts::default(),
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn test_approval_quorum() {
+        let entering = PromotionRules {
+            required_approvals: 2,
+            ..PromotionRules::default_for(Lifecycle::Prod)
+        };
+        let check = |approvals| {
+            failed(check_transition(
+                Lifecycle::Stage,
+                Lifecycle::Prod,
+                &PromotionRules::default(),
+                &entering,
+                &VersionFacts {
+                    approvals,
+                    ..Default::default()
+                },
+            ))
+        };
+        assert_eq!(
+            check(1),
+            vec![Precondition::Approvals {
+                required: 2,
+                found: 1
+            }]
+        );
+        assert!(check(2).is_empty());
+    }
+}
@@ -1,6 +1,123 @@
-use bytes::Bytes;
+use std::{
+    pin::Pin,
+    time::{Duration, SystemTime},
+};
 
-use crate::{errs::FlymodelResult, lifecycle::Lifecycle};
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    errs::{FlymodelError, FlymodelResult},
+    lifecycle::Lifecycle,
+};
+
+pub type ByteStream = Pin<Box<dyn Stream<Item = FlymodelResult<Bytes>> + Send>>;
+
+/// incrementally computes the size and sha256 of streamed data
+#[derive(Default)]
+pub struct StreamDigest {
+    hasher: Sha256,
+    size: u64,
+}
+
+impl StreamDigest {
+    pub fn update(&mut self, bs: &[u8]) {
+        self.hasher.update(bs);
+        self.size += bs.len() as u64;
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn finish(self) -> (u64, String) {
+        (self.size, hex::encode(self.hasher.finalize()))
+    }
+}
+
+/// an inclusive byte range of an object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectMeta {
+    pub size: u64,
+    pub version_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectVersion {
+    /// the path of the object, relative to the prefix of the provider
+    pub path: String,
+    pub version_id: Option<String>,
+    pub size: u64,
+    pub last_modified: SystemTime,
+}
+
+/// where a provider may copy an object from without streaming it through the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopySource {
+    /// the endpoint of the storage holding the object, copies do not cross endpoints
+    pub endpoint: Option<String>,
+    pub bucket: String,
+    pub key: String,
+    pub version_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UploadState {
+    /// the object is visible in storage
+    Complete { version_id: Option<String> },
+    /// parts are uploaded but not yet assembled into an object
+    Multipart {
+        upload_id: String,
+        parts: Vec<(i32, String)>,
+    },
+}
+
+/// an upload not yet committed to, see [StorageProvider::complete_upload]
+#[derive(Debug, Clone)]
+pub struct StagedUpload {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    pub state: UploadState,
+}
+
+impl StagedUpload {
+    pub fn version_id(&self) -> Option<String> {
+        match &self.state {
+            UploadState::Complete { version_id } => version_id.clone(),
+            UploadState::Multipart { .. } => None,
+        }
+    }
+}
+
+/// copies an object between providers by streaming it through the server
+pub async fn stream_copy<S: StorageProvider + Sync + ?Sized>(
+    source: &(dyn StorageProvider + Sync + Send),
+    sink: &S,
+    path: String,
+    version_id: Option<String>,
+    to: String,
+) -> FlymodelResult<ObjectMeta> {
+    let stream = source.get_stream(path, version_id, None).await?;
+    let mut upload = sink.put_stream(to, stream).await?;
+    match sink.complete_upload(&mut upload).await {
+        Ok(version_id) => Ok(ObjectMeta {
+            size: upload.size,
+            version_id,
+        }),
+        Err(err) => {
+            let _ = sink.abort_upload(upload).await;
+            Err(err)
+        }
+    }
+}
 
 #[async_trait::async_trait]
 pub trait StorageProvider {
@@ -20,4 +137,129 @@ pub trait StorageProvider {
     async fn put(&self, path: String, bs: Bytes) -> FlymodelResult<Option<String>>;
     async fn del(&self, path: String, version_id: Option<String>) -> FlymodelResult<()>;
     async fn get(&self, path: String, version_id: Option<String>) -> FlymodelResult<Bytes>;
+
+    /// every stored version of the objects below the prefix of the provider
+    async fn list(&self) -> FlymodelResult<Vec<ObjectVersion>> {
+        Err(FlymodelError::UnsupportedOperation(
+            "listing objects is not supported by this storage".into(),
+        ))
+    }
+
+    /// the metadata of an object, by default loaded with [StorageProvider::get]
+    async fn head(&self, path: String, version_id: Option<String>) -> FlymodelResult<ObjectMeta> {
+        let bs = self.get(path, version_id.clone()).await?;
+        Ok(ObjectMeta {
+            size: bs.len() as u64,
+            version_id,
+        })
+    }
+
+    /// a url clients may upload an object to directly, bypassing the server
+    async fn presign_put(&self, path: String, expires_in: Duration) -> FlymodelResult<String> {
+        let _ = (path, expires_in);
+        Err(FlymodelError::UnsupportedOperation(
+            "presigned uploads are not supported by this storage".into(),
+        ))
+    }
+
+    /// a url clients may download an object from directly, bypassing the server
+    async fn presign_get(
+        &self,
+        path: String,
+        version_id: Option<String>,
+        expires_in: Duration,
+    ) -> FlymodelResult<String> {
+        let _ = (path, version_id, expires_in);
+        Err(FlymodelError::UnsupportedOperation(
+            "presigned downloads are not supported by this storage".into(),
+        ))
+    }
+
+    /// the default loads the whole object with [StorageProvider::get]
+    async fn get_stream(
+        &self,
+        path: String,
+        version_id: Option<String>,
+        range: Option<ByteRange>,
+    ) -> FlymodelResult<ByteStream> {
+        let mut bs = self.get(path, version_id).await?;
+        if let Some(range) = range {
+            let end = (range.end as usize).saturating_add(1).min(bs.len());
+            bs = bs.slice((range.start as usize).min(end)..end);
+        }
+        Ok(Box::pin(futures_util::stream::once(async move { Ok(bs) })))
+    }
+
+    /// the default buffers the stream and defers to [StorageProvider::put]
+    async fn put_stream(
+        &self,
+        path: String,
+        mut stream: ByteStream,
+    ) -> FlymodelResult<StagedUpload> {
+        let mut digest = StreamDigest::default();
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            digest.update(&chunk);
+            buf.extend_from_slice(&chunk);
+        }
+        let (size, sha256) = digest.finish();
+        let version_id = self.put(path.clone(), buf.freeze()).await?;
+        Ok(StagedUpload {
+            path,
+            size,
+            sha256,
+            state: UploadState::Complete { version_id },
+        })
+    }
+
+    /// makes a staged upload visible, returning its version
+    async fn complete_upload(&self, upload: &mut StagedUpload) -> FlymodelResult<Option<String>> {
+        match &upload.state {
+            UploadState::Complete { version_id } => Ok(version_id.clone()),
+            UploadState::Multipart { .. } => Err(FlymodelError::RuntimeDependencyError(
+                "multipart uploads are not supported by this storage".into(),
+            )),
+        }
+    }
+
+    /// the location other providers may copy an object of this one from, if any
+    fn copy_source(&self, path: String, version_id: Option<String>) -> Option<CopySource> {
+        let _ = (path, version_id);
+        None
+    }
+
+    /// the default streams the object through the server, see [stream_copy]
+    async fn copy_from(
+        &self,
+        source: &(dyn StorageProvider + Sync + Send),
+        path: String,
+        version_id: Option<String>,
+        to: String,
+    ) -> FlymodelResult<ObjectMeta> {
+        stream_copy(source, self, path, version_id, to).await
+    }
+
+    /// discards a staged upload, whether or not it was completed
+    async fn abort_upload(&self, upload: StagedUpload) -> FlymodelResult<()> {
+        match upload.state {
+            UploadState::Complete { version_id } => self.del(upload.path, version_id).await,
+            UploadState::Multipart { .. } => Err(FlymodelError::RuntimeDependencyError(
+                "multipart uploads are not supported by this storage".into(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StreamDigest;
+
+    #[test]
+    fn test_stream_digest() {
+        let mut digest = StreamDigest::default();
+        digest.update(b"hello ");
+        digest.update(b"world");
+        assert_eq!(digest.finish(), (11, sha256::digest("hello world")));
+    }
 }
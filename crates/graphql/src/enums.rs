@@ -47,3 +47,28 @@ pub enum ArchiveFormat {
     Xls,
     Xml,
 }
+
+#[derive(HybridEnum, cynic::Enum, Clone, Copy, Debug)]
+#[hybrid_feature_class(python = true, ts = true, rename_ts = true)]
+pub enum RunState {
+    Created,
+    Failed,
+    Passed,
+    Running,
+}
+
+#[derive(HybridEnum, cynic::Enum, Clone, Copy, Debug)]
+#[hybrid_feature_class(python = true, ts = true, rename_ts = true)]
+pub enum ParamKind {
+    Bool,
+    Float,
+    Int,
+    Text,
+}
+
+#[derive(HybridEnum, cynic::Enum, Clone, Copy, Debug)]
+#[hybrid_feature_class(python = true, ts = true, rename_ts = true)]
+pub enum PackageEcosystem {
+    Python,
+    Rust,
+}
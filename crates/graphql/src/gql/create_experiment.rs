@@ -1,3 +1,4 @@
+use super::{record_experiment_environment::EnvironmentInput, set_experiment_params::ParamInput};
 use crate::{jsvalue, schema};
 use flymodel_macros::hybrid_feature_class;
 use partial_context::PartialContext;
@@ -13,19 +14,29 @@ pub struct CreateExperimentVariables {
     pub experiment_name: String,
     #[context]
     pub model_version_id: i32,
+    pub params: Option<Vec<ParamInput>>,
+    pub environment: Option<EnvironmentInput>,
 }
 
 crate::new_for! {
+    #[pyo3(signature = (experiment_name, model_version_id, params = None, environment = None))]
     CreateExperimentVariables,
     experiment_name: &str,
     model_version_id: i32,
+    params: Option<Vec<ParamInput>>,
+    environment: Option<EnvironmentInput>,
 }
 
 #[hybrid_feature_class(python = true, into_ts = true, rename_into_ts = true)]
 #[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
 #[cynic(graphql_type = "Mutation", variables = "CreateExperimentVariables")]
 pub struct CreateExperiment {
-    #[arguments(modelVersion: $model_version_id, name: $experiment_name)]
+    #[arguments(
+        modelVersion: $model_version_id,
+        name: $experiment_name,
+        params: $params,
+        environment: $environment
+    )]
     pub create_experiment: Experiment,
 }
 
@@ -0,0 +1,47 @@
+use crate::{jsvalue, scalars::DateTime, schema};
+use flymodel_macros::hybrid_feature_class;
+use serde::{Deserialize, Serialize};
+
+#[hybrid_feature_class(python = true)]
+#[derive(cynic::InputObject, Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify), tsify(from_wasm_abi))]
+pub struct MetricInput {
+    pub key: String,
+    pub value: f64,
+    pub step: Option<i32>,
+    pub timestamp: Option<DateTime>,
+}
+
+crate::new_for! {
+    #[pyo3(signature = (key, value, step = None, timestamp = None))]
+    MetricInput,
+    key: String,
+    value: f64,
+    step: Option<i32>,
+    timestamp: Option<DateTime>
+}
+
+#[hybrid_feature_class(python = true, from_ts = true, rename_from_ts = true)]
+#[derive(cynic::QueryVariables, Debug, Clone, Deserialize)]
+pub struct LogMetricsVariables {
+    pub experiment: i32,
+    pub metrics: Vec<MetricInput>,
+}
+
+crate::new_for! {
+    LogMetricsVariables,
+    experiment: i32,
+    metrics: Vec<MetricInput>
+}
+
+#[hybrid_feature_class(python = true, into_ts = true, rename_into_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+#[cynic(graphql_type = "Mutation", variables = "LogMetricsVariables")]
+pub struct LogMetrics {
+    #[arguments(experiment: $experiment, metrics: $metrics)]
+    pub log_metrics: i32,
+}
+
+jsvalue! {
+    LogMetrics
+}
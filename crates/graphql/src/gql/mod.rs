@@ -8,11 +8,15 @@ pub mod delete_experiment;
 pub mod delete_model;
 pub mod delete_model_version;
 pub mod delete_namespace;
+pub mod log_metrics;
 pub mod query_buckets;
 pub mod query_experiment;
 pub mod query_experiment_artifacts;
 pub mod query_models;
 pub mod query_namespaces;
+pub mod record_experiment_environment;
+pub mod set_experiment_params;
+pub mod update_experiment_state;
 pub mod update_model;
 pub mod update_model_version_state;
 pub mod update_namespace;
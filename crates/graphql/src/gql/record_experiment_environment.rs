@@ -0,0 +1,94 @@
+use crate::{enums::*, jsvalue, scalars::DateTime, schema};
+use flymodel_macros::hybrid_feature_class;
+use serde::{Deserialize, Serialize};
+
+#[hybrid_feature_class(python = true)]
+#[derive(cynic::InputObject, Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify), tsify(from_wasm_abi))]
+pub struct PackageInput {
+    pub ecosystem: PackageEcosystem,
+    pub name: String,
+    pub version: String,
+}
+
+crate::new_for! {
+    PackageInput,
+    ecosystem: PackageEcosystem,
+    name: String,
+    version: String
+}
+
+#[hybrid_feature_class(python = true)]
+#[derive(cynic::InputObject, Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify), tsify(from_wasm_abi))]
+pub struct EnvVarInput {
+    pub name: String,
+    pub value: String,
+}
+
+crate::new_for! {
+    EnvVarInput,
+    name: String,
+    value: String
+}
+
+#[hybrid_feature_class(python = true)]
+#[derive(cynic::InputObject, Clone, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify), tsify(from_wasm_abi))]
+pub struct EnvironmentInput {
+    pub git_commit: Option<String>,
+    pub git_dirty: Option<bool>,
+    pub hostname: Option<String>,
+    pub packages: Vec<PackageInput>,
+    pub variables: Vec<EnvVarInput>,
+}
+
+crate::new_for! {
+    #[pyo3(signature = (git_commit = None, git_dirty = None, hostname = None, packages = Vec::new(), variables = Vec::new()))]
+    EnvironmentInput,
+    git_commit: Option<String>,
+    git_dirty: Option<bool>,
+    hostname: Option<String>,
+    packages: Vec<PackageInput>,
+    variables: Vec<EnvVarInput>
+}
+
+#[hybrid_feature_class(python = true, from_ts = true, rename_from_ts = true)]
+#[derive(cynic::QueryVariables, Debug, Clone, Deserialize)]
+pub struct RecordExperimentEnvironmentVariables {
+    pub experiment: i32,
+    pub environment: EnvironmentInput,
+}
+
+crate::new_for! {
+    RecordExperimentEnvironmentVariables,
+    experiment: i32,
+    environment: EnvironmentInput
+}
+
+#[hybrid_feature_class(python = true, into_ts = true, rename_into_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+#[cynic(
+    graphql_type = "Mutation",
+    variables = "RecordExperimentEnvironmentVariables"
+)]
+pub struct RecordExperimentEnvironment {
+    #[arguments(experiment: $experiment, environment: $environment)]
+    pub record_experiment_environment: ExperimentEnvironment,
+}
+
+#[hybrid_feature_class(python = true, ts = true, rename_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+pub struct ExperimentEnvironment {
+    pub id: i32,
+    pub experiment_id: i32,
+    pub git_commit: Option<String>,
+    pub git_dirty: Option<bool>,
+    pub hostname: Option<String>,
+    pub captured_at: DateTime,
+}
+
+jsvalue! {
+    ExperimentEnvironment,
+    RecordExperimentEnvironment
+}
@@ -0,0 +1,83 @@
+use crate::{enums::*, jsvalue, schema};
+use flymodel_macros::hybrid_feature_class;
+use serde::{Deserialize, Serialize};
+
+#[hybrid_feature_class(python = true)]
+#[derive(cynic::InputObject, Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify), tsify(from_wasm_abi))]
+pub struct ParamInput {
+    pub key: String,
+    pub kind: ParamKind,
+    pub value: String,
+}
+
+crate::new_for! {
+    ParamInput,
+    key: String,
+    kind: ParamKind,
+    value: String
+}
+
+impl ParamInput {
+    pub fn int(key: impl Into<String>, value: i64) -> Self {
+        Self::of(key, ParamKind::Int, value)
+    }
+
+    pub fn float(key: impl Into<String>, value: f64) -> Self {
+        Self::of(key, ParamKind::Float, value)
+    }
+
+    pub fn bool(key: impl Into<String>, value: bool) -> Self {
+        Self::of(key, ParamKind::Bool, value)
+    }
+
+    pub fn text(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            kind: ParamKind::Text,
+            value: value.into(),
+        }
+    }
+
+    fn of(key: impl Into<String>, kind: ParamKind, value: impl ToString) -> Self {
+        Self {
+            key: key.into(),
+            kind,
+            value: value.to_string(),
+        }
+    }
+}
+
+#[hybrid_feature_class(python = true, from_ts = true, rename_from_ts = true)]
+#[derive(cynic::QueryVariables, Debug, Clone, Deserialize)]
+pub struct SetExperimentParamsVariables {
+    pub experiment: i32,
+    pub params: Vec<ParamInput>,
+}
+
+crate::new_for! {
+    SetExperimentParamsVariables,
+    experiment: i32,
+    params: Vec<ParamInput>
+}
+
+#[hybrid_feature_class(python = true, into_ts = true, rename_into_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+#[cynic(graphql_type = "Mutation", variables = "SetExperimentParamsVariables")]
+pub struct SetExperimentParams {
+    #[arguments(experiment: $experiment, params: $params)]
+    pub set_experiment_params: Vec<ExperimentParam>,
+}
+
+#[hybrid_feature_class(python = true, ts = true, rename_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+pub struct ExperimentParam {
+    pub key: String,
+    pub kind: ParamKind,
+    pub value: String,
+}
+
+jsvalue! {
+    ExperimentParam,
+    SetExperimentParams
+}
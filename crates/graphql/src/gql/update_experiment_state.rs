@@ -0,0 +1,77 @@
+use crate::{enums::*, jsvalue, schema};
+use flymodel_macros::hybrid_feature_class;
+use serde::{Deserialize, Serialize};
+
+#[hybrid_feature_class(python = true, from_ts = true, rename_from_ts = true)]
+#[derive(cynic::QueryVariables, Debug, Clone, Deserialize)]
+pub struct ExperimentRunVariables {
+    pub id: i32,
+}
+
+crate::new_for! {
+    ExperimentRunVariables,
+    id: i32
+}
+
+#[hybrid_feature_class(python = true, from_ts = true, rename_from_ts = true)]
+#[derive(cynic::QueryVariables, Debug, Clone, Deserialize)]
+pub struct ExperimentFinishVariables {
+    pub id: i32,
+    pub duration_ms: Option<i32>,
+}
+
+crate::new_for! {
+    #[pyo3(signature = (id, duration_ms = None))]
+    ExperimentFinishVariables,
+    id: i32,
+    duration_ms: Option<i32>
+}
+
+#[hybrid_feature_class(python = true, into_ts = true, rename_into_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+#[cynic(graphql_type = "Mutation", variables = "ExperimentRunVariables")]
+pub struct StartExperiment {
+    #[arguments(id: $id)]
+    pub start_experiment: ExperimentState,
+}
+
+#[hybrid_feature_class(python = true, into_ts = true, rename_into_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+#[cynic(graphql_type = "Mutation", variables = "ExperimentFinishVariables")]
+pub struct PassExperiment {
+    #[arguments(id: $id, durationMs: $duration_ms)]
+    pub pass_experiment: ExperimentState,
+}
+
+#[hybrid_feature_class(python = true, into_ts = true, rename_into_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+#[cynic(graphql_type = "Mutation", variables = "ExperimentFinishVariables")]
+pub struct FailExperiment {
+    #[arguments(id: $id, durationMs: $duration_ms)]
+    pub fail_experiment: ExperimentState,
+}
+
+#[hybrid_feature_class(python = true, into_ts = true, rename_into_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+#[cynic(graphql_type = "Mutation", variables = "ExperimentRunVariables")]
+pub struct RetryExperiment {
+    #[arguments(id: $id)]
+    pub retry_experiment: ExperimentState,
+}
+
+#[hybrid_feature_class(python = true, ts = true, rename_ts = true)]
+#[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
+pub struct ExperimentState {
+    pub id: i32,
+    pub experiment_id: i32,
+    pub state: RunState,
+    pub retry: Option<i32>,
+}
+
+jsvalue! {
+    ExperimentState,
+    StartExperiment,
+    PassExperiment,
+    FailExperiment,
+    RetryExperiment
+}
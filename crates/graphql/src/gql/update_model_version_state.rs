@@ -7,12 +7,15 @@ use serde::{Deserialize, Serialize};
 pub struct UpdateModelVersionStateVariables {
     pub id: i32,
     pub state: Lifecycle,
+    pub reason: Option<String>,
 }
 
 crate::new_for! {
+    #[pyo3(signature = (id, state, reason = None))]
     UpdateModelVersionStateVariables,
     id: i32,
-    state: Lifecycle
+    state: Lifecycle,
+    reason: Option<String>
 }
 
 #[derive(cynic::QueryFragment, Debug, Clone, Serialize)]
@@ -22,7 +25,7 @@ crate::new_for! {
 )]
 #[hybrid_feature_class(python = true, ts = true, rename_ts = true)]
 pub struct UpdateModelVersionState {
-    #[arguments(id: $id, state: $state)]
+    #[arguments(id: $id, state: $state, reason: $reason)]
     pub update_model_version_state: ModelState,
 }
 
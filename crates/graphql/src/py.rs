@@ -39,6 +39,9 @@ pub fn submodule<'py>(py: Python<'py>) -> PyResult<&'py PyModule> {
         enums::Lifecycle,
         enums::ArchiveCompression,
         enums::ArchiveFormat,
+        enums::RunState,
+        enums::ParamKind,
+        enums::PackageEcosystem,
     }
 
     submodule_model! {
@@ -144,6 +147,50 @@ pub fn submodule<'py>(py: Python<'py>) -> PyResult<&'py PyModule> {
         gql::update_model_version_state::UpdateModelVersionStateVariables,
     }
 
+    submodule_model! {
+        py,
+        m,
+        update_experiment_state,
+        gql::update_experiment_state::ExperimentState,
+        gql::update_experiment_state::StartExperiment,
+        gql::update_experiment_state::PassExperiment,
+        gql::update_experiment_state::FailExperiment,
+        gql::update_experiment_state::RetryExperiment,
+        gql::update_experiment_state::ExperimentRunVariables,
+        gql::update_experiment_state::ExperimentFinishVariables,
+    }
+
+    submodule_model! {
+        py,
+        m,
+        log_metrics,
+        gql::log_metrics::MetricInput,
+        gql::log_metrics::LogMetrics,
+        gql::log_metrics::LogMetricsVariables,
+    }
+
+    submodule_model! {
+        py,
+        m,
+        set_experiment_params,
+        gql::set_experiment_params::ParamInput,
+        gql::set_experiment_params::ExperimentParam,
+        gql::set_experiment_params::SetExperimentParams,
+        gql::set_experiment_params::SetExperimentParamsVariables,
+    }
+
+    submodule_model! {
+        py,
+        m,
+        record_experiment_environment,
+        gql::record_experiment_environment::PackageInput,
+        gql::record_experiment_environment::EnvVarInput,
+        gql::record_experiment_environment::EnvironmentInput,
+        gql::record_experiment_environment::ExperimentEnvironment,
+        gql::record_experiment_environment::RecordExperimentEnvironment,
+        gql::record_experiment_environment::RecordExperimentEnvironmentVariables,
+    }
+
     submodule_model! {
         py,
         m,
@@ -0,0 +1,4 @@
+set
+    client_encoding = 'UTF8';
+
+drop table api_key cascade;
@@ -0,0 +1,21 @@
+set
+    client_encoding = 'UTF8';
+
+-- static credentials for non-interactive clients, e.g. ci pipelines
+create table api_key (
+    id bigserial primary key not null,
+    name text not null,
+    -- hex encoded sha256 of the key, the key itself is never stored
+    key_hash varchar(64) not null,
+    -- permission strings, e.g. [ "namespace:3:write" ]
+    permissions jsonb not null default '[]' :: jsonb,
+    created_at timestamptz not null default now(),
+    last_used_at timestamptz,
+    revoked_at timestamptz
+);
+
+comment on table api_key is 'a hashed api key & the permissions it grants';
+
+create unique index api_key_name_idx on api_key (name);
+
+create unique index api_key_hash_idx on api_key (key_hash);
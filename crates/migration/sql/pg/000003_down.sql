@@ -0,0 +1,22 @@
+set
+    client_encoding = 'UTF8';
+
+drop index upload_tickets_status_expiry_idx;
+
+drop index upload_tickets_active_key_idx;
+
+alter table upload_tickets
+    drop constraint upload_tickets_target_check,
+    drop column bucket_id,
+    drop column key,
+    drop column model_version_id,
+    drop column experiment_id,
+    drop column artifact_name,
+    drop column encode,
+    drop column format,
+    drop column extra,
+    drop column size,
+    drop column sha256,
+    drop column created_by,
+    drop column expires_at,
+    drop column blob;
@@ -0,0 +1,35 @@
+set
+    client_encoding = 'UTF8';
+
+-- tickets track artifacts uploaded directly to the bucket with a presigned url
+alter table upload_tickets
+    add column bucket_id bigint references bucket(id) on delete cascade on update cascade not null,
+    -- the derived key the object is uploaded to
+    add column key text not null,
+    -- exactly one of the artifact owners is set
+    add column model_version_id bigint references model_version(id) on delete cascade,
+    add column experiment_id bigint references experiment(id) on delete cascade,
+    add column artifact_name text not null,
+    add column encode archive_compression,
+    add column format archive_format,
+    add column extra jsonb,
+    -- the declared size & hex encoded sha256, checked on completion
+    add column size bigint not null,
+    add column sha256 varchar(64) not null,
+    -- the subject of the principal that requested the ticket
+    add column created_by text not null,
+    add column expires_at timestamptz not null,
+    -- set once the upload has been verified & registered
+    add column blob bigint references object_blob(id) on delete set null,
+    add constraint upload_tickets_target_check check (
+        (model_version_id is null) <> (experiment_id is null)
+    );
+
+comment on table upload_tickets is 'a presigned upload & its lifecycle';
+
+-- only one in flight upload per key
+create unique index upload_tickets_active_key_idx on upload_tickets (bucket_id, key)
+where
+    status in ('pending', 'uploading');
+
+create index upload_tickets_status_expiry_idx on upload_tickets (status, expires_at);
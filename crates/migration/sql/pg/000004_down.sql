@@ -0,0 +1,8 @@
+set
+    client_encoding = 'UTF8';
+
+drop table upload_ticket_part cascade;
+
+alter table upload_tickets
+    drop constraint upload_tickets_chunk_size_check,
+    drop column chunk_size;
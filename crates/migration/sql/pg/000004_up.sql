@@ -0,0 +1,27 @@
+set
+    client_encoding = 'UTF8';
+
+-- resumable tickets receive the artifact in numbered chunks of this size (the last may be shorter)
+-- presigned tickets leave it unset
+alter table upload_tickets
+    add column chunk_size bigint;
+
+alter table upload_tickets
+    add constraint upload_tickets_chunk_size_check check (
+        chunk_size is null
+        or chunk_size > 0
+    );
+
+create table upload_ticket_part (
+    ticket_id bigint references upload_tickets(id) on delete cascade not null,
+    -- 1 based, as with s3 multipart uploads
+    part_number integer not null,
+    -- the staged object version holding this chunk
+    version_id varchar not null,
+    size bigint not null,
+    sha256 varchar(64) not null,
+    created_at timestamptz not null default now(),
+    primary key (ticket_id, part_number)
+);
+
+comment on table upload_ticket_part is 'a chunk received for a resumable upload';
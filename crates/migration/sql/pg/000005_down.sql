@@ -0,0 +1,4 @@
+set
+    client_encoding = 'UTF8';
+
+drop table promotion_policy cascade;
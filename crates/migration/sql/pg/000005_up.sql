@@ -0,0 +1,24 @@
+set
+    client_encoding = 'UTF8';
+
+-- rules a namespace places on model versions entering a lifecycle
+-- lifecycles without a policy only enforce the default promotion order
+create table promotion_policy (
+    id bigserial primary key not null,
+    namespace_id bigint references namespace(id) on delete cascade on update cascade not null,
+    -- the lifecycle the rules guard
+    state lifecycle not null,
+    require_passed_experiment boolean not null default false,
+    min_artifacts integer not null default 0,
+    -- tag names, e.g. [ "reviewed" ]
+    required_tags jsonb not null default '[]' :: jsonb,
+    -- whether versions in this lifecycle may move back to an earlier one
+    block_demotion boolean not null default false,
+    created_at timestamptz not null default now(),
+    last_modified timestamptz not null default now(),
+    constraint promotion_policy_min_artifacts_check check (min_artifacts >= 0)
+);
+
+comment on table promotion_policy is 'the promotion rules of a lifecycle in a namespace';
+
+create unique index promotion_policy_namespace_state_idx on promotion_policy (namespace_id, state);
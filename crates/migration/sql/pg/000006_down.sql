@@ -0,0 +1,6 @@
+set
+    client_encoding = 'UTF8';
+
+drop table model_state_history cascade;
+
+drop function model_state_history_append_only;
@@ -0,0 +1,34 @@
+set
+    client_encoding = 'UTF8';
+
+-- every lifecycle transition of a model version, written alongside the model_state update
+create table model_state_history (
+    id bigserial primary key not null,
+    -- unset once the version is deleted, its transitions are kept
+    version_id bigint references model_version(id) on delete set null on update cascade,
+    previous lifecycle not null,
+    state lifecycle not null,
+    -- the subject of the principal which made the transition
+    actor text not null,
+    reason text,
+    created_at timestamptz not null default now()
+);
+
+comment on table model_state_history is 'an append only audit trail of model version lifecycles';
+
+create index model_state_history_version_idx on model_state_history (version_id, created_at);
+
+-- detaching a row from its deleted version is the only change allowed
+create function model_state_history_append_only() returns trigger as $$ begin
+    if new.version_id is null
+        and (new.id, new.previous, new.state, new.actor, new.reason, new.created_at)
+        is not distinct from (old.id, old.previous, old.state, old.actor, old.reason, old.created_at) then
+        return new;
+    end if;
+    raise exception 'model_state_history rows may not be modified';
+end;
+$$ language plpgsql;
+
+create trigger model_state_history_no_update before
+update
+    on model_state_history for each row execute function model_state_history_append_only();
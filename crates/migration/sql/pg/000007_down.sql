@@ -0,0 +1,12 @@
+set
+    client_encoding = 'UTF8';
+
+drop table promotion_approval cascade;
+
+drop table promotion_request cascade;
+
+drop type promotion_request_status cascade;
+
+alter table promotion_policy
+    drop constraint promotion_policy_required_approvals_check,
+    drop column required_approvals;
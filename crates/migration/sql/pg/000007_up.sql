@@ -0,0 +1,57 @@
+set
+    client_encoding = 'UTF8';
+
+-- approvals a promotion request into the lifecycle needs, prod keeps requiring one
+alter table promotion_policy
+    add column required_approvals integer not null default 0;
+
+alter table promotion_policy
+    add constraint promotion_policy_required_approvals_check check (required_approvals >= 0);
+
+update
+    promotion_policy
+set
+    required_approvals = 1
+where
+    state = 'prod';
+
+create type promotion_request_status as enum (
+    'open',
+    'approved',
+    'rejected',
+    'cancelled'
+);
+
+create table promotion_request (
+    id bigserial primary key not null,
+    version_id bigint references model_version(id) on delete cascade on update cascade not null,
+    -- the lifecycle the version is promoted to once the request is approved
+    state lifecycle not null,
+    -- the subject of the principal which opened the request
+    requested_by text not null,
+    reason text,
+    status promotion_request_status not null default 'open',
+    created_at timestamptz not null default now(),
+    last_modified timestamptz not null default now()
+);
+
+comment on table promotion_request is 'a lifecycle transition awaiting the approval of a namespace';
+
+-- a version has at most one open request at a time
+create unique index promotion_request_open_idx on promotion_request (version_id)
+where
+    status = 'open';
+
+create table promotion_approval (
+    id bigserial primary key not null,
+    request_id bigint references promotion_request(id) on delete cascade on update cascade not null,
+    -- the subject of the principal which approved or rejected the request
+    approver text not null,
+    approved boolean not null,
+    comment text,
+    created_at timestamptz not null default now()
+);
+
+comment on table promotion_approval is 'the decision of a single approver on a promotion request';
+
+create unique index promotion_approval_request_approver_idx on promotion_approval (request_id, approver);
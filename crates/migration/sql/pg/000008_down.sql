@@ -0,0 +1,24 @@
+set
+    client_encoding = 'UTF8';
+
+delete from experiment
+where
+    deleted_at is not null;
+
+delete from model_version
+where
+    deleted_at is not null;
+
+drop index experiment_name_idx;
+
+create unique index experiment_name_idx on experiment (version_id, name);
+
+drop index model_version_model_version_idx;
+
+create unique index model_version_model_version_idx on model_version (model_id, version);
+
+alter table experiment
+    drop column deleted_at;
+
+alter table model_version
+    drop column deleted_at;
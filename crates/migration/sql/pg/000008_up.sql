@@ -0,0 +1,26 @@
+set
+    client_encoding = 'UTF8';
+
+-- soft deleted rows are hidden from queries until restored, or purged along with their blobs
+alter table model_version
+    add column deleted_at timestamptz;
+
+alter table experiment
+    add column deleted_at timestamptz;
+
+comment on column model_version.deleted_at is 'set while the version is soft deleted';
+
+comment on column experiment.deleted_at is 'set while the experiment is soft deleted, versions soft delete their experiments alongside them';
+
+-- names only need to be unique among live rows
+drop index model_version_model_version_idx;
+
+create unique index model_version_model_version_idx on model_version (model_id, version)
+where
+    deleted_at is null;
+
+drop index experiment_name_idx;
+
+create unique index experiment_name_idx on experiment (version_id, name)
+where
+    deleted_at is null;
@@ -0,0 +1,24 @@
+set
+    client_encoding = 'UTF8';
+
+drop index namespace_tag_namespace_tag_idx;
+
+alter table experiment_tag
+    alter column id drop default;
+
+drop sequence experiment_tag_id_seq;
+
+alter table model_version_tag
+    alter column id drop default;
+
+drop sequence model_version_tag_id_seq;
+
+alter table model_tag
+    alter column id drop default;
+
+drop sequence model_tag_id_seq;
+
+alter table namespace_tag
+    alter column id drop default;
+
+drop sequence namespace_tag_id_seq;
@@ -0,0 +1,136 @@
+set
+    client_encoding = 'UTF8';
+
+-- the tag tables were created without a sequence behind their ids
+create sequence namespace_tag_id_seq owned by namespace_tag.id;
+
+select
+    setval('namespace_tag_id_seq', coalesce(max(id), 0) + 1, false)
+from
+    namespace_tag;
+
+alter table namespace_tag
+    alter column id set default nextval('namespace_tag_id_seq');
+
+create sequence model_tag_id_seq owned by model_tag.id;
+
+select
+    setval('model_tag_id_seq', coalesce(max(id), 0) + 1, false)
+from
+    model_tag;
+
+alter table model_tag
+    alter column id set default nextval('model_tag_id_seq');
+
+create sequence model_version_tag_id_seq owned by model_version_tag.id;
+
+select
+    setval('model_version_tag_id_seq', coalesce(max(id), 0) + 1, false)
+from
+    model_version_tag;
+
+alter table model_version_tag
+    alter column id set default nextval('model_version_tag_id_seq');
+
+create sequence experiment_tag_id_seq owned by experiment_tag.id;
+
+select
+    setval('experiment_tag_id_seq', coalesce(max(id), 0) + 1, false)
+from
+    experiment_tag;
+
+alter table experiment_tag
+    alter column id set default nextval('experiment_tag_id_seq');
+
+-- tags created twice within a namespace are merged into the one with the lowest id
+create temporary table namespace_tag_merged as
+select
+    id,
+    merged_into
+from
+    (
+        select
+            id,
+            min(id) over (partition by namespace_id, tag) as merged_into
+        from
+            namespace_tag
+    ) tags
+where
+    id <> merged_into;
+
+-- whatever was linked to several copies of a tag keeps a single link, to the merged tag
+delete from
+    model_tag l using model_tag o
+where
+    o.model_id = l.model_id
+    and o.id < l.id
+    and coalesce(
+        (select merged_into from namespace_tag_merged where id = o.tag),
+        o.tag
+    ) = coalesce(
+        (select merged_into from namespace_tag_merged where id = l.tag),
+        l.tag
+    );
+
+update
+    model_tag l
+set
+    tag = m.merged_into
+from
+    namespace_tag_merged m
+where
+    l.tag = m.id;
+
+delete from
+    model_version_tag l using model_version_tag o
+where
+    o.version_id = l.version_id
+    and o.id < l.id
+    and coalesce(
+        (select merged_into from namespace_tag_merged where id = o.tag),
+        o.tag
+    ) = coalesce(
+        (select merged_into from namespace_tag_merged where id = l.tag),
+        l.tag
+    );
+
+update
+    model_version_tag l
+set
+    tag = m.merged_into
+from
+    namespace_tag_merged m
+where
+    l.tag = m.id;
+
+delete from
+    experiment_tag l using experiment_tag o
+where
+    o.experiment_id = l.experiment_id
+    and o.id < l.id
+    and coalesce(
+        (select merged_into from namespace_tag_merged where id = o.tag),
+        o.tag
+    ) = coalesce(
+        (select merged_into from namespace_tag_merged where id = l.tag),
+        l.tag
+    );
+
+update
+    experiment_tag l
+set
+    tag = m.merged_into
+from
+    namespace_tag_merged m
+where
+    l.tag = m.id;
+
+delete from
+    namespace_tag t using namespace_tag_merged m
+where
+    t.id = m.id;
+
+drop table namespace_tag_merged;
+
+-- tags are searched by name, so a name means one tag within a namespace
+create unique index namespace_tag_namespace_tag_idx on namespace_tag (namespace_id, tag);
@@ -0,0 +1,10 @@
+set
+    client_encoding = 'UTF8';
+
+alter table experiment_result
+    drop constraint experiment_result_duration_check;
+
+alter table experiment_state
+    drop constraint experiment_state_retry_check;
+
+comment on column experiment_state.retry is null;
@@ -0,0 +1,28 @@
+set
+    client_encoding = 'UTF8';
+
+-- experiments are created in the created state from now on, older ones had none
+insert into
+    experiment_state (experiment_id, state)
+select
+    experiment.id,
+    'created'
+from
+    experiment
+where
+    not exists (
+        select
+            1
+        from
+            experiment_state
+        where
+            experiment_state.experiment_id = experiment.id
+    );
+
+comment on column experiment_state.retry is 'the number of times a failed run was retried, null until the first retry';
+
+alter table experiment_state
+    add constraint experiment_state_retry_check check (retry >= 0);
+
+alter table experiment_result
+    add constraint experiment_result_duration_check check (duration_ms >= 0);
@@ -0,0 +1,20 @@
+set
+    client_encoding = 'UTF8';
+
+create table experiment_metric (
+    id bigserial primary key not null,
+    experiment_id bigint references experiment(id) on delete cascade on update cascade not null,
+    key text not null,
+    step bigint not null,
+    value double precision not null,
+    -- when the client measured the value, rather than when it was flushed
+    timestamp timestamptz not null default now()
+);
+
+comment on table experiment_metric is 'a scalar logged against an experiment at a step, each key forming a series';
+
+alter table experiment_metric
+    add constraint experiment_metric_step_check check (step >= 0);
+
+-- a step holds one value per key, logging it again replaces the value
+create unique index experiment_metric_series_idx on experiment_metric (experiment_id, key, step);
@@ -0,0 +1,8 @@
+set
+    client_encoding = 'UTF8';
+
+drop table experiment_environment;
+
+drop table experiment_param;
+
+drop type param_kind;
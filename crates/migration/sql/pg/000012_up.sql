@@ -0,0 +1,36 @@
+set
+    client_encoding = 'UTF8';
+
+create type param_kind as enum ('int', 'float', 'bool', 'text');
+
+create table experiment_param (
+    id bigserial primary key not null,
+    experiment_id bigint references experiment(id) on delete cascade on update cascade not null,
+    key text not null,
+    kind param_kind not null,
+    -- the canonical text of the value, parsed according to its kind
+    value text not null
+);
+
+comment on table experiment_param is 'a hyperparameter an experiment was run with';
+
+create unique index experiment_param_key_idx on experiment_param (experiment_id, key);
+
+create table experiment_environment (
+    id bigserial primary key not null,
+    experiment_id bigint references experiment(id) on delete cascade on update cascade not null unique,
+    git_commit text,
+    git_dirty boolean,
+    hostname text,
+    -- [{"ecosystem": "PYTHON", "name": "torch", "version": "2.1.0"}, ..]
+    packages jsonb not null default '[]',
+    -- {"CUDA_VISIBLE_DEVICES": "0", ..}, limited by the allowlist of the client
+    variables jsonb not null default '{}',
+    captured_at timestamptz not null default now()
+);
+
+comment on table experiment_environment is 'the host, source & packages an experiment last ran with';
+
+alter table experiment_environment
+    add constraint experiment_environment_packages_check check (jsonb_typeof(packages) = 'array'),
+    add constraint experiment_environment_variables_check check (jsonb_typeof(variables) = 'object');
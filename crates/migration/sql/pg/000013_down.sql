@@ -0,0 +1,10 @@
+set
+    client_encoding = 'UTF8';
+
+drop table webhook_delivery;
+
+drop table webhook;
+
+drop type webhook_delivery_status;
+
+drop type webhook_event;
@@ -0,0 +1,54 @@
+set
+    client_encoding = 'UTF8';
+
+create type webhook_event as enum (
+    'model_version_state_changed',
+    'artifact_uploaded',
+    'experiment_finished'
+);
+
+create type webhook_delivery_status as enum ('pending', 'delivered', 'dead');
+
+create table webhook (
+    id bigserial primary key not null,
+    namespace_id bigint references namespace(id) on delete cascade on update cascade not null,
+    url text not null,
+    -- ["ARTIFACT_UPLOADED", ..], the events the webhook is delivered
+    events jsonb not null default '[]',
+    -- the key deliveries are signed with
+    secret text not null,
+    active boolean not null default true,
+    created_at timestamptz not null default now(),
+    last_modified timestamptz not null default now()
+);
+
+comment on table webhook is 'an endpoint notified of the events of a namespace';
+
+alter table webhook
+    add constraint webhook_events_check check (jsonb_typeof(events) = 'array');
+
+create index webhook_namespace_idx on webhook (namespace_id);
+
+create table webhook_delivery (
+    id bigserial primary key not null,
+    webhook_id bigint references webhook(id) on delete cascade on update cascade not null,
+    event webhook_event not null,
+    payload jsonb not null,
+    status webhook_delivery_status not null default 'pending',
+    attempts integer not null default 0 check (attempts >= 0),
+    next_attempt_at timestamptz not null default now(),
+    -- the response to the last attempt, or why it got none
+    response_status integer,
+    error text,
+    created_at timestamptz not null default now(),
+    last_attempt_at timestamptz,
+    delivered_at timestamptz
+);
+
+comment on table webhook_delivery is 'the outbox of webhook deliveries, queued with the change they report';
+
+create index webhook_delivery_due_idx on webhook_delivery (next_attempt_at)
+where
+    status = 'pending';
+
+create index webhook_delivery_webhook_idx on webhook_delivery (webhook_id, id);
@@ -0,0 +1,24 @@
+set
+    client_encoding = 'UTF8';
+
+create table audit_log (
+    id bigserial primary key not null,
+    -- the subject of the principal which made the change
+    actor text not null,
+    -- `create`, `transition`, `upload`, .. as named by flymodel-events
+    action text not null,
+    -- `model_version`, `bucket`, .. as named by flymodel-events
+    resource text not null,
+    resource_id bigint not null,
+    -- short summaries of the resource before & after the change, when relevant
+    before text,
+    after text,
+    occurred_at timestamptz not null,
+    recorded_at timestamptz not null default now()
+);
+
+comment on table audit_log is 'the changes made to the registry, and by whom';
+
+create index audit_log_resource_idx on audit_log (resource, resource_id, id);
+
+create index audit_log_actor_idx on audit_log (actor, id);
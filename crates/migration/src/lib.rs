@@ -6,6 +6,19 @@ use sea_orm_migration::prelude::*;
 pub mod cli;
 pub mod hooks;
 mod m000001_create_table;
+mod m000002_api_keys;
+mod m000003_upload_tickets;
+mod m000004_resumable_uploads;
+mod m000005_promotion_policies;
+mod m000006_model_state_history;
+mod m000007_promotion_requests;
+mod m000008_soft_delete;
+mod m000009_tag_sequences;
+mod m000010_experiment_runs;
+mod m000011_experiment_metrics;
+mod m000012_experiment_params;
+mod m000013_webhooks;
+mod m000014_audit_log;
 
 static ONCE: std::sync::Once = std::sync::Once::new();
 pub(crate) static FIXTURES: Mutex<Option<Fixtures>> = Mutex::new(None);
@@ -25,6 +38,21 @@ impl Migrator {
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m000001_create_table::Migration)]
+        vec![
+            Box::new(m000001_create_table::Migration),
+            Box::new(m000002_api_keys::Migration),
+            Box::new(m000003_upload_tickets::Migration),
+            Box::new(m000004_resumable_uploads::Migration),
+            Box::new(m000005_promotion_policies::Migration),
+            Box::new(m000006_model_state_history::Migration),
+            Box::new(m000007_promotion_requests::Migration),
+            Box::new(m000008_soft_delete::Migration),
+            Box::new(m000009_tag_sequences::Migration),
+            Box::new(m000010_experiment_runs::Migration),
+            Box::new(m000011_experiment_metrics::Migration),
+            Box::new(m000012_experiment_params::Migration),
+            Box::new(m000013_webhooks::Migration),
+            Box::new(m000014_audit_log::Migration),
+        ]
     }
 }
@@ -0,0 +1,417 @@
+use std::{
+    ffi::OsString,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use flymodel::{
+    errs::{FlymodelError, FlymodelResult},
+    lifecycle::Lifecycle,
+    storage::{
+        self, ByteRange, ObjectMeta, ObjectVersion, StagedUpload, StorageProvider, StreamDigest,
+        UploadState,
+    },
+};
+use futures_util::StreamExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+use tracing::{debug, trace};
+
+fn default_prefix() -> String {
+    "/".to_string()
+}
+
+const VERSIONS_SUFFIX: &str = ".versions";
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
+#[derive(serde::Deserialize, Debug)]
+pub struct FilesystemConfiguration {
+    pub root: PathBuf,
+    #[serde(default = "default_prefix")]
+    prefix: String,
+    pub bucket: String,
+    role: Lifecycle,
+}
+
+/// every put writes a new file to `<key>.versions/` named by a time ordered version id, emulating a versioned bucket
+pub struct FilesystemStorage {
+    root: PathBuf,
+    prefix: String,
+    pub role: Lifecycle,
+}
+
+fn io_error(err: std::io::Error) -> FlymodelError {
+    FlymodelError::StorageIoError(err)
+}
+
+fn new_version_id() -> FlymodelResult<String> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(FlymodelError::internal_error)?
+        .as_nanos();
+    let mut salt = [0u8; 4];
+    getrandom::getrandom(&mut salt).map_err(FlymodelError::internal_error)?;
+    Ok(format!("{nanos:032x}{}", hex::encode(salt)))
+}
+
+impl FilesystemStorage {
+    pub fn new(conf: FilesystemConfiguration) -> anyhow::Result<Self> {
+        Ok(Self {
+            root: conf.root,
+            prefix: conf.prefix,
+            role: conf.role,
+        })
+    }
+
+    fn versions_dir(&self, path: String) -> FlymodelResult<PathBuf> {
+        let key = self.resolve_path(path);
+        let mut dir = self.root.clone();
+        for part in key.split('/').filter(|part| !part.is_empty()) {
+            if part == "." || part == ".." {
+                return Err(FlymodelError::ContraintError(format!(
+                    "invalid object key: {key}"
+                )));
+            }
+            dir.push(part);
+        }
+        let name = match dir.file_name() {
+            Some(name) if dir != self.root => name,
+            _ => {
+                return Err(FlymodelError::ContraintError(format!(
+                    "invalid object key: {key}"
+                )))
+            }
+        };
+        let mut name = OsString::from(name);
+        name.push(VERSIONS_SUFFIX);
+        dir.set_file_name(name);
+        Ok(dir)
+    }
+
+    fn prefix_dir(&self) -> PathBuf {
+        let mut dir = self.root.clone();
+        for part in self.prefix.split('/').filter(|part| !part.is_empty()) {
+            dir.push(part);
+        }
+        dir
+    }
+
+    async fn latest_version(&self, dir: &PathBuf) -> FlymodelResult<String> {
+        let mut entries = tokio::fs::read_dir(dir).await.map_err(io_error)?;
+        let mut latest: Option<String> = None;
+        while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            let newer = match &latest {
+                Some(latest) => &name > latest,
+                None => true,
+            };
+            if newer {
+                latest = Some(name);
+            }
+        }
+        latest.ok_or_else(|| {
+            io_error(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no versions in {}", dir.display()),
+            ))
+        })
+    }
+
+    async fn version_file(
+        &self,
+        path: String,
+        version_id: Option<String>,
+    ) -> FlymodelResult<PathBuf> {
+        let dir = self.versions_dir(path)?;
+        let version_id = match version_id {
+            Some(version_id) => version_id,
+            None => self.latest_version(&dir).await?,
+        };
+        if version_id.is_empty() || version_id.starts_with('.') || version_id.contains('/') {
+            return Err(FlymodelError::ContraintError(format!(
+                "invalid version id: {version_id}"
+            )));
+        }
+        Ok(dir.join(version_id))
+    }
+
+    /// writes `stream` to a new version, only making it visible once fully written
+    async fn write_version(
+        &self,
+        path: String,
+        mut stream: storage::ByteStream,
+    ) -> FlymodelResult<(String, StreamDigest)> {
+        let dir = self.versions_dir(path)?;
+        tokio::fs::create_dir_all(&dir).await.map_err(io_error)?;
+
+        let version_id = new_version_id()?;
+        let partial = dir.join(format!(".{version_id}.partial"));
+        trace!("writing object version: {}", partial.display());
+
+        let mut digest = StreamDigest::default();
+        let written = async {
+            let mut file = tokio::fs::File::create(&partial).await.map_err(io_error)?;
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                digest.update(&chunk);
+                file.write_all(&chunk).await.map_err(io_error)?;
+            }
+            file.sync_all().await.map_err(io_error)?;
+            tokio::fs::rename(&partial, dir.join(&version_id))
+                .await
+                .map_err(io_error)
+        }
+        .await;
+
+        if let Err(err) = written {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(err);
+        }
+        Ok((version_id, digest))
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageProvider for FilesystemStorage {
+    fn role(&self) -> Lifecycle {
+        self.role
+    }
+
+    fn prefix(&self) -> String {
+        self.prefix.clone()
+    }
+
+    async fn setup(&self) -> FlymodelResult<()> {
+        debug!("creating storage root: {}", self.root.display());
+        tokio::fs::create_dir_all(&self.root)
+            .await
+            .map_err(|err| FlymodelError::StorageSetupError(err.into()))
+    }
+
+    async fn put(&self, path: String, bs: Bytes) -> FlymodelResult<Option<String>> {
+        let stream = futures_util::stream::once(async move { Ok(bs) });
+        let (version_id, _) = self.write_version(path, Box::pin(stream)).await?;
+        Ok(Some(version_id))
+    }
+
+    async fn put_stream(
+        &self,
+        path: String,
+        stream: storage::ByteStream,
+    ) -> FlymodelResult<StagedUpload> {
+        let (version_id, digest) = self.write_version(path.clone(), stream).await?;
+        let (size, sha256) = digest.finish();
+        Ok(StagedUpload {
+            path,
+            size,
+            sha256,
+            state: UploadState::Complete {
+                version_id: Some(version_id),
+            },
+        })
+    }
+
+    /// without a version, every version of the object is removed
+    async fn del(&self, path: String, version_id: Option<String>) -> FlymodelResult<()> {
+        match version_id {
+            Some(version_id) => {
+                let file = self.version_file(path, Some(version_id)).await?;
+                trace!("deleting object version: {}", file.display());
+                tokio::fs::remove_file(&file).await.map_err(io_error)?;
+                if let Some(dir) = file.parent() {
+                    // only succeeds once the last version is gone
+                    let _ = tokio::fs::remove_dir(dir).await;
+                }
+            }
+            None => {
+                let dir = self.versions_dir(path)?;
+                trace!("deleting object: {}", dir.display());
+                tokio::fs::remove_dir_all(&dir).await.map_err(io_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn list(&self) -> FlymodelResult<Vec<ObjectVersion>> {
+        let base = self.prefix_dir();
+        let mut listed = vec![];
+        let mut dirs = vec![base.clone()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(io_error(err)),
+            };
+            while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+                if !entry.file_type().await.map_err(io_error)?.is_dir() {
+                    continue;
+                }
+                let path = entry.path();
+                let name = entry.file_name().to_string_lossy().to_string();
+                let Some(object) = name.strip_suffix(VERSIONS_SUFFIX) else {
+                    dirs.push(path);
+                    continue;
+                };
+                let mut parts: Vec<String> = path
+                    .parent()
+                    .and_then(|parent| parent.strip_prefix(&base).ok())
+                    .map(|parent| {
+                        parent
+                            .iter()
+                            .map(|part| part.to_string_lossy().to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                parts.push(object.to_string());
+                let object = parts.join("/");
+
+                let mut versions = tokio::fs::read_dir(&path).await.map_err(io_error)?;
+                while let Some(version) = versions.next_entry().await.map_err(io_error)? {
+                    let version_id = version.file_name().to_string_lossy().to_string();
+                    // partially written versions are not visible
+                    if version_id.starts_with('.') {
+                        continue;
+                    }
+                    let meta = version.metadata().await.map_err(io_error)?;
+                    listed.push(ObjectVersion {
+                        path: object.clone(),
+                        version_id: Some(version_id),
+                        size: meta.len(),
+                        last_modified: meta.modified().map_err(io_error)?,
+                    });
+                }
+            }
+        }
+        Ok(listed)
+    }
+
+    async fn get(&self, path: String, version_id: Option<String>) -> FlymodelResult<Bytes> {
+        let file = self.version_file(path, version_id).await?;
+        trace!("reading object: {}", file.display());
+        Ok(Bytes::from(tokio::fs::read(file).await.map_err(io_error)?))
+    }
+
+    async fn head(&self, path: String, version_id: Option<String>) -> FlymodelResult<ObjectMeta> {
+        let file = self.version_file(path, version_id).await?;
+        let meta = tokio::fs::metadata(&file).await.map_err(io_error)?;
+        Ok(ObjectMeta {
+            size: meta.len(),
+            version_id: file
+                .file_name()
+                .map(|version_id| version_id.to_string_lossy().to_string()),
+        })
+    }
+
+    async fn get_stream(
+        &self,
+        path: String,
+        version_id: Option<String>,
+        range: Option<ByteRange>,
+    ) -> FlymodelResult<storage::ByteStream> {
+        let file = self.version_file(path, version_id).await?;
+        trace!("streaming object: {} ({:?})", file.display(), range);
+        let mut file = tokio::fs::File::open(file).await.map_err(io_error)?;
+        let reader = match range {
+            Some(range) => {
+                file.seek(std::io::SeekFrom::Start(range.start))
+                    .await
+                    .map_err(io_error)?;
+                file.take(range.end - range.start + 1)
+            }
+            None => file.take(u64::MAX),
+        };
+        Ok(Box::pin(
+            ReaderStream::with_capacity(reader, READ_CHUNK_SIZE)
+                .map(|chunk| chunk.map_err(io_error)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use flymodel::{
+        lifecycle::Lifecycle,
+        storage::{ByteRange, StorageProvider},
+    };
+    use futures_util::StreamExt;
+
+    use super::{FilesystemConfiguration, FilesystemStorage};
+
+    fn new_fs_test_client(name: &str) -> FilesystemStorage {
+        let root = std::env::temp_dir().join(format!("flymodel-fs-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        FilesystemStorage::new(FilesystemConfiguration {
+            root,
+            prefix: "/".into(),
+            bucket: "ml-dev".into(),
+            role: Lifecycle::Test,
+        })
+        .expect("storage")
+    }
+
+    #[tokio::test]
+    async fn test_fs_versions() -> anyhow::Result<()> {
+        let client = new_fs_test_client("versions");
+        client.setup().await?;
+
+        let first = client
+            .put("a/test.txt".into(), Bytes::from_static(b"abc"))
+            .await?;
+        let second = client
+            .put("a/test.txt".into(), Bytes::from_static(b"def"))
+            .await?;
+        assert_ne!(first, second);
+
+        assert_eq!(client.get("a/test.txt".into(), None).await?, "def");
+        assert_eq!(client.get("a/test.txt".into(), first.clone()).await?, "abc");
+
+        let range: Vec<_> = client
+            .get_stream(
+                "a/test.txt".into(),
+                first.clone(),
+                Some(ByteRange { start: 1, end: 2 }),
+            )
+            .await?
+            .collect()
+            .await;
+        assert_eq!(range.into_iter().next().unwrap()?, "bc");
+
+        let mut listed: Vec<_> = client
+            .list()
+            .await?
+            .into_iter()
+            .map(|version| (version.path, version.version_id))
+            .collect();
+        listed.sort();
+        assert_eq!(
+            listed,
+            vec![
+                ("a/test.txt".to_string(), first.clone()),
+                ("a/test.txt".to_string(), second.clone())
+            ]
+        );
+
+        client.del("a/test.txt".into(), second).await?;
+        assert_eq!(client.get("a/test.txt".into(), None).await?, "abc");
+        client.del("a/test.txt".into(), first).await?;
+        assert!(client.get("a/test.txt".into(), None).await.is_err());
+
+        client.del("a".into(), None).await.ok();
+        std::fs::remove_dir_all(&client.root)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fs_rejects_traversal() {
+        let client = new_fs_test_client("traversal");
+        assert!(client
+            .put("../escape.txt".into(), Bytes::from_static(b"abc"))
+            .await
+            .is_err());
+    }
+}
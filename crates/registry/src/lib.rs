@@ -1,3 +1,5 @@
+pub mod fs;
+pub mod memory;
 pub mod minio;
 pub mod storage;
 pub mod utils;
@@ -0,0 +1,300 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::SystemTime,
+};
+
+use bytes::Bytes;
+use flymodel::{
+    errs::{FlymodelError, FlymodelResult},
+    lifecycle::Lifecycle,
+    storage::{ObjectMeta, ObjectVersion, StorageProvider},
+};
+
+type Versions = BTreeMap<String, (SystemTime, Bytes)>;
+
+/// version ids are unique across every bucket, as they are in s3
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
+fn default_prefix() -> String {
+    "/".to_string()
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct MemoryConfiguration {
+    #[serde(default = "default_prefix")]
+    prefix: String,
+    pub bucket: String,
+    role: Lifecycle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageOperation {
+    Put,
+    Get,
+    Del,
+}
+
+/// schedules failures of upcoming storage operations
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    pending: Arc<Mutex<HashMap<StorageOperation, usize>>>,
+}
+
+impl FaultInjector {
+    pub fn fail_next(&self, op: StorageOperation) {
+        self.fail_times(op, 1)
+    }
+
+    pub fn fail_times(&self, op: StorageOperation, times: usize) {
+        *self.pending.lock().unwrap().entry(op).or_default() += times;
+    }
+
+    pub fn clear(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+
+    fn check(&self, op: StorageOperation) -> FlymodelResult<()> {
+        let mut pending = self.pending.lock().unwrap();
+        match pending.get_mut(&op) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                Err(FlymodelError::StorageIoError(std::io::Error::other(
+                    format!("injected {op:?} failure"),
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// a versioned bucket held in memory, whose failures can be injected through [MemoryStorage::faults]
+#[derive(Clone)]
+pub struct MemoryStorage {
+    prefix: String,
+    pub role: Lifecycle,
+    objects: Arc<Mutex<HashMap<String, Versions>>>,
+    faults: FaultInjector,
+}
+
+impl MemoryStorage {
+    pub fn new(conf: MemoryConfiguration) -> Self {
+        Self {
+            prefix: conf.prefix,
+            role: conf.role,
+            objects: Arc::default(),
+            faults: FaultInjector::default(),
+        }
+    }
+
+    pub fn faults(&self) -> FaultInjector {
+        self.faults.clone()
+    }
+
+    pub fn versions(&self, path: String) -> Vec<String> {
+        let key = self.resolve_path(path);
+        self.objects
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map(|versions| versions.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn find(&self, path: String, version_id: Option<String>) -> FlymodelResult<(String, Bytes)> {
+        let key = self.resolve_path(path);
+        let objects = self.objects.lock().unwrap();
+        let versions = objects.get(&key).ok_or_else(|| Self::not_found(&key))?;
+        match version_id {
+            Some(version_id) => versions.get_key_value(&version_id),
+            None => versions.iter().next_back(),
+        }
+        .map(|(version_id, (_, bs))| (version_id.clone(), bs.clone()))
+        .ok_or_else(|| Self::not_found(&key))
+    }
+
+    fn not_found(key: &str) -> FlymodelError {
+        FlymodelError::StorageIoError(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no such object: {key}"),
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageProvider for MemoryStorage {
+    fn role(&self) -> Lifecycle {
+        self.role
+    }
+
+    fn prefix(&self) -> String {
+        self.prefix.clone()
+    }
+
+    async fn setup(&self) -> FlymodelResult<()> {
+        Ok(())
+    }
+
+    async fn put(&self, path: String, bs: Bytes) -> FlymodelResult<Option<String>> {
+        self.faults.check(StorageOperation::Put)?;
+        let version_id = format!("{:016x}", NEXT_VERSION.fetch_add(1, Ordering::SeqCst));
+        self.objects
+            .lock()
+            .unwrap()
+            .entry(self.resolve_path(path))
+            .or_default()
+            .insert(version_id.clone(), (SystemTime::now(), bs));
+        Ok(Some(version_id))
+    }
+
+    async fn del(&self, path: String, version_id: Option<String>) -> FlymodelResult<()> {
+        self.faults.check(StorageOperation::Del)?;
+        let key = self.resolve_path(path);
+        let mut objects = self.objects.lock().unwrap();
+        let versions = objects.get_mut(&key).ok_or_else(|| Self::not_found(&key))?;
+        match version_id {
+            Some(version_id) => {
+                versions
+                    .remove(&version_id)
+                    .ok_or_else(|| Self::not_found(&key))?;
+                if versions.is_empty() {
+                    objects.remove(&key);
+                }
+            }
+            None => {
+                objects.remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    async fn list(&self) -> FlymodelResult<Vec<ObjectVersion>> {
+        self.faults.check(StorageOperation::Get)?;
+        let prefix = self.resolve_path(String::new());
+        let objects = self.objects.lock().unwrap();
+        Ok(objects
+            .iter()
+            .filter_map(|(key, versions)| Some((key.strip_prefix(&prefix)?, versions)))
+            .flat_map(|(path, versions)| {
+                versions
+                    .iter()
+                    .map(move |(version_id, (last_modified, bs))| ObjectVersion {
+                        path: path.to_string(),
+                        version_id: Some(version_id.clone()),
+                        size: bs.len() as u64,
+                        last_modified: *last_modified,
+                    })
+            })
+            .collect())
+    }
+
+    async fn get(&self, path: String, version_id: Option<String>) -> FlymodelResult<Bytes> {
+        self.faults.check(StorageOperation::Get)?;
+        self.find(path, version_id).map(|(_, bs)| bs)
+    }
+
+    async fn head(&self, path: String, version_id: Option<String>) -> FlymodelResult<ObjectMeta> {
+        self.faults.check(StorageOperation::Get)?;
+        self.find(path, version_id)
+            .map(|(version_id, bs)| ObjectMeta {
+                size: bs.len() as u64,
+                version_id: Some(version_id),
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use flymodel::{lifecycle::Lifecycle, storage::StorageProvider};
+
+    use super::{MemoryConfiguration, MemoryStorage, StorageOperation};
+
+    fn new_memory_test_client() -> MemoryStorage {
+        MemoryStorage::new(MemoryConfiguration {
+            prefix: "/".into(),
+            bucket: "ml-dev".into(),
+            role: Lifecycle::Test,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_memory_versions_and_faults() -> anyhow::Result<()> {
+        let client = new_memory_test_client();
+
+        let first = client
+            .put("test.txt".into(), Bytes::from_static(b"abc"))
+            .await?;
+        let second = client
+            .put("test.txt".into(), Bytes::from_static(b"def"))
+            .await?;
+        assert_eq!(client.get("test.txt".into(), None).await?, "def");
+        assert_eq!(client.get("test.txt".into(), first.clone()).await?, "abc");
+
+        client.faults().fail_next(StorageOperation::Get);
+        assert!(client.get("test.txt".into(), None).await.is_err());
+        assert!(client.get("test.txt".into(), None).await.is_ok());
+
+        client.faults().fail_next(StorageOperation::Put);
+        assert!(client
+            .put("test.txt".into(), Bytes::from_static(b"ghi"))
+            .await
+            .is_err());
+        assert_eq!(client.versions("test.txt".into()).len(), 2);
+
+        let mut listed = client.list().await?;
+        listed.sort_by(|a, b| a.version_id.cmp(&b.version_id));
+        assert_eq!(
+            listed
+                .iter()
+                .map(|version| (version.path.as_str(), version.version_id.clone()))
+                .collect::<Vec<_>>(),
+            vec![("test.txt", first.clone()), ("test.txt", second.clone())]
+        );
+
+        client.del("test.txt".into(), second).await?;
+        assert_eq!(client.versions("test.txt".into()), vec![first.unwrap()]);
+        client.del("test.txt".into(), None).await?;
+        assert!(client.get("test.txt".into(), None).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_copy_between_buckets() -> anyhow::Result<()> {
+        let source = new_memory_test_client();
+        let sink = MemoryStorage::new(MemoryConfiguration {
+            prefix: "/".into(),
+            bucket: "ml-qa".into(),
+            role: Lifecycle::Qa,
+        });
+
+        let version = source
+            .put("test.txt".into(), Bytes::from_static(b"abc"))
+            .await?;
+        source
+            .put("test.txt".into(), Bytes::from_static(b"def"))
+            .await?;
+        let copied = sink
+            .copy_from(
+                &source,
+                "test.txt".into(),
+                version.clone(),
+                "copy.txt".into(),
+            )
+            .await?;
+        assert_eq!(copied.size, 3);
+        assert_ne!(copied.version_id, version);
+        assert_eq!(sink.get("copy.txt".into(), copied.version_id).await?, "abc");
+
+        sink.faults().fail_next(StorageOperation::Put);
+        assert!(sink
+            .copy_from(&source, "test.txt".into(), None, "copy.txt".into())
+            .await
+            .is_err());
+        assert_eq!(sink.versions("copy.txt".into()).len(), 1);
+        Ok(())
+    }
+}
@@ -1,16 +1,26 @@
 use aws_config::{environment::EnvironmentVariableCredentialsProvider, AppName, Region};
+use std::time::{Duration, SystemTime};
+
 use aws_sdk_s3::{
+    presigning::PresigningConfig,
     primitives::ByteStream,
-    types::{BucketVersioningStatus, VersioningConfiguration},
+    types::{
+        BucketVersioningStatus, CompletedMultipartUpload, CompletedPart, VersioningConfiguration,
+    },
     Client,
 };
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use flymodel::{
     errs::{FlymodelError, FlymodelResult},
     lifecycle::Lifecycle,
-    storage::StorageProvider,
+    storage::{
+        self, ByteRange, ObjectMeta, ObjectVersion, StagedUpload, StorageProvider, StreamDigest,
+        UploadState,
+    },
 };
-use tracing::{debug, trace};
+use futures_util::StreamExt;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use tracing::{debug, trace, warn};
 
 fn default_path() -> String {
     "/".to_string()
@@ -21,6 +31,30 @@ fn default_pathstyle() -> bool {
 fn default_public() -> bool {
     false
 }
+fn default_part_size() -> usize {
+    8 * 1024 * 1024
+}
+
+/// the smallest part size s3 accepts (for all but the last part)
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// the largest object s3 copies in a single request
+const MAX_COPY_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+const COPY_SOURCE_KEY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+fn presigning(expires_in: Duration) -> FlymodelResult<PresigningConfig> {
+    PresigningConfig::expires_in(expires_in).map_err(FlymodelError::internal_error)
+}
+
+fn multipart_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> FlymodelError {
+    FlymodelError::S3MultipartError(anyhow::Error::new(err))
+}
 
 #[derive(serde::Deserialize, Debug)]
 pub struct S3Configuration {
@@ -34,22 +68,30 @@ pub struct S3Configuration {
     role: Lifecycle,
     #[serde(default = "default_pathstyle")]
     path_style: bool,
+    #[serde(default = "default_part_size")]
+    part_size: usize,
 }
 
 pub struct S3Storage {
     cli: Client,
+    endpoint: Option<String>,
     prefix: String,
     pub role: Lifecycle,
     bucket: String,
+    part_size: usize,
 }
 
 impl S3Storage {
     pub fn new<'a>(conf: S3Configuration) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            conf.part_size >= MIN_PART_SIZE,
+            "s3 part_size must be at least {MIN_PART_SIZE} bytes"
+        );
         let mut builder = aws_sdk_s3::config::Builder::new().force_path_style(conf.path_style);
         if !conf.public {
             builder = builder.credentials_provider(EnvironmentVariableCredentialsProvider::new())
         }
-        if let Some(endpoint) = conf.endpoint {
+        if let Some(endpoint) = conf.endpoint.clone() {
             builder = builder.endpoint_url(endpoint)
         }
         if let Some(region) = conf.region {
@@ -59,12 +101,105 @@ impl S3Storage {
         let cli = Client::from_conf(builder.build());
         return Ok(Self {
             cli,
+            endpoint: conf.endpoint,
             prefix: conf.prefix,
             role: conf.role,
             bucket: conf.bucket,
+            part_size: conf.part_size,
         });
     }
 
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        bs: Bytes,
+    ) -> FlymodelResult<(i32, String)> {
+        trace!("uploading part {part_number} of {key}");
+        let e_tag = self
+            .cli
+            .upload_part()
+            .bucket(self.bucket.clone())
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(bs))
+            .send()
+            .await
+            .map_err(multipart_error)?
+            .e_tag
+            .ok_or_else(|| {
+                FlymodelError::S3MultipartError(anyhow::anyhow!("part {part_number} has no etag"))
+            })?;
+        Ok((part_number, e_tag))
+    }
+
+    async fn abort_multipart(&self, key: String, upload_id: String) -> FlymodelResult<()> {
+        trace!("aborting multipart upload: {}", key);
+        self.cli
+            .abort_multipart_upload()
+            .bucket(self.bucket.clone())
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await
+            .map_err(multipart_error)?;
+        Ok(())
+    }
+
+    /// returns the data which did not fill a part, only ever left when no multipart upload was started
+    async fn stream_parts(
+        &self,
+        key: &str,
+        stream: &mut storage::ByteStream,
+        digest: &mut StreamDigest,
+        upload_id: &mut Option<String>,
+        parts: &mut Vec<(i32, String)>,
+    ) -> FlymodelResult<Bytes> {
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            digest.update(&chunk);
+            buf.extend_from_slice(&chunk);
+            while buf.len() >= self.part_size {
+                let part = buf.split_to(self.part_size).freeze();
+                let id = match upload_id {
+                    Some(id) => id.clone(),
+                    None => {
+                        trace!("starting multipart upload: {}", key);
+                        let id = self
+                            .cli
+                            .create_multipart_upload()
+                            .bucket(self.bucket.clone())
+                            .key(key)
+                            .send()
+                            .await
+                            .map_err(multipart_error)?
+                            .upload_id
+                            .ok_or_else(|| {
+                                FlymodelError::S3MultipartError(anyhow::anyhow!(
+                                    "multipart upload has no id"
+                                ))
+                            })?;
+                        upload_id.insert(id).clone()
+                    }
+                };
+                let part_number = parts.len() as i32 + 1;
+                parts.push(self.upload_part(key, &id, part_number, part).await?);
+            }
+        }
+
+        match upload_id {
+            Some(id) if !buf.is_empty() => {
+                let part_number = parts.len() as i32 + 1;
+                parts.push(self.upload_part(key, id, part_number, buf.freeze()).await?);
+                Ok(Bytes::new())
+            }
+            _ => Ok(buf.freeze()),
+        }
+    }
+
     pub async fn setup_bucket(&self) -> anyhow::Result<()> {
         debug!("testing bucket existence (soft)");
         match self
@@ -167,6 +302,93 @@ impl StorageProvider for S3Storage {
             .version_id)
     }
 
+    async fn put_stream(
+        &self,
+        path: String,
+        mut stream: storage::ByteStream,
+    ) -> FlymodelResult<StagedUpload> {
+        let key = self.resolve_path(path.clone());
+        let mut digest = StreamDigest::default();
+        let mut upload_id = None;
+        let mut parts = vec![];
+
+        let rest = match self
+            .stream_parts(&key, &mut stream, &mut digest, &mut upload_id, &mut parts)
+            .await
+        {
+            Ok(rest) => rest,
+            Err(err) => {
+                if let Some(upload_id) = upload_id {
+                    if let Err(abort) = self.abort_multipart(key, upload_id).await {
+                        warn!("failed to abort multipart upload: {abort}");
+                    }
+                }
+                return Err(err);
+            }
+        };
+
+        let (size, sha256) = digest.finish();
+        let state = match upload_id {
+            Some(upload_id) => UploadState::Multipart { upload_id, parts },
+            None => UploadState::Complete {
+                version_id: self.put(path.clone(), rest).await?,
+            },
+        };
+        Ok(StagedUpload {
+            path,
+            size,
+            sha256,
+            state,
+        })
+    }
+
+    async fn complete_upload(&self, upload: &mut StagedUpload) -> FlymodelResult<Option<String>> {
+        let (upload_id, parts) = match &upload.state {
+            UploadState::Complete { version_id } => return Ok(version_id.clone()),
+            UploadState::Multipart { upload_id, parts } => (upload_id, parts),
+        };
+        let key = self.resolve_path(upload.path.clone());
+        trace!("completing multipart upload: {}", key);
+        let parts = parts
+            .iter()
+            .map(|(part_number, e_tag)| {
+                CompletedPart::builder()
+                    .part_number(*part_number)
+                    .e_tag(e_tag)
+                    .build()
+            })
+            .collect();
+        let version_id = self
+            .cli
+            .complete_multipart_upload()
+            .bucket(self.bucket.clone())
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(multipart_error)?
+            .version_id;
+        upload.state = UploadState::Complete {
+            version_id: version_id.clone(),
+        };
+        Ok(version_id)
+    }
+
+    async fn abort_upload(&self, upload: StagedUpload) -> FlymodelResult<()> {
+        match upload.state {
+            UploadState::Complete { version_id } => self.del(upload.path, version_id).await,
+            UploadState::Multipart { upload_id, .. } => {
+                self.abort_multipart(self.resolve_path(upload.path), upload_id)
+                    .await
+            }
+        }
+    }
+
     async fn del(&self, path: String, version_id: Option<String>) -> FlymodelResult<()> {
         let key = self.resolve_path(path);
         trace!("deleting object: {}", key);
@@ -180,6 +402,45 @@ impl StorageProvider for S3Storage {
         Ok(())
     }
 
+    async fn list(&self) -> FlymodelResult<Vec<ObjectVersion>> {
+        let prefix = self.resolve_path(String::new());
+        trace!("listing object versions: {}", prefix);
+        let mut listed = vec![];
+        let (mut key_marker, mut version_id_marker) = (None, None);
+        loop {
+            let page = self
+                .cli
+                .list_object_versions()
+                .bucket(self.bucket.clone())
+                .prefix(prefix.clone())
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send()
+                .await?;
+            for version in page.versions() {
+                let Some(path) = version.key().and_then(|key| key.strip_prefix(&prefix)) else {
+                    continue;
+                };
+                listed.push(ObjectVersion {
+                    path: path.to_string(),
+                    version_id: version.version_id().map(str::to_string),
+                    size: version.size().unwrap_or_default().max(0) as u64,
+                    // versions of unknown age are treated as new
+                    last_modified: version
+                        .last_modified()
+                        .and_then(|modified| SystemTime::try_from(*modified).ok())
+                        .unwrap_or_else(SystemTime::now),
+                });
+            }
+            if !page.is_truncated().unwrap_or_default() {
+                break;
+            }
+            key_marker = page.next_key_marker;
+            version_id_marker = page.next_version_id_marker;
+        }
+        Ok(listed)
+    }
+
     async fn get(&self, path: String, version_id: Option<String>) -> FlymodelResult<Bytes> {
         let key = self.resolve_path(path);
         trace!("getting object: {}", key);
@@ -191,6 +452,132 @@ impl StorageProvider for S3Storage {
             .set_version_id(version_id);
         Ok(base.send().await?.body.collect().await?.into_bytes())
     }
+
+    async fn head(&self, path: String, version_id: Option<String>) -> FlymodelResult<ObjectMeta> {
+        let key = self.resolve_path(path);
+        trace!("heading object: {}", key);
+        let head = self
+            .cli
+            .head_object()
+            .bucket(self.bucket.clone())
+            .key(key)
+            .set_version_id(version_id)
+            .send()
+            .await?;
+        Ok(ObjectMeta {
+            size: head.content_length.unwrap_or_default() as u64,
+            version_id: head.version_id,
+        })
+    }
+
+    fn copy_source(&self, path: String, version_id: Option<String>) -> Option<storage::CopySource> {
+        Some(storage::CopySource {
+            endpoint: self.endpoint.clone(),
+            bucket: self.bucket.clone(),
+            key: self.resolve_path(path),
+            version_id,
+        })
+    }
+
+    /// copies server side when `source` shares the endpoint, streaming otherwise
+    async fn copy_from(
+        &self,
+        source: &(dyn StorageProvider + Sync + Send),
+        path: String,
+        version_id: Option<String>,
+        to: String,
+    ) -> FlymodelResult<ObjectMeta> {
+        let from = match source.copy_source(path.clone(), version_id.clone()) {
+            Some(from) if from.endpoint == self.endpoint => from,
+            _ => return storage::stream_copy(source, self, path, version_id, to).await,
+        };
+        let size = source.head(path.clone(), version_id.clone()).await?.size;
+        if size > MAX_COPY_SIZE {
+            return storage::stream_copy(source, self, path, version_id, to).await;
+        }
+
+        let key = self.resolve_path(to);
+        trace!("copying object: {}/{} to {}", from.bucket, from.key, key);
+        let mut copy_source = format!(
+            "{}/{}",
+            from.bucket,
+            utf8_percent_encode(&from.key, COPY_SOURCE_KEY)
+        );
+        if let Some(version_id) = from.version_id {
+            copy_source.push_str("?versionId=");
+            copy_source.push_str(&utf8_percent_encode(&version_id, NON_ALPHANUMERIC).to_string());
+        }
+        let copied = self
+            .cli
+            .copy_object()
+            .bucket(self.bucket.clone())
+            .key(key)
+            .copy_source(copy_source)
+            .send()
+            .await?;
+        Ok(ObjectMeta {
+            size,
+            version_id: copied.version_id,
+        })
+    }
+
+    async fn presign_put(&self, path: String, expires_in: Duration) -> FlymodelResult<String> {
+        let key = self.resolve_path(path);
+        trace!("presigning upload: {}", key);
+        Ok(self
+            .cli
+            .put_object()
+            .bucket(self.bucket.clone())
+            .key(key)
+            .presigned(presigning(expires_in)?)
+            .await?
+            .uri()
+            .to_string())
+    }
+
+    async fn presign_get(
+        &self,
+        path: String,
+        version_id: Option<String>,
+        expires_in: Duration,
+    ) -> FlymodelResult<String> {
+        let key = self.resolve_path(path);
+        trace!("presigning download: {}", key);
+        Ok(self
+            .cli
+            .get_object()
+            .bucket(self.bucket.clone())
+            .key(key)
+            .set_version_id(version_id)
+            .presigned(presigning(expires_in)?)
+            .await?
+            .uri()
+            .to_string())
+    }
+
+    async fn get_stream(
+        &self,
+        path: String,
+        version_id: Option<String>,
+        range: Option<ByteRange>,
+    ) -> FlymodelResult<storage::ByteStream> {
+        let key = self.resolve_path(path);
+        trace!("streaming object: {} ({:?})", key, range);
+        let body = self
+            .cli
+            .get_object()
+            .bucket(self.bucket.clone())
+            .key(key)
+            .set_version_id(version_id)
+            .set_range(range.map(|range| format!("bytes={}-{}", range.start, range.end)))
+            .send()
+            .await?
+            .body;
+        Ok(Box::pin(futures_util::stream::try_unfold(
+            body,
+            |mut body| async move { Ok(body.try_next().await?.map(|bs| (bs, body))) },
+        )))
+    }
 }
 
 #[cfg(test)]
@@ -198,7 +585,7 @@ mod test {
     use bytes::Bytes;
 
     use super::{Lifecycle, S3Storage};
-    use flymodel::storage::StorageProvider;
+    use flymodel::storage::{StorageProvider, UploadState};
 
     fn new_minio_test_client() -> S3Storage {
         dotenv::dotenv().ok();
@@ -210,6 +597,7 @@ mod test {
             bucket: "ml-dev".into(),
             role: Lifecycle::Test,
             path_style: true,
+            part_size: super::default_part_size(),
         })
         .expect("storage")
     }
@@ -226,4 +614,35 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_minio_multipart() -> anyhow::Result<()> {
+        let client = new_minio_test_client();
+        client.setup().await?;
+
+        let part = Bytes::from(vec![7u8; super::default_part_size()]);
+        let expect = [part.clone(), part, Bytes::from_static(b"tail")].concat();
+        let stream = futures_util::stream::iter(
+            expect
+                .chunks(1024 * 1024)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                .collect::<Vec<_>>(),
+        );
+
+        let mut upload = client
+            .put_stream("multipart.bin".into(), Box::pin(stream))
+            .await?;
+        assert_eq!(upload.size, expect.len() as u64);
+        assert_eq!(upload.sha256, sha256::digest(&expect[..]));
+        assert!(
+            matches!(upload.state, UploadState::Multipart { ref parts, .. } if parts.len() == 3)
+        );
+
+        let version_id = client.complete_upload(&mut upload).await?;
+        let resp = client.get("multipart.bin".into(), version_id).await?;
+        assert_eq!(&resp[..], &expect[..]);
+
+        client.abort_upload(upload).await?;
+        Ok(())
+    }
 }
@@ -2,13 +2,19 @@ use std::{collections::HashMap, sync::Arc};
 
 use flymodel::{errs::FlymodelResult, storage::StorageProvider};
 
-use crate::minio::{S3Configuration, S3Storage};
+use crate::{
+    fs::{FilesystemConfiguration, FilesystemStorage},
+    memory::{MemoryConfiguration, MemoryStorage},
+    minio::{S3Configuration, S3Storage},
+};
 
 type StorageMap = HashMap<String, Box<dyn StorageProvider + Sync + Send + 'static>>;
 
 #[derive(serde::Deserialize, Debug)]
 pub struct StorageConfig {
     s3: Option<Vec<S3Configuration>>,
+    filesystem: Option<Vec<FilesystemConfiguration>>,
+    memory: Option<Vec<MemoryConfiguration>>,
 }
 
 pub struct StorageOrchestrator {
@@ -16,6 +22,14 @@ pub struct StorageOrchestrator {
 }
 
 impl StorageOrchestrator {
+    pub fn from_providers(
+        providers: impl IntoIterator<Item = (String, Box<dyn StorageProvider + Sync + Send + 'static>)>,
+    ) -> Self {
+        Self {
+            storage: Arc::new(providers.into_iter().collect()),
+        }
+    }
+
     pub fn get(&self, store: &String) -> Option<&Box<dyn StorageProvider + Sync + Send + 'static>> {
         self.storage.get(store)
     }
@@ -30,11 +44,19 @@ impl StorageOrchestrator {
 impl StorageConfig {
     pub async fn build(self) -> anyhow::Result<StorageOrchestrator> {
         let mut storage = StorageMap::new();
-        if let Some(confmap) = self.s3 {
-            for config in confmap {
-                storage.insert(config.bucket.clone(), Box::new(S3Storage::new(config)?));
-            }
-        } else {
+        for config in self.s3.unwrap_or_default() {
+            storage.insert(config.bucket.clone(), Box::new(S3Storage::new(config)?));
+        }
+        for config in self.filesystem.unwrap_or_default() {
+            storage.insert(
+                config.bucket.clone(),
+                Box::new(FilesystemStorage::new(config)?),
+            );
+        }
+        for config in self.memory.unwrap_or_default() {
+            storage.insert(config.bucket.clone(), Box::new(MemoryStorage::new(config)));
+        }
+        if storage.is_empty() {
             anyhow::bail!("no storage provided")
         }
         Ok(StorageOrchestrator {
@@ -10,9 +10,20 @@ use actix_web::{
 };
 
 use async_graphql::{http::GraphiQLSource, Schema};
-use async_graphql_actix_web::{GraphQL, GraphQLSubscription};
-use flymodel::tls::TlsConf;
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
+use flymodel::{
+    config::{
+        auth::{AuthConfiguration, AuthHandlers},
+        gc::GcConfiguration,
+        relocation::RelocationConfiguration,
+        retention::RetentionConfiguration,
+        uploads::UploadConfiguration,
+        webhooks::WebhookConfiguration,
+    },
+    tls::TlsConf,
+};
 use flymodel_entities::{db::DbLoader, entities};
+use flymodel_events::{log::spawn_audit_log, AuditEvent};
 use flymodel_registry::storage::StorageOrchestrator;
 use flymodel_tracing::tracer::{OtlpTracer, OtlpTracerConfig};
 use opentelemetry::{
@@ -25,21 +36,57 @@ use tracing::info;
 use crate::{
     apply_data,
     artifacts::{
-        experiments::{download_experiment_artifact, upload_experiment_artifact},
-        model_version::{download_model_version_artifact, upload_model_version_artifact},
+        experiments::{
+            download_experiment_artifact, upload_experiment_artifact, verify_experiment_artifact,
+        },
+        model_version::{
+            download_model_version_artifact, upload_model_version_artifact,
+            verify_model_version_artifact,
+        },
+        relocation::spawn_relocation_sweeper,
+        resumable::{
+            cancel_resumable_upload, complete_resumable_upload, create_resumable_upload,
+            resumable_upload_status, spawn_upload_sweeper, upload_resumable_chunk,
+        },
     },
+    auth::{
+        oidc::{oauth_callback, oauth_login},
+        Authenticated, Authenticator,
+    },
+    events::EventBus,
+    gc::spawn_garbage_collector,
+    retention::spawn_retention,
     schema::{build_schema, FlymodelSchema},
+    webhooks::spawn_webhook_dispatcher,
 };
 use tracing_actix_web::TracingLogger;
 
 const SUBSCRIPTION: &str = "/graphql";
+const LOGIN: &str = "/auth/login";
+const AUDIT_CAPACITY: usize = 1024;
+
+async fn graphql(
+    schema: web::Data<FlymodelSchema>,
+    Authenticated(principal): Authenticated,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    schema
+        .execute(req.into_inner().data(principal))
+        .await
+        .into()
+}
 
 async fn graphql_ws(
     schema: web::Data<FlymodelSchema>,
+    Authenticated(principal): Authenticated,
     req: HttpRequest,
     payload: web::Payload,
 ) -> Result<HttpResponse> {
-    GraphQLSubscription::new(Schema::clone(&*schema)).start(&req, payload)
+    let mut data = async_graphql::Data::default();
+    data.insert(principal);
+    GraphQLSubscription::new(Schema::clone(&*schema))
+        .with_data(data)
+        .start(&req, payload)
 }
 
 async fn graphql_idx() -> Result<HttpResponse> {
@@ -53,6 +100,17 @@ async fn graphql_idx() -> Result<HttpResponse> {
         ))
 }
 
+/// what the server is configured with, besides where it binds & stores artifacts
+pub struct ServerConfiguration {
+    pub auth: AuthConfiguration,
+    pub uploads: UploadConfiguration,
+    pub retention: RetentionConfiguration,
+    pub gc: GcConfiguration,
+    pub relocation: RelocationConfiguration,
+    pub webhooks: WebhookConfiguration,
+    pub tls: Option<TlsConf>,
+}
+
 pub async fn start_server<
     A,
     P: std::convert::AsRef<std::path::Path> + Clone + Send + Sync + 'static,
@@ -62,29 +120,76 @@ pub async fn start_server<
     temp_dir: P,
     tracer: Option<OtlpTracerConfig>,
     store: Arc<StorageOrchestrator>,
-    tls: Option<TlsConf>,
+    conf: ServerConfiguration,
     dry: bool,
 ) -> anyhow::Result<()>
 where
     A: std::net::ToSocketAddrs + Display,
 {
+    let ServerConfiguration {
+        auth,
+        uploads,
+        retention,
+        gc,
+        relocation,
+        webhooks,
+        tls,
+    } = conf;
     std::fs::create_dir_all(temp_dir.clone())?;
 
     info!("starting on http://{}", bind);
-    let schema = build_schema(db.clone(), None, None, tracer.clone())?;
+    let events = EventBus::new(db.clone());
+    let (audit, audit_log) = flymodel_events::bus::<AuditEvent>(AUDIT_CAPACITY)?;
+    spawn_audit_log(db.clone(), audit_log);
+    let schema = build_schema(
+        db.clone(),
+        store.clone(),
+        events.clone(),
+        audit.clone(),
+        None,
+        None,
+        tracer.clone(),
+    )?;
     let service_tracer = if let Some(tracer) = tracer.clone() {
         Some(tracer.new_tracer_provider("flymodel-graphql")?)
     } else {
         None
     };
     let store = store.clone();
+    let callback = match &auth.handler {
+        AuthHandlers::OAuth2(conf) => Some(conf.callback.clone()),
+        _ => None,
+    };
+    let authenticator = Data::new(Authenticator::new(auth, db.clone())?);
+    if !dry {
+        spawn_upload_sweeper(
+            db.clone(),
+            store.clone(),
+            std::time::Duration::from_secs(uploads.sweep_interval),
+        );
+        spawn_relocation_sweeper(db.clone(), store.clone(), relocation);
+        spawn_retention(db.clone(), store.clone(), retention);
+        spawn_garbage_collector(db.clone(), store.clone(), gc);
+        spawn_webhook_dispatcher(db.clone(), webhooks)?;
+        events.spawn_listener();
+    }
+    // resumable chunks are buffered in memory, up to the largest chunk size
+    let payload = web::PayloadConfig::new(uploads.max_chunk_size as usize);
+    let uploads = Data::new(uploads);
+    let events = Data::new(events);
+    let audit = Data::new(audit);
     let server = HttpServer::new(move || {
         let temp_dir = temp_dir.clone();
         let store = store.clone();
         let base = App::new()
             .wrap(TracingLogger::default())
             .app_data(TempFileConfig::default().directory(temp_dir))
-            .app_data(Data::new(store));
+            .app_data(Data::new(store))
+            .app_data(uploads.clone())
+            .app_data(events.clone())
+            .app_data(audit.clone())
+            .app_data(payload.clone())
+            .app_data(authenticator.clone());
 
         apply_data! {
             base,
@@ -116,15 +221,33 @@ where
             }
         });
 
+        let base = if let Some(callback) = callback.clone() {
+            base.service(web::resource(LOGIN).guard(guard::Get()).to(oauth_login))
+                .service(
+                    web::resource(callback)
+                        .guard(guard::Get())
+                        .to(oauth_callback),
+                )
+        } else {
+            base
+        };
+
         base.service(upload_model_version_artifact)
             .service(upload_experiment_artifact)
             .service(download_model_version_artifact)
             .service(download_experiment_artifact)
+            .service(verify_model_version_artifact)
+            .service(verify_experiment_artifact)
+            .service(create_resumable_upload)
+            .service(resumable_upload_status)
+            .service(upload_resumable_chunk)
+            .service(complete_resumable_upload)
+            .service(cancel_resumable_upload)
             .service(
                 web::resource(SUBSCRIPTION)
                     .guard(guard::Post())
                     .app_data(Data::new(schema.clone()))
-                    .to(GraphQL::new(schema.clone())),
+                    .to(graphql),
             )
             .service(
                 web::resource(SUBSCRIPTION)
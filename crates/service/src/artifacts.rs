@@ -1,32 +1,53 @@
 use actix_web::{
-    body::BoxBody,
-    dev::Response,
+    dev::Payload,
     http::{
-        header::{self, HeaderName, HeaderValue},
+        header::{
+            self, ByteRangeSpec, ContentRange, ContentRangeSpec, ETag, EntityTag, Header,
+            HeaderName, HeaderValue, IfNoneMatch, Range,
+        },
         StatusCode,
     },
+    web::Data,
+    FromRequest, HttpRequest, HttpResponse, HttpResponseBuilder,
 };
 use anyhow::Error;
-use sea_orm::{ConnectionTrait, TransactionError, TransactionTrait};
+use async_graphql::{dataloader::DataLoader, Context};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, TransactionTrait};
 use std::{
-    io::{Read, Seek, SeekFrom},
+    collections::HashMap,
+    io::{Seek, SeekFrom},
     pin::Pin,
     str::FromStr,
+    sync::Arc,
+    time::Duration,
 };
 
-use bytes::Bytes;
-use flymodel::{errs::FlymodelError, storage::StorageProvider};
-use flymodel_entities::entities::{
-    self,
-    enums::{ArchiveCompression, ArchiveFormat},
+use crate::auth::Authenticated;
+use flymodel::{
+    errs::FlymodelError,
+    perms::Principal,
+    storage::{ByteRange, ByteStream, StagedUpload, StorageProvider, StreamDigest},
 };
+use flymodel_entities::{
+    db::DbLoader,
+    entities::{
+        self,
+        enums::{ArchiveCompression, ArchiveFormat},
+    },
+};
+use flymodel_events::AuditPublisher;
 use flymodel_registry::storage::StorageOrchestrator;
-use futures_util::Future;
+use futures_util::{future::LocalBoxFuture, Future, StreamExt};
 use sea_orm::DatabaseTransaction;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio_util::io::ReaderStream;
+use tracing::warn;
 
 pub mod experiments;
 pub mod model_version;
+pub mod relocation;
+pub mod resumable;
+pub mod tickets;
 
 #[macro_export]
 macro_rules! params_for {
@@ -51,54 +72,58 @@ macro_rules! params_for {
     };
 }
 
+const UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// the upload is only completed once a transaction is open, & is aborted or deleted should it fail
 pub(crate) async fn guarded_upload<
     T: Send + Sync,
     C: TransactionTrait + ConnectionTrait,
     F: for<'c> FnOnce(
             &'c DatabaseTransaction,
-            Option<String>,
+            StagedUpload,
         )
             -> Pin<Box<dyn Future<Output = Result<T, FlymodelError>> + Send + 'c>>
         + Send,
 >(
-    sink: &Box<dyn StorageProvider + std::marker::Send + Sync>,
-    bs: Bytes,
+    sink: &(dyn StorageProvider + Send + Sync + 'static),
+    stream: ByteStream,
     db: &C,
     key: String,
     with_tx: F,
 ) -> Result<T, FlymodelError> {
-    let upload_res = sink.put(key.clone(), bs).await?;
-    let created = match (&db)
-        .transaction(|tx| (with_tx)(tx.to_owned(), upload_res.clone()))
-        .await
-    {
-        Ok(created) => created,
-        Err(e) => {
-            let res = upload_res.clone();
-            sink.del(key, res).await?;
-            return Err(match e {
-                TransactionError::Connection(conn) => FlymodelError::DbOperationError(conn),
-                TransactionError::Transaction(tx) => tx,
-            });
+    let mut upload = sink.put_stream(key, stream).await?;
+    let res = async {
+        let tx = db.begin().await?;
+        sink.complete_upload(&mut upload).await?;
+        let created = (with_tx)(&tx, upload.clone()).await?;
+        tx.commit().await?;
+        Ok(created)
+    }
+    .await;
+    if res.is_err() {
+        if let Err(err) = sink.abort_upload(upload).await {
+            warn!("failed to roll back upload: {err}");
         }
-    };
-    Ok(created)
+    }
+    res
 }
 
-pub(crate) fn read_bts(
-    mut bs: actix_multipart::form::tempfile::TempFile,
-) -> Result<Bytes, FlymodelError> {
+pub(crate) fn stream_file(
+    bs: actix_multipart::form::tempfile::TempFile,
+) -> Result<ByteStream, FlymodelError> {
     let on_err = |err| FlymodelError::UploadError(Error::new(err));
-    let mut arr = vec![];
-    bs.file.seek(SeekFrom::Start(0)).map_err(on_err)?;
-    bs.file.read_to_end(&mut arr).map_err(on_err)?;
-    let bs = Bytes::from_iter(arr);
-    if bs.len() == 0 {
-        return Err(
-            FlymodelError::NonDeterministicError("Uploads must contain data".into()).into(),
-        );
+    if bs.size == 0 {
+        return Err(FlymodelError::NonDeterministicError(
+            "Uploads must contain data".into(),
+        ));
     }
-    Ok(bs)
+    // the handle keeps the (unlinked) file alive for the duration of the stream
+    let mut file = bs.file.into_file();
+    file.seek(SeekFrom::Start(0)).map_err(on_err)?;
+    Ok(Box::pin(
+        ReaderStream::with_capacity(tokio::fs::File::from_std(file), UPLOAD_CHUNK_SIZE)
+            .map(move |chunk| chunk.map_err(on_err)),
+    ))
 }
 
 #[derive(Deserialize, Debug)]
@@ -106,39 +131,236 @@ pub(crate) struct DownloadParams {
     artifact_id: i64,
 }
 
-pub(crate) async fn download_with_blob(
-    blobref: &entities::object_blob::Model,
+#[derive(Serialize, Debug)]
+pub(crate) struct BlobVerification {
+    blob: i64,
+    size: i64,
+    sha256: String,
+}
+
+const DEFAULT_PRESIGN_EXPIRY: i64 = 60 * 60;
+/// the longest validity s3 accepts for presigned urls (7 days)
+const MAX_PRESIGN_EXPIRY: i64 = 7 * 24 * 60 * 60;
+
+pub(crate) fn presign_expiry(expires_in: Option<i64>) -> Result<Duration, FlymodelError> {
+    match expires_in.unwrap_or(DEFAULT_PRESIGN_EXPIRY) {
+        secs @ 1..=MAX_PRESIGN_EXPIRY => Ok(Duration::from_secs(secs as u64)),
+        secs => Err(FlymodelError::ContraintError(format!(
+            "expiry must be between 1 and {MAX_PRESIGN_EXPIRY} seconds, got {secs}"
+        ))),
+    }
+}
+
+pub(crate) fn storage<'ctx>(
+    ctx: &Context<'ctx>,
+) -> Result<&'ctx StorageOrchestrator, FlymodelError> {
+    ctx.data_opt::<Arc<StorageOrchestrator>>()
+        .map(Arc::as_ref)
+        .ok_or_else(|| FlymodelError::RuntimeDependencyError("missing storage".into()))
+}
+
+pub(crate) fn app_data<T: ?Sized + 'static>(req: &HttpRequest) -> Result<Data<T>, FlymodelError> {
+    req.app_data::<Data<T>>().cloned().ok_or_else(|| {
+        FlymodelError::RuntimeDependencyError(format!("missing {}", std::any::type_name::<T>()))
+    })
+}
+
+pub(crate) struct ArtifactLoaders<'a> {
+    pub(crate) namespaces: &'a DataLoader<DbLoader<entities::namespace::Model>>,
+    pub(crate) versions: &'a DataLoader<DbLoader<entities::model_version::Model>>,
+    pub(crate) experiments: &'a DataLoader<DbLoader<entities::experiment::Model>>,
+    pub(crate) model_artifacts: &'a DataLoader<DbLoader<entities::model_artifact::Model>>,
+    pub(crate) experiment_artifacts: &'a DataLoader<DbLoader<entities::experiment_artifact::Model>>,
+    pub(crate) buckets: &'a DataLoader<DbLoader<entities::bucket::Model>>,
+    pub(crate) blobs: &'a DataLoader<DbLoader<entities::object_blob::Model>>,
+}
+
+impl<'a> ArtifactLoaders<'a> {
+    pub(crate) fn with_context(ctx: &Context<'a>) -> Result<Self, FlymodelError> {
+        Ok(Self {
+            namespaces: DbLoader::with_context(ctx)?,
+            versions: DbLoader::with_context(ctx)?,
+            experiments: DbLoader::with_context(ctx)?,
+            model_artifacts: DbLoader::with_context(ctx)?,
+            experiment_artifacts: DbLoader::with_context(ctx)?,
+            buckets: DbLoader::with_context(ctx)?,
+            blobs: DbLoader::with_context(ctx)?,
+        })
+    }
+}
+
+pub struct ArtifactRequest {
+    pub(crate) req: HttpRequest,
+    pub(crate) principal: Principal,
+    pub(crate) storage: Data<Arc<StorageOrchestrator>>,
+    namespaces: Data<DataLoader<DbLoader<entities::namespace::Model>>>,
+    versions: Data<DataLoader<DbLoader<entities::model_version::Model>>>,
+    experiments: Data<DataLoader<DbLoader<entities::experiment::Model>>>,
+    model_artifacts: Data<DataLoader<DbLoader<entities::model_artifact::Model>>>,
+    experiment_artifacts: Data<DataLoader<DbLoader<entities::experiment_artifact::Model>>>,
+    buckets: Data<DataLoader<DbLoader<entities::bucket::Model>>>,
+    blobs: Data<DataLoader<DbLoader<entities::object_blob::Model>>>,
+    pub(crate) audit: Data<AuditPublisher>,
+}
+
+impl ArtifactRequest {
+    pub(crate) fn loaders(&self) -> ArtifactLoaders<'_> {
+        ArtifactLoaders {
+            namespaces: &self.namespaces,
+            versions: &self.versions,
+            experiments: &self.experiments,
+            model_artifacts: &self.model_artifacts,
+            experiment_artifacts: &self.experiment_artifacts,
+            buckets: &self.buckets,
+            blobs: &self.blobs,
+        }
+    }
+}
+
+impl FromRequest for ArtifactRequest {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let authenticated = Authenticated::from_request(req, payload);
+        let req = req.clone();
+        Box::pin(async move {
+            let Authenticated(principal) = authenticated.await?;
+            Ok(Self {
+                principal,
+                storage: app_data(&req)?,
+                namespaces: app_data(&req)?,
+                versions: app_data(&req)?,
+                experiments: app_data(&req)?,
+                model_artifacts: app_data(&req)?,
+                experiment_artifacts: app_data(&req)?,
+                buckets: app_data(&req)?,
+                blobs: app_data(&req)?,
+                audit: app_data(&req)?,
+                req,
+            })
+        })
+    }
+}
+
+pub(crate) fn sink_of<'a>(
     bucket: &entities::bucket::Model,
-    storage: &StorageOrchestrator,
-    artifact_name: String,
-) -> Result<Response<BoxBody>, FlymodelError> {
-    let sink = storage
-        .get(&bucket.name)
-        .ok_or(FlymodelError::RuntimeDependencyError(format!(
+    storage: &'a StorageOrchestrator,
+) -> Result<&'a (dyn StorageProvider + Sync + Send + 'static), FlymodelError> {
+    storage.get(&bucket.name).map(|sink| sink.as_ref()).ok_or(
+        FlymodelError::RuntimeDependencyError(format!(
             "missing {} bucket configurations",
             bucket.name
-        )))?;
+        )),
+    )
+}
 
-    let blob = sink
-        .get(blobref.key.clone(), Some(blobref.version_id.clone()))
-        .await?;
+/// returns the blobs whose objects were left in storage for the garbage collector
+pub(crate) async fn delete_blob_objects<C: ConnectionTrait>(
+    db: &C,
+    storage: &StorageOrchestrator,
+    blobs: &[entities::object_blob::Model],
+) -> Vec<i64> {
+    let buckets: HashMap<i64, entities::bucket::Model> = match entities::bucket::Entity::find()
+        .filter(entities::bucket::Column::Id.is_in(blobs.iter().map(|blob| blob.bucket_id)))
+        .all(db)
+        .await
+    {
+        Ok(buckets) => buckets
+            .into_iter()
+            .map(|bucket| (bucket.id, bucket))
+            .collect(),
+        Err(err) => {
+            warn!("failed to load the buckets of {} blobs: {err}", blobs.len());
+            return blobs.iter().map(|blob| blob.id).collect();
+        }
+    };
+    let mut failed = vec![];
+    for blob in blobs {
+        let deleted = match buckets.get(&blob.bucket_id) {
+            Some(bucket) => match sink_of(bucket, storage) {
+                Ok(sink) => {
+                    sink.del(blob.key.clone(), Some(blob.version_id.clone()))
+                        .await
+                }
+                Err(err) => Err(err),
+            },
+            None => Err(FlymodelError::InvalidResourceId(blob.bucket_id)),
+        };
+        if let Err(err) = deleted {
+            warn!("failed to delete the object of blob {}: {err}", blob.id);
+            failed.push(blob.id);
+        }
+    }
+    failed
+}
 
-    let hash = sha256::digest(&*blob);
-    if hash != blobref.sha256 {
-        return Err((FlymodelError::IntegrityError {
-            kind: "artifact tampering".into(),
-            expect: blobref.sha256.clone(),
-            receive: hash,
-        })
-        .into());
+fn integrity_error(expect: &str, receive: String) -> FlymodelError {
+    FlymodelError::IntegrityError {
+        kind: "artifact tampering".into(),
+        expect: expect.to_string(),
+        receive,
     }
+}
 
-    let mut resp = Response::new(StatusCode::OK).set_body(BoxBody::new(blob));
+fn verified_stream(stream: ByteStream, expect: String) -> ByteStream {
+    Box::pin(futures_util::stream::try_unfold(
+        (stream, StreamDigest::default(), expect),
+        |(mut stream, mut digest, expect)| async move {
+            match stream.next().await {
+                Some(chunk) => {
+                    let chunk = chunk?;
+                    digest.update(&chunk);
+                    Ok(Some((chunk, (stream, digest, expect))))
+                }
+                None => {
+                    let (_, receive) = digest.finish();
+                    if receive != expect {
+                        let err = integrity_error(&expect, receive);
+                        tracing::error!("aborting download: {err}");
+                        return Err(err);
+                    }
+                    Ok(None)
+                }
+            }
+        },
+    ))
+}
+
+enum RequestedRange {
+    Full,
+    Partial(ByteRange),
+    Unsatisfiable,
+}
 
-    let headers = resp.headers_mut();
+/// only single byte ranges are honored, anything else is served in full
+fn requested_range(req: &HttpRequest, size: u64) -> RequestedRange {
+    match Range::parse(req) {
+        Ok(Range::Bytes(specs)) if specs.len() == 1 => {
+            match ByteRangeSpec::to_satisfiable_range(&specs[0], size) {
+                Some((start, end)) => RequestedRange::Partial(ByteRange { start, end }),
+                None => RequestedRange::Unsatisfiable,
+            }
+        }
+        _ => RequestedRange::Full,
+    }
+}
+
+fn not_modified(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        Err(_) => false,
+    }
+}
 
+fn content_headers(
+    resp: &mut HttpResponseBuilder,
+    blobref: &entities::object_blob::Model,
+    artifact_name: &str,
+) -> Result<(), FlymodelError> {
     if let Some(content_typ) = blobref.format {
-        headers.insert(
+        resp.insert_header((
             header::CONTENT_TYPE,
             HeaderValue::from_static(match content_typ {
                 ArchiveFormat::Arrow => "application/arrow",
@@ -146,11 +368,11 @@ pub(crate) async fn download_with_blob(
                 ArchiveFormat::Parquet => "application/parquet",
                 _ => todo!("{:?}", content_typ),
             }),
-        );
+        ));
     }
 
     if let Some(encoding) = blobref.encode {
-        headers.insert(
+        resp.insert_header((
             header::CONTENT_ENCODING,
             HeaderValue::from_static(match encoding {
                 ArchiveCompression::Gzip => "gzip",
@@ -160,22 +382,370 @@ pub(crate) async fn download_with_blob(
                 ArchiveCompression::Zip => "zip",
                 _ => todo!("{:?}", encoding),
             }),
-        );
+        ));
     }
 
-    headers.insert(
+    resp.insert_header((
         header::CONTENT_DISPOSITION,
         HeaderValue::from_str(&format!(
             r#"attachment; filename="{name}""#,
             name = artifact_name
         ))
         .map_err(FlymodelError::internal_error)?,
-    );
+    ));
+    Ok(())
+}
 
-    headers.insert(
-        HeaderName::from_str("Digest").map_err(FlymodelError::internal_error)?,
-        HeaderValue::from_str(&format!("sha256={hash}")).map_err(FlymodelError::internal_error)?,
-    );
+/// full reads are verified as they are streamed, partial reads can be checked with [verify_blob]
+pub(crate) async fn download_with_blob(
+    req: &HttpRequest,
+    blobref: &entities::object_blob::Model,
+    bucket: &entities::bucket::Model,
+    storage: &StorageOrchestrator,
+    artifact_name: String,
+) -> Result<HttpResponse, FlymodelError> {
+    let sink = sink_of(bucket, storage)?;
+
+    let etag = EntityTag::new_strong(blobref.sha256.clone());
+    if not_modified(req, &etag) {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(ETag(etag))
+            .finish());
+    }
+
+    let size = blobref.size as u64;
+    let range = match requested_range(req, size) {
+        RequestedRange::Unsatisfiable => {
+            return Ok(HttpResponse::RangeNotSatisfiable()
+                .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                    range: None,
+                    instance_length: Some(size),
+                }))
+                .finish());
+        }
+        RequestedRange::Partial(range) => Some(range),
+        RequestedRange::Full => None,
+    };
+
+    let stream = sink
+        .get_stream(blobref.key.clone(), Some(blobref.version_id.clone()), range)
+        .await?;
+
+    let mut resp = HttpResponse::build(match range {
+        Some(..) => StatusCode::PARTIAL_CONTENT,
+        None => StatusCode::OK,
+    });
+    resp.insert_header(ETag(etag))
+        .insert_header((header::ACCEPT_RANGES, "bytes"));
+    content_headers(&mut resp, blobref, &artifact_name)?;
+
+    Ok(match range {
+        Some(range) => resp
+            .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                range: Some((range.start, range.end)),
+                instance_length: Some(size),
+            }))
+            .no_chunking(range.end - range.start + 1)
+            .streaming(stream),
+        None => resp
+            .insert_header((
+                HeaderName::from_str("Digest").map_err(FlymodelError::internal_error)?,
+                HeaderValue::from_str(&format!("sha256={}", blobref.sha256))
+                    .map_err(FlymodelError::internal_error)?,
+            ))
+            .no_chunking(size)
+            .streaming(verified_stream(stream, blobref.sha256.clone())),
+    })
+}
+
+async fn digest_object(
+    sink: &(dyn StorageProvider + Sync + Send + 'static),
+    key: String,
+    version_id: Option<String>,
+) -> Result<(u64, String), FlymodelError> {
+    let mut stream = sink.get_stream(key, version_id, None).await?;
+    let mut digest = StreamDigest::default();
+    while let Some(chunk) = stream.next().await {
+        digest.update(&chunk?);
+    }
+    Ok(digest.finish())
+}
+
+fn size_error(expect: u64, receive: u64) -> FlymodelError {
+    FlymodelError::IntegrityError {
+        kind: "artifact size".into(),
+        expect: expect.to_string(),
+        receive: receive.to_string(),
+    }
+}
+
+pub(crate) async fn verify_blob(
+    blobref: &entities::object_blob::Model,
+    bucket: &entities::bucket::Model,
+    storage: &StorageOrchestrator,
+) -> Result<BlobVerification, FlymodelError> {
+    let (size, hash) = digest_object(
+        sink_of(bucket, storage)?,
+        blobref.key.clone(),
+        Some(blobref.version_id.clone()),
+    )
+    .await?;
+    if hash != blobref.sha256 {
+        return Err(integrity_error(&blobref.sha256, hash));
+    }
+    if size != blobref.size as u64 {
+        return Err(size_error(blobref.size as u64, size));
+    }
+    Ok(BlobVerification {
+        blob: blobref.id,
+        size: blobref.size,
+        sha256: hash,
+    })
+}
+
+pub(crate) async fn verify_upload(
+    sink: &(dyn StorageProvider + Sync + Send + 'static),
+    key: String,
+    size: u64,
+    sha256: &str,
+) -> Result<String, FlymodelError> {
+    let meta = sink.head(key.clone(), None).await?;
+    let version_id = meta.version_id.ok_or_else(|| {
+        FlymodelError::NonDeterministicError(format!("no object version reported for {key}"))
+    })?;
+    if meta.size != size {
+        return Err(size_error(size, meta.size));
+    }
+    let (_, hash) = digest_object(sink, key, Some(version_id.clone())).await?;
+    if hash != sha256 {
+        return Err(FlymodelError::IntegrityError {
+            kind: "upload checksum".into(),
+            expect: sha256.to_string(),
+            receive: hash,
+        });
+    }
+    Ok(version_id)
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{
+        body::to_bytes,
+        http::{
+            header::{self, EntityTag},
+            StatusCode,
+        },
+        test::TestRequest,
+    };
+    use bytes::Bytes;
+    use flymodel::{
+        errs::FlymodelError,
+        lifecycle::Lifecycle,
+        storage::{ByteRange, ByteStream, StorageProvider},
+    };
+    use flymodel_entities::{entities, testing};
+    use flymodel_registry::memory::StorageOperation;
+    use futures_util::StreamExt;
+
+    use super::{
+        download_with_blob, guarded_upload, not_modified, presign_expiry, requested_range,
+        verified_stream, verify_upload, RequestedRange,
+    };
+    use crate::testing::memory_storage;
 
-    Ok(resp)
+    fn chunks() -> ByteStream {
+        Box::pin(futures_util::stream::iter(vec![
+            Ok(Bytes::from_static(b"hello ")),
+            Ok(Bytes::from_static(b"world")),
+        ]))
+    }
+
+    #[tokio::test]
+    async fn test_guarded_upload_commits() {
+        let (storage, _) = memory_storage("ml-test", Lifecycle::Test);
+        let sink: Box<dyn StorageProvider + Send + Sync> = Box::new(storage.clone());
+        let db = testing::database().await;
+
+        let upload = guarded_upload(sink.as_ref(), chunks(), &db, "a.txt".into(), |_, upload| {
+            Box::pin(async move { Ok(upload) })
+        })
+        .await
+        .unwrap();
+        assert_eq!(upload.size, 11);
+        assert_eq!(upload.sha256, sha256::digest("hello world"));
+        assert_eq!(
+            storage.versions("a.txt".into()),
+            vec![upload.version_id().unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_guarded_upload_rolls_back() {
+        let (storage, _) = memory_storage("ml-test", Lifecycle::Test);
+        let sink: Box<dyn StorageProvider + Send + Sync> = Box::new(storage.clone());
+        let db = testing::database().await;
+
+        let failed = guarded_upload(sink.as_ref(), chunks(), &db, "a.txt".into(), |_, _| {
+            Box::pin(async move { Err::<(), _>(FlymodelError::ContraintError("rejected".into())) })
+        })
+        .await;
+        assert!(matches!(failed, Err(FlymodelError::ContraintError(..))));
+        assert!(storage.versions("a.txt".into()).is_empty());
+
+        storage.faults().fail_next(StorageOperation::Put);
+        let failed = guarded_upload(sink.as_ref(), chunks(), &db, "a.txt".into(), |_, _| {
+            Box::pin(async move { unreachable!("the transaction must not run") as Result<(), _> })
+        })
+        .await;
+        assert!(matches!(failed, Err(FlymodelError::StorageIoError(..))));
+        assert!(storage.versions("a.txt".into()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_download_ranges_and_tampering() {
+        let (storage, orchestrator) = memory_storage("ml-test", Lifecycle::Test);
+        let version_id = storage
+            .put("a.txt".into(), Bytes::from_static(b"hello world"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let bucket = entities::bucket::Model {
+            id: 1,
+            namespace: 1,
+            name: "ml-test".into(),
+            region: "local".into(),
+            role: Lifecycle::Test,
+            created_at: chrono::Utc::now(),
+            last_modified: chrono::Utc::now(),
+        };
+        let mut blob = entities::object_blob::Model {
+            id: 1,
+            bucket_id: 1,
+            key: "a.txt".into(),
+            version_id,
+            size: 11,
+            sha256: sha256::digest("hello world"),
+            encode: None,
+            format: None,
+            created_at: chrono::Utc::now(),
+        };
+
+        let req = TestRequest::default()
+            .insert_header((header::RANGE, "bytes=6-"))
+            .to_http_request();
+        let resp = download_with_blob(&req, &blob, &bucket, &orchestrator, "a.txt".into())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(to_bytes(resp.into_body()).await.unwrap(), "world");
+
+        let req = TestRequest::default().to_http_request();
+        let resp = download_with_blob(&req, &blob, &bucket, &orchestrator, "a.txt".into())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(to_bytes(resp.into_body()).await.unwrap(), "hello world");
+
+        blob.sha256 = sha256::digest("tampered");
+        let resp = download_with_blob(&req, &blob, &bucket, &orchestrator, "a.txt".into())
+            .await
+            .unwrap();
+        assert!(to_bytes(resp.into_body()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_upload() {
+        let (storage, _) = memory_storage("ml-test", Lifecycle::Test);
+        let version_id = storage
+            .put("a.txt".into(), Bytes::from_static(b"hello world"))
+            .await
+            .unwrap()
+            .unwrap();
+        let hash = sha256::digest("hello world");
+
+        let verified = verify_upload(&storage, "a.txt".into(), 11, &hash).await;
+        assert_eq!(verified.unwrap(), version_id);
+
+        let wrong_size = verify_upload(&storage, "a.txt".into(), 12, &hash).await;
+        assert!(matches!(
+            wrong_size,
+            Err(FlymodelError::IntegrityError { .. })
+        ));
+
+        let wrong_hash =
+            verify_upload(&storage, "a.txt".into(), 11, &sha256::digest("hello")).await;
+        assert!(matches!(
+            wrong_hash,
+            Err(FlymodelError::IntegrityError { .. })
+        ));
+
+        let missing = verify_upload(&storage, "b.txt".into(), 11, &hash).await;
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_presign_expiry() {
+        assert_eq!(presign_expiry(None).unwrap().as_secs(), 3600);
+        assert_eq!(presign_expiry(Some(60)).unwrap().as_secs(), 60);
+        assert!(presign_expiry(Some(0)).is_err());
+        assert!(presign_expiry(Some(8 * 24 * 60 * 60)).is_err());
+    }
+
+    fn range_of(value: &str, size: u64) -> RequestedRange {
+        let req = TestRequest::default()
+            .insert_header((header::RANGE, value))
+            .to_http_request();
+        requested_range(&req, size)
+    }
+
+    #[test]
+    fn test_requested_range() {
+        assert!(matches!(
+            range_of("bytes=2-5", 10),
+            RequestedRange::Partial(ByteRange { start: 2, end: 5 })
+        ));
+        assert!(matches!(
+            range_of("bytes=-3", 10),
+            RequestedRange::Partial(ByteRange { start: 7, end: 9 })
+        ));
+        assert!(matches!(
+            range_of("bytes=20-", 10),
+            RequestedRange::Unsatisfiable
+        ));
+        assert!(matches!(
+            range_of("bytes=0-1,4-5", 10),
+            RequestedRange::Full
+        ));
+        assert!(matches!(
+            requested_range(&TestRequest::default().to_http_request(), 10),
+            RequestedRange::Full
+        ));
+    }
+
+    #[test]
+    fn test_not_modified() {
+        let etag = EntityTag::new_strong("abc".into());
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, r#""xyz", "abc""#))
+            .to_http_request();
+        assert!(not_modified(&req, &etag));
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, r#""xyz""#))
+            .to_http_request();
+        assert!(!not_modified(&req, &etag));
+    }
+
+    #[tokio::test]
+    async fn test_verified_stream() {
+        let ok: Vec<_> = verified_stream(chunks(), sha256::digest("hello world"))
+            .collect()
+            .await;
+        assert!(ok.iter().all(Result::is_ok));
+
+        let tampered: Vec<_> = verified_stream(chunks(), sha256::digest("hello there"))
+            .collect()
+            .await;
+        assert!(tampered.last().unwrap().is_err());
+    }
 }
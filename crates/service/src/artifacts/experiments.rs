@@ -1,5 +1,10 @@
 use crate::{
-    artifacts::{download_with_blob, guarded_upload, read_bts, DownloadParams},
+    artifacts::{
+        download_with_blob, guarded_upload, sink_of, stream_file, verify_blob, ArtifactLoaders,
+        ArtifactRequest, DownloadParams,
+    },
+    audit::{summary, AuditAction, AuditEvent, ResourceKind},
+    events::{EventBus, RegistryEvent},
     params_for,
 };
 use actix_web::{
@@ -10,32 +15,34 @@ use actix_web::{
 
 use async_graphql::dataloader::{DataLoader, Loader};
 
-use flymodel::errs::FlymodelError;
+use flymodel::{
+    errs::FlymodelError,
+    perms::{Perm, Principal},
+};
 use flymodel_entities::{
     db::DbLoader,
     entities::{self},
 };
-use flymodel_registry::storage::StorageOrchestrator;
 use serde::Deserialize;
-use std::sync::Arc;
 use tracing::debug;
 
 use actix_multipart::form::{self, tempfile::TempFile, MultipartForm};
 
 params_for!(Experiment, [(experiment: i64)]);
 
-struct CommonExperimentCte {
-    experiment: entities::experiment::Model,
-    model_version: entities::model_version::Model,
-    bucket: entities::bucket::Model,
+pub(crate) struct CommonExperimentCte {
+    pub(crate) namespace: entities::namespace::Model,
+    pub(crate) experiment: entities::experiment::Model,
+    pub(crate) model_version: entities::model_version::Model,
+    pub(crate) bucket: entities::bucket::Model,
 }
 
-async fn get_common_from_experiment<FM: Fn() -> FlymodelError + Copy>(
+pub(crate) async fn get_common_from_experiment<FM: Fn() -> FlymodelError + Copy>(
     experiment_id: i64,
-    experiment: Data<DataLoader<DbLoader<entities::experiment::Model>>>,
-    namespaces: Data<DataLoader<DbLoader<entities::namespace::Model>>>,
-    versions: Data<DataLoader<DbLoader<entities::model_version::Model>>>,
-    buckets: Data<DataLoader<DbLoader<entities::bucket::Model>>>,
+    experiment: &DataLoader<DbLoader<entities::experiment::Model>>,
+    namespaces: &DataLoader<DbLoader<entities::namespace::Model>>,
+    versions: &DataLoader<DbLoader<entities::model_version::Model>>,
+    buckets: &DataLoader<DbLoader<entities::bucket::Model>>,
     on_missing: FM,
 ) -> Result<CommonExperimentCte, FlymodelError> {
     let (model_version, experiment) = experiment
@@ -47,7 +54,7 @@ async fn get_common_from_experiment<FM: Fn() -> FlymodelError + Copy>(
     let state = versions.loader().state(&model_version).await?.expect("ok");
 
     let namespace =
-        DbLoader::<entities::namespace::Model>::namespace_of_model(&namespaces, &model_version)
+        DbLoader::<entities::namespace::Model>::namespace_of_model(namespaces, &model_version)
             .await?
             .ok_or_else(on_missing)?;
 
@@ -57,11 +64,10 @@ async fn get_common_from_experiment<FM: Fn() -> FlymodelError + Copy>(
         .await?;
 
     Ok(CommonExperimentCte {
+        namespace,
         experiment,
         model_version,
         bucket,
-        // namespace,
-        // state,
     })
 }
 
@@ -69,38 +75,34 @@ async fn get_common_from_experiment<FM: Fn() -> FlymodelError + Copy>(
 #[post("/upload/experiment-artifact")]
 pub async fn upload_experiment_artifact(
     MultipartForm(form): MultipartForm<UploadExperimentArtifact>,
-
-    storage: Data<Arc<StorageOrchestrator>>,
-    namespaces: Data<DataLoader<DbLoader<entities::namespace::Model>>>,
-    experiment: Data<DataLoader<DbLoader<entities::experiment::Model>>>,
-    buckets: Data<DataLoader<DbLoader<entities::bucket::Model>>>,
-    versions: Data<DataLoader<DbLoader<entities::model_version::Model>>>,
-    blobs: Data<DataLoader<DbLoader<entities::object_blob::Model>>>,
+    artifact: ArtifactRequest,
+    events: Data<EventBus>,
 ) -> actix_web::Result<impl Responder> {
+    let ArtifactRequest {
+        principal,
+        storage,
+        audit,
+        ..
+    } = &artifact;
+    let loaders = artifact.loaders();
     let data = form.artifact;
     let on_missing = || FlymodelError::InvalidResourceId(data.experiment);
     let cte = get_common_from_experiment(
         data.experiment,
-        experiment,
-        namespaces,
-        versions,
-        buckets,
+        loaders.experiments,
+        loaders.namespaces,
+        loaders.versions,
+        loaders.buckets,
         on_missing,
     )
     .await?;
 
-    let sink = storage
-        .get(&cte.bucket.name)
-        .ok_or(FlymodelError::RuntimeDependencyError(format!(
-            "missing {} bucket configurations",
-            cte.bucket.name
-        )))?;
+    principal.authorize_model(cte.namespace.id, cte.model_version.model_id, Perm::W)?;
 
-    let bs = read_bts(form.file)?;
-    let sz = bs.len();
-    debug!("upload size: {}", sz);
+    let sink = sink_of(&cte.bucket, storage)?;
 
-    let hash = sha256::digest(&*bs);
+    debug!("upload size: {}", form.file.size);
+    let stream = stream_file(form.file)?;
 
     let key = format!(
         "experiments/{id}/{artifact}",
@@ -110,19 +112,24 @@ pub async fn upload_experiment_artifact(
 
     let created = guarded_upload(
         sink,
-        bs,
-        &blobs.loader().db,
+        stream,
+        &loaders.blobs.loader().db,
         key.clone(),
-        |tx, version_id| {
+        |tx, upload| {
             Box::pin(async move {
+                let version_id = upload.version_id().ok_or_else(|| {
+                    FlymodelError::NonDeterministicError(format!(
+                        "storage returned no version id for {key}"
+                    ))
+                })?;
                 let blob = DbLoader::<entities::object_blob::Model>::create_new_blob(
                     tx,
                     cte.bucket.id,
                     key,
-                    version_id.expect("version id"),
+                    version_id,
                     &data.blob,
-                    sz as i64,
-                    hash,
+                    upload.size as i64,
+                    upload.sha256,
                 )
                 .await?;
 
@@ -141,31 +148,53 @@ pub async fn upload_experiment_artifact(
     )
     .await?;
 
+    audit
+        .emit(
+            AuditEvent::new(
+                AuditAction::Upload,
+                ResourceKind::ExperimentArtifact,
+                created.id,
+            )
+            .by(&principal.subject)
+            .after(&summary(&created)),
+        )
+        .await;
+    events
+        .publish(RegistryEvent::ArtifactUploaded((&created).into()))
+        .await;
     Ok(web::Json(created))
 }
 
-#[routes]
-#[get("/download/experiment-artifact")]
-pub async fn download_experiment_artifact(
-    storage: Data<Arc<StorageOrchestrator>>,
-    params: Query<DownloadParams>,
-    artifact: Data<DataLoader<DbLoader<entities::experiment_artifact::Model>>>,
-    experiment: Data<DataLoader<DbLoader<entities::experiment::Model>>>,
-    namespaces: Data<DataLoader<DbLoader<entities::namespace::Model>>>,
-    versions: Data<DataLoader<DbLoader<entities::model_version::Model>>>,
-    buckets: Data<DataLoader<DbLoader<entities::bucket::Model>>>,
-    blobs: Data<DataLoader<DbLoader<entities::object_blob::Model>>>,
-) -> actix_web::Result<impl Responder> {
-    let on_missing = || FlymodelError::InvalidResourceId(params.artifact_id);
+pub(crate) struct ArtifactBlob {
+    pub(crate) artifact: entities::experiment_artifact::Model,
+    pub(crate) blob: entities::object_blob::Model,
+    pub(crate) bucket: entities::bucket::Model,
+}
+
+pub(crate) async fn get_artifact_blob(
+    artifact_id: i64,
+    principal: &Principal,
+    loaders: &ArtifactLoaders<'_>,
+) -> Result<ArtifactBlob, FlymodelError> {
+    let ArtifactLoaders {
+        namespaces,
+        versions,
+        experiments: experiment,
+        experiment_artifacts: artifact,
+        buckets,
+        blobs,
+        ..
+    } = *loaders;
+    let on_missing = || FlymodelError::InvalidResourceId(artifact_id);
     let on_err = |err| FlymodelError::DbLoaderError(err);
 
     let artifact = artifact
         .loader()
-        .load(&[params.artifact_id])
+        .load(&[artifact_id])
         .await
         .map_err(on_err)?;
 
-    let artifact = artifact.get(&params.artifact_id).ok_or_else(on_missing)?;
+    let artifact = artifact.get(&artifact_id).ok_or_else(on_missing)?;
 
     let cte = get_common_from_experiment(
         artifact.experiment_id,
@@ -177,19 +206,86 @@ pub async fn download_experiment_artifact(
     )
     .await?;
 
+    principal.authorize_model(cte.namespace.id, cte.model_version.model_id, Perm::R)?;
+
     let blobref = blobs
         .loader()
         .load(&[artifact.blob])
         .await
         .map_err(on_err)?;
 
-    let blobref = blobref.get(&artifact.blob).ok_or_else(on_missing)?;
+    let blob = blobref.get(&artifact.blob).ok_or_else(on_missing)?;
+
+    // the blob may still sit in the bucket of an earlier lifecycle while it is relocated
+    let bucket = if blob.bucket_id == cte.bucket.id {
+        cte.bucket
+    } else {
+        buckets
+            .load_one(blob.bucket_id)
+            .await
+            .map_err(FlymodelError::DbLoaderError)?
+            .ok_or_else(on_missing)?
+    };
 
-    Ok(download_with_blob(
-        blobref,
-        &cte.bucket,
-        storage.as_ref(),
-        artifact.name.clone(),
+    Ok(ArtifactBlob {
+        artifact: artifact.clone(),
+        blob: blob.clone(),
+        bucket,
+    })
+}
+
+#[routes]
+#[get("/download/experiment-artifact")]
+pub async fn download_experiment_artifact(
+    params: Query<DownloadParams>,
+    artifact: ArtifactRequest,
+) -> actix_web::Result<impl Responder> {
+    let found =
+        get_artifact_blob(params.artifact_id, &artifact.principal, &artifact.loaders()).await?;
+
+    let download = download_with_blob(
+        &artifact.req,
+        &found.blob,
+        &found.bucket,
+        artifact.storage.as_ref(),
+        found.artifact.name,
     )
-    .await?)
+    .await?;
+    artifact
+        .audit
+        .emit(
+            AuditEvent::new(
+                AuditAction::Download,
+                ResourceKind::ExperimentArtifact,
+                found.artifact.id,
+            )
+            .by(&artifact.principal.subject),
+        )
+        .await;
+    Ok(download)
+}
+
+#[routes]
+#[get("/verify/experiment-artifact")]
+pub async fn verify_experiment_artifact(
+    params: Query<DownloadParams>,
+    artifact: ArtifactRequest,
+) -> actix_web::Result<impl Responder> {
+    let found =
+        get_artifact_blob(params.artifact_id, &artifact.principal, &artifact.loaders()).await?;
+
+    let verified = verify_blob(&found.blob, &found.bucket, artifact.storage.as_ref()).await?;
+    artifact
+        .audit
+        .emit(
+            AuditEvent::new(
+                AuditAction::Verify,
+                ResourceKind::ExperimentArtifact,
+                found.artifact.id,
+            )
+            .by(&artifact.principal.subject)
+            .after(&summary(&verified)),
+        )
+        .await;
+    Ok(web::Json(verified))
 }
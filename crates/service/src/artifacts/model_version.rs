@@ -1,7 +1,12 @@
 use std::sync::Arc;
 
 use crate::{
-    artifacts::{download_with_blob, guarded_upload, read_bts, DownloadParams},
+    artifacts::{
+        download_with_blob, guarded_upload, sink_of, stream_file, verify_blob, ArtifactLoaders,
+        ArtifactRequest, DownloadParams,
+    },
+    audit::{summary, AuditAction, AuditEvent, ResourceKind},
+    events::{EventBus, RegistryEvent},
     params_for,
 };
 use actix_web::{
@@ -11,13 +16,15 @@ use actix_web::{
 };
 
 use async_graphql::dataloader::{DataLoader, Loader};
-use flymodel::errs::FlymodelError;
+use flymodel::{
+    errs::FlymodelError,
+    perms::{Perm, Principal},
+};
 use flymodel_entities::{
     db::DbLoader,
     entities::{self},
 };
 
-use flymodel_registry::storage::StorageOrchestrator;
 use sea_orm::{DbErr, EntityTrait};
 use serde::Deserialize;
 
@@ -26,19 +33,20 @@ use actix_multipart::form::{self, tempfile::TempFile, MultipartForm};
 params_for!(ModelVersion, [(model_version: i64), (extra: Option<serde_json::Value>)]);
 
 #[derive(Clone, Debug)]
-struct CommonModelCte {
-    model_version: entities::model_version::Model,
-    bucket: entities::bucket::Model,
+pub(crate) struct CommonModelCte {
+    pub(crate) model: entities::model::Model,
+    pub(crate) model_version: entities::model_version::Model,
+    pub(crate) bucket: entities::bucket::Model,
 }
 
-async fn get_common_from_model_version<
+pub(crate) async fn get_common_from_model_version<
     FM: Fn() -> FlymodelError + Copy,
     FE: Fn(DbErr) -> FlymodelError + Copy,
 >(
     model_version_id: i64,
-    namespaces: Data<DataLoader<DbLoader<entities::namespace::Model>>>,
-    versions: Data<DataLoader<DbLoader<entities::model_version::Model>>>,
-    buckets: Data<DataLoader<DbLoader<entities::bucket::Model>>>,
+    namespaces: &DataLoader<DbLoader<entities::namespace::Model>>,
+    versions: &DataLoader<DbLoader<entities::model_version::Model>>,
+    buckets: &DataLoader<DbLoader<entities::bucket::Model>>,
     on_missing: FM,
     on_error: FE,
 ) -> Result<CommonModelCte, FlymodelError> {
@@ -50,7 +58,7 @@ async fn get_common_from_model_version<
 
     let state = versions.loader().state(&model_version).await?.expect("ok");
 
-    let (_model, namespace) = entities::model::Entity::find_by_id(model_version.model_id)
+    let (model, namespace) = entities::model::Entity::find_by_id(model_version.model_id)
         .find_also_related(entities::namespace::Entity)
         .one(&namespaces.loader().db)
         .await
@@ -65,7 +73,7 @@ async fn get_common_from_model_version<
         .await?;
 
     Ok(CommonModelCte {
-        // model,
+        model,
         model_version,
         bucket,
     })
@@ -75,36 +83,34 @@ async fn get_common_from_model_version<
 #[post("/upload/model-version-artifact")]
 pub async fn upload_model_version_artifact(
     MultipartForm(form): MultipartForm<UploadModelVersionArtifact>,
-
-    storage: Data<Arc<StorageOrchestrator>>,
-    namespaces: Data<DataLoader<DbLoader<entities::namespace::Model>>>,
-    versions: Data<DataLoader<DbLoader<entities::model_version::Model>>>,
-    buckets: Data<DataLoader<DbLoader<entities::bucket::Model>>>,
-    blobs: Data<DataLoader<DbLoader<entities::object_blob::Model>>>,
+    artifact: ArtifactRequest,
+    events: Data<EventBus>,
 ) -> actix_web::Result<impl Responder> {
+    let ArtifactRequest {
+        principal,
+        storage,
+        audit,
+        ..
+    } = &artifact;
+    let loaders = artifact.loaders();
     let data = form.artifact;
     let on_err = |err| FlymodelError::DbLoaderError(Arc::new(err));
     let on_missing = || FlymodelError::InvalidResourceId(data.model_version);
     let cte = get_common_from_model_version(
         data.model_version,
-        namespaces,
-        versions,
-        buckets,
+        loaders.namespaces,
+        loaders.versions,
+        loaders.buckets,
         on_missing,
         on_err,
     )
     .await?;
 
-    let sink = storage
-        .get(&cte.bucket.name)
-        .ok_or(FlymodelError::RuntimeDependencyError(format!(
-            "missing {} bucket configurations",
-            cte.bucket.name
-        )))?;
+    principal.authorize_model(cte.model.namespace_id, cte.model.id, Perm::W)?;
 
-    let bs = read_bts(form.file)?;
-    let sz = bs.len();
-    let hash = sha256::digest(&*bs);
+    let sink = sink_of(&cte.bucket, storage)?;
+
+    let stream = stream_file(form.file)?;
 
     let key = format!(
         "model_versions/{id}/{artifact}",
@@ -114,20 +120,25 @@ pub async fn upload_model_version_artifact(
 
     let created = guarded_upload(
         sink,
-        bs,
-        &blobs.loader().db,
+        stream,
+        &loaders.blobs.loader().db,
         key.clone(),
-        |tx, version_id| {
+        |tx, upload| {
             Box::pin(async move {
                 let tx = tx;
+                let version_id = upload.version_id().ok_or_else(|| {
+                    FlymodelError::NonDeterministicError(format!(
+                        "storage returned no version id for {key}"
+                    ))
+                })?;
                 let blob = DbLoader::<entities::object_blob::Model>::create_new_blob(
                     tx,
                     cte.bucket.id,
                     key,
-                    version_id.expect("version id"),
+                    version_id,
                     &data.blob,
-                    sz as i64,
-                    hash,
+                    upload.size as i64,
+                    upload.sha256,
                 )
                 .await?;
                 Ok(
@@ -145,31 +156,48 @@ pub async fn upload_model_version_artifact(
     )
     .await?;
 
+    audit
+        .emit(
+            AuditEvent::new(AuditAction::Upload, ResourceKind::ModelArtifact, created.id)
+                .by(&principal.subject)
+                .after(&summary(&created)),
+        )
+        .await;
+    events
+        .publish(RegistryEvent::ArtifactUploaded((&created).into()))
+        .await;
     Ok(Json(created))
 }
 
-#[routes]
-#[get("/download/model-version-artifact")]
-pub async fn download_model_version_artifact(
-    storage: Data<Arc<StorageOrchestrator>>,
-    params: Query<DownloadParams>,
+pub(crate) struct ArtifactBlob {
+    pub(crate) artifact: entities::model_artifact::Model,
+    pub(crate) blob: entities::object_blob::Model,
+    pub(crate) bucket: entities::bucket::Model,
+}
 
-    namespaces: Data<DataLoader<DbLoader<entities::namespace::Model>>>,
-    versions: Data<DataLoader<DbLoader<entities::model_version::Model>>>,
-    artifact: Data<DataLoader<DbLoader<entities::model_artifact::Model>>>,
-    buckets: Data<DataLoader<DbLoader<entities::bucket::Model>>>,
-    blobs: Data<DataLoader<DbLoader<entities::object_blob::Model>>>,
-) -> actix_web::Result<impl Responder> {
+pub(crate) async fn get_artifact_blob(
+    artifact_id: i64,
+    principal: &Principal,
+    loaders: &ArtifactLoaders<'_>,
+) -> Result<ArtifactBlob, FlymodelError> {
+    let ArtifactLoaders {
+        namespaces,
+        versions,
+        model_artifacts: artifact,
+        buckets,
+        blobs,
+        ..
+    } = *loaders;
     let on_err = |err| FlymodelError::DbLoaderError(Arc::new(err));
-    let on_missing = || FlymodelError::InvalidResourceId(params.artifact_id);
+    let on_missing = || FlymodelError::InvalidResourceId(artifact_id);
 
     let artifact = artifact
         .loader()
-        .load(&[params.artifact_id])
+        .load(&[artifact_id])
         .await
         .map_err(|err| FlymodelError::DbLoaderError(err))?;
 
-    let artifact = artifact.get(&params.artifact_id).ok_or_else(on_missing)?;
+    let artifact = artifact.get(&artifact_id).ok_or_else(on_missing)?;
 
     let cte = get_common_from_model_version(
         artifact.version_id,
@@ -181,19 +209,86 @@ pub async fn download_model_version_artifact(
     )
     .await?;
 
+    principal.authorize_model(cte.model.namespace_id, cte.model.id, Perm::R)?;
+
     let blobref = blobs
         .loader()
         .load(&[artifact.blob])
         .await
         .map_err(|err| FlymodelError::DbLoaderError(err))?;
 
-    let blobref = blobref.get(&artifact.blob).ok_or_else(on_missing)?;
+    let blob = blobref.get(&artifact.blob).ok_or_else(on_missing)?;
+
+    // the blob may still sit in the bucket of an earlier lifecycle while it is relocated
+    let bucket = if blob.bucket_id == cte.bucket.id {
+        cte.bucket
+    } else {
+        buckets
+            .load_one(blob.bucket_id)
+            .await
+            .map_err(FlymodelError::DbLoaderError)?
+            .ok_or_else(on_missing)?
+    };
 
-    Ok(download_with_blob(
-        blobref,
-        &cte.bucket,
-        storage.as_ref(),
-        artifact.name.clone(),
+    Ok(ArtifactBlob {
+        artifact: artifact.clone(),
+        blob: blob.clone(),
+        bucket,
+    })
+}
+
+#[routes]
+#[get("/download/model-version-artifact")]
+pub async fn download_model_version_artifact(
+    params: Query<DownloadParams>,
+    artifact: ArtifactRequest,
+) -> actix_web::Result<impl Responder> {
+    let found =
+        get_artifact_blob(params.artifact_id, &artifact.principal, &artifact.loaders()).await?;
+
+    let download = download_with_blob(
+        &artifact.req,
+        &found.blob,
+        &found.bucket,
+        artifact.storage.as_ref(),
+        found.artifact.name,
     )
-    .await?)
+    .await?;
+    artifact
+        .audit
+        .emit(
+            AuditEvent::new(
+                AuditAction::Download,
+                ResourceKind::ModelArtifact,
+                found.artifact.id,
+            )
+            .by(&artifact.principal.subject),
+        )
+        .await;
+    Ok(download)
+}
+
+#[routes]
+#[get("/verify/model-version-artifact")]
+pub async fn verify_model_version_artifact(
+    params: Query<DownloadParams>,
+    artifact: ArtifactRequest,
+) -> actix_web::Result<impl Responder> {
+    let found =
+        get_artifact_blob(params.artifact_id, &artifact.principal, &artifact.loaders()).await?;
+
+    let verified = verify_blob(&found.blob, &found.bucket, artifact.storage.as_ref()).await?;
+    artifact
+        .audit
+        .emit(
+            AuditEvent::new(
+                AuditAction::Verify,
+                ResourceKind::ModelArtifact,
+                found.artifact.id,
+            )
+            .by(&artifact.principal.subject)
+            .after(&summary(&verified)),
+        )
+        .await;
+    Ok(Json(verified))
 }
@@ -0,0 +1,269 @@
+use std::{sync::Arc, time::Duration};
+
+use async_graphql::Context;
+use flymodel::{config::relocation::RelocationConfiguration, errs::FlymodelError};
+use flymodel_entities::{db::DbLoader, entities};
+use flymodel_registry::storage::StorageOrchestrator;
+use sea_orm::{DbConn, EntityTrait, TransactionTrait};
+use tracing::{debug, info, warn};
+
+use super::sink_of;
+
+/// the copy is made before the blob is claimed to keep the transaction short,
+/// and is deleted again when another relocation claimed the blob meanwhile
+async fn relocate_blob(
+    db: &DbConn,
+    storage: &StorageOrchestrator,
+    version_id: i64,
+    blob: entities::object_blob::Model,
+    to: &entities::bucket::Model,
+) -> Result<bool, FlymodelError> {
+    let from = entities::bucket::Entity::find_by_id(blob.bucket_id)
+        .one(db)
+        .await?
+        .ok_or(FlymodelError::InvalidResourceId(blob.bucket_id))?;
+    let source = sink_of(&from, storage)?;
+    let sink = sink_of(to, storage)?;
+
+    let copied = sink
+        .copy_from(
+            source,
+            blob.key.clone(),
+            Some(blob.version_id.clone()),
+            blob.key.clone(),
+        )
+        .await?;
+    let recorded = async {
+        if copied.size != blob.size as u64 {
+            return Err(FlymodelError::IntegrityError {
+                kind: "artifact relocation".into(),
+                expect: blob.size.to_string(),
+                receive: copied.size.to_string(),
+            });
+        }
+        let copy_version = copied.version_id.clone().ok_or_else(|| {
+            FlymodelError::NonDeterministicError(format!("{} did not version the copy", to.name))
+        })?;
+        let tx = db.begin().await?;
+        if !DbLoader::<entities::object_blob::Model>::claim(&tx, blob.id, version_id).await? {
+            return Ok(false);
+        }
+        DbLoader::<entities::object_blob::Model>::relocate(
+            &tx,
+            &blob,
+            to.id,
+            copy_version,
+            version_id,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+    .await;
+    if !matches!(recorded, Ok(true)) {
+        if let Err(del) = sink.del(blob.key.clone(), copied.version_id).await {
+            warn!("failed to remove the copy of blob {}: {del}", blob.id);
+        }
+    }
+    recorded
+}
+
+/// copies the blobs of a version into the bucket of its lifecycle, leaving the previous copies in place
+pub async fn relocate_version(
+    db: &DbConn,
+    storage: &StorageOrchestrator,
+    version_id: i64,
+) -> Result<u64, FlymodelError> {
+    let Some(bucket) = DbLoader::<entities::bucket::Model>::of_version(db, version_id).await?
+    else {
+        debug!("version {version_id} has no bucket for its lifecycle");
+        return Ok(0);
+    };
+    let mut copied = 0;
+    for blob in
+        DbLoader::<entities::object_blob::Model>::misplaced(db, version_id, bucket.id).await?
+    {
+        if relocate_blob(db, storage, version_id, blob, &bucket).await? {
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+/// relocates every version left with artifacts outside the bucket of its lifecycle
+pub async fn sweep_relocations(
+    db: &DbConn,
+    storage: &StorageOrchestrator,
+) -> Result<u64, FlymodelError> {
+    let mut copied = 0;
+    for version_id in DbLoader::<entities::object_blob::Model>::misplaced_versions(db).await? {
+        match relocate_version(db, storage, version_id).await {
+            Ok(count) => copied += count,
+            Err(err) => warn!("failed to relocate the artifacts of version {version_id}: {err}"),
+        }
+    }
+    Ok(copied)
+}
+
+pub fn spawn_relocation_sweeper(
+    db: DbConn,
+    storage: Arc<StorageOrchestrator>,
+    conf: RelocationConfiguration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(Duration::from_secs(conf.interval));
+        loop {
+            ticks.tick().await;
+            match sweep_relocations(&db, &storage).await {
+                Ok(0) => {}
+                Ok(copied) => info!("relocated {copied} artifact blobs"),
+                Err(err) => warn!("relocation sweep failed: {err}"),
+            }
+        }
+    })
+}
+
+pub(crate) fn spawn_relocation(ctx: &Context<'_>, version_id: i64) {
+    let Some(storage) = ctx.data_opt::<Arc<StorageOrchestrator>>().cloned() else {
+        return;
+    };
+    let Ok(blobs) = DbLoader::<entities::object_blob::Model>::with_context(ctx) else {
+        return;
+    };
+    let db = blobs.loader().db.clone();
+    tokio::spawn(async move {
+        match relocate_version(&db, &storage, version_id).await {
+            Ok(0) => {}
+            Ok(copied) => info!("relocated {copied} artifact blobs of version {version_id}"),
+            Err(err) => {
+                warn!("failed to relocate the artifacts of version {version_id}, it will be retried: {err}")
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use flymodel::lifecycle::Lifecycle;
+    use flymodel_entities::{entities, testing};
+    use flymodel_registry::memory::StorageOperation;
+    use sea_orm::{DbConn, EntityTrait};
+
+    use super::{relocate_blob, relocate_version, sweep_relocations};
+    use crate::testing::{memory_storages, stored_blob};
+
+    async fn buckets_of(db: &DbConn) -> Vec<(i64, i64)> {
+        let mut artifacts = entities::model_artifact::Entity::find()
+            .find_also_related(entities::object_blob::Entity)
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(artifact, blob)| (artifact.id, blob.unwrap().bucket_id))
+            .collect::<Vec<_>>();
+        artifacts.sort();
+        artifacts
+    }
+
+    #[tokio::test]
+    async fn test_relocation_resumes() {
+        let db = testing::database().await;
+        let (storages, orchestrator) =
+            memory_storages(&[("ml-test", Lifecycle::Test), ("ml-qa", Lifecycle::Qa)]);
+        let (test, qa) = (&storages[0], &storages[1]);
+        let namespace = testing::namespace(&db, "ns").await;
+        let test_bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+        let qa_bucket = testing::bucket(&db, namespace.id, "ml-qa", Lifecycle::Qa).await;
+        let model = testing::model(&db, namespace.id, "model").await;
+        let version = testing::version(&db, model.id, "1", Lifecycle::Qa, Utc::now()).await;
+        let weights = stored_blob(&db, test, test_bucket.id, "weights", Utc::now()).await;
+        let weights_artifact =
+            testing::model_artifact(&db, version.id, weights.id, "weights").await;
+        let config = stored_blob(&db, test, test_bucket.id, "config", Utc::now()).await;
+        let config_artifact = testing::model_artifact(&db, version.id, config.id, "config").await;
+
+        // a failed copy records nothing & leaves nothing behind
+        qa.faults().fail_next(StorageOperation::Put);
+        assert!(relocate_version(&db, &orchestrator, version.id)
+            .await
+            .is_err());
+        assert_eq!(
+            buckets_of(&db).await,
+            vec![
+                (weights_artifact.id, test_bucket.id),
+                (config_artifact.id, test_bucket.id)
+            ]
+        );
+        assert!(qa.versions("weights".into()).is_empty());
+        assert!(qa.versions("config".into()).is_empty());
+
+        // a relocation interrupted after its first blob resumes from the second
+        assert!(
+            relocate_blob(&db, &orchestrator, version.id, weights.clone(), &qa_bucket)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            relocate_version(&db, &orchestrator, version.id)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            buckets_of(&db).await,
+            vec![
+                (weights_artifact.id, qa_bucket.id),
+                (config_artifact.id, qa_bucket.id)
+            ]
+        );
+        assert_eq!(qa.versions("weights".into()).len(), 1);
+        assert_eq!(qa.versions("config".into()).len(), 1);
+        assert_eq!(
+            relocate_version(&db, &orchestrator, version.id)
+                .await
+                .unwrap(),
+            0
+        );
+
+        // a relocation which lost the blob to another deletes its copy
+        assert!(
+            !relocate_blob(&db, &orchestrator, version.id, weights, &qa_bucket)
+                .await
+                .unwrap()
+        );
+        assert_eq!(qa.versions("weights".into()).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_relocation_leaves_shared_blob() {
+        let db = testing::database().await;
+        let (storages, orchestrator) =
+            memory_storages(&[("ml-test", Lifecycle::Test), ("ml-prod", Lifecycle::Prod)]);
+        let (test, prod) = (&storages[0], &storages[1]);
+        let namespace = testing::namespace(&db, "ns").await;
+        let test_bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+        let prod_bucket = testing::bucket(&db, namespace.id, "ml-prod", Lifecycle::Prod).await;
+        let model = testing::model(&db, namespace.id, "model").await;
+        let tested = testing::version(&db, model.id, "1", Lifecycle::Test, Utc::now()).await;
+        let released = testing::version(&db, model.id, "2", Lifecycle::Prod, Utc::now()).await;
+        let weights = stored_blob(&db, test, test_bucket.id, "weights", Utc::now()).await;
+        let tested_artifact = testing::model_artifact(&db, tested.id, weights.id, "weights").await;
+        let released_artifact =
+            testing::model_artifact(&db, released.id, weights.id, "weights").await;
+
+        // only the artifact of the promoted version moves
+        assert_eq!(sweep_relocations(&db, &orchestrator).await.unwrap(), 1);
+        assert_eq!(
+            buckets_of(&db).await,
+            vec![
+                (tested_artifact.id, test_bucket.id),
+                (released_artifact.id, prod_bucket.id)
+            ]
+        );
+
+        assert_eq!(sweep_relocations(&db, &orchestrator).await.unwrap(), 0);
+        assert_eq!(test.versions("weights".into()).len(), 1);
+        assert_eq!(prod.versions("weights".into()).len(), 1);
+    }
+}
@@ -0,0 +1,964 @@
+use std::{
+    future::{ready, Ready},
+    sync::Arc,
+    time::Duration,
+};
+
+use actix_web::{
+    dev::Payload,
+    http::header::HeaderMap,
+    routes,
+    web::{Bytes, Data, Json, Path},
+    FromRequest, HttpRequest, HttpResponse, Responder,
+};
+use async_graphql::dataloader::DataLoader;
+use chrono::Utc;
+use flymodel::{
+    config::uploads::UploadConfiguration,
+    errs::FlymodelError,
+    perms::{Perm, Principal},
+    storage::ByteStream,
+};
+use flymodel_entities::{
+    db::{Database, DbLoader},
+    entities::{
+        self, enums::UploadTicketStatus, upload::UploadBlobRequestParams,
+        upload_ticket::NewUploadTicket,
+    },
+};
+use flymodel_events::AuditPublisher;
+use flymodel_registry::storage::StorageOrchestrator;
+use futures_util::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::{
+    audit::{summary, uploaded, AuditAction, AuditEvent, ResourceKind},
+    auth::Authenticated,
+    events::{EventBus, RegistryEvent},
+};
+
+use super::{
+    app_data, guarded_upload, sink_of,
+    tickets::{declared_sha256, register_artifact, target_location, target_owner, upload_target},
+};
+
+const MAX_CHUNKS: i64 = 10_000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateResumableUpload {
+    pub model_version: Option<i64>,
+    pub experiment: Option<i64>,
+    #[serde(flatten)]
+    pub blob: UploadBlobRequestParams,
+    pub extra: Option<serde_json::Value>,
+    pub size: i64,
+    pub sha256: String,
+    pub chunk_size: Option<i64>,
+}
+
+pub struct ResumableUploads {
+    conf: Data<UploadConfiguration>,
+    storage: Data<Arc<StorageOrchestrator>>,
+    namespaces: Data<DataLoader<DbLoader<entities::namespace::Model>>>,
+    versions: Data<DataLoader<DbLoader<entities::model_version::Model>>>,
+    experiments: Data<DataLoader<DbLoader<entities::experiment::Model>>>,
+    buckets: Data<DataLoader<DbLoader<entities::bucket::Model>>>,
+    tickets: Data<DataLoader<DbLoader<entities::upload_ticket::Model>>>,
+    parts: Data<DataLoader<DbLoader<entities::upload_ticket_part::Model>>>,
+    events: Data<EventBus>,
+    audit: Data<AuditPublisher>,
+}
+
+impl ResumableUploads {
+    fn extract(req: &HttpRequest) -> Result<Self, FlymodelError> {
+        Ok(Self {
+            conf: app_data(req)?,
+            storage: app_data(req)?,
+            namespaces: app_data(req)?,
+            versions: app_data(req)?,
+            experiments: app_data(req)?,
+            buckets: app_data(req)?,
+            tickets: app_data(req)?,
+            parts: app_data(req)?,
+            events: app_data(req)?,
+            audit: app_data(req)?,
+        })
+    }
+}
+
+impl FromRequest for ResumableUploads {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::extract(req).map_err(Into::into))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResumableUpload {
+    pub ticket: entities::upload_ticket::Model,
+    pub chunks: i64,
+    pub received: Vec<i32>,
+}
+
+pub(crate) fn part_key(ticket: i64, part_number: i32) -> String {
+    format!("uploads/{ticket}/{part_number:05}")
+}
+
+fn ticket_ttl(conf: &UploadConfiguration) -> Result<chrono::Duration, FlymodelError> {
+    chrono::Duration::from_std(Duration::from_secs(conf.ticket_ttl))
+        .map_err(FlymodelError::internal_error)
+}
+
+fn declared_digest(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("Digest")?
+        .to_str()
+        .ok()?
+        .strip_prefix("sha256=")
+        .map(str::to_ascii_lowercase)
+}
+
+async fn load_resumable(
+    id: i64,
+    principal: &Principal,
+    tickets: &DataLoader<DbLoader<entities::upload_ticket::Model>>,
+    versions: &DataLoader<DbLoader<entities::model_version::Model>>,
+    experiments: &DataLoader<DbLoader<entities::experiment::Model>>,
+) -> Result<entities::upload_ticket::Model, FlymodelError> {
+    let ticket = tickets
+        .load_one(id)
+        .await?
+        .filter(|ticket| ticket.chunk_size.is_some())
+        .ok_or(FlymodelError::InvalidResourceId(id))?;
+    let (namespace, model) = target_owner(ticket.target()?, versions, experiments).await?;
+    principal.authorize_model(namespace, model, Perm::W)?;
+    Ok(ticket)
+}
+
+async fn ticket_bucket(
+    ticket: &entities::upload_ticket::Model,
+    buckets: &DataLoader<DbLoader<entities::bucket::Model>>,
+) -> Result<entities::bucket::Model, FlymodelError> {
+    buckets
+        .load_one(ticket.bucket_id)
+        .await?
+        .ok_or(FlymodelError::InvalidResourceId(ticket.bucket_id))
+}
+
+fn concat_parts(
+    storage: Arc<StorageOrchestrator>,
+    bucket: entities::bucket::Model,
+    parts: Vec<entities::upload_ticket_part::Model>,
+) -> ByteStream {
+    Box::pin(
+        futures_util::stream::iter(parts)
+            .then(move |part| {
+                let storage = storage.clone();
+                let bucket = bucket.clone();
+                async move {
+                    sink_of(&bucket, &storage)?
+                        .get_stream(
+                            part_key(part.ticket_id, part.part_number),
+                            Some(part.version_id),
+                            None,
+                        )
+                        .await
+                }
+            })
+            .try_flatten(),
+    )
+}
+
+/// best effort removal of staged chunks, which the sweeper retries otherwise
+async fn remove_parts(
+    storage: &StorageOrchestrator,
+    bucket: &entities::bucket::Model,
+    parts: &Database<entities::upload_ticket_part::Model>,
+    received: Vec<entities::upload_ticket_part::Model>,
+) -> Result<usize, FlymodelError> {
+    let sink = sink_of(bucket, storage)?;
+    let mut removed = 0;
+    for part in received {
+        sink.del(
+            part_key(part.ticket_id, part.part_number),
+            Some(part.version_id.clone()),
+        )
+        .await?;
+        parts.loader().delete_part(&part).await?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+#[routes]
+#[post("/upload/resumable")]
+pub async fn create_resumable_upload(
+    Json(req): Json<CreateResumableUpload>,
+    Authenticated(principal): Authenticated,
+    ResumableUploads {
+        conf,
+        storage,
+        namespaces,
+        versions,
+        experiments,
+        buckets,
+        tickets,
+        audit,
+        ..
+    }: ResumableUploads,
+) -> actix_web::Result<impl Responder> {
+    let target = upload_target(req.model_version, req.experiment)?;
+    let sha256 = declared_sha256(req.size, &req.sha256)?;
+    let chunk_size = req.chunk_size.unwrap_or(conf.chunk_size as i64);
+    if chunk_size < 1 || chunk_size > conf.max_chunk_size as i64 {
+        return Err(FlymodelError::ContraintError(format!(
+            "chunk size must be between 1 and {} bytes",
+            conf.max_chunk_size
+        ))
+        .into());
+    }
+    let chunks = (req.size + chunk_size - 1) / chunk_size;
+    if chunks > MAX_CHUNKS {
+        return Err(FlymodelError::ContraintError(format!(
+            "uploads may not be split into more than {MAX_CHUNKS} chunks"
+        ))
+        .into());
+    }
+
+    let (namespace, model) = target_owner(target, &versions, &experiments).await?;
+    principal.authorize_model(namespace, model, Perm::W)?;
+
+    let (bucket, key) = target_location(
+        target,
+        &req.blob.artifact_name,
+        &namespaces,
+        &versions,
+        &experiments,
+        &buckets,
+    )
+    .await?;
+    sink_of(&bucket, &storage)?;
+
+    let ticket = tickets
+        .loader()
+        .create_ticket(NewUploadTicket {
+            bucket_id: bucket.id,
+            key,
+            target,
+            blob: req.blob,
+            extra: req.extra,
+            size: req.size,
+            sha256,
+            created_by: principal.subject.clone(),
+            expires_at: Utc::now() + ticket_ttl(&conf)?,
+            chunk_size: Some(chunk_size),
+        })
+        .await?;
+    debug!("created resumable upload {} of {chunks} chunks", ticket.id);
+    audit
+        .emit(
+            AuditEvent::new(AuditAction::Create, ResourceKind::UploadTicket, ticket.id)
+                .by(&principal.subject)
+                .after(&summary(&ticket)),
+        )
+        .await;
+
+    Ok(Json(ResumableUpload {
+        ticket,
+        chunks,
+        received: vec![],
+    }))
+}
+
+#[routes]
+#[get("/upload/resumable/{ticket}")]
+pub async fn resumable_upload_status(
+    ticket: Path<i64>,
+    Authenticated(principal): Authenticated,
+    ResumableUploads {
+        versions,
+        experiments,
+        tickets,
+        parts,
+        ..
+    }: ResumableUploads,
+) -> actix_web::Result<impl Responder> {
+    let ticket = load_resumable(*ticket, &principal, &tickets, &versions, &experiments).await?;
+    let received = parts
+        .loader()
+        .parts(ticket.id)
+        .await?
+        .into_iter()
+        .map(|part| part.part_number)
+        .collect();
+    Ok(Json(ResumableUpload {
+        chunks: ticket.chunks().unwrap_or_default(),
+        ticket,
+        received,
+    }))
+}
+
+#[routes]
+#[put("/upload/resumable/{ticket}/{part}")]
+pub async fn upload_resumable_chunk(
+    req: HttpRequest,
+    path: Path<(i64, i32)>,
+    body: Bytes,
+    Authenticated(principal): Authenticated,
+    ResumableUploads {
+        conf,
+        storage,
+        versions,
+        experiments,
+        buckets,
+        tickets,
+        audit,
+        ..
+    }: ResumableUploads,
+) -> actix_web::Result<impl Responder> {
+    let (id, part_number) = path.into_inner();
+    let ticket = load_resumable(id, &principal, &tickets, &versions, &experiments).await?;
+    if !ticket.is_active() {
+        return Err(FlymodelError::ContraintError(format!(
+            "upload ticket {id} is {:?}",
+            ticket.status
+        ))
+        .into());
+    }
+    let expected = ticket.chunk_len(part_number).ok_or_else(|| {
+        FlymodelError::ContraintError(format!(
+            "chunk {part_number} is out of range for upload ticket {id}"
+        ))
+    })?;
+    if body.len() as i64 != expected {
+        return Err(FlymodelError::IntegrityError {
+            kind: "chunk size".into(),
+            expect: expected.to_string(),
+            receive: body.len().to_string(),
+        }
+        .into());
+    }
+
+    let digest = declared_digest(req.headers());
+    let bucket = ticket_bucket(&ticket, &buckets).await?;
+    let sink = sink_of(&bucket, &storage)?;
+    let expires_at = Utc::now() + ticket_ttl(&conf)?;
+    let stream: ByteStream = Box::pin(futures_util::stream::once(async move { Ok(body) }));
+
+    let (part, replaced) = guarded_upload(
+        sink,
+        stream,
+        &tickets.loader().db,
+        part_key(id, part_number),
+        |tx, upload| {
+            Box::pin(async move {
+                if let Some(digest) = digest {
+                    if digest != upload.sha256 {
+                        return Err(FlymodelError::IntegrityError {
+                            kind: "chunk checksum".into(),
+                            expect: digest,
+                            receive: upload.sha256,
+                        });
+                    }
+                }
+                DbLoader::<entities::upload_ticket::Model>::receive_chunk(tx, id, expires_at)
+                    .await?;
+                let version_id = upload.version_id().ok_or_else(|| {
+                    FlymodelError::NonDeterministicError(format!(
+                        "storage returned no version id for chunk {part_number} of upload {id}"
+                    ))
+                })?;
+                let part = entities::upload_ticket_part::Model {
+                    ticket_id: id,
+                    part_number,
+                    version_id,
+                    size: upload.size as i64,
+                    sha256: upload.sha256,
+                    created_at: Utc::now(),
+                };
+                let replaced =
+                    DbLoader::<entities::upload_ticket_part::Model>::record_part(tx, part.clone())
+                        .await?;
+                Ok((part, replaced))
+            })
+        },
+    )
+    .await?;
+
+    // a resent chunk supersedes the version staged before it
+    if let Some(replaced) = replaced.filter(|replaced| replaced.version_id != part.version_id) {
+        if let Err(err) = sink
+            .del(part_key(id, part_number), Some(replaced.version_id))
+            .await
+        {
+            warn!("failed to remove replaced chunk {part_number} of upload {id}: {err}");
+        }
+    }
+
+    audit
+        .emit(
+            AuditEvent::new(AuditAction::Update, ResourceKind::UploadTicket, id)
+                .by(&principal.subject)
+                .after(&format!("part {part_number}")),
+        )
+        .await;
+    Ok(Json(part))
+}
+
+#[routes]
+#[post("/upload/resumable/{ticket}/complete")]
+pub async fn complete_resumable_upload(
+    ticket: Path<i64>,
+    Authenticated(principal): Authenticated,
+    ResumableUploads {
+        storage,
+        versions,
+        experiments,
+        buckets,
+        tickets,
+        parts,
+        events,
+        audit,
+        ..
+    }: ResumableUploads,
+) -> actix_web::Result<impl Responder> {
+    let ticket = load_resumable(*ticket, &principal, &tickets, &versions, &experiments).await?;
+    if ticket.status != UploadTicketStatus::Uploading || ticket.expires_at < Utc::now() {
+        return Err(FlymodelError::ContraintError(format!(
+            "upload ticket {} is not uploading",
+            ticket.id
+        ))
+        .into());
+    }
+    let received = parts.loader().parts(ticket.id).await?;
+    let chunks = ticket.chunks().unwrap_or_default();
+    if received.len() as i64 != chunks {
+        return Err(FlymodelError::ContraintError(format!(
+            "received {} of {chunks} chunks for upload ticket {}",
+            received.len(),
+            ticket.id
+        ))
+        .into());
+    }
+
+    let bucket = ticket_bucket(&ticket, &buckets).await?;
+    let stream = concat_parts(storage.as_ref().clone(), bucket.clone(), received.clone());
+    let registered = {
+        let ticket = ticket.clone();
+        let versions = versions.clone();
+        let experiments = experiments.clone();
+        guarded_upload(
+            sink_of(&bucket, &storage)?,
+            stream,
+            &tickets.loader().db,
+            ticket.key.clone(),
+            |tx, upload| {
+                Box::pin(async move {
+                    if upload.sha256 != ticket.sha256 {
+                        return Err(FlymodelError::IntegrityError {
+                            kind: "upload checksum".into(),
+                            expect: ticket.sha256.clone(),
+                            receive: upload.sha256,
+                        });
+                    }
+                    let version_id = upload.version_id().ok_or_else(|| {
+                        FlymodelError::NonDeterministicError(format!(
+                            "storage returned no version id for {}",
+                            ticket.key
+                        ))
+                    })?;
+                    register_artifact(tx, &ticket, version_id, &versions, &experiments).await
+                })
+            },
+        )
+        .await
+    };
+
+    let created = match registered {
+        Ok(created) => created,
+        Err(err) => {
+            // the chunks do not add up to the declared artifact, so a retry cannot succeed
+            if matches!(err, FlymodelError::IntegrityError { .. }) {
+                tickets
+                    .loader()
+                    .transition_ticket(
+                        ticket.id,
+                        &[UploadTicketStatus::Uploading],
+                        UploadTicketStatus::Erred,
+                    )
+                    .await?;
+            }
+            return Err(err.into());
+        }
+    };
+
+    if let Err(err) = remove_parts(&storage, &bucket, &parts, received).await {
+        warn!("failed to remove chunks of upload {}: {err}", ticket.id);
+    }
+    audit.emit(uploaded(&created).by(&principal.subject)).await;
+    events
+        .publish(RegistryEvent::ArtifactUploaded((&created).into()))
+        .await;
+    Ok(Json(created))
+}
+
+#[routes]
+#[delete("/upload/resumable/{ticket}")]
+pub async fn cancel_resumable_upload(
+    ticket: Path<i64>,
+    Authenticated(principal): Authenticated,
+    ResumableUploads {
+        storage,
+        versions,
+        experiments,
+        buckets,
+        tickets,
+        parts,
+        audit,
+        ..
+    }: ResumableUploads,
+) -> actix_web::Result<impl Responder> {
+    let ticket = load_resumable(*ticket, &principal, &tickets, &versions, &experiments).await?;
+    let cancelled = tickets
+        .loader()
+        .transition_ticket(
+            ticket.id,
+            &[UploadTicketStatus::Pending, UploadTicketStatus::Uploading],
+            UploadTicketStatus::Cancelled,
+        )
+        .await?;
+    if !cancelled {
+        return Err(FlymodelError::ContraintError(format!(
+            "upload ticket {} is {:?}",
+            ticket.id, ticket.status
+        ))
+        .into());
+    }
+    audit
+        .emit(
+            AuditEvent::new(AuditAction::Cancel, ResourceKind::UploadTicket, ticket.id)
+                .by(&principal.subject)
+                .before(&summary(&ticket.status)),
+        )
+        .await;
+    let bucket = ticket_bucket(&ticket, &buckets).await?;
+    let received = parts.loader().parts(ticket.id).await?;
+    if let Err(err) = remove_parts(&storage, &bucket, &parts, received).await {
+        warn!("failed to remove chunks of upload {}: {err}", ticket.id);
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// expires stale tickets, then removes the chunks of every ticket which can no longer be finalized
+pub async fn sweep_uploads(
+    storage: &StorageOrchestrator,
+    tickets: &Database<entities::upload_ticket::Model>,
+    parts: &Database<entities::upload_ticket_part::Model>,
+    buckets: &Database<entities::bucket::Model>,
+) -> Result<(u64, usize), FlymodelError> {
+    let expired = tickets.loader().expire_stale().await?;
+    let mut removed = 0;
+    for (part, ticket) in parts.loader().stale_parts().await? {
+        let bucket = ticket_bucket(&ticket, buckets).await?;
+        match remove_parts(storage, &bucket, parts, vec![part]).await {
+            Ok(count) => removed += count,
+            Err(err) => warn!("failed to remove a chunk of upload {}: {err}", ticket.id),
+        }
+    }
+    Ok((expired, removed))
+}
+
+pub fn spawn_upload_sweeper(
+    db: sea_orm::DbConn,
+    storage: Arc<StorageOrchestrator>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    let tickets = DbLoader::<entities::upload_ticket::Model>::new(db.clone(), None);
+    let parts = DbLoader::<entities::upload_ticket_part::Model>::new(db.clone(), None);
+    let buckets = DbLoader::<entities::bucket::Model>::new(db, None);
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            match sweep_uploads(&storage, &tickets, &parts, &buckets).await {
+                Ok((0, 0)) => {}
+                Ok((expired, removed)) => {
+                    info!("expired {expired} upload tickets, removed {removed} staged chunks")
+                }
+                Err(err) => warn!("upload sweep failed: {err}"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use actix_web::{
+        body::BoxBody,
+        dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+        http::{header::HeaderValue, StatusCode},
+        test::{call_service, init_service, TestRequest},
+        web::Data,
+        App,
+    };
+    use bytes::Bytes;
+    use chrono::{Duration, Utc};
+    use flymodel::{
+        config::{auth::AuthConfiguration, uploads::UploadConfiguration},
+        lifecycle::Lifecycle,
+    };
+    use flymodel_entities::{
+        db::DbLoader,
+        entities::{self, enums::UploadTicketStatus},
+        testing,
+    };
+    use flymodel_registry::{memory::MemoryStorage, storage::StorageOrchestrator};
+    use futures_util::TryStreamExt;
+    use sea_orm::{ActiveModelTrait, ActiveValue, DbConn};
+
+    use super::{
+        cancel_resumable_upload, complete_resumable_upload, concat_parts, declared_digest,
+        part_key, sweep_uploads, upload_resumable_chunk,
+    };
+    use crate::{
+        audit::AuditEvent, auth::Authenticator, events::EventBus, testing::memory_storage,
+    };
+
+    struct Fixture {
+        db: DbConn,
+        storage: MemoryStorage,
+        orchestrator: Arc<StorageOrchestrator>,
+        bucket: entities::bucket::Model,
+        version: i64,
+    }
+
+    async fn fixture() -> Fixture {
+        let db = testing::database().await;
+        let (storage, orchestrator) = memory_storage("ml-test", Lifecycle::Test);
+        let ns = testing::namespace(&db, "ns").await;
+        let bucket = testing::bucket(&db, ns.id, "ml-test", Lifecycle::Test).await;
+        let model = testing::model(&db, ns.id, "model").await;
+        let version = testing::version(&db, model.id, "v1", Lifecycle::Test, Utc::now()).await;
+        Fixture {
+            db,
+            storage,
+            orchestrator: Arc::new(orchestrator),
+            bucket,
+            version: version.id,
+        }
+    }
+
+    /// a ticket for `content`, split into chunks of 4 bytes
+    async fn ticket(fx: &Fixture, key: &str, content: &str) -> entities::upload_ticket::Model {
+        let ticket = testing::upload_ticket(
+            &fx.db,
+            fx.bucket.id,
+            fx.version,
+            key,
+            UploadTicketStatus::Pending,
+        )
+        .await;
+        entities::upload_ticket::ActiveModel {
+            id: ActiveValue::Unchanged(ticket.id),
+            size: ActiveValue::Set(content.len() as i64),
+            sha256: ActiveValue::Set(sha256::digest(content)),
+            chunk_size: ActiveValue::Set(Some(4)),
+            ..Default::default()
+        }
+        .update(&fx.db)
+        .await
+        .unwrap()
+    }
+
+    fn uploads(
+        fx: &Fixture,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<BoxBody>,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        let db = fx.db.clone();
+        let (audit, _) = flymodel_events::bus::<AuditEvent>(64).unwrap();
+        App::new()
+            .app_data(Data::new(UploadConfiguration::default()))
+            .app_data(Data::new(fx.orchestrator.clone()))
+            .app_data(Data::new(EventBus::new(db.clone())))
+            .app_data(Data::new(audit))
+            .app_data(Data::new(
+                Authenticator::new(AuthConfiguration::default(), db.clone()).unwrap(),
+            ))
+            .app_data(Data::new(DbLoader::<entities::namespace::Model>::new(
+                db.clone(),
+                None,
+            )))
+            .app_data(Data::new(DbLoader::<entities::model_version::Model>::new(
+                db.clone(),
+                None,
+            )))
+            .app_data(Data::new(DbLoader::<entities::experiment::Model>::new(
+                db.clone(),
+                None,
+            )))
+            .app_data(Data::new(DbLoader::<entities::bucket::Model>::new(
+                db.clone(),
+                None,
+            )))
+            .app_data(Data::new(DbLoader::<entities::upload_ticket::Model>::new(
+                db.clone(),
+                None,
+            )))
+            .app_data(Data::new(
+                DbLoader::<entities::upload_ticket_part::Model>::new(db, None),
+            ))
+            .service(upload_resumable_chunk)
+            .service(complete_resumable_upload)
+            .service(cancel_resumable_upload)
+    }
+
+    fn put_chunk(ticket: i64, part: i32, chunk: &'static str) -> TestRequest {
+        TestRequest::put()
+            .uri(&format!("/upload/resumable/{ticket}/{part}"))
+            .set_payload(chunk)
+    }
+
+    fn complete(ticket: i64) -> TestRequest {
+        TestRequest::post().uri(&format!("/upload/resumable/{ticket}/complete"))
+    }
+
+    fn cancel(ticket: i64) -> TestRequest {
+        TestRequest::delete().uri(&format!("/upload/resumable/{ticket}"))
+    }
+
+    macro_rules! status {
+        ($app: expr, $req: expr) => {
+            call_service(&$app, $req.to_request()).await.status()
+        };
+    }
+
+    async fn status_of(fx: &Fixture, ticket: i64) -> UploadTicketStatus {
+        DbLoader::<entities::upload_ticket::Model>::new(fx.db.clone(), None)
+            .load_one(ticket)
+            .await
+            .unwrap()
+            .unwrap()
+            .status
+    }
+
+    async fn staged(fx: &Fixture, ticket: i64) -> Vec<i32> {
+        DbLoader::<entities::upload_ticket_part::Model>::new(fx.db.clone(), None)
+            .loader()
+            .parts(ticket)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|part| part.part_number)
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_complete_resumable_upload() {
+        let fx = fixture().await;
+        let app = init_service(uploads(&fx)).await;
+        let tickets = DbLoader::<entities::upload_ticket::Model>::new(fx.db.clone(), None);
+        let ticket = ticket(&fx, "weights", "hello world").await;
+
+        // chunks must fall in range & match their expected length
+        assert_eq!(
+            status!(app, put_chunk(ticket.id, 4, "")),
+            StatusCode::EXPECTATION_FAILED
+        );
+        assert_eq!(
+            status!(app, put_chunk(ticket.id, 3, "rl")),
+            StatusCode::EXPECTATION_FAILED
+        );
+
+        // a missing chunk keeps the upload open
+        assert_eq!(
+            status!(app, put_chunk(ticket.id, 1, "hell")),
+            StatusCode::OK
+        );
+        assert_eq!(status!(app, put_chunk(ticket.id, 3, "rld")), StatusCode::OK);
+        assert_eq!(
+            status!(app, complete(ticket.id)),
+            StatusCode::EXPECTATION_FAILED
+        );
+        assert_eq!(
+            status_of(&fx, ticket.id).await,
+            UploadTicketStatus::Uploading
+        );
+
+        // a resent chunk replaces the one staged before it
+        assert_eq!(
+            status!(app, put_chunk(ticket.id, 2, "xxxx")),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status!(app, put_chunk(ticket.id, 2, "o wo")),
+            StatusCode::OK
+        );
+        assert_eq!(fx.storage.versions(part_key(ticket.id, 2)).len(), 1);
+        assert_eq!(staged(&fx, ticket.id).await, vec![1, 2, 3]);
+
+        // the chunks staged last are the ones put together on completion
+        let received = DbLoader::<entities::upload_ticket_part::Model>::new(fx.db.clone(), None)
+            .loader()
+            .parts(ticket.id)
+            .await
+            .unwrap();
+        let assembled: Vec<Bytes> =
+            concat_parts(fx.orchestrator.clone(), fx.bucket.clone(), received)
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(assembled.concat(), b"hello world");
+
+        // a completed ticket takes no further chunks
+        assert!(tickets
+            .loader()
+            .transition_ticket(
+                ticket.id,
+                &[UploadTicketStatus::Uploading],
+                UploadTicketStatus::Completed
+            )
+            .await
+            .unwrap());
+        assert_eq!(
+            status!(app, put_chunk(ticket.id, 1, "hell")),
+            StatusCode::EXPECTATION_FAILED
+        );
+        assert_eq!(
+            status!(app, complete(ticket.id)),
+            StatusCode::EXPECTATION_FAILED
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_cancel_resumable_upload() {
+        let fx = fixture().await;
+        let app = init_service(uploads(&fx)).await;
+        let ticket = ticket(&fx, "weights", "hello world").await;
+
+        assert_eq!(
+            status!(app, put_chunk(ticket.id, 1, "hell")),
+            StatusCode::OK
+        );
+        assert_eq!(status!(app, cancel(ticket.id)), StatusCode::NO_CONTENT);
+        assert_eq!(
+            status_of(&fx, ticket.id).await,
+            UploadTicketStatus::Cancelled
+        );
+        assert!(staged(&fx, ticket.id).await.is_empty());
+        assert!(fx.storage.versions(part_key(ticket.id, 1)).is_empty());
+
+        assert_eq!(
+            status!(app, cancel(ticket.id)),
+            StatusCode::EXPECTATION_FAILED
+        );
+        assert_eq!(
+            status!(app, put_chunk(ticket.id, 2, "o wo")),
+            StatusCode::EXPECTATION_FAILED
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_sweep_uploads() {
+        let fx = fixture().await;
+        let app = init_service(uploads(&fx)).await;
+        let tickets = DbLoader::<entities::upload_ticket::Model>::new(fx.db.clone(), None);
+        let parts = DbLoader::<entities::upload_ticket_part::Model>::new(fx.db.clone(), None);
+        let buckets = DbLoader::<entities::bucket::Model>::new(fx.db.clone(), None);
+
+        // chunks which do not add up to the declared artifact fail the ticket for good
+        let corrupted = ticket(&fx, "corrupted", "hello world").await;
+        assert_eq!(
+            status!(app, put_chunk(corrupted.id, 1, "hell")),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status!(app, put_chunk(corrupted.id, 2, "o wo")),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status!(app, put_chunk(corrupted.id, 3, "RLD")),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status!(app, complete(corrupted.id)),
+            StatusCode::EXPECTATION_FAILED
+        );
+        assert_eq!(
+            status_of(&fx, corrupted.id).await,
+            UploadTicketStatus::Erred
+        );
+        assert!(fx.storage.versions(corrupted.key.clone()).is_empty());
+
+        let stale = ticket(&fx, "stale", "hello world").await;
+        assert_eq!(status!(app, put_chunk(stale.id, 1, "hell")), StatusCode::OK);
+        entities::upload_ticket::ActiveModel {
+            id: ActiveValue::Unchanged(stale.id),
+            expires_at: ActiveValue::Set(Utc::now() - Duration::minutes(1)),
+            ..Default::default()
+        }
+        .update(&fx.db)
+        .await
+        .unwrap();
+
+        let live = ticket(&fx, "live", "hello world").await;
+        assert_eq!(status!(app, put_chunk(live.id, 1, "hell")), StatusCode::OK);
+
+        assert_eq!(
+            sweep_uploads(&fx.orchestrator, &tickets, &parts, &buckets)
+                .await
+                .unwrap(),
+            (1, 4)
+        );
+        assert_eq!(status_of(&fx, stale.id).await, UploadTicketStatus::Expired);
+        assert!(staged(&fx, corrupted.id).await.is_empty());
+        assert!(staged(&fx, stale.id).await.is_empty());
+        assert!(fx.storage.versions(part_key(stale.id, 1)).is_empty());
+        assert_eq!(staged(&fx, live.id).await, vec![1]);
+        assert_eq!(fx.storage.versions(part_key(live.id, 1)).len(), 1);
+        assert_eq!(
+            sweep_uploads(&fx.orchestrator, &tickets, &parts, &buckets)
+                .await
+                .unwrap(),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_part_keys_sort_in_order() {
+        let mut keys = vec![part_key(7, 10), part_key(7, 2), part_key(7, 1)];
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["uploads/7/00001", "uploads/7/00002", "uploads/7/00010"]
+        );
+    }
+
+    #[test]
+    fn test_declared_digest() {
+        let req = TestRequest::default()
+            .insert_header(("Digest", HeaderValue::from_static("sha256=ABCDEF")))
+            .to_http_request();
+        assert_eq!(declared_digest(req.headers()), Some("abcdef".into()));
+
+        let req = TestRequest::default()
+            .insert_header(("Digest", HeaderValue::from_static("md5=abcdef")))
+            .to_http_request();
+        assert_eq!(declared_digest(req.headers()), None);
+        assert_eq!(
+            declared_digest(TestRequest::default().to_http_request().headers()),
+            None
+        );
+    }
+}
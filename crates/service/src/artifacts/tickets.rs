@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use async_graphql::{dataloader::DataLoader, Union};
+use flymodel::errs::FlymodelError;
+use flymodel_entities::{
+    db::DbLoader,
+    entities::{self, upload_ticket::UploadTarget},
+};
+use sea_orm::DatabaseTransaction;
+use serde::Serialize;
+
+use super::{
+    experiments::get_common_from_experiment, model_version::get_common_from_model_version,
+};
+
+#[derive(Union, Serialize)]
+#[serde(untagged)]
+pub enum UploadedArtifact {
+    ModelArtifact(entities::model_artifact::Model),
+    ExperimentArtifact(entities::experiment_artifact::Model),
+}
+
+pub(crate) fn upload_target(
+    model_version: Option<i64>,
+    experiment: Option<i64>,
+) -> Result<UploadTarget, FlymodelError> {
+    match (model_version, experiment) {
+        (Some(id), None) => Ok(UploadTarget::ModelVersion(id)),
+        (None, Some(id)) => Ok(UploadTarget::Experiment(id)),
+        _ => Err(FlymodelError::ContraintError(
+            "exactly one of a model version or experiment is required".into(),
+        )),
+    }
+}
+
+pub(crate) fn declared_sha256(size: i64, sha256: &str) -> Result<String, FlymodelError> {
+    if size <= 0 {
+        return Err(FlymodelError::ContraintError(
+            "the artifact size must be positive".into(),
+        ));
+    }
+    let sha256 = sha256.to_ascii_lowercase();
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(FlymodelError::ContraintError(
+            "sha256 must be 64 hex characters".into(),
+        ));
+    }
+    Ok(sha256)
+}
+
+pub(crate) async fn target_owner(
+    target: UploadTarget,
+    versions: &DataLoader<DbLoader<entities::model_version::Model>>,
+    experiments: &DataLoader<DbLoader<entities::experiment::Model>>,
+) -> Result<(i64, i64), FlymodelError> {
+    match target {
+        UploadTarget::ModelVersion(id) => versions.loader().owner(id).await?,
+        UploadTarget::Experiment(id) => experiments.loader().owner(id).await?,
+    }
+    .ok_or_else(|| {
+        FlymodelError::InvalidResourceId(match target {
+            UploadTarget::ModelVersion(id) | UploadTarget::Experiment(id) => id,
+        })
+    })
+}
+
+pub(crate) async fn target_location(
+    target: UploadTarget,
+    artifact_name: &str,
+    namespaces: &DataLoader<DbLoader<entities::namespace::Model>>,
+    versions: &DataLoader<DbLoader<entities::model_version::Model>>,
+    experiments: &DataLoader<DbLoader<entities::experiment::Model>>,
+    buckets: &DataLoader<DbLoader<entities::bucket::Model>>,
+) -> Result<(entities::bucket::Model, String), FlymodelError> {
+    match target {
+        UploadTarget::ModelVersion(id) => {
+            let cte = get_common_from_model_version(
+                id,
+                namespaces,
+                versions,
+                buckets,
+                || FlymodelError::InvalidResourceId(id),
+                |err| FlymodelError::DbLoaderError(Arc::new(err)),
+            )
+            .await?;
+            Ok((cte.bucket, format!("model_versions/{id}/{artifact_name}")))
+        }
+        UploadTarget::Experiment(id) => {
+            let cte =
+                get_common_from_experiment(id, experiments, namespaces, versions, buckets, || {
+                    FlymodelError::InvalidResourceId(id)
+                })
+                .await?;
+            Ok((cte.bucket, format!("experiments/{id}/{artifact_name}")))
+        }
+    }
+}
+
+pub(crate) async fn register_artifact(
+    tx: &DatabaseTransaction,
+    ticket: &entities::upload_ticket::Model,
+    version_id: String,
+    versions: &DataLoader<DbLoader<entities::model_version::Model>>,
+    experiments: &DataLoader<DbLoader<entities::experiment::Model>>,
+) -> Result<UploadedArtifact, FlymodelError> {
+    let params = ticket.blob_params();
+    let blob = DbLoader::<entities::object_blob::Model>::create_new_blob(
+        tx,
+        ticket.bucket_id,
+        ticket.key.clone(),
+        version_id,
+        &params,
+        ticket.size,
+        ticket.sha256.clone(),
+    )
+    .await?;
+    let created = match ticket.target()? {
+        UploadTarget::ModelVersion(id) => {
+            let version = versions
+                .load_one(id)
+                .await?
+                .ok_or(FlymodelError::InvalidResourceId(id))?;
+            UploadedArtifact::ModelArtifact(
+                DbLoader::<entities::model_artifact::Model>::create_new_artifact(
+                    tx,
+                    &version,
+                    &blob,
+                    &params,
+                    ticket.extra.clone(),
+                )
+                .await?,
+            )
+        }
+        UploadTarget::Experiment(id) => {
+            let (version, experiment) = experiments
+                .loader()
+                .single_model_version(id)
+                .await?
+                .ok_or(FlymodelError::InvalidResourceId(id))?;
+            UploadedArtifact::ExperimentArtifact(
+                DbLoader::<entities::experiment_artifact::Model>::create_new_artifact(
+                    tx,
+                    &experiment,
+                    &version,
+                    &blob,
+                    &params,
+                )
+                .await?,
+            )
+        }
+    };
+    DbLoader::<entities::upload_ticket::Model>::complete_ticket(tx, ticket.id, blob.id).await?;
+    Ok(created)
+}
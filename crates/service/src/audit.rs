@@ -0,0 +1,38 @@
+use async_graphql::Context;
+use flymodel::perms::Principal;
+use flymodel_events::AuditPublisher;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::artifacts::tickets::UploadedArtifact;
+
+pub(crate) use flymodel_events::{AuditAction, AuditEvent, ResourceKind};
+
+pub(crate) fn summary(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(value)) => value,
+        Ok(value) => value.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+pub(crate) async fn audit(ctx: &Context<'_>, event: AuditEvent) {
+    let actor = ctx
+        .data_opt::<Principal>()
+        .map(|principal| principal.subject.as_str())
+        .unwrap_or_default();
+    match ctx.data_opt::<AuditPublisher>() {
+        Some(publisher) => publisher.emit(event.by(actor)).await,
+        None => debug!("no audit publisher to emit {event:?} on"),
+    }
+}
+
+pub(crate) fn uploaded(artifact: &UploadedArtifact) -> AuditEvent {
+    let (resource, id) = match artifact {
+        UploadedArtifact::ModelArtifact(artifact) => (ResourceKind::ModelArtifact, artifact.id),
+        UploadedArtifact::ExperimentArtifact(artifact) => {
+            (ResourceKind::ExperimentArtifact, artifact.id)
+        }
+    };
+    AuditEvent::new(AuditAction::Upload, resource, id).after(&summary(artifact))
+}
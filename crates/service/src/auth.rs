@@ -0,0 +1,184 @@
+use actix_web::{dev::Payload, http::header, web::Data, FromRequest, HttpMessage, HttpRequest};
+use async_graphql::{dataloader::DataLoader, Context};
+use flymodel::{
+    config::auth::{AuthConfiguration, AuthHandlers},
+    errs::FlymodelError,
+    perms::{Perm, Principal},
+};
+use flymodel_entities::{db::DbLoader, entities};
+use futures_util::future::LocalBoxFuture;
+use sea_orm::DbConn;
+
+use self::{api_key::ApiKeyAuthenticator, oidc::OidcProvider};
+
+pub mod api_key;
+pub mod oidc;
+
+enum Handler {
+    NoOp,
+    OAuth2(Box<OidcProvider>),
+    ApiKey(ApiKeyAuthenticator),
+}
+
+pub struct Authenticator {
+    handler: Handler,
+}
+
+pub(crate) fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+impl Authenticator {
+    pub fn new(conf: AuthConfiguration, db: DbConn) -> Result<Self, FlymodelError> {
+        Ok(Self {
+            handler: match conf.handler {
+                AuthHandlers::NoOp => Handler::NoOp,
+                AuthHandlers::OAuth2(conf) => Handler::OAuth2(Box::new(OidcProvider::new(conf))),
+                AuthHandlers::ApiKey(conf) => Handler::ApiKey(ApiKeyAuthenticator::new(conf, db)?),
+            },
+        })
+    }
+
+    pub fn oidc(&self) -> Option<&OidcProvider> {
+        match &self.handler {
+            Handler::OAuth2(provider) => Some(provider),
+            _ => None,
+        }
+    }
+
+    pub async fn authenticate(&self, req: &HttpRequest) -> Result<Principal, FlymodelError> {
+        match &self.handler {
+            // without an authorizer every caller is trusted, as before
+            Handler::NoOp => Ok(Principal::anonymous()),
+            Handler::OAuth2(provider) => {
+                let token = bearer_token(req)
+                    .ok_or_else(|| FlymodelError::Unauthenticated("missing bearer token".into()))?;
+                provider.validate(token).await
+            }
+            Handler::ApiKey(keys) => {
+                let key = bearer_token(req)
+                    .ok_or_else(|| FlymodelError::Unauthenticated("missing api key".into()))?;
+                keys.validate(key).await
+            }
+        }
+    }
+}
+
+/// extracts (and caches) the principal of the current request
+pub struct Authenticated(pub Principal);
+
+impl FromRequest for Authenticated {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            if let Some(principal) = req.extensions().get::<Principal>() {
+                return Ok(Self(principal.clone()));
+            }
+            let auth = req.app_data::<Data<Authenticator>>().ok_or_else(|| {
+                FlymodelError::RuntimeDependencyError("missing authenticator".into())
+            })?;
+            let principal = auth.authenticate(&req).await?;
+            req.extensions_mut().insert(principal.clone());
+            Ok(Self(principal))
+        })
+    }
+}
+
+pub(crate) fn principal<'ctx>(
+    ctx: &Context<'ctx>,
+) -> Result<&'ctx Principal, async_graphql::Error> {
+    ctx.data_opt::<Principal>().ok_or_else(|| {
+        FlymodelError::RuntimeDependencyError("missing request principal".into())
+            .into_graphql_error()
+    })
+}
+
+pub(crate) fn authorize_global(ctx: &Context<'_>, perm: Perm) -> Result<(), async_graphql::Error> {
+    principal(ctx)?
+        .authorize_global(perm)
+        .map_err(|err| err.into_graphql_error())
+}
+
+pub(crate) fn authorize_namespace(
+    ctx: &Context<'_>,
+    namespace: i64,
+    perm: Perm,
+) -> Result<(), async_graphql::Error> {
+    principal(ctx)?
+        .authorize_namespace(namespace, perm)
+        .map_err(|err| err.into_graphql_error())
+}
+
+pub(crate) async fn authorize_bucket(
+    ctx: &Context<'_>,
+    id: i64,
+    perm: Perm,
+) -> Result<(), async_graphql::Error> {
+    let bucket = DbLoader::<entities::bucket::Model>::with_context(ctx)
+        .map_err(|err| err.into_graphql_error())?
+        .load_one(id)
+        .await
+        .map_err(|err| FlymodelError::DbLoaderError(err).into_graphql_error())?
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())?;
+    authorize_namespace(ctx, bucket.namespace, perm)
+}
+
+pub(crate) async fn authorize_model(
+    ctx: &Context<'_>,
+    id: i64,
+    perm: Perm,
+) -> Result<(), async_graphql::Error> {
+    let model = DbLoader::<entities::model::Model>::with_context(ctx)
+        .map_err(|err| err.into_graphql_error())?
+        .load_one(id)
+        .await
+        .map_err(|err| FlymodelError::DbLoaderError(err).into_graphql_error())?
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())?;
+    principal(ctx)?
+        .authorize_model(model.namespace_id, model.id, perm)
+        .map_err(|err| err.into_graphql_error())
+}
+
+pub(crate) async fn authorize_model_version(
+    ctx: &Context<'_>,
+    id: i64,
+    perm: Perm,
+) -> Result<(), async_graphql::Error> {
+    let versions: &DataLoader<DbLoader<entities::model_version::Model>> =
+        DbLoader::with_context(ctx).map_err(|err| err.into_graphql_error())?;
+    let (namespace, model) = versions
+        .loader()
+        .owner(id)
+        .await
+        .map_err(|err| err.into_graphql_error())?
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())?;
+    principal(ctx)?
+        .authorize_model(namespace, model, perm)
+        .map_err(|err| err.into_graphql_error())
+}
+
+pub(crate) async fn authorize_experiment(
+    ctx: &Context<'_>,
+    id: i64,
+    perm: Perm,
+) -> Result<(), async_graphql::Error> {
+    let experiments: &DataLoader<DbLoader<entities::experiment::Model>> =
+        DbLoader::with_context(ctx).map_err(|err| err.into_graphql_error())?;
+    let (namespace, model) = experiments
+        .loader()
+        .owner(id)
+        .await
+        .map_err(|err| err.into_graphql_error())?
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())?;
+    principal(ctx)?
+        .authorize_model(namespace, model, perm)
+        .map_err(|err| err.into_graphql_error())
+}
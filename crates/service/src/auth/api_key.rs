@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use flymodel::{
+    config::auth::{hash_api_key, ApiKeyConfiguration},
+    errs::FlymodelError,
+    perms::{Permission, Permissions, Principal},
+};
+use flymodel_entities::{
+    db::{Database, DbLoader},
+    entities,
+};
+use sea_orm::DbConn;
+
+fn parse_permissions(perms: &[String]) -> Result<Permissions, FlymodelError> {
+    Ok(Permissions::new(
+        perms
+            .iter()
+            .map(|perm| Permission::try_from(perm.as_str()))
+            .collect::<Result<_, _>>()?,
+    ))
+}
+
+/// a new random key, only its hash should ever be stored
+pub fn generate_api_key() -> Result<String, FlymodelError> {
+    let mut bs = [0u8; 32];
+    getrandom::getrandom(&mut bs)
+        .map_err(|err| FlymodelError::RuntimeDependencyError(err.to_string()))?;
+    Ok(format!("fm_{}", hex::encode(bs)))
+}
+
+/// keys are resolved from the configuration first, then (if enabled) from the `api_key` table
+pub struct ApiKeyAuthenticator {
+    keys: HashMap<String, (String, Permissions)>,
+    database: Option<Database<entities::api_key::Model>>,
+}
+
+impl ApiKeyAuthenticator {
+    pub fn new(conf: ApiKeyConfiguration, db: DbConn) -> Result<Self, FlymodelError> {
+        let mut keys = HashMap::new();
+        for key in conf.keys {
+            let perms = parse_permissions(&key.permissions)?;
+            keys.insert(key.hash.as_ref().to_lowercase(), (key.name, perms));
+        }
+        Ok(Self {
+            keys,
+            database: conf.database.then(|| DbLoader::new(db, None)),
+        })
+    }
+
+    pub async fn validate(&self, key: &str) -> Result<Principal, FlymodelError> {
+        let hash = hash_api_key(key);
+        if let Some((name, perms)) = self.keys.get(&hash) {
+            return Ok(Principal::new(format!("api-key:{name}"), perms.clone()));
+        }
+        if let Some(database) = &self.database {
+            if let Some(found) = database.loader().find_active_key(&hash).await? {
+                return Ok(Principal::new(
+                    format!("api-key:{}", found.name),
+                    parse_permissions(&found.permission_strings())?,
+                ));
+            }
+        }
+        Err(FlymodelError::Unauthenticated("unknown api key".into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use flymodel::{
+        config::auth::{hash_api_key, ApiKeyConfiguration, ApiKeyEntry},
+        perms::Perm,
+    };
+    use sea_orm::DatabaseConnection;
+
+    use super::ApiKeyAuthenticator;
+
+    fn entry(name: &str, key: &str, permissions: &[&str]) -> ApiKeyEntry {
+        ApiKeyEntry {
+            name: name.into(),
+            hash: serde_json::from_value(serde_json::json!(hash_api_key(key))).unwrap(),
+            permissions: permissions.iter().map(|perm| perm.to_string()).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configured_keys() {
+        let auth = ApiKeyAuthenticator::new(
+            ApiKeyConfiguration {
+                keys: vec![entry("ci", "fm-secret", &["namespace:3:write"])],
+                database: false,
+            },
+            DatabaseConnection::Disconnected,
+        )
+        .unwrap();
+
+        let principal = auth.validate("fm-secret").await.unwrap();
+        assert_eq!(principal.subject, "api-key:ci");
+        assert!(principal.authorize_namespace(3, Perm::W).is_ok());
+        assert!(principal.authorize_namespace(1, Perm::R).is_err());
+
+        assert!(auth.validate("fm-other").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_configured_permissions() {
+        assert!(ApiKeyAuthenticator::new(
+            ApiKeyConfiguration {
+                keys: vec![entry("ci", "fm-secret", &["namespace:write"])],
+                database: false,
+            },
+            DatabaseConnection::Disconnected,
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,538 @@
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    cookie::{Cookie, SameSite},
+    http::header,
+    web::{Data, Query},
+    HttpRequest, HttpResponse,
+};
+use flymodel::{
+    config::auth::Oauth2Configuration,
+    errs::FlymodelError,
+    perms::{Permission, Permissions, Principal},
+};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use url::Url;
+
+use super::Authenticator;
+
+const STATE_COOKIE: &str = "flymodel_oauth_state";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcDiscovery {
+    pub issuer: String,
+    pub authorization_endpoint: Url,
+    pub token_endpoint: Url,
+    pub jwks_uri: Url,
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+    /// key ids the jwks was searched for in vain since it was fetched
+    misses: HashSet<Option<String>>,
+}
+
+impl CachedJwks {
+    /// an unknown key only refetches the jwks once per `cooldown`, and not when it already missed
+    fn refetch(&self, kid: Option<&str>, ttl: Duration, cooldown: Duration) -> bool {
+        let age = self.fetched_at.elapsed();
+        age >= ttl || (age >= cooldown && !self.misses.contains(&kid.map(str::to_string)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// the jwks is refreshed once it expires, or when a token is signed by an unknown key at most
+/// once per `jwks_refetch_interval`
+pub struct OidcProvider {
+    conf: Oauth2Configuration,
+    http: reqwest::Client,
+    discovery: RwLock<Option<OidcDiscovery>>,
+    jwks: RwLock<Option<CachedJwks>>,
+}
+
+fn provider_error<E: std::fmt::Display>(err: E) -> FlymodelError {
+    FlymodelError::RuntimeDependencyError(format!("oidc provider: {err}"))
+}
+
+fn invalid_token<E: std::fmt::Display>(err: E) -> FlymodelError {
+    FlymodelError::Unauthenticated(format!("invalid bearer token: {err}"))
+}
+
+impl OidcProvider {
+    pub fn new(conf: Oauth2Configuration) -> Self {
+        Self {
+            conf,
+            http: reqwest::Client::new(),
+            discovery: RwLock::new(None),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    pub fn configuration(&self) -> &Oauth2Configuration {
+        &self.conf
+    }
+
+    pub async fn discovery(&self) -> Result<OidcDiscovery, FlymodelError> {
+        if let Some(found) = self.discovery.read().await.as_ref() {
+            return Ok(found.clone());
+        }
+        let found: OidcDiscovery = self
+            .http
+            .get(self.conf.well_known_url.clone())
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+        *self.discovery.write().await = Some(found.clone());
+        Ok(found)
+    }
+
+    async fn fetch_jwks(&self) -> Result<CachedJwks, FlymodelError> {
+        let discovery = self.discovery().await?;
+        let keys: JwkSet = self
+            .http
+            .get(discovery.jwks_uri)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+        Ok(CachedJwks {
+            keys,
+            fetched_at: Instant::now(),
+            misses: HashSet::new(),
+        })
+    }
+
+    async fn find_key(&self, kid: Option<&str>) -> Result<Jwk, FlymodelError> {
+        let select = |keys: &JwkSet| match kid {
+            Some(kid) => keys.find(kid).cloned(),
+            None if keys.keys.len() == 1 => keys.keys.first().cloned(),
+            None => None,
+        };
+        let unknown = || invalid_token(format!("unknown signing key {kid:?}"));
+        let ttl = Duration::from_secs(self.conf.jwks_ttl);
+        let cooldown = Duration::from_secs(self.conf.jwks_refetch_interval);
+        if let Some(cached) = self.jwks.read().await.as_ref() {
+            if cached.fetched_at.elapsed() < ttl {
+                if let Some(key) = select(&cached.keys) {
+                    return Ok(key);
+                }
+            }
+            if !cached.refetch(kid, ttl, cooldown) {
+                return Err(unknown());
+            }
+        }
+
+        // expired, or the provider may have rotated its signing keys. the write lock is held while
+        // fetching, so validations waiting on it find the keys fetched rather than fetch again
+        let mut jwks = self.jwks.write().await;
+        let cached = match &mut *jwks {
+            Some(cached) if !cached.refetch(kid, ttl, cooldown) => cached,
+            slot => slot.insert(self.fetch_jwks().await?),
+        };
+        match select(&cached.keys) {
+            Some(key) => Ok(key),
+            None => {
+                cached.misses.insert(kid.map(str::to_string));
+                Err(unknown())
+            }
+        }
+    }
+
+    pub async fn validate(&self, token: &str) -> Result<Principal, FlymodelError> {
+        let header = decode_header(token).map_err(invalid_token)?;
+        let jwk = self.find_key(header.kid.as_deref()).await?;
+        if let Some(alg) = jwk.common.key_algorithm {
+            if Algorithm::from_str(&alg.to_string()).ok() != Some(header.alg) {
+                return Err(invalid_token("algorithm does not match the signing key"));
+            }
+        }
+        let key = DecodingKey::from_jwk(&jwk).map_err(invalid_token)?;
+
+        let discovery = self.discovery().await?;
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[discovery.issuer]);
+        validation.set_audience(&[self.conf.audience()]);
+
+        let claims = decode::<Claims>(token, &key, &validation)
+            .map_err(invalid_token)?
+            .claims;
+
+        Ok(Principal::new(
+            claims.sub,
+            Permissions::new(self.permissions_of(&claims.extra)),
+        ))
+    }
+
+    fn permissions_of(
+        &self,
+        claims: &serde_json::Map<String, serde_json::Value>,
+    ) -> Vec<Permission> {
+        let values: Vec<&str> = match claims.get(&self.conf.permissions_claim) {
+            Some(serde_json::Value::Array(values)) => {
+                values.iter().filter_map(|value| value.as_str()).collect()
+            }
+            Some(serde_json::Value::String(values)) => values.split_whitespace().collect(),
+            _ => vec![],
+        };
+        values
+            .into_iter()
+            .filter_map(|value| match Permission::try_from(value) {
+                Ok(perm) => Some(perm),
+                Err(err) => {
+                    tracing::warn!("ignoring unrecognised permission claim: {err}");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub async fn authorization_url(&self, state: &str) -> Result<Url, FlymodelError> {
+        let mut url = self.discovery().await?.authorization_endpoint;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.conf.client_id)
+            .append_pair(
+                "redirect_uri",
+                self.conf.redirect_url().map_err(provider_error)?.as_str(),
+            )
+            .append_pair("scope", &self.conf.scopes.join(" "))
+            .append_pair("state", state);
+        Ok(url)
+    }
+
+    pub async fn exchange_code(&self, code: &str) -> Result<serde_json::Value, FlymodelError> {
+        let discovery = self.discovery().await?;
+        let redirect = self.conf.redirect_url().map_err(provider_error)?;
+        let resp = self
+            .http
+            .post(discovery.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect.as_str()),
+                ("client_id", &self.conf.client_id),
+                ("client_secret", self.conf.client_secret.as_ref()),
+            ])
+            .send()
+            .await
+            .map_err(provider_error)?;
+        if !resp.status().is_success() {
+            return Err(FlymodelError::Unauthenticated(format!(
+                "the authorization code was rejected ({})",
+                resp.status()
+            )));
+        }
+        resp.json().await.map_err(provider_error)
+    }
+}
+
+fn new_state() -> Result<String, FlymodelError> {
+    let mut bs = [0u8; 24];
+    getrandom::getrandom(&mut bs).map_err(provider_error)?;
+    Ok(hex::encode(bs))
+}
+
+fn provider_of(auth: &Authenticator) -> Result<&OidcProvider, FlymodelError> {
+    auth.oidc()
+        .ok_or_else(|| FlymodelError::RuntimeDependencyError("oauth2 is not configured".into()))
+}
+
+pub async fn oauth_login(auth: Data<Authenticator>) -> actix_web::Result<HttpResponse> {
+    let provider = provider_of(&auth)?;
+    let state = new_state()?;
+    let location = provider.authorization_url(&state).await?;
+    Ok(HttpResponse::Found()
+        .cookie(
+            Cookie::build(STATE_COOKIE, state)
+                .path(provider.configuration().callback.clone())
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .finish(),
+        )
+        .insert_header((header::LOCATION, location.as_str()))
+        .finish())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+pub async fn oauth_callback(
+    req: HttpRequest,
+    auth: Data<Authenticator>,
+    params: Query<CallbackParams>,
+) -> actix_web::Result<HttpResponse> {
+    let provider = provider_of(&auth)?;
+    if let Some(error) = &params.error {
+        return Err(
+            FlymodelError::Unauthenticated(format!("authorization failed: {error}")).into(),
+        );
+    }
+    let expected = req.cookie(STATE_COOKIE).map(|it| it.value().to_string());
+    match (&params.state, expected) {
+        (Some(state), Some(expected)) if *state == expected => (),
+        _ => {
+            return Err(
+                FlymodelError::Unauthenticated("the login state does not match".into()).into(),
+            )
+        }
+    }
+    let code = params
+        .code
+        .as_deref()
+        .ok_or_else(|| FlymodelError::Unauthenticated("missing authorization code".into()))?;
+    let tokens = provider.exchange_code(code).await?;
+
+    let mut expired = Cookie::build(STATE_COOKIE, "")
+        .path(provider.configuration().callback.clone())
+        .finish();
+    expired.make_removal();
+    Ok(HttpResponse::Ok().cookie(expired).json(tokens))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use flymodel::{
+        config::{auth::Oauth2Configuration, secret::Secret},
+        perms::Perm,
+    };
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::OidcProvider;
+
+    const SECRET: &[u8] = b"flymodel-stub-issuer-secret";
+
+    struct StubIssuer {
+        base: String,
+        jwks_hits: Arc<AtomicUsize>,
+    }
+
+    async fn stub_issuer() -> StubIssuer {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let jwks_hits = Arc::new(AtomicUsize::new(0));
+
+        let issuer = base.clone();
+        let hits = jwks_hits.clone();
+        let server = HttpServer::new(move || {
+            let issuer = issuer.clone();
+            let hits = hits.clone();
+            App::new()
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(move || {
+                        let issuer = issuer.clone();
+                        async move {
+                            HttpResponse::Ok().json(json!({
+                                "issuer": issuer,
+                                "authorization_endpoint": format!("{issuer}/authorize"),
+                                "token_endpoint": format!("{issuer}/token"),
+                                "jwks_uri": format!("{issuer}/jwks"),
+                            }))
+                        }
+                    }),
+                )
+                .route(
+                    "/jwks",
+                    web::get().to(move || {
+                        hits.fetch_add(1, Ordering::SeqCst);
+                        async move {
+                            HttpResponse::Ok().json(json!({
+                                "keys": [{
+                                    "kty": "oct",
+                                    "kid": "stub",
+                                    "alg": "HS256",
+                                    "k": URL_SAFE_NO_PAD.encode(SECRET),
+                                }]
+                            }))
+                        }
+                    }),
+                )
+                .route(
+                    "/token",
+                    web::post().to(|form: web::Form<Vec<(String, String)>>| async move {
+                        let code = form.iter().find(|(k, _)| k == "code").map(|(_, v)| v);
+                        if code.map(String::as_str) == Some("valid") {
+                            HttpResponse::Ok().json(json!({
+                                "access_token": "issued",
+                                "token_type": "Bearer",
+                            }))
+                        } else {
+                            HttpResponse::BadRequest().finish()
+                        }
+                    }),
+                )
+        })
+        .listen(listener)
+        .unwrap()
+        .run();
+        tokio::spawn(server);
+        StubIssuer { base, jwks_hits }
+    }
+
+    fn provider(issuer: &StubIssuer) -> OidcProvider {
+        refetching_provider(issuer, 60)
+    }
+
+    fn refetching_provider(issuer: &StubIssuer, refetch_interval: u64) -> OidcProvider {
+        OidcProvider::new(Oauth2Configuration {
+            base_url: "http://localhost:9009".parse().unwrap(),
+            callback: "/auth/callback".into(),
+            client_id: "flymodel".into(),
+            client_secret: serde_json::from_value::<Secret<String>>(json!("shh")).unwrap(),
+            scopes: vec!["openid".into()],
+            well_known_url: format!("{}/.well-known/openid-configuration", issuer.base)
+                .parse()
+                .unwrap(),
+            audience: None,
+            permissions_claim: "flymodel_permissions".into(),
+            jwks_ttl: 300,
+            jwks_refetch_interval: refetch_interval,
+        })
+    }
+
+    fn token(issuer: &str, audience: &str, perms: serde_json::Value) -> String {
+        signed_by("stub", issuer, audience, perms)
+    }
+
+    fn signed_by(kid: &str, issuer: &str, audience: &str, perms: serde_json::Value) -> String {
+        let mut header = Header::new(jsonwebtoken::Algorithm::HS256);
+        header.kid = Some(kid.into());
+        encode(
+            &header,
+            &json!({
+                "sub": "ci-user",
+                "iss": issuer,
+                "aud": audience,
+                "exp": chrono::Utc::now().timestamp() + 600,
+                "flymodel_permissions": perms,
+            }),
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_bearer_validation_against_stub_issuer() {
+        let issuer = stub_issuer().await;
+        let provider = provider(&issuer);
+
+        let principal = provider
+            .validate(&token(
+                &issuer.base,
+                "flymodel",
+                json!(["namespace:3:write", "model:1:read", "bogus"]),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(principal.subject, "ci-user");
+        assert!(principal.authorize_namespace(3, Perm::W).is_ok());
+        assert!(principal.authorize_namespace(4, Perm::R).is_err());
+
+        // the jwks is cached between validations
+        provider
+            .validate(&token(&issuer.base, "flymodel", json!([])))
+            .await
+            .unwrap();
+        assert_eq!(issuer.jwks_hits.load(Ordering::SeqCst), 1);
+
+        assert!(provider
+            .validate(&token(&issuer.base, "someone-else", json!([])))
+            .await
+            .is_err());
+        assert!(provider
+            .validate(&token("http://elsewhere", "flymodel", json!([])))
+            .await
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_keys_refetch_sparingly() {
+        let issuer = stub_issuer().await;
+        let unknown = |kid| signed_by(kid, &issuer.base, "flymodel", json!([]));
+        let hits = || issuer.jwks_hits.load(Ordering::SeqCst);
+
+        // unknown keys wait out the refetch interval
+        let provider = provider(&issuer);
+        provider
+            .validate(&token(&issuer.base, "flymodel", json!([])))
+            .await
+            .unwrap();
+        for kid in ["rotated", "rotated", "forged"] {
+            assert!(provider.validate(&unknown(kid)).await.is_err());
+        }
+        assert_eq!(hits(), 1);
+
+        // past the interval an unknown key refetches once, then is remembered as a miss
+        let provider = refetching_provider(&issuer, 0);
+        provider
+            .validate(&token(&issuer.base, "flymodel", json!([])))
+            .await
+            .unwrap();
+        assert_eq!(hits(), 2);
+        assert!(provider.validate(&unknown("rotated")).await.is_err());
+        assert_eq!(hits(), 3);
+        assert!(provider.validate(&unknown("rotated")).await.is_err());
+        assert_eq!(hits(), 3);
+        assert!(provider.validate(&unknown("forged")).await.is_err());
+        assert_eq!(hits(), 4);
+        provider
+            .validate(&token(&issuer.base, "flymodel", json!([])))
+            .await
+            .unwrap();
+        assert_eq!(hits(), 4);
+    }
+
+    #[actix_web::test]
+    async fn test_code_exchange_against_stub_issuer() {
+        let issuer = stub_issuer().await;
+        let provider = provider(&issuer);
+
+        let url = provider.authorization_url("abc").await.unwrap();
+        assert!(url
+            .as_str()
+            .starts_with(&format!("{}/authorize", issuer.base)));
+        assert!(url
+            .query_pairs()
+            .any(|(k, v)| k == "redirect_uri" && v == "http://localhost:9009/auth/callback"));
+
+        let tokens = provider.exchange_code("valid").await.unwrap();
+        assert_eq!(tokens["access_token"], "issued");
+        assert!(provider.exchange_code("invalid").await.is_err());
+    }
+}
@@ -0,0 +1,328 @@
+use std::time::Duration;
+
+use async_graphql::{Context, SimpleObject};
+use chrono::{DateTime, Utc};
+use flymodel::{errs::FlymodelError, lifecycle::Lifecycle};
+use flymodel_entities::entities::{self, enums::RunState};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, DbConn, Statement};
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgListener, PgPool};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::artifacts::tickets::UploadedArtifact;
+
+/// the postgres channel server instances share registry events on
+pub const CHANNEL: &str = "flymodel_registry_events";
+
+const CAPACITY: usize = 1024;
+
+/// postgres rejects notifications with a payload of this many bytes or more
+const MAX_PAYLOAD: usize = 8000;
+
+const MAX_REASON: usize = 1024;
+
+const RELISTEN_AFTER: Duration = Duration::from_secs(5);
+
+/// a model version moved to another lifecycle
+#[derive(Clone, Debug, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
+pub struct ModelVersionStateChanged {
+    pub namespace: i64,
+    pub model: i64,
+    pub model_version: i64,
+    pub previous: Lifecycle,
+    pub state: Lifecycle,
+    /// the subject of the principal which made the transition
+    pub actor: String,
+    pub reason: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl ModelVersionStateChanged {
+    pub(crate) fn of(
+        namespace: i64,
+        model: i64,
+        model_version: i64,
+        recorded: entities::model_state_history::Model,
+    ) -> Self {
+        Self {
+            namespace,
+            model,
+            model_version,
+            previous: recorded.previous,
+            state: recorded.state,
+            actor: recorded.actor,
+            reason: recorded.reason,
+            changed_at: recorded.created_at,
+        }
+    }
+}
+
+/// an artifact was registered for a model version or one of its experiments
+#[derive(Clone, Debug, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
+pub struct ArtifactUploaded {
+    pub model_version: i64,
+    /// the experiment the artifact belongs to, unset for model artifacts
+    pub experiment: Option<i64>,
+    /// the id of the model artifact, or of the experiment artifact
+    pub artifact: i64,
+    pub name: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+impl From<&entities::model_artifact::Model> for ArtifactUploaded {
+    fn from(artifact: &entities::model_artifact::Model) -> Self {
+        Self {
+            model_version: artifact.version_id,
+            experiment: None,
+            artifact: artifact.id,
+            name: artifact.name.clone(),
+            uploaded_at: Utc::now(),
+        }
+    }
+}
+
+impl From<&entities::experiment_artifact::Model> for ArtifactUploaded {
+    fn from(artifact: &entities::experiment_artifact::Model) -> Self {
+        Self {
+            model_version: artifact.version_id,
+            experiment: Some(artifact.experiment_id),
+            artifact: artifact.id,
+            name: artifact.name.clone(),
+            uploaded_at: Utc::now(),
+        }
+    }
+}
+
+impl From<&UploadedArtifact> for ArtifactUploaded {
+    fn from(uploaded: &UploadedArtifact) -> Self {
+        match uploaded {
+            UploadedArtifact::ModelArtifact(artifact) => artifact.into(),
+            UploadedArtifact::ExperimentArtifact(artifact) => artifact.into(),
+        }
+    }
+}
+
+/// the run of an experiment moved to another state
+#[derive(Clone, Debug, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
+pub struct ExperimentStateChanged {
+    pub experiment: i64,
+    pub previous: RunState,
+    pub state: RunState,
+    /// the number of times the run was retried after failing
+    pub retry: Option<i32>,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl ExperimentStateChanged {
+    pub(crate) fn of(updated: &entities::experiment_state::Model, previous: RunState) -> Self {
+        Self {
+            experiment: updated.experiment_id,
+            previous,
+            state: updated.state,
+            retry: updated.retry,
+            changed_at: updated.last_modified,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RegistryEvent {
+    ModelVersionStateChanged(ModelVersionStateChanged),
+    ArtifactUploaded(ArtifactUploaded),
+    ExperimentStateChanged(ExperimentStateChanged),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Notification {
+    origin: u64,
+    event: RegistryEvent,
+}
+
+/// on postgres events are also notified on [CHANNEL] for the other instances sharing the database
+#[derive(Clone)]
+pub struct EventBus {
+    origin: u64,
+    sender: broadcast::Sender<RegistryEvent>,
+    db: Option<DbConn>,
+}
+
+impl EventBus {
+    pub fn new(db: DbConn) -> Self {
+        let mut bs = [0u8; 8];
+        let origin = match getrandom::getrandom(&mut bs) {
+            Ok(()) => u64::from_le_bytes(bs),
+            Err(_) => std::process::id().into(),
+        };
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self {
+            origin,
+            sender,
+            db: matches!(db, DatabaseConnection::SqlxPostgresPoolConnection(_)).then_some(db),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RegistryEvent> {
+        self.sender.subscribe()
+    }
+
+    /// failing to notify other instances is only logged, as the change was already committed
+    pub async fn publish(&self, event: RegistryEvent) {
+        // sending only fails while this instance has no subscriptions
+        let _ = self.sender.send(event.clone());
+        if let Some(db) = &self.db {
+            if let Err(err) = notify(db, self.origin, event).await {
+                warn!("failed to notify other instances of a registry event: {err}");
+            }
+        }
+    }
+
+    /// `None` unless the database is postgres, events notified while reconnecting are missed
+    pub fn spawn_listener(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let pool = self.db.as_ref()?.get_postgres_connection_pool().clone();
+        let origin = self.origin;
+        let sender = self.sender.clone();
+        Some(tokio::spawn(async move {
+            loop {
+                if let Err(err) = relay(&pool, origin, &sender).await {
+                    warn!("stopped listening for registry events, retrying in {RELISTEN_AFTER:?}: {err}");
+                }
+                tokio::time::sleep(RELISTEN_AFTER).await;
+            }
+        }))
+    }
+}
+
+fn payload(origin: u64, mut event: RegistryEvent) -> Result<String, FlymodelError> {
+    if let RegistryEvent::ModelVersionStateChanged(ModelVersionStateChanged {
+        reason: Some(reason),
+        ..
+    }) = &mut event
+    {
+        if reason.len() > MAX_REASON {
+            let mut at = MAX_REASON;
+            while !reason.is_char_boundary(at) {
+                at -= 1;
+            }
+            reason.truncate(at);
+        }
+    }
+    let payload = serde_json::to_string(&Notification { origin, event })
+        .map_err(|err| FlymodelError::NonDeterministicError(err.to_string()))?;
+    if payload.len() >= MAX_PAYLOAD {
+        return Err(FlymodelError::ContraintError(format!(
+            "the notification is {} bytes, postgres takes less than {MAX_PAYLOAD}",
+            payload.len()
+        )));
+    }
+    Ok(payload)
+}
+
+async fn notify(db: &DbConn, origin: u64, event: RegistryEvent) -> Result<(), FlymodelError> {
+    let payload = payload(origin, event)?;
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "select pg_notify($1, $2)",
+        [CHANNEL.into(), payload.into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn relay(
+    pool: &PgPool,
+    origin: u64,
+    sender: &broadcast::Sender<RegistryEvent>,
+) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CHANNEL).await?;
+    debug!("listening for registry events on {CHANNEL}");
+    loop {
+        let notification = listener.recv().await?;
+        match serde_json::from_str::<Notification>(notification.payload()) {
+            // published to the subscriptions of this instance already
+            Ok(notification) if notification.origin == origin => {}
+            Ok(notification) => {
+                let _ = sender.send(notification.event);
+            }
+            Err(err) => warn!("ignoring a malformed registry event: {err}"),
+        }
+    }
+}
+
+pub(crate) async fn publish(ctx: &Context<'_>, event: RegistryEvent) {
+    match ctx.data_opt::<EventBus>() {
+        Some(bus) => bus.publish(event).await,
+        None => debug!("no event bus to publish {event:?} on"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use flymodel::lifecycle::Lifecycle;
+
+    use super::{
+        payload, ArtifactUploaded, ModelVersionStateChanged, Notification, RegistryEvent,
+        MAX_PAYLOAD, MAX_REASON,
+    };
+
+    #[test]
+    fn test_notification_roundtrip() {
+        let events = [
+            RegistryEvent::ModelVersionStateChanged(ModelVersionStateChanged {
+                namespace: 1,
+                model: 2,
+                model_version: 3,
+                previous: Lifecycle::Test,
+                state: Lifecycle::Qa,
+                actor: "anonymous".into(),
+                reason: None,
+                changed_at: Utc::now(),
+            }),
+            RegistryEvent::ArtifactUploaded(ArtifactUploaded {
+                model_version: 3,
+                experiment: Some(4),
+                artifact: 5,
+                name: "weights.safetensors".into(),
+                uploaded_at: Utc::now(),
+            }),
+        ];
+        for event in events {
+            let payload = serde_json::to_string(&Notification {
+                origin: 7,
+                event: event.clone(),
+            })
+            .unwrap();
+            let notification: Notification = serde_json::from_str(&payload).unwrap();
+            assert_eq!(notification.origin, 7);
+            assert_eq!(notification.event, event);
+        }
+    }
+
+    #[test]
+    fn test_payload_cuts_the_reason() {
+        let changed_at = Utc::now();
+        let event = |reason: String| {
+            RegistryEvent::ModelVersionStateChanged(ModelVersionStateChanged {
+                namespace: 1,
+                model: 2,
+                model_version: 3,
+                previous: Lifecycle::Test,
+                state: Lifecycle::Qa,
+                actor: "anonymous".into(),
+                reason: Some(reason),
+                changed_at,
+            })
+        };
+        // each `é` takes 2 bytes, so the cut falls within one
+        let sent = payload(7, event(format!("a{}", "é".repeat(MAX_PAYLOAD)))).unwrap();
+        assert!(sent.len() < MAX_PAYLOAD);
+        let notification: Notification = serde_json::from_str(&sent).unwrap();
+        assert_eq!(
+            notification.event,
+            event(format!("a{}", "é".repeat((MAX_REASON - 1) / 2)))
+        );
+    }
+}
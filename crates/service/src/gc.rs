@@ -0,0 +1,448 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use chrono::Utc;
+use flymodel::{config::gc::GcConfiguration, errs::FlymodelError, storage::ObjectVersion};
+use flymodel_entities::{db::DbLoader, entities};
+use flymodel_registry::storage::StorageOrchestrator;
+use sea_orm::{DbConn, EntityTrait, TransactionTrait};
+use tracing::{debug, info, warn};
+
+use crate::artifacts::{resumable::part_key, sink_of};
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct StoredObject {
+    pub bucket: String,
+    pub path: String,
+    pub version_id: Option<String>,
+    pub size: u64,
+}
+
+impl StoredObject {
+    fn of_blob(bucket: &entities::bucket::Model, blob: &entities::object_blob::Model) -> Self {
+        Self {
+            bucket: bucket.name.clone(),
+            path: blob.key.clone(),
+            version_id: Some(blob.version_id.clone()),
+            size: blob.size.max(0) as u64,
+        }
+    }
+
+    fn listed(bucket: &entities::bucket::Model, version: ObjectVersion) -> Self {
+        Self {
+            bucket: bucket.name.clone(),
+            path: version.path,
+            version_id: version.version_id,
+            size: version.size,
+        }
+    }
+}
+
+/// what a garbage collection removed, or would have removed when dry
+#[derive(Debug, Default, serde::Serialize)]
+pub struct GcReport {
+    pub dry: bool,
+    pub blobs: Vec<entities::object_blob::Model>,
+    /// objects no blob or staged chunk described
+    pub objects: Vec<StoredObject>,
+    pub failed: Vec<StoredObject>,
+}
+
+impl GcReport {
+    pub fn bytes(&self) -> u64 {
+        self.blobs
+            .iter()
+            .map(|blob| blob.size.max(0) as u64)
+            .chain(self.objects.iter().map(|object| object.size))
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blobs.is_empty() && self.objects.is_empty()
+    }
+}
+
+async fn delete_object(
+    storage: &StorageOrchestrator,
+    bucket: &entities::bucket::Model,
+    object: &StoredObject,
+) -> Result<(), FlymodelError> {
+    sink_of(bucket, storage)?
+        .del(object.path.clone(), object.version_id.clone())
+        .await
+}
+
+// buckets of several namespaces may share a storage by name,
+// which is listed once against the references of every one of them
+async fn strays(
+    db: &DbConn,
+    storage: &StorageOrchestrator,
+    buckets: &[&entities::bucket::Model],
+    before: SystemTime,
+) -> Result<Vec<StoredObject>, FlymodelError> {
+    let Some(bucket) = buckets.first() else {
+        return Ok(vec![]);
+    };
+    // listed first, so objects referenced by the time the database is read are kept
+    let listed = sink_of(bucket, storage)?.list().await?;
+    let mut referenced: HashSet<(String, Option<String>)> = HashSet::new();
+    let mut awaited: HashSet<String> = HashSet::new();
+    for sharing in buckets {
+        referenced.extend(
+            DbLoader::<entities::object_blob::Model>::stored(db, sharing.id)
+                .await?
+                .into_iter()
+                .map(|(key, version_id)| (key, Some(version_id))),
+        );
+        referenced.extend(
+            DbLoader::<entities::upload_ticket_part::Model>::staged(db, sharing.id)
+                .await?
+                .into_iter()
+                .map(|part| {
+                    (
+                        part_key(part.ticket_id, part.part_number),
+                        Some(part.version_id),
+                    )
+                }),
+        );
+        awaited.extend(
+            DbLoader::<entities::upload_ticket::Model>::awaited_keys(db, sharing.id).await?,
+        );
+    }
+    Ok(listed
+        .into_iter()
+        .filter(|version| version.last_modified < before)
+        .filter(|version| !awaited.contains(&version.path))
+        .filter(|version| !referenced.contains(&(version.path.clone(), version.version_id.clone())))
+        .map(|version| StoredObject::listed(bucket, version))
+        .collect())
+}
+
+/// removes unreferenced blobs, then the objects nothing accounts for, both kept for `grace_period`
+pub async fn collect_garbage(
+    db: &DbConn,
+    storage: &StorageOrchestrator,
+    grace_period: Duration,
+    dry: bool,
+) -> Result<GcReport, FlymodelError> {
+    let before = chrono::Duration::from_std(grace_period)
+        .ok()
+        .and_then(|grace| Utc::now().checked_sub_signed(grace))
+        .ok_or_else(|| {
+            FlymodelError::ContraintError(format!("invalid grace period: {grace_period:?}"))
+        })?;
+    let buckets: HashMap<i64, entities::bucket::Model> = entities::bucket::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|bucket| (bucket.id, bucket))
+        .collect();
+    let mut report = GcReport {
+        dry,
+        ..Default::default()
+    };
+
+    let orphans = DbLoader::<entities::object_blob::Model>::orphaned(db, before).await?;
+    report.blobs = if dry {
+        orphans
+    } else {
+        let tx = db.begin().await?;
+        let released = DbLoader::<entities::object_blob::Model>::release(&tx, orphans).await?;
+        tx.commit().await?;
+        released
+    };
+    // objects left behind by a failed deletion are retried by the next collection
+    let mut attempted = HashSet::new();
+    if !dry {
+        for blob in &report.blobs {
+            let Some(bucket) = buckets.get(&blob.bucket_id) else {
+                continue;
+            };
+            let object = StoredObject::of_blob(bucket, blob);
+            if let Err(err) = delete_object(storage, bucket, &object).await {
+                warn!("failed to delete the object of blob {}: {err}", blob.id);
+                report.failed.push(object.clone());
+            }
+            attempted.insert((object.bucket, object.path, object.version_id));
+        }
+    }
+
+    let before = SystemTime::now()
+        .checked_sub(grace_period)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut shared: BTreeMap<&str, Vec<&entities::bucket::Model>> = BTreeMap::new();
+    for bucket in buckets.values() {
+        shared.entry(&bucket.name).or_default().push(bucket);
+    }
+    for sharing in shared.values() {
+        let bucket = sharing[0];
+        let strays = match strays(db, storage, sharing, before).await {
+            Ok(strays) => strays,
+            Err(FlymodelError::UnsupportedOperation(reason)) => {
+                debug!("skipping the objects of {}: {reason}", bucket.name);
+                continue;
+            }
+            Err(err) => {
+                warn!("failed to list the objects of {}: {err}", bucket.name);
+                continue;
+            }
+        };
+        for object in strays {
+            if attempted.contains(&(
+                object.bucket.clone(),
+                object.path.clone(),
+                object.version_id.clone(),
+            )) {
+                continue;
+            }
+            if !dry {
+                if let Err(err) = delete_object(storage, bucket, &object).await {
+                    warn!(
+                        "failed to delete {} ({:?}) from {}: {err}",
+                        object.path, object.version_id, bucket.name
+                    );
+                    report.failed.push(object);
+                    continue;
+                }
+            }
+            report.objects.push(object);
+        }
+    }
+    Ok(report)
+}
+
+pub fn spawn_garbage_collector(
+    db: DbConn,
+    storage: Arc<StorageOrchestrator>,
+    conf: GcConfiguration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(Duration::from_secs(conf.interval));
+        loop {
+            ticks.tick().await;
+            match collect_garbage(
+                &db,
+                &storage,
+                Duration::from_secs(conf.grace_period),
+                false,
+            )
+            .await
+            {
+                Ok(report) if report.is_empty() => {}
+                Ok(report) => info!(
+                    "garbage collection removed {} blobs & {} objects ({} bytes), {} objects left in storage",
+                    report.blobs.len(),
+                    report.objects.len(),
+                    report.bytes(),
+                    report.failed.len(),
+                ),
+                Err(err) => warn!("garbage collection failed: {err}"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use chrono::Utc;
+    use flymodel::{lifecycle::Lifecycle, storage::StorageProvider};
+    use flymodel_entities::{
+        entities::{self, enums::UploadTicketStatus},
+        testing,
+    };
+    use flymodel_registry::memory::MemoryStorage;
+    use sea_orm::{ActiveModelTrait, ActiveValue, DbConn, EntityTrait};
+
+    use super::collect_garbage;
+    use crate::{
+        artifacts::resumable::part_key,
+        testing::{memory_storage, stored_blob},
+    };
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    async fn put(storage: &MemoryStorage, key: &str) -> String {
+        storage
+            .put(key.into(), Bytes::from_static(b"hello world"))
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn blob_exists(db: &DbConn, id: i64) -> bool {
+        entities::object_blob::Entity::find_by_id(id)
+            .one(db)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    #[tokio::test]
+    async fn test_collect_orphaned_blobs() {
+        let db = testing::database().await;
+        let (storage, orchestrator) = memory_storage("ml-test", Lifecycle::Test);
+        let namespace = testing::namespace(&db, "ns").await;
+        let bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+        let model = testing::model(&db, namespace.id, "model").await;
+        let version = testing::version(&db, model.id, "1", Lifecycle::Test, Utc::now()).await;
+
+        let stale = Utc::now() - chrono::Duration::hours(2);
+        let referenced = stored_blob(&db, &storage, bucket.id, "referenced", stale).await;
+        testing::model_artifact(&db, version.id, referenced.id, "weights").await;
+        let orphan = stored_blob(&db, &storage, bucket.id, "orphan", stale).await;
+        let recent = stored_blob(&db, &storage, bucket.id, "recent", Utc::now()).await;
+
+        let report = collect_garbage(&db, &orchestrator, HOUR, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            report.blobs.iter().map(|blob| blob.id).collect::<Vec<_>>(),
+            vec![orphan.id]
+        );
+        assert!(report.objects.is_empty());
+        assert!(report.failed.is_empty());
+
+        assert!(blob_exists(&db, referenced.id).await);
+        assert!(!blob_exists(&db, orphan.id).await);
+        assert!(blob_exists(&db, recent.id).await);
+        assert_eq!(storage.versions("referenced".into()).len(), 1);
+        assert!(storage.versions("orphan".into()).is_empty());
+        assert_eq!(storage.versions("recent".into()).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_collect_stray_objects() {
+        let db = testing::database().await;
+        let (storage, orchestrator) = memory_storage("ml-test", Lifecycle::Test);
+        let namespace = testing::namespace(&db, "ns").await;
+        let bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+        let model = testing::model(&db, namespace.id, "model").await;
+        let version = testing::version(&db, model.id, "1", Lifecycle::Test, Utc::now()).await;
+
+        let referenced = stored_blob(&db, &storage, bucket.id, "referenced", Utc::now()).await;
+        testing::model_artifact(&db, version.id, referenced.id, "weights").await;
+        put(&storage, "stray").await;
+
+        // a chunk staged for a resumable upload, and an object a presigned upload is writing
+        let resumable = testing::upload_ticket(
+            &db,
+            bucket.id,
+            version.id,
+            "resumable",
+            UploadTicketStatus::Uploading,
+        )
+        .await;
+        let part = part_key(resumable.id, 1);
+        let staged = put(&storage, &part).await;
+        entities::upload_ticket_part::ActiveModel {
+            ticket_id: ActiveValue::Set(resumable.id),
+            part_number: ActiveValue::Set(1),
+            version_id: ActiveValue::Set(staged),
+            size: ActiveValue::Set(11),
+            sha256: ActiveValue::Set(String::new()),
+            created_at: ActiveValue::Set(Utc::now()),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        testing::upload_ticket(
+            &db,
+            bucket.id,
+            version.id,
+            "presigned",
+            UploadTicketStatus::Pending,
+        )
+        .await;
+        put(&storage, "presigned").await;
+
+        let report = collect_garbage(&db, &orchestrator, Duration::ZERO, false)
+            .await
+            .unwrap();
+        assert!(report.blobs.is_empty());
+        assert_eq!(
+            report
+                .objects
+                .iter()
+                .map(|object| object.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["stray"]
+        );
+
+        assert!(storage.versions("stray".into()).is_empty());
+        assert_eq!(storage.versions("referenced".into()).len(), 1);
+        assert_eq!(storage.versions(part).len(), 1);
+        assert_eq!(storage.versions("presigned".into()).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_collect_shared_bucket() {
+        let db = testing::database().await;
+        let (storage, orchestrator) = memory_storage("ml-test", Lifecycle::Test);
+        let mut blobs = vec![];
+        for name in ["first", "second"] {
+            let namespace = testing::namespace(&db, name).await;
+            let bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+            let model = testing::model(&db, namespace.id, "model").await;
+            let version = testing::version(&db, model.id, "1", Lifecycle::Test, Utc::now()).await;
+            let blob = stored_blob(&db, &storage, bucket.id, name, Utc::now()).await;
+            testing::model_artifact(&db, version.id, blob.id, "weights").await;
+            blobs.push(blob);
+        }
+        put(&storage, "stray").await;
+
+        let report = collect_garbage(&db, &orchestrator, Duration::ZERO, false)
+            .await
+            .unwrap();
+        assert!(report.blobs.is_empty());
+        assert_eq!(
+            report
+                .objects
+                .iter()
+                .map(|object| object.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["stray"]
+        );
+
+        for blob in blobs {
+            assert!(blob_exists(&db, blob.id).await);
+            assert_eq!(storage.versions(blob.key).len(), 1);
+        }
+        assert!(storage.versions("stray".into()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collect_dry() {
+        let db = testing::database().await;
+        let (storage, orchestrator) = memory_storage("ml-test", Lifecycle::Test);
+        let namespace = testing::namespace(&db, "ns").await;
+        let bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+
+        let orphan = stored_blob(
+            &db,
+            &storage,
+            bucket.id,
+            "orphan",
+            Utc::now() - chrono::Duration::hours(2),
+        )
+        .await;
+        put(&storage, "stray").await;
+
+        let report = collect_garbage(&db, &orchestrator, Duration::ZERO, true)
+            .await
+            .unwrap();
+        assert!(report.dry);
+        assert_eq!(report.blobs, vec![orphan.clone()]);
+        assert_eq!(report.objects.len(), 1);
+        assert_eq!(report.objects[0].path, "stray");
+
+        assert!(blob_exists(&db, orphan.id).await);
+        assert_eq!(storage.versions("orphan".into()).len(), 1);
+        assert_eq!(storage.versions("stray".into()).len(), 1);
+    }
+}
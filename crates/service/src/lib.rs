@@ -1,6 +1,15 @@
 pub mod app;
 pub mod artifacts;
+pub mod audit;
+pub mod auth;
+pub mod events;
+pub mod gc;
 pub mod mutations;
 pub mod queries;
+pub mod retention;
 pub mod schema;
+pub mod subscriptions;
+#[cfg(test)]
+mod testing;
+pub mod webhooks;
 pub use flymodel_entities::db;
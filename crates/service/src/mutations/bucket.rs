@@ -1,8 +1,13 @@
 use async_graphql::{Context, Object};
 
-use flymodel::lifecycle::Lifecycle;
+use flymodel::{lifecycle::Lifecycle, perms::Perm};
 use flymodel_entities::{db::DbLoader, entities};
 
+use crate::{
+    audit::{audit, summary, AuditAction, AuditEvent, ResourceKind},
+    auth::{authorize_bucket, authorize_namespace},
+};
+
 #[derive(Clone, Default)]
 pub struct BucketMutations;
 
@@ -13,11 +18,19 @@ impl BucketMutations {
         ctx: &Context<'ctx>,
         id: i64,
     ) -> Result<bool, async_graphql::Error> {
+        authorize_bucket(ctx, id, Perm::W).await?;
         let db = DbLoader::<entities::bucket::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        // user validation here
-        db.delete_bucket(id).await
+        let deleted = db.delete_bucket(id).await?;
+        if deleted {
+            audit(
+                ctx,
+                AuditEvent::new(AuditAction::Delete, ResourceKind::Bucket, id),
+            )
+            .await;
+        }
+        Ok(deleted)
     }
 
     pub async fn create_bucket<'ctx>(
@@ -28,10 +41,17 @@ impl BucketMutations {
         region: Option<String>,
         role: Lifecycle,
     ) -> Result<entities::bucket::Model, async_graphql::Error> {
+        authorize_namespace(ctx, namespace, Perm::W)?;
         let db = DbLoader::<entities::bucket::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        // user validation here
-        db.create_bucket(namespace, name, region, role).await
+        let created = db.create_bucket(namespace, name, region, role).await?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Create, ResourceKind::Bucket, created.id)
+                .after(&summary(&created)),
+        )
+        .await;
+        Ok(created)
     }
 }
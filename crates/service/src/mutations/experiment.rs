@@ -1,10 +1,55 @@
 use async_graphql::{Context, Object};
 
-use flymodel_entities::{db::DbLoader, entities};
+use flymodel::{errs::FlymodelError, perms::Perm};
+use flymodel_entities::{
+    db::DbLoader,
+    entities::{self, experiment_state::RunTransition},
+};
+use sea_orm::TransactionTrait;
+
+use crate::{
+    artifacts::{delete_blob_objects, storage},
+    audit::{audit, summary, AuditAction, AuditEvent, ResourceKind},
+    auth::{authorize_experiment, authorize_model_version},
+    events::{publish, ExperimentStateChanged, RegistryEvent},
+};
 
 #[derive(Clone, Default)]
 pub struct ExperimentMutations;
 
+async fn advance(
+    ctx: &Context<'_>,
+    id: i64,
+    transition: RunTransition,
+    duration_ms: Option<i64>,
+) -> Result<entities::experiment_state::Model, async_graphql::Error> {
+    authorize_experiment(ctx, id, Perm::W).await?;
+    let (updated, previous) = DbLoader::<entities::experiment_state::Model>::with_context(ctx)?
+        .loader()
+        .advance(id, transition, duration_ms)
+        .await
+        .map_err(|err| err.into_graphql_error())?;
+    let action = match transition {
+        RunTransition::Start => AuditAction::Start,
+        RunTransition::Pass => AuditAction::Pass,
+        RunTransition::Fail => AuditAction::Fail,
+        RunTransition::Retry => AuditAction::Retry,
+    };
+    audit(
+        ctx,
+        AuditEvent::new(action, ResourceKind::Experiment, id)
+            .before(&summary(&previous))
+            .after(&summary(&updated.state)),
+    )
+    .await;
+    publish(
+        ctx,
+        RegistryEvent::ExperimentStateChanged(ExperimentStateChanged::of(&updated, previous)),
+    )
+    .await;
+    Ok(updated)
+}
+
 #[Object]
 impl ExperimentMutations {
     pub async fn create_experiment<'ctx>(
@@ -12,25 +57,189 @@ impl ExperimentMutations {
         ctx: &Context<'ctx>,
         model_version: i64,
         name: String,
+        params: Option<Vec<entities::experiment_param::ParamInput>>,
+        environment: Option<entities::experiment_environment::EnvironmentInput>,
     ) -> Result<entities::experiment::Model, async_graphql::Error> {
+        authorize_model_version(ctx, model_version, Perm::W).await?;
         let db = DbLoader::<entities::experiment::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        // user validation here
-        db.create_experiment(model_version, name).await
+        let created = db
+            .create_experiment(model_version, name, params.unwrap_or_default(), environment)
+            .await?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Create, ResourceKind::Experiment, created.id)
+                .after(&summary(&created)),
+        )
+        .await;
+        Ok(created)
     }
 
-    #[allow(unused_variables)]
+    /// trashes an experiment, or deletes it & its unreferenced objects for good when `hard` is set
     pub async fn delete_experiment<'ctx>(
         &self,
         ctx: &Context<'ctx>,
         id: i64,
         hard: Option<bool>,
     ) -> Result<bool, async_graphql::Error> {
+        authorize_experiment(ctx, id, Perm::W).await?;
+        let db = DbLoader::<entities::experiment::Model>::with_context(ctx)
+            .map_err(|err| err.into_graphql_error())?
+            .loader();
+        let deleted = AuditEvent::new(AuditAction::Delete, ResourceKind::Experiment, id);
+        if !hard.unwrap_or_default() {
+            let trashed = db
+                .soft_delete(id)
+                .await
+                .map_err(|err| err.into_graphql_error())?;
+            if trashed {
+                audit(ctx, deleted.after("trashed")).await;
+            }
+            return Ok(trashed);
+        }
+        let storage = storage(ctx).map_err(|err| err.into_graphql_error())?;
+        let purged = async {
+            let tx = db.db.begin().await?;
+            let purged = DbLoader::<entities::experiment::Model>::purge(&tx, id).await?;
+            tx.commit().await?;
+            Ok::<_, FlymodelError>(purged)
+        }
+        .await
+        .map_err(|err| err.into_graphql_error())?;
+        match purged {
+            Some(blobs) => {
+                audit(ctx, deleted.after("purged")).await;
+                delete_blob_objects(&db.db, storage, &blobs).await;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// marks the run of a created experiment as running
+    pub async fn start_experiment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+    ) -> Result<entities::experiment_state::Model, async_graphql::Error> {
+        advance(ctx, id, RunTransition::Start, None).await
+    }
+
+    /// records a passed run, lasting `durationMs` or else the time since it started
+    pub async fn pass_experiment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+        duration_ms: Option<i64>,
+    ) -> Result<entities::experiment_state::Model, async_graphql::Error> {
+        advance(ctx, id, RunTransition::Pass, duration_ms).await
+    }
+
+    /// records a failed run, lasting `durationMs` or else the time since it started
+    pub async fn fail_experiment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+        duration_ms: Option<i64>,
+    ) -> Result<entities::experiment_state::Model, async_graphql::Error> {
+        advance(ctx, id, RunTransition::Fail, duration_ms).await
+    }
+
+    /// runs a failed experiment again, counting the retry & discarding its result
+    pub async fn retry_experiment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+    ) -> Result<entities::experiment_state::Model, async_graphql::Error> {
+        advance(ctx, id, RunTransition::Retry, None).await
+    }
+
+    /// takes an experiment out of the trash, its version must not be in the trash
+    pub async fn restore_experiment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+    ) -> Result<entities::experiment::Model, async_graphql::Error> {
+        authorize_experiment(ctx, id, Perm::W).await?;
         let db = DbLoader::<entities::experiment::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        // user validation here
-        db.delete_experiment(id).await
+        let restored = db
+            .restore(id)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Restore, ResourceKind::Experiment, id),
+        )
+        .await;
+        Ok(restored)
+    }
+
+    /// logs a batch of metrics, a metric logged again at the same step replacing the stored value
+    pub async fn log_metrics<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        experiment: i64,
+        metrics: Vec<entities::experiment_metric::MetricInput>,
+    ) -> Result<u64, async_graphql::Error> {
+        authorize_experiment(ctx, experiment, Perm::W).await?;
+        let logged = DbLoader::<entities::experiment_metric::Model>::with_context(ctx)?
+            .loader()
+            .log(experiment, metrics)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Update, ResourceKind::Experiment, experiment)
+                .after(&format!("logged {logged} metrics")),
+        )
+        .await;
+        Ok(logged)
+    }
+
+    /// sets hyperparameters of an experiment, replacing the values of keys it already has
+    pub async fn set_experiment_params<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        experiment: i64,
+        params: Vec<entities::experiment_param::ParamInput>,
+    ) -> Result<Vec<entities::experiment_param::Model>, async_graphql::Error> {
+        authorize_experiment(ctx, experiment, Perm::W).await?;
+        let params = DbLoader::<entities::experiment_param::Model>::with_context(ctx)?
+            .loader()
+            .set_params(experiment, params)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Update, ResourceKind::Experiment, experiment)
+                .after(&summary(&params)),
+        )
+        .await;
+        Ok(params)
+    }
+
+    /// records the environment an experiment runs in, replacing the one captured before
+    pub async fn record_experiment_environment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        experiment: i64,
+        environment: entities::experiment_environment::EnvironmentInput,
+    ) -> Result<entities::experiment_environment::Model, async_graphql::Error> {
+        authorize_experiment(ctx, experiment, Perm::W).await?;
+        let recorded = DbLoader::<entities::experiment_environment::Model>::with_context(ctx)?
+            .loader()
+            .record(experiment, environment)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Update, ResourceKind::Experiment, experiment)
+                .after(&summary(&recorded)),
+        )
+        .await;
+        Ok(recorded)
     }
 }
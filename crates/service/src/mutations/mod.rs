@@ -3,12 +3,18 @@ use async_graphql::MergedObject;
 use self::{
     bucket::BucketMutations, experiment::ExperimentMutations, model::ModelMutations,
     model_version::ModelVersionMutations, namespace::NamespaceMutations,
+    promotion::PromotionMutations, tag::TagMutations, upload::UploadMutations,
+    webhook::WebhookMutations,
 };
 pub mod bucket;
 pub mod experiment;
 pub mod model;
 pub mod model_version;
 pub mod namespace;
+pub mod promotion;
+pub mod tag;
+pub mod upload;
+pub mod webhook;
 
 #[derive(MergedObject, Clone, Default)]
 pub struct Mutation(
@@ -16,5 +22,145 @@ pub struct Mutation(
     BucketMutations,
     ModelMutations,
     ModelVersionMutations,
+    PromotionMutations,
     ExperimentMutations,
+    TagMutations,
+    UploadMutations,
+    WebhookMutations,
 );
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use async_graphql::{Request, Response};
+    use flymodel::{
+        lifecycle::Lifecycle,
+        perms::{Perm, Permission, Permissions, Principal},
+    };
+    use flymodel_entities::{entities, testing};
+    use flymodel_events::{AuditAction, AuditEvent, Subscriber};
+    use flymodel_registry::storage::StorageOrchestrator;
+    use sea_orm::{DbConn, EntityTrait};
+
+    use crate::{
+        events::EventBus,
+        schema::{build_schema, FlymodelSchema},
+        testing::memory_storage,
+    };
+
+    pub(super) fn schema(
+        db: &DbConn,
+        storage: StorageOrchestrator,
+    ) -> (FlymodelSchema, Subscriber<AuditEvent>) {
+        let (audit, audit_log) = flymodel_events::bus::<AuditEvent>(8).unwrap();
+        let schema = build_schema(
+            db.clone(),
+            Arc::new(storage),
+            EventBus::new(db.clone()),
+            audit,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        (schema, audit_log)
+    }
+
+    pub(super) async fn execute(
+        schema: &FlymodelSchema,
+        query: String,
+        permissions: Vec<Permission>,
+    ) -> Response {
+        schema
+            .execute(
+                Request::new(query).data(Principal::new("alice", Permissions::new(permissions))),
+            )
+            .await
+    }
+
+    fn denied(resp: &Response) -> bool {
+        resp.errors.iter().any(|err| {
+            err.extensions
+                .as_ref()
+                .and_then(|ext| ext.get("kind"))
+                .is_some_and(|kind| kind == &async_graphql::Value::from("PermissionDenied"))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_mutations_require_write() {
+        let db = testing::database().await;
+        let (_, storage) = memory_storage("ml-test", Lifecycle::Test);
+        let (schema, mut audit_log) = schema(&db, storage);
+        let namespace = testing::namespace(&db, "ns").await;
+        let model = testing::model(&db, namespace.id, "model").await;
+        let other = testing::model(&db, namespace.id, "other").await;
+        let reader = vec![Permission::Namespace {
+            perm: Perm::R,
+            id: namespace.id,
+        }];
+
+        let resp = execute(
+            &schema,
+            format!(
+                r#"mutation {{ createModel(namespace: {}, name: "new") {{ id }} }}"#,
+                namespace.id
+            ),
+            reader.clone(),
+        )
+        .await;
+        assert!(denied(&resp), "{:?}", resp.errors);
+        let resp = execute(
+            &schema,
+            format!("mutation {{ deleteModel(id: {}) }}", model.id),
+            reader,
+        )
+        .await;
+        assert!(denied(&resp), "{:?}", resp.errors);
+        // writing to one model does not extend to the others of its namespace
+        let resp = execute(
+            &schema,
+            format!("mutation {{ deleteModel(id: {}) }}", model.id),
+            vec![Permission::Model {
+                perm: Perm::W,
+                id: other.id,
+            }],
+        )
+        .await;
+        assert!(denied(&resp), "{:?}", resp.errors);
+        assert_eq!(
+            entities::model::Entity::find()
+                .all(&db)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let resp = execute(
+            &schema,
+            format!("mutation {{ deleteModel(id: {}) }}", model.id),
+            vec![Permission::Namespace {
+                perm: Perm::W,
+                id: namespace.id,
+            }],
+        )
+        .await;
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+        assert!(entities::model::Entity::find_by_id(model.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .is_none());
+
+        // only the permitted deletion was audited
+        let event = audit_log.recv().await.unwrap();
+        assert_eq!(event.action, AuditAction::Delete);
+        assert_eq!(event.resource_id, model.id);
+        assert_eq!(event.actor.as_str(), "alice");
+        let mut rest = Vec::new();
+        audit_log.drain(&mut rest, 8);
+        assert!(rest.is_empty());
+    }
+}
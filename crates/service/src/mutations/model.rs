@@ -1,7 +1,13 @@
 use async_graphql::{Context, Object};
 
+use flymodel::perms::Perm;
 use flymodel_entities::{db::DbLoader, entities};
 
+use crate::{
+    audit::{audit, summary, AuditAction, AuditEvent, ResourceKind},
+    auth::{authorize_model, authorize_namespace},
+};
+
 #[derive(Clone, Default)]
 pub struct ModelMutations;
 
@@ -13,11 +19,18 @@ impl ModelMutations {
         namespace: i64,
         name: String,
     ) -> Result<entities::model::Model, async_graphql::Error> {
+        authorize_namespace(ctx, namespace, Perm::W)?;
         let db = DbLoader::<entities::model::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        // user validation here
-        db.create_model(namespace, name).await
+        let created = db.create_model(namespace, name).await?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Create, ResourceKind::Model, created.id)
+                .after(&summary(&created)),
+        )
+        .await;
+        Ok(created)
     }
 
     pub async fn delete_model<'ctx>(
@@ -25,11 +38,19 @@ impl ModelMutations {
         ctx: &Context<'ctx>,
         id: i64,
     ) -> Result<bool, async_graphql::Error> {
+        authorize_model(ctx, id, Perm::W).await?;
         let db = DbLoader::<entities::model::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        // user validation here
-        db.delete_model(id).await
+        let deleted = db.delete_model(id).await?;
+        if deleted {
+            audit(
+                ctx,
+                AuditEvent::new(AuditAction::Delete, ResourceKind::Model, id),
+            )
+            .await;
+        }
+        Ok(deleted)
     }
 
     pub async fn update_model<'ctx>(
@@ -38,10 +59,16 @@ impl ModelMutations {
         id: i64,
         name: String,
     ) -> Result<entities::model::Model, async_graphql::Error> {
+        authorize_model(ctx, id, Perm::W).await?;
         let db = DbLoader::<entities::model::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        // user validation here
-        db.update_model(id, name).await
+        let updated = db.update_model(id, name).await?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Update, ResourceKind::Model, id).after(&summary(&updated)),
+        )
+        .await;
+        Ok(updated)
     }
 }
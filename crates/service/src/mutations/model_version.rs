@@ -1,7 +1,16 @@
 use async_graphql::{Context, Object};
 
-use flymodel::lifecycle::Lifecycle;
+use flymodel::{errs::FlymodelError, lifecycle::Lifecycle, perms::Perm};
 use flymodel_entities::{db::DbLoader, entities};
+use sea_orm::TransactionTrait;
+use tracing::warn;
+
+use crate::{
+    artifacts::{delete_blob_objects, relocation::spawn_relocation, storage},
+    audit::{audit, summary, AuditAction, AuditEvent, ResourceKind},
+    auth::{authorize_model, authorize_model_version, principal},
+    events::{publish, ModelVersionStateChanged, RegistryEvent},
+};
 
 #[derive(Clone, Default)]
 pub struct ModelVersionMutations;
@@ -14,23 +23,77 @@ impl ModelVersionMutations {
         model: i64,
         name: String,
     ) -> Result<entities::model_version::Model, async_graphql::Error> {
+        authorize_model(ctx, model, Perm::W).await?;
         let db = DbLoader::<entities::model_version::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        // user validation here
-        db.create_version(model, name).await
+        let created = db.create_version(model, name).await?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Create, ResourceKind::ModelVersion, created.id)
+                .after(&summary(&created)),
+        )
+        .await;
+        Ok(created)
     }
 
-    #[allow(unused_variables)]
+    /// trashes a version & its experiments, or deletes them & their unreferenced objects for good when `hard` is set
     pub async fn delete_model_version<'ctx>(
         &self,
         ctx: &Context<'ctx>,
         id: i64,
         hard: Option<bool>,
     ) -> Result<bool, async_graphql::Error> {
+        authorize_model_version(ctx, id, Perm::W).await?;
+        let db = DbLoader::<entities::model_version::Model>::with_context(ctx)?.loader();
+        let deleted = AuditEvent::new(AuditAction::Delete, ResourceKind::ModelVersion, id);
+        if !hard.unwrap_or_default() {
+            let trashed = db
+                .soft_delete(id)
+                .await
+                .map_err(|err| err.into_graphql_error())?;
+            if trashed {
+                audit(ctx, deleted.after("trashed")).await;
+            }
+            return Ok(trashed);
+        }
+        let storage = storage(ctx).map_err(|err| err.into_graphql_error())?;
+        let purged = async {
+            let tx = db.db.begin().await?;
+            let purged = DbLoader::<entities::model_version::Model>::purge(&tx, id).await?;
+            tx.commit().await?;
+            Ok::<_, FlymodelError>(purged)
+        }
+        .await
+        .map_err(|err| err.into_graphql_error())?;
+        match purged {
+            Some(blobs) => {
+                audit(ctx, deleted.after("purged")).await;
+                delete_blob_objects(&db.db, storage, &blobs).await;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// takes a version out of the trash, along with the experiments trashed with it
+    pub async fn restore_model_version<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+    ) -> Result<entities::model_version::Model, async_graphql::Error> {
+        authorize_model_version(ctx, id, Perm::W).await?;
         let db = DbLoader::<entities::model_version::Model>::with_context(ctx)?.loader();
-        // todo: add 'hard' / 'soft' delete
-        db.delete_version(id).await
+        let restored = db
+            .restore(id)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Restore, ResourceKind::ModelVersion, id),
+        )
+        .await;
+        Ok(restored)
     }
 
     pub async fn update_model_version_state<'ctx>(
@@ -38,9 +101,111 @@ impl ModelVersionMutations {
         ctx: &Context<'ctx>,
         id: i64,
         state: Lifecycle,
+        reason: Option<String>,
     ) -> Result<entities::model_state::Model, async_graphql::Error> {
+        authorize_model_version(ctx, id, Perm::W).await?;
+        let actor = principal(ctx)?.subject.clone();
         let db = DbLoader::<entities::model_state::Model>::with_context(ctx)?.loader();
 
-        db.update_state(id, state).await
+        let (updated, transitioned) = db.update_state(id, state, actor, reason).await?;
+        spawn_relocation(ctx, id);
+        if let Some(recorded) = transitioned {
+            audit(
+                ctx,
+                AuditEvent::new(AuditAction::Transition, ResourceKind::ModelVersion, id)
+                    .before(&summary(&recorded.previous))
+                    .after(&summary(&recorded.state)),
+            )
+            .await;
+            // the transition is committed, so it is not undone for want of its event
+            match DbLoader::<entities::model_version::Model>::with_context(ctx)?
+                .loader()
+                .owner(id)
+                .await
+            {
+                Ok(Some((namespace, model))) => {
+                    publish(
+                        ctx,
+                        RegistryEvent::ModelVersionStateChanged(ModelVersionStateChanged::of(
+                            namespace, model, id, recorded,
+                        )),
+                    )
+                    .await
+                }
+                Ok(None) => warn!("version {id} has no model to publish its transition for"),
+                Err(err) => warn!("failed to publish the transition of version {id}: {err}"),
+            }
+        }
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use flymodel::{
+        lifecycle::Lifecycle,
+        perms::{Perm, Permission},
+    };
+    use flymodel_entities::{entities, testing};
+    use sea_orm::EntityTrait;
+
+    use crate::{
+        mutations::test::{execute, schema},
+        testing::{memory_storage, stored_blob},
+    };
+
+    #[tokio::test]
+    async fn test_hard_delete_model_version() {
+        let db = testing::database().await;
+        let (storage, orchestrator) = memory_storage("ml-test", Lifecycle::Test);
+        let (schema, _audit_log) = schema(&db, orchestrator);
+        let namespace = testing::namespace(&db, "ns").await;
+        let bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+        let model = testing::model(&db, namespace.id, "model").await;
+        let version = testing::version(&db, model.id, "1", Lifecycle::Test, Utc::now()).await;
+        let other = testing::version(&db, model.id, "2", Lifecycle::Test, Utc::now()).await;
+        let experiment = testing::experiment(&db, version.id, "run").await;
+
+        let owned = stored_blob(&db, &storage, bucket.id, "owned", Utc::now()).await;
+        testing::model_artifact(&db, version.id, owned.id, "weights").await;
+        let logged = stored_blob(&db, &storage, bucket.id, "logged", Utc::now()).await;
+        testing::experiment_artifact(&db, &experiment, logged.id, "plot").await;
+        // a blob an experiment of the other version logged too
+        let shared = stored_blob(&db, &storage, bucket.id, "shared", Utc::now()).await;
+        testing::model_artifact(&db, version.id, shared.id, "tokenizer").await;
+        let elsewhere = testing::experiment(&db, other.id, "run").await;
+        testing::experiment_artifact(&db, &elsewhere, shared.id, "tokenizer").await;
+
+        let resp = execute(
+            &schema,
+            format!(
+                "mutation {{ deleteModelVersion(id: {}, hard: true) }}",
+                version.id
+            ),
+            vec![Permission::Model {
+                perm: Perm::W,
+                id: model.id,
+            }],
+        )
+        .await;
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+
+        assert!(entities::model_version::Entity::find_by_id(version.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .is_none());
+        let blobs = entities::object_blob::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|blob| blob.id)
+            .collect::<Vec<_>>();
+        assert_eq!(blobs, vec![shared.id]);
+        assert!(storage.versions("owned".into()).is_empty());
+        assert!(storage.versions("logged".into()).is_empty());
+        assert_eq!(storage.versions("shared".into()).len(), 1);
     }
 }
@@ -1,10 +1,28 @@
-use async_graphql::{Context, Object};
+use async_graphql::{Context, InputObject, Object};
 
+use flymodel::{lifecycle::Lifecycle, perms::Perm, promotion::PromotionRules};
 use flymodel_entities::{db::DbLoader, entities};
 
+use crate::{
+    audit::{audit, summary, AuditAction, AuditEvent, ResourceKind},
+    auth::{authorize_global, authorize_namespace},
+};
+
 #[derive(Clone, Default)]
 pub struct NamespaceMutations;
 
+/// rules model versions must meet to enter a lifecycle, unset rules are not enforced
+#[derive(InputObject)]
+pub struct PromotionPolicyInput {
+    pub require_passed_experiment: Option<bool>,
+    pub min_artifacts: Option<u32>,
+    pub required_tags: Option<Vec<String>>,
+    /// whether versions may move back out of the lifecycle, only prod blocks this by default
+    pub block_demotion: Option<bool>,
+    /// approvals a promotion request into the lifecycle needs, prod requires one by default
+    pub required_approvals: Option<u32>,
+}
+
 #[Object]
 impl NamespaceMutations {
     pub async fn create_namespace<'ctx>(
@@ -13,11 +31,18 @@ impl NamespaceMutations {
         name: String,
         description: Option<String>,
     ) -> Result<entities::namespace::Model, async_graphql::Error> {
+        authorize_global(ctx, Perm::W)?;
         let db = DbLoader::<entities::namespace::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        // user validation here
-        db.create_namespace(name, description).await
+        let created = db.create_namespace(name, description).await?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Create, ResourceKind::Namespace, created.id)
+                .after(&summary(&created)),
+        )
+        .await;
+        Ok(created)
     }
 
     pub async fn delete_namespace<'ctx>(
@@ -25,10 +50,19 @@ impl NamespaceMutations {
         ctx: &Context<'ctx>,
         id: i64,
     ) -> Result<bool, async_graphql::Error> {
+        authorize_namespace(ctx, id, Perm::W)?;
         let db = DbLoader::<entities::namespace::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        db.delete_namespace(id).await
+        let deleted = db.delete_namespace(id).await?;
+        if deleted {
+            audit(
+                ctx,
+                AuditEvent::new(AuditAction::Delete, ResourceKind::Namespace, id),
+            )
+            .await;
+        }
+        Ok(deleted)
     }
 
     pub async fn update_namespace<'ctx>(
@@ -38,9 +72,96 @@ impl NamespaceMutations {
         name: Option<String>,
         description: Option<String>,
     ) -> Result<entities::namespace::Model, async_graphql::Error> {
+        authorize_namespace(ctx, id, Perm::W)?;
         let db = DbLoader::<entities::namespace::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?
             .loader();
-        db.update_namespace(id, name, description).await
+        let updated = db.update_namespace(id, name, description).await?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Update, ResourceKind::Namespace, id)
+                .after(&summary(&updated)),
+        )
+        .await;
+        Ok(updated)
+    }
+
+    /// replaces the promotion rules of a lifecycle in the namespace
+    pub async fn set_promotion_policy<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        namespace: i64,
+        state: Lifecycle,
+        policy: PromotionPolicyInput,
+    ) -> Result<entities::promotion_policy::Model, async_graphql::Error> {
+        authorize_namespace(ctx, namespace, Perm::W)?;
+        let db = DbLoader::<entities::promotion_policy::Model>::with_context(ctx)
+            .map_err(|err| err.into_graphql_error())?
+            .loader();
+        let defaults = PromotionRules::default_for(state);
+        let policy = db
+            .set_policy(
+                namespace,
+                state,
+                PromotionRules {
+                    require_passed_experiment: policy.require_passed_experiment.unwrap_or_default(),
+                    min_artifacts: policy.min_artifacts.unwrap_or_default() as u64,
+                    required_tags: policy.required_tags.unwrap_or_default(),
+                    block_demotion: policy.block_demotion.unwrap_or(defaults.block_demotion),
+                    required_approvals: policy
+                        .required_approvals
+                        .map(u64::from)
+                        .unwrap_or(defaults.required_approvals),
+                },
+            )
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(
+                AuditAction::Update,
+                ResourceKind::PromotionPolicy,
+                policy.id,
+            )
+            .after(&summary(&policy)),
+        )
+        .await;
+        Ok(policy)
+    }
+
+    /// removes the promotion rules of a lifecycle, restoring its defaults
+    pub async fn delete_promotion_policy<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        namespace: i64,
+        state: Lifecycle,
+    ) -> Result<bool, async_graphql::Error> {
+        authorize_namespace(ctx, namespace, Perm::W)?;
+        let db = DbLoader::<entities::promotion_policy::Model>::with_context(ctx)
+            .map_err(|err| err.into_graphql_error())?
+            .loader();
+        let policy = db
+            .policies(namespace)
+            .await
+            .map_err(|err| err.into_graphql_error())?
+            .into_iter()
+            .find(|policy| policy.state == state);
+        let deleted = db
+            .delete_policy(namespace, state)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        if let (true, Some(policy)) = (deleted, policy) {
+            audit(
+                ctx,
+                AuditEvent::new(
+                    AuditAction::Delete,
+                    ResourceKind::PromotionPolicy,
+                    policy.id,
+                )
+                .before(&summary(&policy)),
+            )
+            .await;
+        }
+        Ok(deleted)
     }
 }
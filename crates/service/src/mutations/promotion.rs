@@ -0,0 +1,154 @@
+use async_graphql::{Context, Object};
+
+use flymodel::{errs::FlymodelError, lifecycle::Lifecycle, perms::Perm};
+use flymodel_entities::{
+    db::DbLoader,
+    entities::{self, enums::PromotionRequestStatus},
+};
+
+use crate::{
+    artifacts::relocation::spawn_relocation,
+    audit::{audit, summary, AuditAction, AuditEvent, ResourceKind},
+    auth::{authorize_model_version, authorize_namespace, principal},
+    events::{publish, ModelVersionStateChanged, RegistryEvent},
+};
+
+#[derive(Clone, Default)]
+pub struct PromotionMutations;
+
+async fn request_owner(ctx: &Context<'_>, id: i64) -> Result<(i64, i64), async_graphql::Error> {
+    DbLoader::<entities::promotion_request::Model>::with_context(ctx)?
+        .loader()
+        .owner(id)
+        .await
+        .map_err(|err| err.into_graphql_error())?
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())
+}
+
+async fn decide<'ctx>(
+    ctx: &Context<'ctx>,
+    request: i64,
+    approved: bool,
+    comment: Option<String>,
+) -> Result<entities::promotion_request::Model, async_graphql::Error> {
+    let (namespace, model) = request_owner(ctx, request).await?;
+    authorize_namespace(ctx, namespace, Perm::W)?;
+    let approver = principal(ctx)?;
+    let (decided, transitioned) =
+        DbLoader::<entities::promotion_request::Model>::with_context(ctx)?
+            .loader()
+            .decide(request, approver, approved, comment)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+    let action = if approved {
+        AuditAction::Approve
+    } else {
+        AuditAction::Reject
+    };
+    audit(
+        ctx,
+        AuditEvent::new(action, ResourceKind::PromotionRequest, request)
+            .after(&summary(&decided.status)),
+    )
+    .await;
+    if decided.status == PromotionRequestStatus::Approved {
+        spawn_relocation(ctx, decided.version_id);
+    }
+    if let Some(recorded) = transitioned {
+        audit(
+            ctx,
+            AuditEvent::new(
+                AuditAction::Transition,
+                ResourceKind::ModelVersion,
+                decided.version_id,
+            )
+            .before(&summary(&recorded.previous))
+            .after(&summary(&recorded.state)),
+        )
+        .await;
+        publish(
+            ctx,
+            RegistryEvent::ModelVersionStateChanged(ModelVersionStateChanged::of(
+                namespace,
+                model,
+                decided.version_id,
+                recorded,
+            )),
+        )
+        .await;
+    }
+    Ok(decided)
+}
+
+#[Object]
+impl PromotionMutations {
+    /// asks the approvers of the namespace to promote a version to `state`
+    pub async fn request_promotion<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        version: i64,
+        state: Lifecycle,
+        reason: Option<String>,
+    ) -> Result<entities::promotion_request::Model, async_graphql::Error> {
+        authorize_model_version(ctx, version, Perm::W).await?;
+        let requested_by = principal(ctx)?.subject.clone();
+        let opened = DbLoader::<entities::promotion_request::Model>::with_context(ctx)?
+            .loader()
+            .open_request(version, state, requested_by, reason)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(
+                AuditAction::Request,
+                ResourceKind::PromotionRequest,
+                opened.id,
+            )
+            .after(&summary(&opened)),
+        )
+        .await;
+        Ok(opened)
+    }
+
+    /// approves a request, promoting the version once the quorum is met
+    pub async fn approve_promotion<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        request: i64,
+        comment: Option<String>,
+    ) -> Result<entities::promotion_request::Model, async_graphql::Error> {
+        decide(ctx, request, true, comment).await
+    }
+
+    /// rejects a request, closing it
+    pub async fn reject_promotion<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        request: i64,
+        comment: Option<String>,
+    ) -> Result<entities::promotion_request::Model, async_graphql::Error> {
+        decide(ctx, request, false, comment).await
+    }
+
+    pub async fn cancel_promotion<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        request: i64,
+    ) -> Result<entities::promotion_request::Model, async_graphql::Error> {
+        let (namespace, model) = request_owner(ctx, request).await?;
+        principal(ctx)?
+            .authorize_model(namespace, model, Perm::W)
+            .map_err(|err| err.into_graphql_error())?;
+        let cancelled = DbLoader::<entities::promotion_request::Model>::with_context(ctx)?
+            .loader()
+            .cancel(request)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Cancel, ResourceKind::PromotionRequest, request),
+        )
+        .await;
+        Ok(cancelled)
+    }
+}
@@ -0,0 +1,279 @@
+use async_graphql::{Context, Object};
+
+use flymodel::{errs::FlymodelError, perms::Perm};
+use flymodel_entities::{db::DbLoader, entities};
+
+use crate::{
+    audit::{audit, summary, AuditAction, AuditEvent, ResourceKind},
+    auth::{authorize_experiment, authorize_model, authorize_model_version, authorize_namespace},
+};
+
+#[derive(Clone, Default)]
+pub struct TagMutations;
+
+async fn namespace_tag(
+    ctx: &Context<'_>,
+    id: i64,
+    namespace: Option<i64>,
+) -> Result<entities::namespace_tag::Model, async_graphql::Error> {
+    let tag = DbLoader::<entities::namespace_tag::Model>::with_context(ctx)
+        .map_err(|err| err.into_graphql_error())?
+        .load_one(id)
+        .await
+        .map_err(|err| FlymodelError::DbLoaderError(err).into_graphql_error())?
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())?;
+    match namespace {
+        Some(namespace) if namespace != tag.namespace_id => Err(FlymodelError::ContraintError(
+            format!("tag {id} belongs to another namespace than {namespace}"),
+        )
+        .into_graphql_error()),
+        _ => Ok(tag),
+    }
+}
+
+async fn model_namespace(ctx: &Context<'_>, id: i64) -> Result<i64, async_graphql::Error> {
+    DbLoader::<entities::model::Model>::with_context(ctx)
+        .map_err(|err| err.into_graphql_error())?
+        .load_one(id)
+        .await
+        .map_err(|err| FlymodelError::DbLoaderError(err).into_graphql_error())?
+        .map(|model| model.namespace_id)
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())
+}
+
+async fn version_namespace(ctx: &Context<'_>, id: i64) -> Result<i64, async_graphql::Error> {
+    DbLoader::<entities::model_version::Model>::with_context(ctx)?
+        .loader()
+        .owner(id)
+        .await
+        .map_err(|err| err.into_graphql_error())?
+        .map(|(namespace, _)| namespace)
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())
+}
+
+async fn experiment_namespace(ctx: &Context<'_>, id: i64) -> Result<i64, async_graphql::Error> {
+    DbLoader::<entities::experiment::Model>::with_context(ctx)?
+        .loader()
+        .owner(id)
+        .await
+        .map_err(|err| err.into_graphql_error())?
+        .map(|(namespace, _)| namespace)
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())
+}
+
+#[Object]
+impl TagMutations {
+    /// creates a tag in a namespace, `color` is a hex color such as `#1f77b4`
+    pub async fn create_namespace_tag<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        namespace: i64,
+        tag: String,
+        color: String,
+    ) -> Result<entities::namespace_tag::Model, async_graphql::Error> {
+        authorize_namespace(ctx, namespace, Perm::W)?;
+        let db = DbLoader::<entities::namespace_tag::Model>::with_context(ctx)?.loader();
+        let created = db
+            .create_tag(namespace, tag, color)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Create, ResourceKind::NamespaceTag, created.id)
+                .after(&summary(&created)),
+        )
+        .await;
+        Ok(created)
+    }
+
+    pub async fn update_namespace_tag<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+        tag: Option<String>,
+        color: Option<String>,
+    ) -> Result<entities::namespace_tag::Model, async_graphql::Error> {
+        let current = namespace_tag(ctx, id, None).await?;
+        authorize_namespace(ctx, current.namespace_id, Perm::W)?;
+        let db = DbLoader::<entities::namespace_tag::Model>::with_context(ctx)?.loader();
+        let updated = db
+            .update_tag(id, tag, color)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Update, ResourceKind::NamespaceTag, id)
+                .before(&summary(&current))
+                .after(&summary(&updated)),
+        )
+        .await;
+        Ok(updated)
+    }
+
+    /// deletes a tag, detaching it from every model, version & experiment
+    pub async fn delete_namespace_tag<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+    ) -> Result<bool, async_graphql::Error> {
+        let current = namespace_tag(ctx, id, None).await?;
+        authorize_namespace(ctx, current.namespace_id, Perm::W)?;
+        let db = DbLoader::<entities::namespace_tag::Model>::with_context(ctx)?.loader();
+        let deleted = db
+            .delete_tag(id)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        if deleted {
+            audit(
+                ctx,
+                AuditEvent::new(AuditAction::Delete, ResourceKind::NamespaceTag, id)
+                    .before(&summary(&current)),
+            )
+            .await;
+        }
+        Ok(deleted)
+    }
+
+    pub async fn attach_model_tag<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        model: i64,
+        tag: i64,
+    ) -> Result<entities::model_tag::Model, async_graphql::Error> {
+        authorize_model(ctx, model, Perm::W).await?;
+        namespace_tag(ctx, tag, Some(model_namespace(ctx, model).await?)).await?;
+        let db = DbLoader::<entities::model_tag::Model>::with_context(ctx)?.loader();
+        let attached = db
+            .attach(model, tag)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Attach, ResourceKind::Model, model)
+                .after(&format!("tag {tag}")),
+        )
+        .await;
+        Ok(attached)
+    }
+
+    pub async fn detach_model_tag<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        model: i64,
+        tag: i64,
+    ) -> Result<bool, async_graphql::Error> {
+        authorize_model(ctx, model, Perm::W).await?;
+        let db = DbLoader::<entities::model_tag::Model>::with_context(ctx)?.loader();
+        let detached = db
+            .detach(model, tag)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        if detached {
+            audit(
+                ctx,
+                AuditEvent::new(AuditAction::Detach, ResourceKind::Model, model)
+                    .before(&format!("tag {tag}")),
+            )
+            .await;
+        }
+        Ok(detached)
+    }
+
+    pub async fn attach_model_version_tag<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        model_version: i64,
+        tag: i64,
+    ) -> Result<entities::model_version_tag::Model, async_graphql::Error> {
+        authorize_model_version(ctx, model_version, Perm::W).await?;
+        namespace_tag(ctx, tag, Some(version_namespace(ctx, model_version).await?)).await?;
+        let db = DbLoader::<entities::model_version_tag::Model>::with_context(ctx)?.loader();
+        let attached = db
+            .attach(model_version, tag)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(
+                AuditAction::Attach,
+                ResourceKind::ModelVersion,
+                model_version,
+            )
+            .after(&format!("tag {tag}")),
+        )
+        .await;
+        Ok(attached)
+    }
+
+    pub async fn detach_model_version_tag<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        model_version: i64,
+        tag: i64,
+    ) -> Result<bool, async_graphql::Error> {
+        authorize_model_version(ctx, model_version, Perm::W).await?;
+        let db = DbLoader::<entities::model_version_tag::Model>::with_context(ctx)?.loader();
+        let detached = db
+            .detach(model_version, tag)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        if detached {
+            audit(
+                ctx,
+                AuditEvent::new(
+                    AuditAction::Detach,
+                    ResourceKind::ModelVersion,
+                    model_version,
+                )
+                .before(&format!("tag {tag}")),
+            )
+            .await;
+        }
+        Ok(detached)
+    }
+
+    pub async fn attach_experiment_tag<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        experiment: i64,
+        tag: i64,
+    ) -> Result<entities::experiment_tag::Model, async_graphql::Error> {
+        authorize_experiment(ctx, experiment, Perm::W).await?;
+        namespace_tag(ctx, tag, Some(experiment_namespace(ctx, experiment).await?)).await?;
+        let db = DbLoader::<entities::experiment_tag::Model>::with_context(ctx)?.loader();
+        let attached = db
+            .attach(experiment, tag)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Attach, ResourceKind::Experiment, experiment)
+                .after(&format!("tag {tag}")),
+        )
+        .await;
+        Ok(attached)
+    }
+
+    pub async fn detach_experiment_tag<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        experiment: i64,
+        tag: i64,
+    ) -> Result<bool, async_graphql::Error> {
+        authorize_experiment(ctx, experiment, Perm::W).await?;
+        let db = DbLoader::<entities::experiment_tag::Model>::with_context(ctx)?.loader();
+        let detached = db
+            .detach(experiment, tag)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        if detached {
+            audit(
+                ctx,
+                AuditEvent::new(AuditAction::Detach, ResourceKind::Experiment, experiment)
+                    .before(&format!("tag {tag}")),
+            )
+            .await;
+        }
+        Ok(detached)
+    }
+}
@@ -0,0 +1,299 @@
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use chrono::Utc;
+use flymodel::{errs::FlymodelError, perms::Perm, storage::StorageProvider};
+use flymodel_entities::{
+    db::DbLoader,
+    entities::{
+        self,
+        enums::{ArchiveCompression, ArchiveFormat, UploadTicketStatus},
+        upload::UploadBlobRequestParams,
+        upload_ticket::{NewUploadTicket, UploadTarget},
+    },
+};
+use sea_orm::TransactionTrait;
+use tracing::warn;
+
+use crate::{
+    artifacts::{
+        presign_expiry, sink_of, storage,
+        tickets::{
+            declared_sha256, register_artifact, target_location, upload_target, UploadedArtifact,
+        },
+        verify_upload,
+    },
+    audit::{audit, summary, uploaded, AuditAction, AuditEvent, ResourceKind},
+    auth::{authorize_experiment, authorize_model_version, principal},
+    events::{publish, RegistryEvent},
+};
+
+#[derive(InputObject)]
+pub struct UploadTicketInput {
+    /// the model version the artifact belongs to
+    pub model_version: Option<i64>,
+    /// the experiment the artifact belongs to
+    pub experiment: Option<i64>,
+    pub artifact_name: String,
+    pub encode: Option<ArchiveCompression>,
+    pub format: Option<ArchiveFormat>,
+    /// artifact metadata, kept for model version artifacts only
+    pub extra: Option<serde_json::Value>,
+    /// the size of the artifact in bytes
+    pub size: i64,
+    /// the hex encoded sha256 of the artifact
+    pub sha256: String,
+    /// seconds the upload url stays valid for, an hour by default
+    pub expires_in: Option<i64>,
+}
+
+#[derive(SimpleObject)]
+pub struct PresignedUpload {
+    pub ticket: entities::upload_ticket::Model,
+    /// the url the artifact should be `PUT` to
+    pub url: String,
+}
+
+#[derive(Clone, Default)]
+pub struct UploadMutations;
+
+async fn authorize_target(
+    ctx: &Context<'_>,
+    target: UploadTarget,
+    perm: Perm,
+) -> Result<(), async_graphql::Error> {
+    match target {
+        UploadTarget::ModelVersion(id) => authorize_model_version(ctx, id, perm).await,
+        UploadTarget::Experiment(id) => authorize_experiment(ctx, id, perm).await,
+    }
+}
+
+async fn create_ticket(
+    ctx: &Context<'_>,
+    target: UploadTarget,
+    input: UploadTicketInput,
+    created_by: String,
+) -> Result<PresignedUpload, FlymodelError> {
+    let sha256 = declared_sha256(input.size, &input.sha256)?;
+    let expires_in = presign_expiry(input.expires_in)?;
+
+    let (bucket, key) = target_location(
+        target,
+        &input.artifact_name,
+        DbLoader::with_context(ctx)?,
+        DbLoader::with_context(ctx)?,
+        DbLoader::with_context(ctx)?,
+        DbLoader::with_context(ctx)?,
+    )
+    .await?;
+    let url = sink_of(&bucket, storage(ctx)?)?
+        .presign_put(key.clone(), expires_in)
+        .await?;
+
+    let ticket = DbLoader::<entities::upload_ticket::Model>::with_context(ctx)?
+        .loader()
+        .create_ticket(NewUploadTicket {
+            bucket_id: bucket.id,
+            key,
+            target,
+            blob: UploadBlobRequestParams {
+                artifact_name: input.artifact_name,
+                encode: input.encode,
+                format: input.format,
+            },
+            extra: input.extra,
+            size: input.size,
+            sha256,
+            created_by,
+            expires_at: Utc::now()
+                + chrono::Duration::from_std(expires_in).map_err(FlymodelError::internal_error)?,
+            chunk_size: None,
+        })
+        .await?;
+
+    Ok(PresignedUpload { ticket, url })
+}
+
+async fn register_upload(
+    ctx: &Context<'_>,
+    sink: &(dyn StorageProvider + Sync + Send + 'static),
+    ticket: &entities::upload_ticket::Model,
+) -> Result<UploadedArtifact, FlymodelError> {
+    let version_id =
+        verify_upload(sink, ticket.key.clone(), ticket.size as u64, &ticket.sha256).await?;
+
+    let tx = DbLoader::<entities::upload_ticket::Model>::with_context(ctx)?
+        .loader()
+        .db
+        .begin()
+        .await?;
+    let created = register_artifact(
+        &tx,
+        ticket,
+        version_id.clone(),
+        DbLoader::with_context(ctx)?,
+        DbLoader::with_context(ctx)?,
+    )
+    .await;
+
+    let created = match created {
+        Ok(created) => tx.commit().await.map(|_| created).map_err(Into::into),
+        Err(err) => Err(err),
+    };
+    if created.is_err() {
+        if let Err(err) = sink.del(ticket.key.clone(), Some(version_id)).await {
+            warn!("failed to remove unregistered upload {}: {err}", ticket.key);
+        }
+    }
+    created
+}
+
+async fn complete_ticket(
+    ctx: &Context<'_>,
+    ticket: entities::upload_ticket::Model,
+) -> Result<UploadedArtifact, FlymodelError> {
+    let tickets = DbLoader::<entities::upload_ticket::Model>::with_context(ctx)?.loader();
+    if ticket.status != UploadTicketStatus::Pending {
+        return Err(FlymodelError::ContraintError(format!(
+            "upload ticket {} is {:?}",
+            ticket.id, ticket.status
+        )));
+    }
+    if ticket.expires_at < Utc::now() {
+        tickets
+            .transition_ticket(
+                ticket.id,
+                &[UploadTicketStatus::Pending],
+                UploadTicketStatus::Expired,
+            )
+            .await?;
+        return Err(FlymodelError::ContraintError(format!(
+            "upload ticket {} expired at {}",
+            ticket.id, ticket.expires_at
+        )));
+    }
+    // claim the ticket so concurrent completions cannot register it twice
+    if !tickets
+        .transition_ticket(
+            ticket.id,
+            &[UploadTicketStatus::Pending],
+            UploadTicketStatus::Uploading,
+        )
+        .await?
+    {
+        return Err(FlymodelError::ContraintError(format!(
+            "upload ticket {} is no longer pending",
+            ticket.id
+        )));
+    }
+
+    let registered = async {
+        let bucket = DbLoader::<entities::bucket::Model>::with_context(ctx)?
+            .load_one(ticket.bucket_id)
+            .await?
+            .ok_or(FlymodelError::InvalidResourceId(ticket.bucket_id))?;
+        register_upload(ctx, sink_of(&bucket, storage(ctx)?)?, &ticket).await
+    }
+    .await;
+
+    if registered.is_err() {
+        if let Err(err) = tickets
+            .transition_ticket(
+                ticket.id,
+                &[UploadTicketStatus::Uploading],
+                UploadTicketStatus::Erred,
+            )
+            .await
+        {
+            warn!("failed to mark upload ticket {} as erred: {err}", ticket.id);
+        }
+    }
+    registered
+}
+
+async fn load_ticket(
+    ctx: &Context<'_>,
+    id: i64,
+) -> Result<entities::upload_ticket::Model, async_graphql::Error> {
+    let ticket = DbLoader::<entities::upload_ticket::Model>::with_context(ctx)
+        .map_err(|err| err.into_graphql_error())?
+        .load_one(id)
+        .await
+        .map_err(|err| FlymodelError::DbLoaderError(err).into_graphql_error())?
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())?;
+    authorize_target(
+        ctx,
+        ticket.target().map_err(|err| err.into_graphql_error())?,
+        Perm::W,
+    )
+    .await?;
+    Ok(ticket)
+}
+
+#[Object]
+impl UploadMutations {
+    /// a presigned url to upload an artifact to directly, & the ticket tracking it
+    pub async fn create_upload_ticket<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        input: UploadTicketInput,
+    ) -> Result<PresignedUpload, async_graphql::Error> {
+        let target = upload_target(input.model_version, input.experiment)
+            .map_err(|err| err.into_graphql_error())?;
+        authorize_target(ctx, target, Perm::W).await?;
+        let created_by = principal(ctx)?.subject.clone();
+        let presigned = create_ticket(ctx, target, input, created_by)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(
+                AuditAction::Create,
+                ResourceKind::UploadTicket,
+                presigned.ticket.id,
+            )
+            .after(&summary(&presigned.ticket)),
+        )
+        .await;
+        Ok(presigned)
+    }
+
+    /// verifies the uploaded object against the ticket & registers the artifact
+    pub async fn complete_upload<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        ticket: i64,
+    ) -> Result<UploadedArtifact, async_graphql::Error> {
+        let ticket = load_ticket(ctx, ticket).await?;
+        let artifact = complete_ticket(ctx, ticket)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(ctx, uploaded(&artifact)).await;
+        publish(ctx, RegistryEvent::ArtifactUploaded((&artifact).into())).await;
+        Ok(artifact)
+    }
+
+    pub async fn cancel_upload<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        ticket: i64,
+    ) -> Result<bool, async_graphql::Error> {
+        let ticket = load_ticket(ctx, ticket).await?;
+        let cancelled = DbLoader::<entities::upload_ticket::Model>::with_context(ctx)
+            .map_err(|err| err.into_graphql_error())?
+            .loader()
+            .transition_ticket(
+                ticket.id,
+                &[UploadTicketStatus::Pending],
+                UploadTicketStatus::Cancelled,
+            )
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        if cancelled {
+            audit(
+                ctx,
+                AuditEvent::new(AuditAction::Cancel, ResourceKind::UploadTicket, ticket.id),
+            )
+            .await;
+        }
+        Ok(cancelled)
+    }
+}
@@ -0,0 +1,128 @@
+use async_graphql::{Context, Object};
+
+use flymodel::{errs::FlymodelError, perms::Perm};
+use flymodel_entities::{
+    db::DbLoader,
+    entities::{
+        self,
+        webhook::{WebhookInput, WebhookUpdate},
+    },
+};
+
+use crate::{
+    audit::{audit, summary, AuditAction, AuditEvent, ResourceKind},
+    auth::authorize_namespace,
+};
+
+#[derive(Clone, Default)]
+pub struct WebhookMutations;
+
+async fn managed_webhook(
+    ctx: &Context<'_>,
+    id: i64,
+) -> Result<entities::webhook::Model, async_graphql::Error> {
+    let webhook = DbLoader::<entities::webhook::Model>::with_context(ctx)?
+        .load_one(id)
+        .await
+        .map_err(|err| FlymodelError::DbLoaderError(err).into_graphql_error())?
+        .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())?;
+    authorize_namespace(ctx, webhook.namespace_id, Perm::W)?;
+    Ok(webhook)
+}
+
+#[Object]
+impl WebhookMutations {
+    /// registers an endpoint to deliver the events of a namespace to
+    pub async fn create_webhook<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        namespace: i64,
+        input: WebhookInput,
+    ) -> Result<entities::webhook::Model, async_graphql::Error> {
+        authorize_namespace(ctx, namespace, Perm::W)?;
+        let created = DbLoader::<entities::webhook::Model>::with_context(ctx)?
+            .loader()
+            .create_webhook(namespace, input)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Create, ResourceKind::Webhook, created.id)
+                .after(&summary(&created)),
+        )
+        .await;
+        Ok(created)
+    }
+
+    pub async fn update_webhook<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+        update: WebhookUpdate,
+    ) -> Result<entities::webhook::Model, async_graphql::Error> {
+        let current = managed_webhook(ctx, id).await?;
+        let updated = DbLoader::<entities::webhook::Model>::with_context(ctx)?
+            .loader()
+            .update_webhook(id, update)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Update, ResourceKind::Webhook, id)
+                .before(&summary(&current))
+                .after(&summary(&updated)),
+        )
+        .await;
+        Ok(updated)
+    }
+
+    /// deletes a webhook along with its delivery history
+    pub async fn delete_webhook<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+    ) -> Result<bool, async_graphql::Error> {
+        let current = managed_webhook(ctx, id).await?;
+        let deleted = DbLoader::<entities::webhook::Model>::with_context(ctx)?
+            .loader()
+            .delete_webhook(id)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        if deleted {
+            audit(
+                ctx,
+                AuditEvent::new(AuditAction::Delete, ResourceKind::Webhook, id)
+                    .before(&summary(&current)),
+            )
+            .await;
+        }
+        Ok(deleted)
+    }
+
+    /// queues a delivery to be sent again, such as one given up on as dead
+    pub async fn redeliver_webhook_delivery<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+    ) -> Result<entities::webhook_delivery::Model, async_graphql::Error> {
+        let deliveries = DbLoader::<entities::webhook_delivery::Model>::with_context(ctx)?;
+        let delivery = deliveries
+            .load_one(id)
+            .await
+            .map_err(|err| FlymodelError::DbLoaderError(err).into_graphql_error())?
+            .ok_or_else(|| FlymodelError::InvalidResourceId(id).into_graphql_error())?;
+        managed_webhook(ctx, delivery.webhook_id).await?;
+        let queued = deliveries
+            .loader()
+            .redeliver(id)
+            .await
+            .map_err(|err| err.into_graphql_error())?;
+        audit(
+            ctx,
+            AuditEvent::new(AuditAction::Redeliver, ResourceKind::WebhookDelivery, id)
+                .before(&summary(&delivery.status)),
+        )
+        .await;
+        Ok(queued)
+    }
+}
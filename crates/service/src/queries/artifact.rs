@@ -0,0 +1,78 @@
+use async_graphql::{Context, Object, SimpleObject};
+use chrono::{DateTime, Utc};
+use flymodel::errs::FlymodelError;
+use flymodel_entities::entities;
+
+use crate::{
+    artifacts::{experiments, model_version, presign_expiry, sink_of, storage, ArtifactLoaders},
+    auth::principal,
+};
+
+#[derive(SimpleObject)]
+pub struct PresignedDownload {
+    /// the url the artifact can be `GET` from
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Default)]
+pub struct ArtifactQueries;
+
+async fn presigned_download(
+    ctx: &Context<'_>,
+    blob: &entities::object_blob::Model,
+    bucket: &entities::bucket::Model,
+    expires_in: Option<i64>,
+) -> Result<PresignedDownload, FlymodelError> {
+    let expires_in = presign_expiry(expires_in)?;
+    let url = sink_of(bucket, storage(ctx)?)?
+        .presign_get(blob.key.clone(), Some(blob.version_id.clone()), expires_in)
+        .await?;
+    Ok(PresignedDownload {
+        url,
+        expires_at: Utc::now()
+            + chrono::Duration::from_std(expires_in).map_err(FlymodelError::internal_error)?,
+    })
+}
+
+#[Object]
+impl ArtifactQueries {
+    /// a presigned url to download a model artifact from directly
+    async fn model_artifact_download_url<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+        expires_in: Option<i64>,
+    ) -> Result<PresignedDownload, async_graphql::Error> {
+        let principal = principal(ctx)?;
+        async {
+            let found = model_version::get_artifact_blob(
+                id,
+                principal,
+                &ArtifactLoaders::with_context(ctx)?,
+            )
+            .await?;
+            presigned_download(ctx, &found.blob, &found.bucket, expires_in).await
+        }
+        .await
+        .map_err(|err| err.into_graphql_error())
+    }
+
+    /// a presigned url to download an experiment artifact from directly
+    async fn experiment_artifact_download_url<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: i64,
+        expires_in: Option<i64>,
+    ) -> Result<PresignedDownload, async_graphql::Error> {
+        let principal = principal(ctx)?;
+        async {
+            let found =
+                experiments::get_artifact_blob(id, principal, &ArtifactLoaders::with_context(ctx)?)
+                    .await?;
+            presigned_download(ctx, &found.blob, &found.bucket, expires_in).await
+        }
+        .await
+        .map_err(|err| err.into_graphql_error())
+    }
+}
@@ -0,0 +1,33 @@
+use async_graphql::*;
+use flymodel::perms::Perm;
+use flymodel_entities::{
+    db::DbLoader,
+    entities::{
+        self,
+        page::{PageInput, PaginatedResult},
+    },
+};
+
+use crate::auth::authorize_global;
+
+#[derive(Clone, Default)]
+pub struct AuditQueries;
+
+#[Object]
+impl AuditQueries {
+    /// the audit log, most recent first. resources are named in snake case, e.g. `model_version`
+    async fn audit_log<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        resource: Option<String>,
+        resource_id: Option<i64>,
+        actor: Option<String>,
+        page: Option<PageInput>,
+    ) -> PaginatedResult<entities::audit_log::Model> {
+        authorize_global(ctx, Perm::R)?;
+        DbLoader::<entities::audit_log::Model>::with_context(ctx)?
+            .loader()
+            .entries(resource, resource_id, actor, page.unwrap_or_default())
+            .await
+    }
+}
@@ -1,6 +1,8 @@
 use anyhow::Context as _;
 use async_graphql::{dataloader::Loader, *};
 use flymodel::lifecycle::Lifecycle;
+
+use crate::auth::principal;
 use flymodel_entities::{
     db::Database,
     entities::{
@@ -23,6 +25,7 @@ impl BucketQueries {
         role: Option<Vec<Lifecycle>>,
     ) -> PaginatedResult<entities::bucket::Model> {
         let db: &Database<entities::bucket::Model> = ctx.data_opt().context("no database")?;
+        let principal = principal(ctx)?;
 
         if let Some(ids) = id {
             let re: Vec<_> = db
@@ -30,6 +33,7 @@ impl BucketQueries {
                 .load(&ids)
                 .await?
                 .values()
+                .filter(|bucket| principal.namespace_permission(bucket.namespace).is_some())
                 .map(entities::bucket::Model::to_owned)
                 .collect();
             return Ok(Paginated::new(
@@ -40,6 +44,10 @@ impl BucketQueries {
             ));
         }
 
+        let namespace = match principal.read_scope() {
+            Some(scope) => Some(scope.restrict_namespaces(namespace)),
+            None => namespace,
+        };
         let page = page.unwrap_or_default();
         db.loader().find_by_namespace(namespace, role, page).await
     }
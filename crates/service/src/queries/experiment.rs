@@ -1,19 +1,51 @@
+use std::collections::HashSet;
+
 use anyhow::Context as _;
 use async_graphql::{dataloader::Loader, *};
 
+use flymodel::perms::Principal;
 use flymodel_entities::{
     db::Database,
     entities::{
         self,
+        leaderboard::{
+            ExperimentComparison, LeaderboardEntry, LeaderboardFilter, Rank, RankBy, RankOrder,
+        },
         page::{PageInput, Paginated, PaginatedResult},
     },
 };
 
+use crate::auth::principal;
+
 #[derive(Clone, Default)]
 pub struct ExperimentQueries;
 
+async fn readable(
+    db: &Database<entities::experiment::Model>,
+    principal: &Principal,
+    ids: &[i64],
+) -> Result<Vec<entities::experiment::Model>, async_graphql::Error> {
+    let mut found = db.loader().load(ids).await?;
+    let mut re = vec![];
+    for id in ids {
+        let Some(experiment) = found.remove(id) else {
+            continue;
+        };
+        if principal.read_scope().is_some() {
+            match db.loader().owner(experiment.id).await? {
+                Some((ns, model))
+                    if principal.permissions.model_permission(ns, model).is_some() => {}
+                _ => continue,
+            }
+        }
+        re.push(experiment);
+    }
+    Ok(re)
+}
+
 #[Object]
 impl ExperimentQueries {
+    /// experiments by id or matching every filter, those in the trash only when `deleted` is set
     async fn experiment<'ctx>(
         &self,
         ctx: &Context<'ctx>,
@@ -21,17 +53,15 @@ impl ExperimentQueries {
         model_id: Option<i64>,
         page: Option<PageInput>,
         name: Option<String>,
+        tags: Option<Vec<String>>,
+        deleted: Option<bool>,
     ) -> PaginatedResult<entities::experiment::Model> {
         let db: &Database<entities::experiment::Model> = ctx.data_opt().context("no database")?;
+        let principal = principal(ctx)?;
+        let scope = principal.read_scope();
 
         if let Some(ids) = id {
-            let re: Vec<_> = db
-                .loader()
-                .load(&ids)
-                .await?
-                .values()
-                .map(entities::experiment::Model::to_owned)
-                .collect();
+            let re = readable(db, principal, &ids).await?;
             return Ok(Paginated::new(
                 (ids.len(), 0),
                 1 as usize,
@@ -41,7 +71,58 @@ impl ExperimentQueries {
         }
 
         db.loader()
-            .bulk_paginated_experiments(name, model_id, page.unwrap_or_default())
+            .bulk_paginated_experiments(
+                name,
+                model_id,
+                scope,
+                tags,
+                deleted.unwrap_or_default(),
+                page.unwrap_or_default(),
+            )
+            .await
+    }
+
+    /// experiments ranked `by` a metric or result field, best first unless `order` is `ASC`
+    async fn experiment_leaderboard<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        model: i64,
+        by: RankBy,
+        #[graphql(default)] order: RankOrder,
+        filter: Option<LeaderboardFilter>,
+        page: Option<PageInput>,
+    ) -> PaginatedResult<LeaderboardEntry> {
+        let db: &Database<entities::experiment::Model> = ctx.data_opt().context("no database")?;
+        let scope = principal(ctx)?.read_scope();
+        let rank =
+            Rank::of(by.metric, by.aggregate, by.field).map_err(|err| err.into_graphql_error())?;
+        db.loader()
+            .leaderboard(
+                model,
+                rank,
+                order,
+                filter.unwrap_or_default(),
+                scope,
+                page.unwrap_or_default(),
+            )
+            .await
+    }
+
+    /// the params & metrics of up to 10 experiments side by side, in the order of `ids`
+    async fn compare_experiments<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        ids: Vec<i64>,
+        metrics: Option<Vec<String>>,
+    ) -> Result<ExperimentComparison, async_graphql::Error> {
+        let db: &Database<entities::experiment::Model> = ctx.data_opt().context("no database")?;
+        let principal = principal(ctx)?;
+        let mut seen = HashSet::new();
+        let ids: Vec<i64> = ids.into_iter().filter(|id| seen.insert(*id)).collect();
+        let experiments = readable(db, principal, &ids).await?;
+        db.loader()
+            .compare(experiments, metrics)
             .await
+            .map_err(|err| err.into_graphql_error())
     }
 }
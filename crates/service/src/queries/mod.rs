@@ -1,12 +1,17 @@
 use async_graphql::MergedObject;
+pub mod artifact;
+pub mod audit;
 pub mod bucket;
 pub mod experiment;
 pub mod model;
 pub mod namespace;
+pub mod promotion;
+pub mod webhook;
 
 use self::{
-    bucket::BucketQueries, experiment::ExperimentQueries, model::ModelQueries,
-    namespace::NamespaceQueries,
+    artifact::ArtifactQueries, audit::AuditQueries, bucket::BucketQueries,
+    experiment::ExperimentQueries, model::ModelQueries, namespace::NamespaceQueries,
+    promotion::PromotionQueries, webhook::WebhookQueries,
 };
 
 #[derive(Clone, Default, MergedObject)]
@@ -15,4 +20,8 @@ pub struct Query(
     NamespaceQueries,
     ModelQueries,
     ExperimentQueries,
+    ArtifactQueries,
+    PromotionQueries,
+    WebhookQueries,
+    AuditQueries,
 );
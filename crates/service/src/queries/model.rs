@@ -8,11 +8,14 @@ use flymodel_entities::{
     },
 };
 
+use crate::auth::principal;
+
 #[derive(Clone, Default)]
 pub struct ModelQueries;
 
 #[Object]
 impl ModelQueries {
+    /// models by id or matching every filter, `tags` keeps those carrying each named tag
     async fn model<'ctx>(
         &self,
         ctx: &Context<'ctx>,
@@ -21,14 +24,22 @@ impl ModelQueries {
         name: Option<String>,
         namespace: Option<Vec<i64>>,
         role: Option<Vec<Lifecycle>>,
+        tags: Option<Vec<String>>,
     ) -> PaginatedResult<entities::model::Model> {
         let db = DbLoader::<entities::model::Model>::with_context(ctx)?;
+        let principal = principal(ctx)?;
         if let Some(ids) = id {
             let re: Vec<_> = db
                 .loader()
                 .load(&ids)
                 .await?
                 .values()
+                .filter(|model| {
+                    principal
+                        .permissions
+                        .model_permission(model.namespace_id, model.id)
+                        .is_some()
+                })
                 .map(|model| model.to_owned())
                 .collect();
             return Ok(Paginated::new(
@@ -41,7 +52,7 @@ impl ModelQueries {
 
         let page = page.unwrap_or_default();
         db.loader()
-            .find_by_name_and_namespace(name, namespace, role, page)
+            .find_by_name_and_namespace(name, namespace, role, principal.read_scope(), tags, page)
             .await
     }
 }
@@ -7,6 +7,8 @@ use flymodel_entities::{
     },
 };
 
+use crate::auth::principal;
+
 #[derive(Clone, Default)]
 pub struct NamespaceQueries;
 
@@ -21,12 +23,14 @@ impl NamespaceQueries {
     ) -> PaginatedResult<entities::namespace::Model> {
         let db = DbLoader::<entities::namespace::Model>::with_context(ctx)
             .map_err(|err| err.into_graphql_error())?;
+        let principal = principal(ctx)?;
         if let Some(id) = id {
             let re: Vec<_> = db
                 .loader()
                 .load(&id)
                 .await?
                 .values()
+                .filter(|ns| principal.namespace_permission(ns.id).is_some())
                 .map(|ns| ns.to_owned())
                 .collect();
             return Ok(Paginated::new(
@@ -37,7 +41,7 @@ impl NamespaceQueries {
             ));
         }
         db.loader()
-            .bulk_paginated_namespaces(name, page.unwrap_or_default())
+            .bulk_paginated_namespaces(name, principal.read_scope(), page.unwrap_or_default())
             .await
     }
 }
@@ -0,0 +1,39 @@
+use async_graphql::*;
+use flymodel_entities::{
+    db::DbLoader,
+    entities::{
+        self,
+        enums::PromotionRequestStatus,
+        page::{PageInput, PaginatedResult},
+    },
+};
+
+use crate::auth::principal;
+
+#[derive(Clone, Default)]
+pub struct PromotionQueries;
+
+#[Object]
+impl PromotionQueries {
+    /// promotion requests visible to the caller, most recent first
+    async fn promotion_requests<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        namespace: Option<i64>,
+        version: Option<i64>,
+        status: Option<Vec<PromotionRequestStatus>>,
+        page: Option<PageInput>,
+    ) -> PaginatedResult<entities::promotion_request::Model> {
+        let db = DbLoader::<entities::promotion_request::Model>::with_context(ctx)?;
+        let principal = principal(ctx)?;
+        db.loader()
+            .requests(
+                version,
+                namespace,
+                status,
+                principal.read_scope(),
+                page.unwrap_or_default(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,52 @@
+use async_graphql::*;
+use flymodel::{errs::FlymodelError, perms::Perm};
+use flymodel_entities::{
+    db::DbLoader,
+    entities::{
+        self,
+        enums::WebhookDeliveryStatus,
+        page::{PageInput, PaginatedResult},
+    },
+};
+
+use crate::auth::authorize_namespace;
+
+#[derive(Clone, Default)]
+pub struct WebhookQueries;
+
+#[Object]
+impl WebhookQueries {
+    /// the webhooks of a namespace, oldest first
+    async fn webhooks<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        namespace: i64,
+    ) -> Result<Vec<entities::webhook::Model>> {
+        authorize_namespace(ctx, namespace, Perm::W)?;
+        DbLoader::<entities::webhook::Model>::with_context(ctx)?
+            .loader()
+            .of_namespace(namespace)
+            .await
+            .map_err(|err| err.into_graphql_error())
+    }
+
+    /// the deliveries of a webhook, most recent first
+    async fn webhook_deliveries<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        webhook: i64,
+        status: Option<Vec<WebhookDeliveryStatus>>,
+        page: Option<PageInput>,
+    ) -> PaginatedResult<entities::webhook_delivery::Model> {
+        let found = DbLoader::<entities::webhook::Model>::with_context(ctx)?
+            .load_one(webhook)
+            .await
+            .map_err(|err| FlymodelError::DbLoaderError(err).into_graphql_error())?
+            .ok_or_else(|| FlymodelError::InvalidResourceId(webhook).into_graphql_error())?;
+        authorize_namespace(ctx, found.namespace_id, Perm::W)?;
+        DbLoader::<entities::webhook_delivery::Model>::with_context(ctx)?
+            .loader()
+            .deliveries(webhook, status, page.unwrap_or_default())
+            .await
+    }
+}
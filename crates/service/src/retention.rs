@@ -0,0 +1,374 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use flymodel::{
+    config::retention::RetentionConfiguration, errs::FlymodelError, lifecycle::Lifecycle,
+};
+use flymodel_entities::{db::DbLoader, entities};
+use flymodel_registry::storage::StorageOrchestrator;
+use sea_orm::{DatabaseTransaction, DbConn, Iterable, TransactionTrait};
+use tracing::{info, warn};
+
+use crate::artifacts::delete_blob_objects;
+
+/// a dry run rolls back every deletion, so blobs shared between expired items are not reported
+#[derive(Debug, Default, serde::Serialize)]
+pub struct RetentionReport {
+    pub dry: bool,
+    pub versions: Vec<entities::model_version::Model>,
+    pub experiments: Vec<entities::experiment::Model>,
+    pub blobs: Vec<entities::object_blob::Model>,
+    pub failed: Vec<i64>,
+}
+
+impl RetentionReport {
+    pub fn bytes(&self) -> u64 {
+        self.blobs.iter().map(|blob| blob.size.max(0) as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty() && self.experiments.is_empty() && self.blobs.is_empty()
+    }
+}
+
+fn cutoff(now: DateTime<Utc>, days: u64) -> Option<DateTime<Utc>> {
+    let age =
+        chrono::Duration::from_std(Duration::from_secs(days.saturating_mul(24 * 60 * 60))).ok()?;
+    now.checked_sub_signed(age)
+}
+
+async fn finish(tx: DatabaseTransaction, dry: bool) -> Result<(), FlymodelError> {
+    if dry {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// each version & experiment is removed in its own transaction, which a dry run rolls back
+pub async fn run_retention(
+    db: &DbConn,
+    storage: &StorageOrchestrator,
+    conf: &RetentionConfiguration,
+    dry: bool,
+) -> Result<RetentionReport, FlymodelError> {
+    let now = Utc::now();
+    let mut report = RetentionReport {
+        dry,
+        ..Default::default()
+    };
+
+    for state in Lifecycle::iter() {
+        let rule = conf.rule(state);
+        if let Some(before) = rule.models.and_then(|days| cutoff(now, days)) {
+            for version in
+                DbLoader::<entities::model_version::Model>::expired(db, state, before).await?
+            {
+                let tx = db.begin().await?;
+                let released = async {
+                    let released = DbLoader::<entities::model_version::Model>::expire(
+                        &tx, version.id, state, before,
+                    )
+                    .await?;
+                    finish(tx, dry).await?;
+                    Ok::<_, FlymodelError>(released)
+                }
+                .await;
+                match released {
+                    Ok(Some(blobs)) => {
+                        if !dry {
+                            report
+                                .failed
+                                .extend(delete_blob_objects(db, storage, &blobs).await);
+                        }
+                        report.versions.push(version);
+                        report.blobs.extend(blobs);
+                    }
+                    Ok(None) => {}
+                    Err(err) => warn!("failed to expire version {}: {err}", version.id),
+                }
+            }
+        }
+
+        if let Some(before) = rule.runs.and_then(|days| cutoff(now, days)) {
+            for experiment in
+                DbLoader::<entities::experiment::Model>::expired(db, state, before).await?
+            {
+                // only reachable when dry, as the expired versions were rolled back
+                if report
+                    .versions
+                    .iter()
+                    .any(|version| version.id == experiment.version_id)
+                {
+                    continue;
+                }
+                let tx = db.begin().await?;
+                let released = async {
+                    let released =
+                        DbLoader::<entities::experiment::Model>::expire(&tx, &experiment, state)
+                            .await?;
+                    finish(tx, dry).await?;
+                    Ok::<_, FlymodelError>(released)
+                }
+                .await;
+                match released {
+                    Ok(Some(blobs)) => {
+                        if !dry {
+                            report
+                                .failed
+                                .extend(delete_blob_objects(db, storage, &blobs).await);
+                        }
+                        report.experiments.push(experiment);
+                        report.blobs.extend(blobs);
+                    }
+                    Ok(None) => {}
+                    Err(err) => warn!("failed to expire experiment {}: {err}", experiment.id),
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+pub fn spawn_retention(
+    db: DbConn,
+    storage: Arc<StorageOrchestrator>,
+    conf: RetentionConfiguration,
+) -> Option<tokio::task::JoinHandle<()>> {
+    if conf.keeps_everything() {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(Duration::from_secs(conf.interval));
+        loop {
+            ticks.tick().await;
+            match run_retention(&db, &storage, &conf, false).await {
+                Ok(report) if report.is_empty() => {}
+                Ok(report) => info!(
+                    "retention removed {} versions, {} experiments & {} blobs ({} bytes), {} objects left in storage",
+                    report.versions.len(),
+                    report.experiments.len(),
+                    report.blobs.len(),
+                    report.bytes(),
+                    report.failed.len(),
+                ),
+                Err(err) => warn!("retention run failed: {err}"),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use flymodel::{
+        config::retention::{RetentionConfiguration, RetentionRule},
+        lifecycle::Lifecycle,
+    };
+    use flymodel_entities::{db::DbLoader, entities, testing};
+    use flymodel_registry::memory::MemoryStorage;
+    use sea_orm::{
+        ActiveModelTrait, ActiveValue, ColumnTrait, DbConn, EntityTrait, IntoActiveModel,
+        QueryFilter, TransactionTrait,
+    };
+
+    use super::{cutoff, run_retention};
+    use crate::testing::{memory_storage, stored_blob};
+
+    fn days(days: i64) -> chrono::DateTime<Utc> {
+        Utc::now() - chrono::Duration::days(days)
+    }
+
+    fn conf() -> RetentionConfiguration {
+        RetentionConfiguration {
+            test: RetentionRule {
+                models: Some(7),
+                runs: None,
+            },
+            ..Default::default()
+        }
+    }
+
+    async fn stored_version(
+        db: &DbConn,
+        storage: &MemoryStorage,
+        bucket: i64,
+        model: i64,
+        name: &str,
+        state: Lifecycle,
+        since: chrono::DateTime<Utc>,
+    ) -> (entities::model_version::Model, entities::object_blob::Model) {
+        let version = testing::version(db, model, name, state, since).await;
+        let blob = stored_blob(db, storage, bucket, name, since).await;
+        testing::model_artifact(db, version.id, blob.id, "weights").await;
+        (version, blob)
+    }
+
+    async fn versions(db: &DbConn) -> Vec<i64> {
+        entities::model_version::Entity::find()
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|version| version.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_cutoff() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            cutoff(now, 28),
+            Some(Utc.with_ymd_and_hms(2024, 2, 2, 0, 0, 0).unwrap())
+        );
+        assert_eq!(cutoff(now, 0), Some(now));
+        assert_eq!(cutoff(now, u64::MAX), None);
+    }
+
+    #[tokio::test]
+    async fn test_run_retention() {
+        let db = testing::database().await;
+        let (storage, orchestrator) = memory_storage("ml-test", Lifecycle::Test);
+        let namespace = testing::namespace(&db, "ns").await;
+        let bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+        let model = testing::model(&db, namespace.id, "model").await;
+        let (expired, expired_blob) = stored_version(
+            &db,
+            &storage,
+            bucket.id,
+            model.id,
+            "1",
+            Lifecycle::Test,
+            days(10),
+        )
+        .await;
+        let (recent, _) = stored_version(
+            &db,
+            &storage,
+            bucket.id,
+            model.id,
+            "2",
+            Lifecycle::Test,
+            days(1),
+        )
+        .await;
+        let (kept, _) = stored_version(
+            &db,
+            &storage,
+            bucket.id,
+            model.id,
+            "3",
+            Lifecycle::Qa,
+            days(10),
+        )
+        .await;
+
+        let report = run_retention(&db, &orchestrator, &conf(), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            report.versions.iter().map(|v| v.id).collect::<Vec<_>>(),
+            vec![expired.id]
+        );
+        assert_eq!(
+            report.blobs.iter().map(|blob| blob.id).collect::<Vec<_>>(),
+            vec![expired_blob.id]
+        );
+        assert!(report.failed.is_empty());
+        assert_eq!(versions(&db).await, vec![recent.id, kept.id]);
+        assert!(entities::object_blob::Entity::find_by_id(expired_blob.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(storage.versions("1".into()).is_empty());
+        assert_eq!(storage.versions("2".into()).len(), 1);
+        assert_eq!(storage.versions("3".into()).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expire_after_transition() {
+        let db = testing::database().await;
+        let (storage, _) = memory_storage("ml-test", Lifecycle::Test);
+        let namespace = testing::namespace(&db, "ns").await;
+        let bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+        let model = testing::model(&db, namespace.id, "model").await;
+        let (version, _) = stored_version(
+            &db,
+            &storage,
+            bucket.id,
+            model.id,
+            "1",
+            Lifecycle::Test,
+            days(10),
+        )
+        .await;
+        let before = days(7);
+
+        let expired =
+            DbLoader::<entities::model_version::Model>::expired(&db, Lifecycle::Test, before)
+                .await
+                .unwrap();
+        assert_eq!(expired, vec![version.clone()]);
+
+        // promoted between listing the expired versions & expiring them
+        let state = entities::model_state::Entity::find()
+            .filter(entities::model_state::Column::VersionId.eq(version.id))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut state = state.into_active_model();
+        state.state = ActiveValue::Set(Lifecycle::Qa);
+        state.update(&db).await.unwrap();
+
+        let tx = db.begin().await.unwrap();
+        let released = DbLoader::<entities::model_version::Model>::expire(
+            &tx,
+            version.id,
+            Lifecycle::Test,
+            before,
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+        assert!(released.is_none());
+        assert_eq!(versions(&db).await, vec![version.id]);
+    }
+
+    #[tokio::test]
+    async fn test_run_retention_dry() {
+        let db = testing::database().await;
+        let (storage, orchestrator) = memory_storage("ml-test", Lifecycle::Test);
+        let namespace = testing::namespace(&db, "ns").await;
+        let bucket = testing::bucket(&db, namespace.id, "ml-test", Lifecycle::Test).await;
+        let model = testing::model(&db, namespace.id, "model").await;
+        let (expired, blob) = stored_version(
+            &db,
+            &storage,
+            bucket.id,
+            model.id,
+            "1",
+            Lifecycle::Test,
+            days(10),
+        )
+        .await;
+
+        let report = run_retention(&db, &orchestrator, &conf(), true)
+            .await
+            .unwrap();
+        assert!(report.dry);
+        assert_eq!(report.versions, vec![expired.clone()]);
+        assert_eq!(report.blobs, vec![blob.clone()]);
+        assert_eq!(report.bytes(), 11);
+
+        assert_eq!(versions(&db).await, vec![expired.id]);
+        assert!(entities::object_blob::Entity::find_by_id(blob.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(storage.versions("1".into()).len(), 1);
+    }
+}
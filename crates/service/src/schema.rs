@@ -1,14 +1,20 @@
-use crate::{db::DbLoader, mutations::Mutation, queries::Query};
+use crate::{
+    db::DbLoader, events::EventBus, mutations::Mutation, queries::Query,
+    subscriptions::Subscription,
+};
 use async_graphql::{
     extensions::{OpenTelemetry, Tracing},
-    EmptySubscription, Schema,
+    Schema,
 };
 use flymodel_entities::entities::{self};
+use flymodel_events::AuditPublisher;
+use flymodel_registry::storage::StorageOrchestrator;
 use flymodel_tracing::tracer::OtlpTracerConfig;
 use sea_orm::DbConn;
+use std::sync::Arc;
 use tracing::debug;
 
-pub type FlymodelSchema = Schema<Query, Mutation, EmptySubscription>;
+pub type FlymodelSchema = Schema<Query, Mutation, Subscription>;
 
 #[macro_export]
 macro_rules! with_dbs {
@@ -44,22 +50,40 @@ macro_rules! apply_data {
             $tracer,
             entities::bucket::Model,
             entities::namespace::Model,
+            entities::namespace_tag::Model,
             entities::model::Model,
             entities::model_artifact::Model,
             entities::model_state::Model,
+            entities::model_state_history::Model,
             entities::model_version::Model,
             entities::model_tag::Model,
             entities::model_version_tag::Model,
             entities::experiment::Model,
             entities::experiment_artifact::Model,
+            entities::experiment_environment::Model,
+            entities::experiment_metric::Model,
+            entities::experiment_param::Model,
+            entities::experiment_result::Model,
+            entities::experiment_state::Model,
             entities::experiment_tag::Model,
             entities::object_blob::Model,
+            entities::promotion_approval::Model,
+            entities::promotion_policy::Model,
+            entities::promotion_request::Model,
+            entities::upload_ticket::Model,
+            entities::upload_ticket_part::Model,
+            entities::webhook::Model,
+            entities::webhook_delivery::Model,
+            entities::audit_log::Model,
         }
     };
 }
 
 pub fn build_schema(
     db: DbConn,
+    storage: Arc<StorageOrchestrator>,
+    events: EventBus,
+    audit: AuditPublisher,
     depth: Option<usize>,
     complexity: Option<usize>,
     tracer: Option<OtlpTracerConfig>,
@@ -68,7 +92,10 @@ pub fn build_schema(
         .extension(Tracing)
         .enable_federation()
         .enable_subscription_in_federation()
-        .data(db.clone());
+        .data(db.clone())
+        .data(storage)
+        .data(events)
+        .data(audit);
 
     apply_data! {
         builder,
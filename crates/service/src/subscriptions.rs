@@ -0,0 +1,91 @@
+use async_graphql::{Context, Subscription};
+use flymodel::{errs::FlymodelError, perms::Perm};
+use futures_util::Stream;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    StreamExt,
+};
+use tracing::warn;
+
+use crate::{
+    auth::{authorize_experiment, authorize_model_version, principal},
+    events::{
+        ArtifactUploaded, EventBus, ExperimentStateChanged, ModelVersionStateChanged, RegistryEvent,
+    },
+};
+
+#[derive(Clone, Default)]
+pub struct Subscription;
+
+fn picked<T>(
+    ctx: &Context<'_>,
+    mut pick: impl FnMut(RegistryEvent) -> Option<T> + Send + 'static,
+) -> Result<impl Stream<Item = T>, async_graphql::Error> {
+    let bus = ctx.data_opt::<EventBus>().ok_or_else(|| {
+        FlymodelError::RuntimeDependencyError("missing event bus".into()).into_graphql_error()
+    })?;
+    Ok(
+        BroadcastStream::new(bus.subscribe()).filter_map(move |event| match event {
+            Ok(event) => pick(event),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                warn!("a subscription fell behind, missing {missed} registry events");
+                None
+            }
+        }),
+    )
+}
+
+#[Subscription]
+impl Subscription {
+    /// lifecycle transitions of the versions in a namespace, of the models the caller may read
+    async fn model_version_state_changed<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        namespace: i64,
+    ) -> Result<impl Stream<Item = ModelVersionStateChanged>, async_graphql::Error> {
+        let principal = principal(ctx)?.clone();
+        picked(ctx, move |event| match event {
+            RegistryEvent::ModelVersionStateChanged(changed)
+                if changed.namespace == namespace
+                    && principal
+                        .authorize_model(changed.namespace, changed.model, Perm::R)
+                        .is_ok() =>
+            {
+                Some(changed)
+            }
+            _ => None,
+        })
+    }
+
+    /// artifacts registered for a model version or any of its experiments
+    async fn artifact_uploaded<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        model_version: i64,
+    ) -> Result<impl Stream<Item = ArtifactUploaded>, async_graphql::Error> {
+        authorize_model_version(ctx, model_version, Perm::R).await?;
+        picked(ctx, move |event| match event {
+            RegistryEvent::ArtifactUploaded(uploaded)
+                if uploaded.model_version == model_version =>
+            {
+                Some(uploaded)
+            }
+            _ => None,
+        })
+    }
+
+    /// the states the run of an experiment moves through
+    async fn experiment_state_changed<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        experiment: i64,
+    ) -> Result<impl Stream<Item = ExperimentStateChanged>, async_graphql::Error> {
+        authorize_experiment(ctx, experiment, Perm::R).await?;
+        picked(ctx, move |event| match event {
+            RegistryEvent::ExperimentStateChanged(changed) if changed.experiment == experiment => {
+                Some(changed)
+            }
+            _ => None,
+        })
+    }
+}
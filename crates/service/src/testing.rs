@@ -0,0 +1,57 @@
+use bytes::Bytes;
+use chrono::Utc;
+use flymodel::{lifecycle::Lifecycle, storage::StorageProvider};
+use flymodel_entities::{entities, testing};
+use flymodel_registry::{
+    memory::{MemoryConfiguration, MemoryStorage},
+    storage::StorageOrchestrator,
+};
+use sea_orm::DbConn;
+
+pub(crate) fn memory_storage(
+    bucket: &str,
+    role: Lifecycle,
+) -> (MemoryStorage, StorageOrchestrator) {
+    let (mut storages, orchestrator) = memory_storages(&[(bucket, role)]);
+    (storages.remove(0), orchestrator)
+}
+
+pub(crate) async fn stored_blob(
+    db: &DbConn,
+    storage: &MemoryStorage,
+    bucket: i64,
+    key: &str,
+    created_at: chrono::DateTime<Utc>,
+) -> entities::object_blob::Model {
+    let version_id = storage
+        .put(key.into(), Bytes::from_static(b"hello world"))
+        .await
+        .unwrap()
+        .unwrap();
+    testing::blob(db, bucket, key, &version_id, created_at).await
+}
+
+pub(crate) fn memory_storages(
+    buckets: &[(&str, Lifecycle)],
+) -> (Vec<MemoryStorage>, StorageOrchestrator) {
+    let storages: Vec<_> = buckets
+        .iter()
+        .map(|(bucket, role)| {
+            serde_json::from_value::<MemoryConfiguration>(serde_json::json!({
+                "bucket": bucket,
+                "role": role,
+            }))
+            .map(MemoryStorage::new)
+            .unwrap()
+        })
+        .collect();
+    let orchestrator = StorageOrchestrator::from_providers(buckets.iter().zip(&storages).map(
+        |((bucket, _), storage)| {
+            (
+                bucket.to_string(),
+                Box::new(storage.clone()) as Box<dyn StorageProvider + Send + Sync>,
+            )
+        },
+    ));
+    (storages, orchestrator)
+}
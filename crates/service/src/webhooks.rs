@@ -0,0 +1,315 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use flymodel::{config::webhooks::WebhookConfiguration, errs::FlymodelError};
+use flymodel_entities::{
+    db::{Database, DbLoader},
+    entities::{
+        self,
+        webhook_delivery::{self, Attempt},
+    },
+};
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use sea_orm::DbConn;
+use sha2::Sha256;
+use tracing::{debug, info, warn};
+
+pub const EVENT_HEADER: &str = "X-Flymodel-Event";
+pub const DELIVERY_HEADER: &str = "X-Flymodel-Delivery";
+pub const SIGNATURE_HEADER: &str = "X-Flymodel-Signature-256";
+
+/// the signature of a body, as sent in [SIGNATURE_HEADER]
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// when a delivery which failed its `attempts`th attempt is retried, unless it is given up on
+pub fn retry_at(
+    attempts: u32,
+    conf: &WebhookConfiguration,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if attempts >= conf.max_attempts {
+        return None;
+    }
+    let backoff = 2u64
+        .checked_pow(attempts.saturating_sub(1))
+        .and_then(|factor| factor.checked_mul(conf.backoff))
+        .map_or(conf.max_backoff, |backoff| backoff.min(conf.max_backoff));
+    Some(
+        chrono::Duration::from_std(Duration::from_secs(backoff))
+            .ok()
+            .and_then(|backoff| now.checked_add_signed(backoff))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC),
+    )
+}
+
+fn client(conf: &WebhookConfiguration) -> Result<reqwest::Client, FlymodelError> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(conf.timeout))
+        // a redirect would resend the signed body somewhere the namespace never registered
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|err| FlymodelError::RuntimeDependencyError(format!("webhook client: {err}")))
+}
+
+pub async fn deliver(
+    client: &reqwest::Client,
+    conf: &WebhookConfiguration,
+    webhook: &entities::webhook::Model,
+    delivery: &webhook_delivery::Model,
+) -> Attempt {
+    let attempts = delivery.attempts.max(0) as u32 + 1;
+    let failed = |status: Option<u16>, error: String| Attempt::Failed {
+        status: status.map(i32::from),
+        error,
+        retry_at: retry_at(attempts, conf, Utc::now()),
+    };
+    let body = match serde_json::to_vec(&delivery.payload) {
+        Ok(body) => body,
+        Err(err) => return failed(None, err.to_string()),
+    };
+    let event = match serde_json::to_value(delivery.event) {
+        Ok(serde_json::Value::String(event)) => event,
+        _ => format!("{:?}", delivery.event),
+    };
+    let sent = client
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, event)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .header(SIGNATURE_HEADER, signature(&webhook.secret, &body))
+        .body(body)
+        .send()
+        .await;
+    match sent {
+        Ok(resp) if resp.status().is_success() => Attempt::Delivered {
+            status: resp.status().as_u16().into(),
+        },
+        Ok(resp) => failed(
+            Some(resp.status().as_u16()),
+            format!("the endpoint answered {}", resp.status()),
+        ),
+        Err(err) => failed(None, err.to_string()),
+    }
+}
+
+/// attempts the deliveries which are due, returning how many were delivered & attempted
+pub async fn dispatch_due(
+    deliveries: &Database<webhook_delivery::Model>,
+    client: &reqwest::Client,
+    conf: &WebhookConfiguration,
+) -> Result<(usize, usize), FlymodelError> {
+    // long enough for every attempt of the batch to be recorded before it is claimed again
+    let lease = chrono::Duration::seconds((2 * conf.timeout + conf.interval) as i64);
+    let deliveries = deliveries.loader();
+    let due = DbLoader::claim_due(&deliveries.db, conf.batch, lease).await?;
+    let attempted = due.len();
+    let attempts = join_all(due.iter().map(|(delivery, webhook)| async move {
+        (delivery, deliver(client, conf, webhook, delivery).await)
+    }))
+    .await;
+    let mut delivered = 0;
+    for (delivery, attempt) in attempts {
+        match &attempt {
+            Attempt::Delivered { .. } => delivered += 1,
+            Attempt::Failed {
+                error,
+                retry_at: None,
+                ..
+            } => warn!(
+                "gave up on delivery {} to webhook {}: {error}",
+                delivery.id, delivery.webhook_id
+            ),
+            Attempt::Failed { error, .. } => debug!(
+                "delivery {} to webhook {} failed: {error}",
+                delivery.id, delivery.webhook_id
+            ),
+        }
+        deliveries.record_attempt(delivery.id, attempt).await?;
+    }
+    Ok((delivered, attempted))
+}
+
+pub fn spawn_webhook_dispatcher(
+    db: DbConn,
+    conf: WebhookConfiguration,
+) -> Result<tokio::task::JoinHandle<()>, FlymodelError> {
+    let client = client(&conf)?;
+    let deliveries = DbLoader::<webhook_delivery::Model>::new(db, None);
+    Ok(tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(Duration::from_secs(conf.interval));
+        loop {
+            ticks.tick().await;
+            match dispatch_due(&deliveries, &client, &conf).await {
+                Ok((_, 0)) => {}
+                Ok((delivered, attempted)) => {
+                    info!("delivered {delivered} of {attempted} due webhook deliveries")
+                }
+                Err(err) => warn!("failed to dispatch webhook deliveries: {err}"),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use chrono::{TimeZone, Utc};
+    use flymodel::config::webhooks::WebhookConfiguration;
+    use flymodel_entities::entities::{
+        self,
+        enums::{WebhookDeliveryStatus, WebhookEvent},
+        webhook_delivery::Attempt,
+    };
+
+    use super::{
+        client, deliver, retry_at, signature, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER,
+    };
+
+    const SECRET: &str = "0123456789abcdef";
+
+    #[test]
+    fn test_signature() {
+        // https://datatracker.ietf.org/doc/html/rfc4231#section-4.3
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_retry_at() {
+        let conf = WebhookConfiguration {
+            max_attempts: 5,
+            backoff: 30,
+            max_backoff: 100,
+            ..Default::default()
+        };
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let waited = |attempts| retry_at(attempts, &conf, now).map(|at| (at - now).num_seconds());
+        assert_eq!(waited(1), Some(30));
+        assert_eq!(waited(2), Some(60));
+        assert_eq!(waited(3), Some(100));
+        assert_eq!(waited(4), Some(100));
+        assert_eq!(waited(5), None);
+        let conf = WebhookConfiguration {
+            max_attempts: u32::MAX,
+            max_backoff: u64::MAX,
+            ..conf
+        };
+        assert!(retry_at(200, &conf, now).is_some());
+    }
+
+    async fn receive(req: HttpRequest, body: web::Bytes) -> HttpResponse {
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+        };
+        if header(EVENT_HEADER) != "ARTIFACT_UPLOADED"
+            || header(DELIVERY_HEADER) != "7"
+            || header(SIGNATURE_HEADER) != signature(SECRET, &body)
+        {
+            return HttpResponse::BadRequest().finish();
+        }
+        match req.path() {
+            "/ok" => HttpResponse::NoContent().finish(),
+            "/moved" => HttpResponse::Found()
+                .insert_header(("Location", "/ok"))
+                .finish(),
+            _ => HttpResponse::ServiceUnavailable().finish(),
+        }
+    }
+
+    fn webhook(url: String, secret: &str) -> entities::webhook::Model {
+        entities::webhook::Model {
+            id: 1,
+            namespace_id: 1,
+            url,
+            events: serde_json::json!(["ARTIFACT_UPLOADED"]),
+            secret: secret.into(),
+            active: true,
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_deliver() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = HttpServer::new(|| App::new().default_service(web::to(receive)))
+            .listen(listener)
+            .unwrap()
+            .run();
+        tokio::spawn(server);
+
+        let conf = WebhookConfiguration {
+            max_attempts: 2,
+            ..Default::default()
+        };
+        let client = client(&conf).unwrap();
+        let mut delivery = entities::webhook_delivery::Model {
+            id: 7,
+            webhook_id: 1,
+            event: WebhookEvent::ArtifactUploaded,
+            payload: serde_json::json!({"event": "ARTIFACT_UPLOADED", "data": {"id": 3}}),
+            status: WebhookDeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: Utc::now(),
+            response_status: None,
+            error: None,
+            created_at: Utc::now(),
+            last_attempt_at: None,
+            delivered_at: None,
+        };
+        let url = |path| format!("http://{addr}{path}");
+
+        assert_eq!(
+            deliver(&client, &conf, &webhook(url("/ok"), SECRET), &delivery).await,
+            Attempt::Delivered { status: 204 }
+        );
+        // signed with another secret than the endpoint expects
+        let attempt = deliver(
+            &client,
+            &conf,
+            &webhook(url("/ok"), "fedcba9876543210"),
+            &delivery,
+        )
+        .await;
+        assert!(matches!(
+            attempt,
+            Attempt::Failed {
+                status: Some(400),
+                retry_at: Some(_),
+                ..
+            }
+        ));
+        // redirects are not followed
+        let attempt = deliver(&client, &conf, &webhook(url("/moved"), SECRET), &delivery).await;
+        assert!(matches!(
+            attempt,
+            Attempt::Failed {
+                status: Some(302),
+                ..
+            }
+        ));
+        // the last attempt fails for good
+        delivery.attempts = 1;
+        let attempt = deliver(&client, &conf, &webhook(url("/down"), SECRET), &delivery).await;
+        assert!(matches!(
+            attempt,
+            Attempt::Failed {
+                status: Some(503),
+                retry_at: None,
+                ..
+            }
+        ));
+    }
+}